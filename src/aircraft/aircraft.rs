@@ -1,10 +1,23 @@
+use crate::aircraft::approach::{Approach, ApproachSchedule, ApproachType, Localizer, MissedApproach, GO_AROUND_ABOVE_GLIDESLOPE_FT};
 use crate::aircraft::flight_plan::FlightPlan;
-use crate::utils::navigation::{FixDatabase, heading_from_to, position_bearing_distance, haversine_nm};
+use crate::aircraft::hold::{Hold, HoldDatabase, HoldLeg};
+use crate::aircraft::route::{LevelChange, Route};
+use crate::utils::atmosphere::{mach_to_ias, mach_to_tas, tas_to_mach};
+use std::sync::Arc;
+use crate::utils::performance::{AircraftPerformance, SpeedPhase, SpeedTarget};
+use crate::utils::registration::ModeS;
+use crate::utils::wake::WakeCategory;
+use crate::utils::wind::WindField;
+use crate::utils::navigation::{FixDatabase, parse_coordinate_fix, TurnDirection, bearing_from_to, cross_track_nm, heading_difference, intercept_heading, intermediate_point, normalize_heading, position_bearing_distance, haversine_nm};
 
 /// Aircraft phases of flight
 #[derive(Debug, Clone, PartialEq)]
 pub enum FlightPhase {
     OnGround,
+    /// Holding short of the departure runway
+    HoldingShort,
+    /// Lined up on the departure runway, waiting for takeoff clearance
+    LinedUp,
     Departing,
     Climbing,
     Cruise,
@@ -13,19 +26,79 @@ pub enum FlightPhase {
     Landing,
 }
 
+/// Lateral navigation mode
+#[derive(Debug, Clone, PartialEq)]
+pub enum NavMode {
+    /// Following the route fixes
+    FlightPlan,
+    /// Flying a holding pattern
+    Hold(Hold),
+    /// Cleared for the ILS: intercepting or established on the localizer
+    Ils(Localizer),
+    /// Flying a heading to intercept a route leg
+    Intercept(LegIntercept),
+    /// Flying a heading assigned by ATC (degrees)
+    Heading(f64),
+    /// Going around from the ILS and flying the missed approach
+    MissedApproach(MissedApproach),
+}
+
+/// A route leg being intercepted on a heading
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegIntercept {
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+    /// Index in `route_fixes` of the fix the leg leads to
+    pub to_index: usize,
+    pub heading: f64,
+}
+
+/// Code squawked before the assigned code is given
+pub const CONSPICUITY_SQUAWK: &str = "2000";
+
+/// Where an aircraft placed at a fix starts without a fix database (Gatwick)
+pub const PLACEHOLDER_POSITION: (f64, f64) = (51.15487, -0.16454);
+
+/// Distance from a fix (NM) at which it is passed, without a turn onto the next leg
+const FIX_CAPTURE_NM: f64 = 0.5;
+
+/// Distance from a leg (NM) at which an intercepting aircraft counts as established on it
+const LEG_ESTABLISHED_NM: f64 = 0.3;
+
+/// Height above the glideslope (feet) within which an aircraft is put straight onto it
+const GLIDESLOPE_CAPTURE_FT: i32 = 20;
+
+/// Legs at least this long (NM) are flown along the great circle between their fixes
+const LONG_LEG_NM: f64 = 100.0;
+
+/// How far ahead along a long leg (NM) the aircraft steers for
+const LEG_LOOKAHEAD_NM: f64 = 20.0;
+
+/// Furthest off a long leg (NM) the aircraft can be and still steer back onto it,
+/// rather than going direct to the fix
+const LEG_REJOIN_NM: f64 = 5.0;
+
 /// Aircraft state
 #[derive(Debug, Clone)]
 pub struct Aircraft {
     pub callsign: String,
     pub aircraft_type: String,
     pub squawk: String,
+    /// Mode-S registration and address, if the airline's country is known
+    pub mode_s: Option<ModeS>,
+    /// Transponder failed: shows as a primary return with no squawk or altitude
+    pub primary_only: bool,
+    /// Squawking the assigned code; until then the transponder shows the conspicuity code
+    pub squawking_assigned: bool,
     
     // Position
     pub latitude: f64,
     pub longitude: f64,
     pub altitude: i32,      // feet
-    pub heading: i32,       // degrees
-    pub ground_speed: u32,  // knots
+    pub heading: f64,       // degrees
+    pub airspeed: u32,      // knots
+    /// Speed over the ground after wind (knots)
+    pub ground_speed: u32,
     
     // Flight plan
     pub flight_plan: FlightPlan,
@@ -34,22 +107,60 @@ pub struct Aircraft {
     pub route_fixes: Vec<String>,
     pub current_fix_index: usize,
     pub phase: FlightPhase,
+    pub nav_mode: NavMode,
+    /// Index in `route_fixes` where the STAR begins (== len if there is none)
+    star_start: usize,
+    /// Speed/level changes filed in the route, still to be reached
+    pub level_changes: Vec<LevelChange>,
+    /// Position of the arrival aerodrome, if known
+    pub destination: Option<(f64, f64)>,
+
+    // Arrival info
+    /// Approach the pilot would like to fly
+    pub requested_approach: Option<Approach>,
+    /// Approach assigned by ATC, overriding the requested one
+    pub assigned_approach: Option<Approach>,
     
     // Departure info
     pub departure_runway: String,
-    pub departure_heading: i32,
+    pub departure_heading: f64,
     
     // Target values
     pub target_altitude: i32,
-    pub target_heading: i32,
+    pub target_heading: f64,
     pub target_speed: u32,
+    /// Mach number assigned by ATC; while set it drives the target speed
+    pub assigned_mach: Option<f64>,
+    /// Indicated airspeed (kt) assigned by ATC; while set it drives the target speed
+    pub assigned_speed: Option<u32>,
+    /// Speed (kt) the approach spacing assistant has slowed it to on final
+    pub spacing_speed: Option<u32>,
+
+    /// Altitude assigned by ATC (feet). While set, the aircraft holds this
+    /// level instead of following its own SID/cruise profile.
+    pub cleared_altitude: Option<i32>,
+    /// Callsign of the controller the aircraft is with
+    pub controller: Option<String>,
+    /// Controller the aircraft has been handed off to, until they accept it
+    pub handoff_to: Option<String>,
     
     // Time tracking
     pub spawn_time: std::time::Instant,
+    /// Simulated seconds since spawn
+    pub flight_time: f64,
+    /// Flight time at rotation, if the aircraft took off in the simulation
+    takeoff_time: Option<f64>,
+    /// Climb and descent performance for the type, if known
+    pub performance: Option<Arc<AircraftPerformance>>,
+    /// Fly the arrival descent as one continuous descent to the end of the route,
+    /// rather than descending at the standard rate and levelling off
+    pub continuous_descent: bool,
 }
 
 impl Aircraft {
-    /// Create a new aircraft on the ground at departure airport
+    /// Create a new aircraft on the ground at departure airport, lined up at `position`
+    /// (normally the runway threshold) on the runway heading
+    #[allow(clippy::too_many_arguments)]
     pub fn new_departure(
         callsign: String,
         aircraft_type: String,
@@ -59,31 +170,28 @@ impl Aircraft {
         route: String,
         cruise_altitude: u32,
         runway: String,
-        airport_coords: (f64, f64),
-        runway_heading: i32,
+        position: (f64, f64),
+        runway_heading: f64,
     ) -> Self {
-        let flight_plan = FlightPlan::new(
+        let mut flight_plan = FlightPlan::new(
             aircraft_type.clone(),
             departure.clone(),
-            arrival,
+            arrival.clone(),
             cruise_altitude,
             route.clone(),
         );
 
-        // Parse route to extract fixes (this gets the enroute portion)
-        let enroute_fixes = Self::parse_route(&route);
-        
-        // Extract SID waypoints and prepend them to the route
-        let sid_fixes = Self::extract_sid_waypoints(&departure, &route, &runway);
-        let mut route_fixes = sid_fixes;
-        
-        // Add enroute fixes, but skip duplicates (e.g., if SID ends at CLN and route starts with CLN)
-        for fix in enroute_fixes {
-            if route_fixes.is_empty() || route_fixes.last() != Some(&fix) {
-                route_fixes.push(fix);
-            }
+        let mode_s = ModeS::generate(&callsign, &mut rand::thread_rng());
+        if let Some(mode_s) = &mode_s {
+            flight_plan.set_mode_s(mode_s);
         }
-        
+
+        // Expand the SID for our runway, the enroute fixes and airways, then the STAR
+        let expanded = Route::with_runways(route.clone(), departure.clone(), Some(arrival), Some(&runway), None);
+        let route_fixes = expanded.fixes;
+        let star_start = expanded.star_start;
+        let level_changes = expanded.level_changes;
+
         // Extract SID altitude restriction (default to 6000 if not found)
         let sid_altitude = Self::extract_sid_altitude(&departure, &route);
 
@@ -94,163 +202,271 @@ impl Aircraft {
             callsign,
             aircraft_type,
             squawk,
-            latitude: airport_coords.0,
-            longitude: airport_coords.1,
+            mode_s,
+            primary_only: false,
+            squawking_assigned: true,
+            latitude: position.0,
+            longitude: position.1,
             altitude: 0,
             heading: runway_heading,
+            airspeed: 0,
             ground_speed: 0,
             flight_plan,
             route_fixes,
             current_fix_index: 0,
             phase: FlightPhase::OnGround,
+            nav_mode: NavMode::FlightPlan,
+            star_start,
+            level_changes,
+            destination: None,
+            requested_approach: None,
+            assigned_approach: None,
             departure_runway: runway,
             departure_heading: runway_heading,
             target_altitude: sid_altitude,
             target_heading: runway_heading,
             target_speed: 250,
+            assigned_mach: None,
+            assigned_speed: None,
+            spacing_speed: None,
+            cleared_altitude: None,
+            controller: None,
+            handoff_to: None,
+            spawn_time: std::time::Instant::now(),
+            flight_time: 0.0,
+            takeoff_time: None,
+            performance: None,
+            continuous_descent: false,
+        }
+    }
+
+    /// Create an arrival already established on the ILS, `range_nm` from the threshold
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_ils_arrival(
+        callsign: String,
+        aircraft_type: String,
+        squawk: String,
+        departure: String,
+        arrival: String,
+        route: String,
+        mut localizer: Localizer,
+        range_nm: f64,
+        altitude: i32,
+    ) -> Self {
+        let mut flight_plan = FlightPlan::new(
+            aircraft_type.clone(),
+            departure,
+            arrival,
+            (altitude / 100) as u32,
+            route,
+        );
+
+        let mode_s = ModeS::generate(&callsign, &mut rand::thread_rng());
+        if let Some(mode_s) = &mode_s {
+            flight_plan.set_mode_s(mode_s);
+        }
+
+        let (latitude, longitude) = position_bearing_distance(
+            localizer.threshold.0,
+            localizer.threshold.1,
+            (localizer.course + 180.0) % 360.0,
+            range_nm,
+        );
+        let heading = normalize_heading(localizer.course);
+        let runway = localizer.runway.clone();
+        localizer.established = true;
+
+        tracing::info!("[AIRCRAFT] Creating {} established on the ILS {} at {:.1}nm",
+                      callsign, runway, range_nm);
+
+        Self {
+            callsign,
+            aircraft_type,
+            squawk,
+            mode_s,
+            primary_only: false,
+            squawking_assigned: true,
+            latitude,
+            longitude,
+            altitude,
+            heading,
+            airspeed: 160,
+            ground_speed: 160,
+            flight_plan,
+            route_fixes: vec![format!("RW{}", runway)],
+            current_fix_index: 0,
+            phase: FlightPhase::Approach,
+            nav_mode: NavMode::Ils(localizer),
+            star_start: 0,
+            level_changes: Vec::new(),
+            destination: None,
+            requested_approach: None,
+            assigned_approach: Some(Approach::new(ApproachType::Ils, &runway)),
+            departure_runway: String::new(),
+            departure_heading: 0.0,
+            target_altitude: altitude,
+            target_heading: heading,
+            target_speed: 160,
+            assigned_mach: None,
+            assigned_speed: None,
+            spacing_speed: None,
+            cleared_altitude: None,
+            controller: None,
+            handoff_to: None,
+            spawn_time: std::time::Instant::now(),
+            flight_time: 0.0,
+            takeoff_time: None,
+            performance: None,
+            continuous_descent: false,
+        }
+    }
+
+    /// Create an aircraft in the cruise at a position along its route
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_enroute(
+        callsign: String,
+        aircraft_type: String,
+        squawk: String,
+        departure: String,
+        arrival: String,
+        route: String,
+        position: (f64, f64),
+        heading: f64,
+        altitude: i32,
+        speed: u32,
+    ) -> Self {
+        let mut flight_plan = FlightPlan::new(
+            aircraft_type.clone(),
+            departure.clone(),
+            arrival.clone(),
+            (altitude / 100) as u32,
+            route.clone(),
+        );
+
+        let mode_s = ModeS::generate(&callsign, &mut rand::thread_rng());
+        if let Some(mode_s) = &mode_s {
+            flight_plan.set_mode_s(mode_s);
+        }
+
+        let expanded = Route::new(route, departure, Some(arrival));
+
+        tracing::info!("[AIRCRAFT] Creating {} enroute at {}ft with {} route fixes: {:?}",
+                      callsign, altitude, expanded.fixes.len(), expanded.fixes);
+
+        Self {
+            callsign,
+            aircraft_type,
+            squawk,
+            mode_s,
+            primary_only: false,
+            squawking_assigned: true,
+            latitude: position.0,
+            longitude: position.1,
+            altitude,
+            heading,
+            airspeed: speed,
+            ground_speed: speed,
+            flight_plan,
+            route_fixes: expanded.fixes,
+            current_fix_index: 0,
+            phase: FlightPhase::Cruise,
+            nav_mode: NavMode::FlightPlan,
+            star_start: expanded.star_start,
+            level_changes: expanded.level_changes,
+            destination: None,
+            requested_approach: None,
+            assigned_approach: None,
+            departure_runway: String::new(),
+            departure_heading: 0.0,
+            target_altitude: altitude,
+            target_heading: heading,
+            target_speed: speed,
+            assigned_mach: None,
+            assigned_speed: None,
+            spacing_speed: None,
+            cleared_altitude: None,
+            controller: None,
+            handoff_to: None,
             spawn_time: std::time::Instant::now(),
+            flight_time: 0.0,
+            takeoff_time: None,
+            performance: None,
+            continuous_descent: false,
         }
     }
 
+    /// Create an aircraft in the cruise over a named fix, found in `fix_db` (or from a
+    /// coordinate waypoint's name). Without a database it starts at the placeholder
+    /// position; with one, a fix that can't be found is an error.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_fix(
+        callsign: String,
+        aircraft_type: String,
+        squawk: String,
+        departure: String,
+        arrival: String,
+        route: String,
+        fix: &str,
+        heading: f64,
+        altitude: i32,
+        speed: u32,
+        fix_db: Option<&FixDatabase>,
+    ) -> anyhow::Result<Self> {
+        let position = match fix_db {
+            Some(fix_db) => fix_db.position(fix, PLACEHOLDER_POSITION)
+                .ok_or_else(|| anyhow::anyhow!("Unknown fix {} for {}", fix, callsign))?,
+            None => parse_coordinate_fix(fix).unwrap_or(PLACEHOLDER_POSITION),
+        };
+
+        Ok(Self::new_enroute(callsign, aircraft_type, squawk, departure, arrival, route, position, heading, altitude, speed))
+    }
+
     /// Placeholder for SID stop altitude - maybe just let UKCP set the tag and read from there??
-    fn extract_sid_altitude(departure: &str, route: &str) -> i32 {
+    fn extract_sid_altitude(departure: &str, _route: &str) -> i32 {
         // Common SID altitude restrictions by airport
-        let default_restrictions = match departure {
-            "EGSS" => 4000,  
-            "EGGW" => 5000,  
+        match departure {
+            "EGSS" => 4000,
+            "EGGW" => 5000,
             "EGLC" => 3000,
-            "EGLL" => 6000,  
-            "EGKK" => 4000,  
-            _ => 6000,       
-        };
-        
-        default_restrictions
-    }
-    
-    /// Extract SID waypoints from the SID file
-    fn extract_sid_waypoints(departure: &str, route: &str, runway: &str) -> Vec<String> {
-        // Extract SID name from route (e.g., "CLN2E/22" -> "CLN2E")
-        let sid_name = if let Some(sid_part) = route.split_whitespace().next() {
-            if sid_part.contains('/') {
-                sid_part.split('/').next().unwrap_or("")
-            } else {
-                return Vec::new();
-            }
-        } else {
-            return Vec::new();
-        };
-        
-        // Try to load the SID file for this airport
-        let sid_file = format!("data/Airports/{}/Sids.txt", departure);
-        if let Ok(content) = std::fs::read_to_string(&sid_file) {
-            for line in content.lines() {
-                let line = line.trim();
-                if line.is_empty() || line.starts_with(';') {
-                    continue;
-                }
-                
-                // Format: SID:ICAO:RUNWAY:SIDNAME:FIXES...
-                let parts: Vec<&str> = line.split(':').collect();
-                if parts.len() >= 5 && parts[0] == "SID" {
-                    let file_runway = parts[2];
-                    let file_sid_name = parts[3];
-                    
-                    // Match the SID name and runway
-                    if file_sid_name == sid_name && file_runway == runway {
-                        // Parse the waypoints
-                        let fixes_str = parts[4];
-                        let waypoints: Vec<String> = fixes_str
-                            .split_whitespace()
-                            .map(|s| s.to_uppercase())
-                            .collect();
-                        
-                        tracing::debug!("[AIRCRAFT] Found SID {} for runway {}: {} waypoints", 
-                                       sid_name, runway, waypoints.len());
-                        return waypoints;
-                    }
-                }
-            }
-            tracing::warn!("[AIRCRAFT] SID {} not found for runway {} at {}", sid_name, runway, departure);
-        } else {
-            tracing::warn!("[AIRCRAFT] Could not read SID file: {}", sid_file);
+            "EGLL" => 6000,
+            "EGKK" => 4000,
+            _ => 6000,
         }
-        
-        Vec::new()
     }
     
-    /// Parse route string to extract fix names
-    fn parse_route(route: &str) -> Vec<String> {
-        let mut fixes = Vec::new();
-        
-        // Split by spaces
-        let parts: Vec<&str> = route.split(|c: char| c.is_whitespace())
-            .filter(|s| !s.is_empty())
-            .collect();
-        
-        for part in parts {
-            // Skip SID/STAR notation with runway (e.g., CLN2E/22)
-            if part.contains("/") {
-                continue;
-            }
-            
-            // Skip airway designators (start with letters followed by numbers, max 5 chars)
-            if part.len() >= 2 && part.len() <= 5 {
-                let chars: Vec<char> = part.chars().collect();
-                if chars[0].is_alphabetic() {
-                    let has_digit = chars.iter().any(|c| c.is_numeric());
-                    let mostly_letters_then_numbers = 
-                        chars.iter().take_while(|c| c.is_alphabetic()).count() <= 2 &&
-                        has_digit;
-                    
-                    if mostly_letters_then_numbers {
-                        // Likely an airway like P44, M197, Q295
-                        continue;
-                    }
-                }
-            }
-            
-            // Skip DCT
-            if part == "DCT" {
-                continue;
-            }
-            
-            // This is likely a fix name (3-6 characters, all alphabetic)
-            if part.len() >= 3 && part.len() <= 6 && part.chars().all(|c| c.is_alphabetic()) {
-                fixes.push(part.to_uppercase());
-            }
-        }
-        
-        fixes
-    }
-
     /// Update aircraft position and state
     pub fn update(&mut self, delta_time: f64, fix_db: &FixDatabase, sim_config: &crate::config::SimulationConfig) {
+        self.flight_time += delta_time;
+        self.apply_assigned_mach(sim_config);
+        self.apply_assigned_speed();
+
         match self.phase {
-            FlightPhase::OnGround => {
-                // Wait a few seconds before starting takeoff
-                if self.spawn_time.elapsed().as_secs() >= 5 {
-                    self.phase = FlightPhase::Departing;
-                    self.ground_speed = 10;
-                    tracing::info!("[{}] Starting takeoff roll", self.callsign);
+            // Wait a few seconds before starting takeoff
+            FlightPhase::OnGround if self.flight_time >= 5.0 => {
+                if sim_config.require_takeoff_clearance {
+                    self.hold_short();
+                } else {
+                    self.start_takeoff_roll();
                 }
             }
             
             FlightPhase::Departing => {
                 // Accelerate on runway
-                if self.ground_speed < 150 {
-                    self.ground_speed += (50.0 * delta_time) as u32;
+                if self.airspeed < 150 {
+                    self.airspeed += (50.0 * delta_time) as u32;
                 } else {
                     tracing::info!("[{}] Rotation speed reached, route_fixes.len()={}", 
                                   self.callsign, self.route_fixes.len());
                     // Rotate and start climbing
                     self.phase = FlightPhase::Climbing;
+                    self.takeoff_time = Some(self.flight_time);
                     self.altitude = 50;
                     self.target_speed = 250;
                     
                     // Set initial heading towards first waypoint
                     if !self.route_fixes.is_empty() {
-                        if let Some((fix_lat, fix_lon)) = fix_db.get(&self.route_fixes[0]) {
-                            self.target_heading = heading_from_to(self.latitude, self.longitude, *fix_lat, *fix_lon);
+                        if let Some((fix_lat, fix_lon)) = fix_db.position(&self.route_fixes[0], (self.latitude, self.longitude)) {
+                            self.target_heading = bearing_from_to(self.latitude, self.longitude, fix_lat, fix_lon);
                             self.heading = self.target_heading;  // Start turning immediately
                             tracing::info!("[{}] Airborne, climbing to {} via {}", 
                                           self.callsign, self.route_fixes[0], self.route_fixes.join(" "));
@@ -265,40 +481,42 @@ impl Aircraft {
             }
             
             FlightPhase::Climbing => {
-                // Realistic climb rate: 1500-2500 ft/min depending on altitude
-                let climb_rate_fpm = if self.altitude < 10000 {
-                    2000.0  // Higher rate at lower altitudes
-                } else if self.altitude < 20000 {
-                    1800.0  // Moderate rate
-                } else {
-                    1500.0  // Lower rate at higher altitudes
-                };
-                
-                let climb_rate = (climb_rate_fpm / 60.0) * delta_time;  // Convert to ft/sec
-                self.altitude += climb_rate as i32;
+                let climb_rate_fpm = self.climb_rate(sim_config);
+                self.step_towards_target_altitude(climb_rate_fpm, delta_time);
                 
-                // Accelerate to target speed
-                if self.ground_speed < self.target_speed {
-                    self.ground_speed += (10.0 * delta_time) as u32;
+                // Accelerate to target speed, or slow to an assigned one
+                if self.airspeed < self.target_speed {
+                    self.airspeed += (10.0 * delta_time) as u32;
+                } else if self.airspeed > self.target_speed {
+                    self.airspeed = self.airspeed
+                        .saturating_sub((5.0 * delta_time) as u32)
+                        .max(self.target_speed);
                 }
                 
                 // Update speed restrictions and target altitude
-                if self.altitude >= self.target_altitude && self.target_altitude < (self.flight_plan.cruise_altitude as i32 * 100) {
+                if self.cleared_altitude.is_none()
+                    && self.altitude >= self.target_altitude
+                    && self.target_altitude < (self.flight_plan.cruise_altitude_feet())
+                {
                     // Reached SID altitude, now climb to cruise
-                    self.target_altitude = self.flight_plan.cruise_altitude as i32 * 100;
+                    self.target_altitude = self.flight_plan.cruise_altitude_feet();
                     self.target_speed = 250;  // Maintain 250 until above 10000
                 }
                 
-                if self.altitude > 10000 && self.target_speed < 300 {
-                    self.target_speed = 300;
+                if !self.speed_assigned() {
+                    match self.performance_speed(SpeedPhase::Climb) {
+                        Some(speed) => self.target_speed = speed,
+                        None if self.altitude > 10000 && self.target_speed < 300 => self.target_speed = 300,
+                        None => {}
+                    }
                 }
                 
-                // Navigate to next fix (this handles turning)
-                self.navigate_to_next_fix(fix_db, delta_time, sim_config);
+                // Navigate (this handles turning)
+                self.navigate(fix_db, delta_time, sim_config);
                 
                 // Check if reached final cruise altitude
-                if self.altitude >= (self.flight_plan.cruise_altitude as i32 * 100) {
-                    self.altitude = self.flight_plan.cruise_altitude as i32 * 100;
+                if self.cleared_altitude.is_none() && self.altitude >= (self.flight_plan.cruise_altitude_feet()) {
+                    self.altitude = self.flight_plan.cruise_altitude_feet();
                     self.phase = FlightPhase::Cruise;
                     self.target_speed = self.flight_plan.cruise_speed;
                     tracing::info!("[{}] Reached cruise FL{:03}", self.callsign, self.flight_plan.cruise_altitude);
@@ -307,12 +525,56 @@ impl Aircraft {
             
             FlightPhase::Cruise => {
                 // Maintain altitude and navigate
-                self.navigate_to_next_fix(fix_db, delta_time, sim_config);
+                self.navigate(fix_db, delta_time, sim_config);
+
+                self.plan_descent(fix_db, sim_config);
                 
-                // Accelerate to cruise speed
-                if self.ground_speed < self.target_speed {
-                    self.ground_speed += (5.0 * delta_time) as u32;
+                // Accelerate to cruise speed, or slow ahead of the descent
+                if self.airspeed < self.target_speed {
+                    self.airspeed += (5.0 * delta_time) as u32;
+                } else if self.airspeed > self.target_speed {
+                    self.airspeed = self.airspeed
+                        .saturating_sub((5.0 * delta_time) as u32)
+                        .max(self.target_speed);
+                }
+            }
+            
+            FlightPhase::Descending => {
+                // Descend to the cleared level and hold it until the next clearance
+                let standard_rate_fpm = if self.altitude > 10000 {
+                    sim_config.high_descent_rate
+                } else {
+                    sim_config.descent_rate
+                };
+                let descent_rate_fpm = self.continuous_descent_rate(fix_db, standard_rate_fpm)
+                    .unwrap_or(standard_rate_fpm);
+                self.step_towards_target_altitude(descent_rate_fpm, delta_time);
+
+                if self.altitude <= 10000 && self.target_speed > 250 && !self.speed_assigned() {
+                    self.target_speed = 250;
+                }
+
+                // Level at a new cruise level filed in the route
+                if self.cleared_altitude.is_none()
+                    && self.target_altitude == self.flight_plan.cruise_altitude_feet()
+                    && self.altitude == self.target_altitude
+                {
+                    self.phase = FlightPhase::Cruise;
+                    self.target_speed = self.flight_plan.cruise_speed;
+                    tracing::info!("[{}] Reached cruise FL{:03}", self.callsign, self.flight_plan.cruise_altitude);
                 }
+                if self.airspeed > self.target_speed {
+                    self.airspeed = self.airspeed
+                        .saturating_sub((5.0 * delta_time) as u32)
+                        .max(self.target_speed);
+                }
+
+                self.navigate(fix_db, delta_time, sim_config);
+            }
+
+            FlightPhase::Approach => {
+                // Vertical profile and speed are flown by the ILS
+                self.navigate(fix_db, delta_time, sim_config);
             }
             
             _ => {
@@ -321,108 +583,1970 @@ impl Aircraft {
         }
         
         // Update position based on heading and speed
-        self.update_position(delta_time);
+        self.update_position(delta_time, &sim_config.wind);
     }
 
-    /// Navigate towards the next fix
-    fn navigate_to_next_fix(&mut self, fix_db: &FixDatabase, delta_time: f64, sim_config: &crate::config::SimulationConfig) {
-        if self.current_fix_index >= self.route_fixes.len() {
-            return;
+    /// Check if the aircraft is still on the ground (including the takeoff roll)
+    pub fn is_on_ground(&self) -> bool {
+        matches!(
+            self.phase,
+            FlightPhase::OnGround | FlightPhase::HoldingShort | FlightPhase::LinedUp | FlightPhase::Departing
+        )
+    }
+
+    /// Hold short of the departure runway
+    pub fn hold_short(&mut self) {
+        match self.phase {
+            FlightPhase::OnGround | FlightPhase::HoldingShort => {
+                self.phase = FlightPhase::HoldingShort;
+                tracing::info!("[{}] Holding short runway {}", self.callsign, self.departure_runway);
+            }
+            _ => tracing::warn!("[{}] Can't hold short while {:?}", self.callsign, self.phase),
         }
-        
-        let current_fix = &self.route_fixes[self.current_fix_index];
-        
-        if let Some((fix_lat, fix_lon)) = fix_db.get(current_fix) {
-            // Calculate distance to fix
-            let distance = haversine_nm(self.latitude, self.longitude, *fix_lat, *fix_lon);
-            
-            // Calculate required heading to fix
-            let required_heading = heading_from_to(self.latitude, self.longitude, *fix_lat, *fix_lon);
-            
-            // If within 0.5 NM of fix, move to next fix
-            if distance < 0.5 {
-                self.current_fix_index += 1;
-                
-                if self.current_fix_index < self.route_fixes.len() {
-                    let next_fix = &self.route_fixes[self.current_fix_index];
-                    if let Some((next_lat, next_lon)) = fix_db.get(next_fix) {
-                        self.target_heading = heading_from_to(self.latitude, self.longitude, *next_lat, *next_lon);
-                        tracing::info!("[{}] Passed {}, turning to next waypoint: {}", 
-                                      self.callsign, current_fix, next_fix);
-                    }
-                }
+    }
+
+    /// Line up on the departure runway and wait for takeoff clearance
+    pub fn line_up_and_wait(&mut self) {
+        match self.phase {
+            FlightPhase::OnGround | FlightPhase::HoldingShort | FlightPhase::LinedUp => {
+                self.phase = FlightPhase::LinedUp;
+                self.heading = self.departure_heading;
+                tracing::info!("[{}] Lining up runway {}", self.callsign, self.departure_runway);
             }
-            
-            // Always turn towards the current fix (whether we just updated it or not)
-            self.turn_towards(required_heading, delta_time, sim_config.turn_rate);
+            _ => tracing::warn!("[{}] Can't line up while {:?}", self.callsign, self.phase),
         }
     }
 
-    /// Turn towards a target heading
-    fn turn_towards(&mut self, target: i32, delta_time: f64, turn_rate: f64) {
-        let diff = ((target - self.heading + 540) % 360) - 180;
-        
-        if diff.abs() < 2 {
-            self.heading = target;
-        } else {
-            // Calculate turn amount as float first, then convert to int (fixes rounding to 0)
-            let turn_amount_f = turn_rate * delta_time;
-            let turn_amount = turn_amount_f.max(1.0) as i32;  // Ensure at least 1 degree per update
-            
-            if diff > 0 {
-                self.heading += turn_amount.min(diff);
-            } else {
-                self.heading -= turn_amount.min(diff.abs());
+    /// Clear the aircraft for takeoff, starting the takeoff roll
+    pub fn clear_for_takeoff(&mut self) {
+        match self.phase {
+            FlightPhase::OnGround | FlightPhase::HoldingShort | FlightPhase::LinedUp => {
+                tracing::info!("[{}] Cleared for takeoff runway {}", self.callsign, self.departure_runway);
+                self.start_takeoff_roll();
             }
-            
-            // Normalize heading
-            self.heading = (self.heading + 360) % 360;
+            _ => tracing::warn!("[{}] Can't take off while {:?}", self.callsign, self.phase),
         }
     }
 
-    /// Update position based on current heading and ground speed
-    fn update_position(&mut self, delta_time: f64) {
-        if self.ground_speed == 0 {
+    fn start_takeoff_roll(&mut self) {
+        self.phase = FlightPhase::Departing;
+        self.heading = self.departure_heading;
+        self.airspeed = 10;
+        tracing::info!("[{}] Starting takeoff roll", self.callsign);
+    }
+
+    /// Start the arrival descent at top of descent, slowing to the descent speed
+    /// `descent_anticipation_nm` beforehand so TOD is crossed at that speed.
+    /// Only arrivals flying a STAR descend on their own, measured along the route, and
+    /// only without an ATC level. Arrivals that will join final without a STAR measure
+    /// straight to the aerodrome instead.
+    fn plan_descent(&mut self, fix_db: &FixDatabase, sim_config: &crate::config::SimulationConfig) {
+        if self.cleared_altitude.is_some() {
             return;
         }
-        
-        // Distance traveled in nautical miles
-        let distance_nm = (self.ground_speed as f64 / 3600.0) * delta_time;
-        
-        // Update position
-        let (new_lat, new_lon) = position_bearing_distance(
-            self.latitude,
-            self.longitude,
-            self.heading as f64,
-            distance_nm
-        );
-        
-        self.latitude = new_lat;
-        self.longitude = new_lon;
+
+        let joins_final = sim_config.starless_arrivals == crate::config::StarlessArrivals::StraightIn
+            || self.is_local_flight();
+        let remaining_nm = match self.destination {
+            _ if self.has_star() => self.route_distance_remaining(fix_db),
+            Some((lat, lon)) if joins_final => Some(haversine_nm(self.latitude, self.longitude, lat, lon)),
+            _ => None,
+        };
+        let Some(remaining_nm) = remaining_nm else {
+            return;
+        };
+
+        // 3nm per 1000ft to lose
+        let target_altitude = sim_config.arrival_descent_altitude;
+        let tod_nm = (self.altitude - target_altitude).max(0) as f64 / 1000.0 * 3.0;
+
+        if remaining_nm <= tod_nm {
+            self.target_altitude = target_altitude;
+            if !self.speed_assigned() {
+                self.target_speed = self.target_speed.min(sim_config.descent_speed);
+            }
+            self.phase = FlightPhase::Descending;
+            tracing::info!("[{}] Top of descent, descending to {} ft", self.callsign, target_altitude);
+        } else if remaining_nm <= tod_nm + sim_config.descent_anticipation_nm
+            && self.target_speed > sim_config.descent_speed
+            && !self.speed_assigned()
+        {
+            self.target_speed = sim_config.descent_speed;
+            tracing::info!("[{}] Slowing to {}kt ahead of descent", self.callsign, sim_config.descent_speed);
+        }
     }
 
-    /// Format position for FSD protocol
-    pub fn to_fsd_position(&self) -> String {
-        // FSD format: @N:<callsign>:<squawk>:<rating>:<lat>:<lon>:<alt>:<groundspeed>:<heading>
-        format!(
-            "@N:{}:{}:1:{}:{}:{}:{}:{}",
-            self.callsign,
-            self.squawk,
-            format!("{:.6}", self.latitude),
-            format!("{:.6}", self.longitude),
-            self.altitude,
-            self.ground_speed,
-            self.heading
-        )
+    /// Descent rate (ft/min) that reaches the target altitude exactly at the end of the
+    /// route, no steeper than `max_rate_fpm`, while flying a continuous descent
+    fn continuous_descent_rate(&self, fix_db: &FixDatabase, max_rate_fpm: f64) -> Option<f64> {
+        if !self.continuous_descent || self.cleared_altitude.is_some() || self.ground_speed == 0 {
+            return None;
+        }
+
+        let remaining_nm = self.route_distance_remaining(fix_db)?;
+        let minutes = remaining_nm / self.ground_speed as f64 * 60.0;
+        let to_lose = (self.altitude - self.target_altitude).max(0) as f64;
+
+        Some((to_lose / minutes.max(1.0 / 60.0)).min(max_rate_fpm.abs()))
     }
 
-    /// Get current fix being navigated to
-    pub fn current_fix(&self) -> Option<&str> {
-        self.route_fixes.get(self.current_fix_index).map(|s| s.as_str())
+    /// Distance (NM) still to fly: to the current fix, then along the rest of the route
+    pub fn route_distance_remaining(&self, fix_db: &FixDatabase) -> Option<f64> {
+        let remaining = self.route_fixes.get(self.current_fix_index..)?;
+        if remaining.is_empty() {
+            return None;
+        }
+
+        Some(self.distance_along(remaining, fix_db))
     }
 
-    /// Check if aircraft has completed its route
-    pub fn is_route_complete(&self) -> bool {
-        self.current_fix_index >= self.route_fixes.len()
+    /// Distance (NM) along the route to a fix still ahead, if it is on the route
+    pub fn route_distance_to(&self, fix: &str, fix_db: &FixDatabase) -> Option<f64> {
+        let remaining = self.route_fixes.get(self.current_fix_index..)?;
+        let end = remaining.iter().position(|f| f == fix)?;
+
+        Some(self.distance_along(&remaining[..=end], fix_db))
+    }
+
+    /// Distance (NM) from the present position through a list of fixes
+    fn distance_along(&self, fixes: &[String], fix_db: &FixDatabase) -> f64 {
+        let resolved = fix_db.resolve_route(fixes, (self.latitude, self.longitude));
+        let mut last = (self.latitude, self.longitude);
+        let mut distance = 0.0;
+        for (_, coords) in resolved {
+            distance += haversine_nm(last.0, last.1, coords.0, coords.1);
+            last = coords;
+        }
+        distance
+    }
+
+    /// Clear the aircraft to climb or descend to an altitude (feet)
+    pub fn set_altitude(&mut self, altitude: i32) {
+        self.cleared_altitude = Some(altitude);
+        self.target_altitude = altitude;
+
+        // Still on the runway: the clearance becomes the initial climb limit
+        if self.is_on_ground() {
+            return;
+        }
+
+        if altitude < self.altitude {
+            self.phase = FlightPhase::Descending;
+        } else if altitude > self.altitude {
+            self.phase = FlightPhase::Climbing;
+        }
+
+        tracing::info!("[{}] Cleared to {} ft", self.callsign, altitude);
+    }
+
+    /// Assign a Mach number, answering "unable" below the minimum Mach altitude
+    pub fn assign_mach(&mut self, mach: f64, sim_config: &crate::config::SimulationConfig) -> anyhow::Result<()> {
+        if self.altitude < sim_config.min_mach_altitude {
+            anyhow::bail!(
+                "{} unable Mach {:.2} below FL{:03}",
+                self.callsign,
+                mach,
+                sim_config.min_mach_altitude / 100
+            );
+        }
+
+        self.assigned_speed = None;
+        self.assigned_mach = Some(mach);
+        self.apply_assigned_mach(sim_config);
+        tracing::info!("[{}] Assigned Mach {:.2} ({}kt IAS)", self.callsign, mach,
+                      mach_to_ias(mach, self.altitude as f64).round());
+        Ok(())
+    }
+
+    /// Convert the assigned Mach to a target speed at the current altitude. The speed
+    /// loop flies true airspeed, so this has to follow the aircraft as it changes level;
+    /// the Mach is dropped once the aircraft descends below the Mach altitude.
+    fn apply_assigned_mach(&mut self, sim_config: &crate::config::SimulationConfig) {
+        let Some(mach) = self.assigned_mach else {
+            return;
+        };
+
+        if self.altitude < sim_config.min_mach_altitude {
+            tracing::info!("[{}] Below FL{:03}, resuming normal speed", self.callsign,
+                          sim_config.min_mach_altitude / 100);
+            self.assigned_mach = None;
+            return;
+        }
+
+        self.target_speed = mach_to_tas(mach, self.altitude as f64).round() as u32;
+    }
+
+    /// Assign an indicated airspeed (kt), replacing any assigned Mach
+    pub fn assign_speed(&mut self, ias: u32) {
+        self.assigned_mach = None;
+        self.assigned_speed = Some(ias);
+        self.apply_assigned_speed();
+        tracing::info!("[{}] Assigned {}kt", self.callsign, ias);
+    }
+
+    /// Convert the assigned indicated airspeed to a target true airspeed at the current
+    /// altitude. On the ILS the approach speed schedule takes over.
+    fn apply_assigned_speed(&mut self) {
+        let Some(ias) = self.assigned_speed else {
+            return;
+        };
+        if matches!(self.nav_mode, NavMode::Ils(_)) {
+            return;
+        }
+
+        self.target_speed = SpeedTarget::Ias(ias).tas(self.altitude as f64).round() as u32;
+    }
+
+    /// Whether ATC have assigned a speed or Mach number
+    fn speed_assigned(&self) -> bool {
+        self.assigned_speed.is_some() || self.assigned_mach.is_some()
+    }
+
+    /// Fly a heading assigned by ATC, leaving the route
+    pub fn fly_heading(&mut self, heading: f64) {
+        tracing::info!("[{}] Fly heading {:03.0}", self.callsign, heading);
+        self.nav_mode = NavMode::Heading(normalize_heading(heading));
+    }
+
+    /// Resume own navigation: leave a hold or assigned heading and continue along the route
+    pub fn resume_navigation(&mut self) {
+        if matches!(self.nav_mode, NavMode::Heading(_) | NavMode::Hold(_) | NavMode::Intercept(_) | NavMode::MissedApproach(_)) {
+            tracing::info!("[{}] Resuming own navigation", self.callsign);
+            self.nav_mode = NavMode::FlightPlan;
+        }
+    }
+
+    /// Current Mach number
+    pub fn mach(&self) -> f64 {
+        tas_to_mach(self.airspeed as f64, self.altitude as f64)
+    }
+
+    /// Current indicated airspeed (kt)
+    pub fn indicated_airspeed(&self) -> f64 {
+        mach_to_ias(self.mach(), self.altitude as f64)
+    }
+
+    /// Move towards the target altitude at the given vertical rate (ft/min),
+    /// levelling off exactly at the target rather than overshooting it
+    fn step_towards_target_altitude(&mut self, rate_fpm: f64, delta_time: f64) {
+        let step = ((rate_fpm.abs() / 60.0) * delta_time) as i32;
+        let diff = self.target_altitude - self.altitude;
+
+        if diff.abs() <= step {
+            self.altitude = self.target_altitude;
+        } else {
+            self.altitude += step * diff.signum();
+        }
+    }
+
+    /// Approach currently being flown: the assigned one, else the requested one
+    pub fn active_approach(&self) -> Option<&Approach> {
+        self.assigned_approach.as_ref().or(self.requested_approach.as_ref())
+    }
+
+    /// Set the approach the pilot would like to fly
+    pub fn request_approach(&mut self, approach: Approach) {
+        tracing::info!("[{}] Requesting {}", self.callsign, approach);
+        self.requested_approach = Some(approach);
+        self.expand_star();
+    }
+
+    /// Assign an approach, overriding any request, and re-expand the STAR for its runway
+    pub fn assign_approach(&mut self, approach: Approach) {
+        if let Some(requested) = &self.requested_approach {
+            if *requested != approach {
+                tracing::info!("[{}] Requested {}, assigned {}", self.callsign, requested, approach);
+            }
+        }
+        self.assigned_approach = Some(approach);
+        self.expand_star();
+    }
+
+    /// Replace the STAR portion of the route with the STAR for the active approach's runway,
+    /// keeping the aircraft's progress along it
+    fn expand_star(&mut self) {
+        let runway = self.active_approach().map(|a| a.runway.clone());
+        let star_fixes = Route::star_fixes(&self.flight_plan.arrival, &self.flight_plan.route, runway.as_deref());
+        if star_fixes.is_empty() {
+            return;
+        }
+
+        let current_fix = self.current_fix().map(|s| s.to_string());
+        self.route_fixes.truncate(self.star_start);
+        for fix in star_fixes {
+            if self.route_fixes.last() != Some(&fix) {
+                self.route_fixes.push(fix);
+            }
+        }
+
+        if self.current_fix_index >= self.star_start {
+            // Rejoin at the same fix if the new STAR has it, else at the same point along it
+            let rejoin = current_fix.and_then(|fix| {
+                self.route_fixes[self.star_start..].iter().position(|f| *f == fix)
+            });
+            self.current_fix_index = match rejoin {
+                Some(offset) => self.star_start + offset,
+                None => self.current_fix_index.min(self.route_fixes.len().saturating_sub(1)),
+            };
+        }
+
+        tracing::info!("[{}] Route now {}", self.callsign, self.route_fixes.join(" "));
+    }
+
+    /// Enter the published hold at a fix (or a standard one if it has none),
+    /// optionally with an EFC `efc_minutes` from now
+    pub fn hold_at(&mut self, fix: &str, efc_minutes: Option<f64>, holds: &HoldDatabase) {
+        let mut hold = Hold::published_or_standard(fix, holds);
+        hold.efc = efc_minutes.map(|m| self.flight_time + m * 60.0);
+
+        tracing::info!("[{}] Holding at {}{}", self.callsign, hold.fix,
+                      efc_minutes.map(|m| format!(", EFC in {:.0} min", m)).unwrap_or_default());
+        self.nav_mode = NavMode::Hold(hold);
+    }
+
+    /// Leave the hold and continue along the route
+    pub fn release_hold(&mut self) {
+        if let NavMode::Hold(hold) = &self.nav_mode {
+            tracing::info!("[{}] Leaving hold at {}", self.callsign, hold.fix);
+            self.nav_mode = NavMode::FlightPlan;
+        }
+    }
+
+    /// Steer according to the current navigation mode
+    fn navigate(&mut self, fix_db: &FixDatabase, delta_time: f64, sim_config: &crate::config::SimulationConfig) {
+        match self.nav_mode {
+            NavMode::FlightPlan => self.navigate_to_next_fix(fix_db, delta_time, sim_config),
+            NavMode::Hold(_) => self.fly_hold(fix_db, delta_time, sim_config),
+            NavMode::Ils(_) => self.fly_ils(delta_time, sim_config),
+            NavMode::Intercept(_) => self.fly_intercept(delta_time, sim_config),
+            NavMode::Heading(heading) => self.turn_towards(heading, delta_time, sim_config.turn_rate),
+            NavMode::MissedApproach(_) => self.fly_missed_approach(fix_db, delta_time, sim_config),
+        }
+    }
+
+    /// Fly a heading to intercept the leg from `from_fix` to `to_fix` on the route at
+    /// `intercept_angle` degrees, then follow the route from there. Returns the heading.
+    pub fn intercept_leg(&mut self, from_fix: &str, to_fix: &str, intercept_angle: f64, fix_db: &FixDatabase) -> anyhow::Result<f64> {
+        let to_index = self.route_fixes[self.current_fix_index.min(self.route_fixes.len())..]
+            .iter()
+            .position(|fix| fix == to_fix)
+            .map(|offset| self.current_fix_index + offset)
+            .ok_or_else(|| anyhow::anyhow!("{} is not ahead on the route of {}", to_fix, self.callsign))?;
+        let from = fix_db.position(from_fix, (self.latitude, self.longitude))
+            .ok_or_else(|| anyhow::anyhow!("Unknown fix {}", from_fix))?;
+        let to = fix_db.position(to_fix, from)
+            .ok_or_else(|| anyhow::anyhow!("Unknown fix {}", to_fix))?;
+
+        let heading = intercept_heading(self.latitude, self.longitude, from, to, intercept_angle);
+        tracing::info!("[{}] Heading {:03.0} to intercept {} - {}", self.callsign, heading, from_fix, to_fix);
+        self.nav_mode = NavMode::Intercept(LegIntercept { from, to, to_index, heading });
+        Ok(heading)
+    }
+
+    /// Hold the intercept heading until established on the leg, then rejoin the route
+    fn fly_intercept(&mut self, delta_time: f64, sim_config: &crate::config::SimulationConfig) {
+        let NavMode::Intercept(intercept) = &self.nav_mode else {
+            return;
+        };
+
+        if cross_track_nm(self.latitude, self.longitude, intercept.from, intercept.to).abs() < LEG_ESTABLISHED_NM {
+            tracing::info!("[{}] Established on the route towards {}", self.callsign,
+                          self.route_fixes[intercept.to_index]);
+            self.current_fix_index = intercept.to_index;
+            self.nav_mode = NavMode::FlightPlan;
+            return;
+        }
+
+        let heading = intercept.heading;
+        self.turn_towards(heading, delta_time, sim_config.turn_rate);
+    }
+
+    /// Clear the aircraft for the ILS. On a radar vector it holds its present heading
+    /// until the localizer captures; otherwise it steers for the centerline.
+    pub fn clear_ils(&mut self, mut localizer: Localizer) {
+        tracing::info!("[{}] Cleared ILS {}", self.callsign, localizer.runway);
+        if let NavMode::Heading(heading) = self.nav_mode {
+            localizer.intercept_heading = Some(heading);
+        }
+        self.assigned_approach = Some(Approach::new(ApproachType::Ils, &localizer.runway));
+        self.nav_mode = NavMode::Ils(localizer);
+    }
+
+    /// Join final for a runway with no STAR or approach clearance to follow: descend to
+    /// the ILS intercept altitude and, if too close in to lose the height, route out along
+    /// the extended centerline first, then fly the ILS
+    pub fn straight_in(&mut self, mut localizer: Localizer, sim_config: &crate::config::SimulationConfig) {
+        let intercept_altitude = sim_config.ils_spawn_altitude;
+
+        // 3nm per 1000ft to lose
+        let join_nm = ((self.altitude - intercept_altitude).max(0) as f64 / 1000.0 * 3.0)
+            .max(sim_config.ils_spawn_range_nm);
+        localizer.join_nm = Some(join_nm);
+
+        tracing::info!("[{}] No STAR, joining final for {} from {:.0}nm", self.callsign, localizer.runway, join_nm);
+        self.route_fixes.push(format!("RW{}", localizer.runway));
+        self.star_start = self.route_fixes.len();
+        self.set_altitude(intercept_altitude);
+        self.clear_ils(localizer);
+    }
+
+    /// Current climb rate (ft/min): the type's performance at this altitude, scaled by
+    /// the climb profile for its wake category while it is still heavy with fuel
+    pub fn climb_rate(&self, sim_config: &crate::config::SimulationConfig) -> f64 {
+        let base = match &self.performance {
+            Some(performance) => performance.interpolated_rate_of_climb(self.altitude as f64),
+            // Realistic climb rate: 1500-2500 ft/min depending on altitude
+            None if self.altitude < 10000 => 2000.0,  // Higher rate at lower altitudes
+            None if self.altitude < 20000 => 1800.0,  // Moderate rate
+            None => 1500.0,  // Lower rate at higher altitudes
+        };
+
+        let factor = match (self.takeoff_time, sim_config.climb_profiles.get(&WakeCategory::for_type(&self.aircraft_type))) {
+            (Some(takeoff_time), Some(profile)) => profile.factor((self.flight_time - takeoff_time) / 60.0),
+            _ => 1.0,
+        };
+
+        base * factor
+    }
+
+    /// True airspeed (kt) for a phase of flight from the type's performance data,
+    /// keeping to 250kt IAS below FL100
+    fn performance_speed(&self, phase: SpeedPhase) -> Option<u32> {
+        let altitude = self.altitude as f64;
+        let mut tas = self.performance.as_ref()?.target_speed_at_altitude(altitude, phase).tas(altitude);
+        if self.altitude < 10000 {
+            tas = tas.min(SpeedTarget::Ias(250).tas(altitude));
+        }
+        Some(tas.round() as u32)
+    }
+
+    /// Altitude (feet) expected after `seconds` more of the current climb or descent,
+    /// levelling at the target altitude
+    pub fn predicted_altitude(&self, seconds: f64, sim_config: &crate::config::SimulationConfig) -> i32 {
+        let rate_fpm = match self.phase {
+            FlightPhase::Climbing => self.climb_rate(sim_config),
+            FlightPhase::Descending if self.altitude > 10000 => sim_config.high_descent_rate,
+            FlightPhase::Descending => sim_config.descent_rate,
+            _ => return self.altitude,
+        };
+
+        let predicted = self.altitude + (rate_fpm * seconds / 60.0) as i32;
+        if rate_fpm > 0.0 {
+            predicted.min(self.target_altitude)
+        } else {
+            predicted.max(self.target_altitude)
+        }
+    }
+
+    /// Whether the route includes a STAR
+    pub fn has_star(&self) -> bool {
+        self.star_start < self.route_fixes.len()
+    }
+
+    /// Intercept and track the localizer, then descend on the glideslope to the threshold
+    fn fly_ils(&mut self, delta_time: f64, sim_config: &crate::config::SimulationConfig) {
+        let NavMode::Ils(localizer) = &mut self.nav_mode else {
+            return;
+        };
+
+        let (along_nm, offset_nm) = localizer.offsets_nm(self.latitude, self.longitude);
+
+        // Too close in or too far off to one side: head for the joining point before
+        // turning onto the localizer
+        if let Some(join_nm) = localizer.join_nm {
+            if along_nm < join_nm - 2.0 || offset_nm.abs() > 2.0 {
+                let (join_lat, join_lon) = localizer.centerline_point(join_nm);
+                let target = bearing_from_to(self.latitude, self.longitude, join_lat, join_lon);
+                self.turn_towards(target, delta_time, sim_config.turn_rate);
+                return;
+            }
+            localizer.join_nm = None;
+        }
+
+        // On a vector, hold the heading until within the turn onto the course
+        if let Some(heading) = localizer.intercept_heading {
+            let track_change = heading_difference(heading, localizer.course).abs().min(90.0);
+            let radius_nm = self.ground_speed as f64 / 3600.0 / sim_config.turn_rate.to_radians();
+            let capture_nm = (radius_nm * (1.0 - track_change.to_radians().cos())).max(LEG_ESTABLISHED_NM);
+            if offset_nm.abs() > capture_nm {
+                self.turn_towards(heading, delta_time, sim_config.turn_rate);
+                return;
+            }
+            tracing::info!("[{}] Localizer {} captured, turning onto {:03.0}", self.callsign, localizer.runway, localizer.course);
+            localizer.intercept_heading = None;
+        }
+
+        // Steer back towards the centerline, intercepting at up to 30 degrees
+        let correction = (offset_nm * 60.0).clamp(-30.0, 30.0);
+        let target = normalize_heading(localizer.course - correction);
+
+        if !localizer.established && offset_nm.abs() < 0.1 {
+            localizer.established = true;
+            self.phase = FlightPhase::Approach;
+            tracing::info!("[{}] Established on the localizer {}", self.callsign, localizer.runway);
+        }
+
+        let established = localizer.established;
+        let glideslope = localizer.glideslope_altitude(along_nm);
+        let runway = localizer.runway.clone();
+
+        self.turn_towards(target, delta_time, sim_config.turn_rate);
+
+        if !established {
+            return;
+        }
+
+        // Far too high to get down: abandon the approach
+        if self.altitude > glideslope + GO_AROUND_ABOVE_GLIDESLOPE_FT {
+            tracing::info!("[{}] {}ft above the glideslope", self.callsign, self.altitude - glideslope);
+            self.go_around(sim_config);
+            return;
+        }
+
+        // Hold level below the glideslope until it is intercepted. Above it, descend
+        // towards it no faster than a normal descent, then follow it down
+        let above_ft = self.altitude - glideslope;
+        if above_ft > GLIDESLOPE_CAPTURE_FT {
+            let step_ft = (-sim_config.descent_rate / 60.0 * delta_time).round() as i32;
+            self.altitude = (self.altitude - step_ft.max(1)).max(glideslope);
+        } else if above_ft > 0 {
+            self.altitude = glideslope;
+        }
+
+        // Slow down as the flaps come out, by the schedule for our category, and
+        // further if held back for spacing
+        let category = WakeCategory::for_type(&self.aircraft_type);
+        let scheduled = match sim_config.approach_schedules.get(&category) {
+            Some(schedule) => schedule.speed_at(along_nm),
+            None => ApproachSchedule::default().speed_at(along_nm),
+        }.round() as u32;
+        self.target_speed = self.spacing_speed.map_or(scheduled, |speed| speed.min(scheduled));
+        if self.airspeed > self.target_speed {
+            self.airspeed = self.airspeed
+                .saturating_sub((2.0 * delta_time).max(1.0) as u32)
+                .max(self.target_speed);
+        }
+
+        if along_nm < 0.2 {
+            tracing::info!("[{}] Landed runway {}", self.callsign, runway);
+            self.phase = FlightPhase::Landing;
+            self.current_fix_index = self.route_fixes.len();
+        }
+    }
+
+    /// Go around from the ILS: climb to the missed-approach altitude and fly the
+    /// published missed approach for the runway. Returns false if not on an ILS.
+    pub fn go_around(&mut self, sim_config: &crate::config::SimulationConfig) -> bool {
+        let NavMode::Ils(localizer) = &self.nav_mode else {
+            return false;
+        };
+        let key = format!("{} {}", self.flight_plan.arrival, localizer.runway);
+        let track = sim_config.missed_approaches.get(&key).cloned().unwrap_or_default();
+        tracing::info!("[{}] Going around runway {}{}", self.callsign, localizer.runway,
+                       if track.is_empty() { String::new() } else { format!(" via {}", track.join(" ")) });
+
+        let missed_approach = MissedApproach::new(localizer.clone(), track);
+        self.nav_mode = NavMode::MissedApproach(missed_approach);
+        self.set_altitude(sim_config.missed_approach_altitude.max(self.altitude));
+        self.phase = FlightPhase::Climbing;
+        if !self.speed_assigned() {
+            self.target_speed = 220;
+        }
+        true
+    }
+
+    /// Climb straight ahead, then follow the missed-approach track. At its end, fly the
+    /// approach again if configured to, otherwise hold at its last fix, or on runway
+    /// heading at the missed-approach altitude without one, at the last fix of the arrival.
+    fn fly_missed_approach(&mut self, fix_db: &FixDatabase, delta_time: f64, sim_config: &crate::config::SimulationConfig) {
+        let NavMode::MissedApproach(missed_approach) = &mut self.nav_mode else {
+            return;
+        };
+        let course = missed_approach.localizer.course;
+
+        if !missed_approach.turn_height_reached(self.altitude) {
+            self.turn_towards(course, delta_time, sim_config.turn_rate);
+            return;
+        }
+
+        if let Some(fix) = missed_approach.next_fix().map(str::to_string) {
+            match fix_db.position(&fix, (self.latitude, self.longitude)) {
+                Some((fix_lat, fix_lon)) => {
+                    if haversine_nm(self.latitude, self.longitude, fix_lat, fix_lon) < 1.0 {
+                        missed_approach.next += 1;
+                    }
+                    let bearing = bearing_from_to(self.latitude, self.longitude, fix_lat, fix_lon);
+                    self.turn_towards(bearing, delta_time, sim_config.turn_rate);
+                }
+                None => {
+                    tracing::warn!("[{}] Missed approach fix {} not found in nav database", self.callsign, fix);
+                    missed_approach.next += 1;
+                }
+            }
+            return;
+        }
+
+        // Without a published track, runway heading up to the missed-approach altitude
+        if missed_approach.track.is_empty() && self.altitude < self.target_altitude {
+            self.turn_towards(course, delta_time, sim_config.turn_rate);
+            return;
+        }
+
+        let mut localizer = missed_approach.localizer.clone();
+        if sim_config.rejoin_after_missed_approach {
+            localizer.established = false;
+            localizer.intercept_heading = None;
+            localizer.join_nm = Some(sim_config.ils_spawn_range_nm);
+            self.set_altitude(sim_config.ils_spawn_altitude);
+            tracing::info!("[{}] Rejoining for another approach", self.callsign);
+            self.clear_ils(localizer);
+            return;
+        }
+
+        let hold_fix = missed_approach.track.last().cloned().or_else(|| {
+            self.route_fixes.iter().rev().find(|fix| !fix.starts_with("RW")).cloned()
+        });
+        match hold_fix {
+            Some(fix) => {
+                tracing::info!("[{}] Missed approach complete, holding at {}", self.callsign, fix);
+                self.nav_mode = NavMode::Hold(Hold::standard(&fix));
+            }
+            None => self.fly_heading(course),
+        }
+    }
+
+    /// Fly the racetrack of the current hold, leaving it once the EFC is reached
+    fn fly_hold(&mut self, fix_db: &FixDatabase, delta_time: f64, sim_config: &crate::config::SimulationConfig) {
+        let NavMode::Hold(mut hold) = std::mem::replace(&mut self.nav_mode, NavMode::FlightPlan) else {
+            return;
+        };
+
+        if hold.efc_reached(self.flight_time) {
+            tracing::info!("[{}] EFC reached, leaving hold at {}", self.callsign, hold.fix);
+            return;
+        }
+
+        let Some((fix_lat, fix_lon)) = fix_db.position(&hold.fix, (self.latitude, self.longitude)) else {
+            tracing::warn!("[{}] Hold fix {} not found in nav database", self.callsign, hold.fix);
+            self.nav_mode = NavMode::Hold(hold);
+            return;
+        };
+
+        let distance = haversine_nm(self.latitude, self.longitude, fix_lat, fix_lon);
+        let bearing_to_fix = bearing_from_to(self.latitude, self.longitude, fix_lat, fix_lon);
+
+        match hold.leg {
+            HoldLeg::Entry => {
+                self.turn_towards(bearing_to_fix, delta_time, sim_config.turn_rate);
+                if distance < 1.0 {
+                    hold.inbound_course.get_or_insert(self.heading);
+                    hold.leg = HoldLeg::Outbound;
+                    hold.leg_timer = 0.0;
+                }
+            }
+            HoldLeg::Outbound => {
+                let outbound = hold.outbound_course().unwrap_or(self.heading);
+                if self.heading == outbound {
+                    // Leg timing starts once established outbound
+                    hold.leg_timer += delta_time;
+                    if hold.leg_timer >= hold.leg_minutes * 60.0 {
+                        hold.leg = HoldLeg::Inbound;
+                    }
+                } else {
+                    self.turn_in_direction(outbound, hold.turn_direction, delta_time, sim_config.turn_rate);
+                }
+            }
+            HoldLeg::Inbound => {
+                if heading_difference(self.heading, bearing_to_fix).abs() > 30.0 {
+                    self.turn_in_direction(bearing_to_fix, hold.turn_direction, delta_time, sim_config.turn_rate);
+                } else {
+                    self.turn_towards(bearing_to_fix, delta_time, sim_config.turn_rate);
+                }
+                if distance < 1.0 {
+                    hold.leg = HoldLeg::Outbound;
+                    hold.leg_timer = 0.0;
+                }
+            }
+        }
+
+        self.nav_mode = NavMode::Hold(hold);
+    }
+
+    /// Navigate towards the next fix
+    fn navigate_to_next_fix(&mut self, fix_db: &FixDatabase, delta_time: f64, sim_config: &crate::config::SimulationConfig) {
+        if self.current_fix_index >= self.route_fixes.len() {
+            return;
+        }
+        
+        let current_fix = self.route_fixes[self.current_fix_index].clone();
+        
+        if let Some((fix_lat, fix_lon)) = fix_db.position(&current_fix, (self.latitude, self.longitude)) {
+            // Calculate distance to fix
+            let distance = haversine_nm(self.latitude, self.longitude, fix_lat, fix_lon);
+            
+            // Calculate required heading to fix, or along a long leg's great circle
+            let (steer_lat, steer_lon) = self.leg_steering_point(fix_db, (fix_lat, fix_lon), distance)
+                .unwrap_or((fix_lat, fix_lon));
+            let required_heading = bearing_from_to(self.latitude, self.longitude, steer_lat, steer_lon);
+            
+            // Fly by the fix: move to the next one early enough to turn onto its leg
+            let next_leg = self.route_fixes
+                .get(self.current_fix_index + 1)
+                .and_then(|next| fix_db.position(next, (fix_lat, fix_lon)))
+                .map(|(next_lat, next_lon)| bearing_from_to(fix_lat, fix_lon, next_lat, next_lon));
+            let capture_nm = next_leg
+                .map(|track| self.turn_anticipation_nm(heading_difference(required_heading, track), sim_config.turn_rate))
+                .unwrap_or(0.0)
+                .max(FIX_CAPTURE_NM);
+
+            if distance < capture_nm {
+                self.current_fix_index += 1;
+                self.start_level_change(&current_fix);
+                
+                if self.current_fix_index < self.route_fixes.len() {
+                    let next_fix = &self.route_fixes[self.current_fix_index];
+                    if let Some((next_lat, next_lon)) = fix_db.position(next_fix, (fix_lat, fix_lon)) {
+                        self.target_heading = bearing_from_to(self.latitude, self.longitude, next_lat, next_lon);
+                        tracing::info!("[{}] Passed {}, turning to next waypoint: {}", 
+                                      self.callsign, current_fix, next_fix);
+                    }
+                }
+            }
+            
+            // Always turn towards the current fix (whether we just updated it or not)
+            self.turn_towards(required_heading, delta_time, sim_config.turn_rate);
+        }
+    }
+
+    /// On a long leg, the point on its great circle `LEG_LOOKAHEAD_NM` ahead, so drift is
+    /// taken out and the track follows the leg. None on short legs, the first leg, close
+    /// to the fix or well off the leg, where the aircraft flies direct to the fix.
+    fn leg_steering_point(&self, fix_db: &FixDatabase, to: (f64, f64), distance_nm: f64) -> Option<(f64, f64)> {
+        let previous = self.route_fixes.get(self.current_fix_index.checked_sub(1)?)?;
+        let from = fix_db.position(previous, to)?;
+        let leg_nm = haversine_nm(from.0, from.1, to.0, to.1);
+        if leg_nm < LONG_LEG_NM
+            || distance_nm <= LEG_LOOKAHEAD_NM
+            || cross_track_nm(self.latitude, self.longitude, from, to).abs() > LEG_REJOIN_NM
+        {
+            return None;
+        }
+
+        let fraction = ((leg_nm - distance_nm).max(0.0) + LEG_LOOKAHEAD_NM) / leg_nm;
+        Some(intermediate_point(from.0, from.1, to.0, to.1, fraction.min(1.0)))
+    }
+
+    /// Distance (NM) before a fix to start a turn of `track_change` degrees at the turn
+    /// rate, to roll out on the next leg
+    fn turn_anticipation_nm(&self, track_change: f64, turn_rate: f64) -> f64 {
+        // Beyond about 120 degrees the aircraft overflies rather than cutting the corner
+        let track_change = track_change.abs().min(120.0);
+        let radius_nm = self.ground_speed as f64 / 3600.0 / turn_rate.to_radians();
+        radius_nm * (track_change / 2.0).to_radians().tan()
+    }
+
+    /// Start a speed/level change filed at a fix on passing it, unless ATC have cleared a level.
+    /// Descending aircraft only ever step down, holding the filed level until the next descent,
+    /// and aircraft climbing to a SID or stop altitude only take it as their new cruise level.
+    fn start_level_change(&mut self, fix: &str) {
+        let Some(index) = self.level_changes.iter().position(|c| c.fix == fix) else {
+            return;
+        };
+        let change = self.level_changes.remove(index);
+        let old_cruise = self.flight_plan.cruise_altitude_feet();
+        self.flight_plan.cruise_altitude = (change.altitude / 100) as u32;
+        self.flight_plan.cruise_speed = change.speed;
+
+        if self.cleared_altitude.is_some() {
+            return;
+        }
+        match self.phase {
+            // Still under the SID level-off: the new cruise level is climbed to once released
+            FlightPhase::Climbing if self.target_altitude != old_cruise => {}
+            FlightPhase::Climbing if change.altitude > self.altitude => {
+                self.target_altitude = change.altitude;
+            }
+            FlightPhase::Climbing | FlightPhase::Cruise if change.altitude != self.altitude => {
+                self.phase = if change.altitude > self.altitude {
+                    FlightPhase::Climbing
+                } else {
+                    FlightPhase::Descending
+                };
+                self.target_altitude = change.altitude;
+                tracing::info!("[{}] Passed {}, changing level to FL{:03}", self.callsign, fix, change.altitude / 100);
+            }
+            FlightPhase::Cruise => self.target_speed = change.speed,
+            // On the way down, a lower level filed at a fix is a step in the descent
+            FlightPhase::Descending => {
+                if change.altitude < self.altitude {
+                    self.target_altitude = change.altitude;
+                    tracing::info!("[{}] Passed {}, descending to FL{:03}", self.callsign, fix, change.altitude / 100);
+                }
+                if !self.speed_assigned() {
+                    self.target_speed = change.speed;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Turn towards a target heading
+    fn turn_towards(&mut self, target: f64, delta_time: f64, turn_rate: f64) {
+        let diff = heading_difference(self.heading, target);
+        let turn_amount = turn_rate * delta_time;
+
+        if diff.abs() <= turn_amount {
+            self.heading = normalize_heading(target);
+        } else {
+            self.heading = normalize_heading(self.heading + turn_amount.copysign(diff));
+        }
+    }
+
+    /// Turn towards a target heading in a fixed direction, even if it's the long way round
+    fn turn_in_direction(&mut self, target: f64, direction: TurnDirection, delta_time: f64, turn_rate: f64) {
+        let remaining = match direction {
+            TurnDirection::Right => normalize_heading(target - self.heading),
+            TurnDirection::Left => normalize_heading(self.heading - target),
+        };
+        let turn_amount = turn_rate * delta_time;
+
+        self.heading = if remaining <= turn_amount {
+            normalize_heading(target)
+        } else {
+            match direction {
+                TurnDirection::Right => normalize_heading(self.heading + turn_amount),
+                TurnDirection::Left => normalize_heading(self.heading - turn_amount),
+            }
+        };
+    }
+
+    /// Update position based on current heading, airspeed and the local wind
+    fn update_position(&mut self, delta_time: f64, wind_field: &WindField) {
+        if self.airspeed == 0 {
+            self.ground_speed = 0;
+            return;
+        }
+
+        // Wind only acts once airborne
+        let (ground_speed, track) = if self.is_on_ground() {
+            (self.airspeed as f64, self.heading)
+        } else {
+            wind_field
+                .wind_at(self.latitude, self.longitude, self.altitude)
+                .ground_vector(self.heading, self.airspeed as f64)
+        };
+        self.ground_speed = ground_speed.round() as u32;
+        
+        // Distance traveled in nautical miles
+        let distance_nm = (ground_speed / 3600.0) * delta_time;
+        
+        // Update position
+        let (new_lat, new_lon) = position_bearing_distance(
+            self.latitude,
+            self.longitude,
+            track,
+            distance_nm
+        );
+        
+        self.latitude = new_lat;
+        self.longitude = new_lon;
+    }
+
+    /// Code the transponder is showing: the assigned code once instructed, otherwise conspicuity
+    pub fn transponder_code(&self) -> &str {
+        if self.squawking_assigned {
+            &self.squawk
+        } else {
+            CONSPICUITY_SQUAWK
+        }
+    }
+
+    /// Format position for FSD protocol
+    pub fn to_fsd_position(&self) -> String {
+        // FSD format: @N:<callsign>:<squawk>:<rating>:<lat>:<lon>:<alt>:<groundspeed>:<heading>
+        format!(
+            "@N:{}:{}:1:{:.6}:{:.6}:{}:{}:{}",
+            self.callsign,
+            self.transponder_code(),
+            self.latitude,
+            self.longitude,
+            self.altitude,
+            self.ground_speed,
+            self.heading_degrees()
+        )
+    }
+
+    /// Heading rounded to a whole degree (0-359) for display and the wire
+    pub fn heading_degrees(&self) -> i32 {
+        (self.heading.round() as i32).rem_euclid(360)
+    }
+
+    /// Get current fix being navigated to
+    pub fn current_fix(&self) -> Option<&str> {
+        self.route_fixes.get(self.current_fix_index).map(|s| s.as_str())
+    }
+
+    /// Check if aircraft has completed its route
+    pub fn is_route_complete(&self) -> bool {
+        self.current_fix_index >= self.route_fixes.len()
+    }
+
+    /// Whether this is a local flight, returning to the aerodrome it departed from
+    pub fn is_local_flight(&self) -> bool {
+        self.flight_plan.departure == self.flight_plan.arrival
+    }
+}
+
+impl std::fmt::Display for Aircraft {
+    /// Compact one-line summary, e.g.
+    /// `BAW123 A320 Climbing 51.47000,-0.46100 FL065 HDG270 IAS250kt GS270kt -> CPT`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {:?} {:.5},{:.5} FL{:03} HDG{:03} IAS{}kt GS{}kt -> {}",
+            self.callsign,
+            self.aircraft_type,
+            self.phase,
+            self.latitude,
+            self.longitude,
+            self.altitude / 100,
+            self.heading_degrees(),
+            self.indicated_airspeed().round(),
+            self.ground_speed,
+            self.current_fix().unwrap_or("-")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_departure() -> Aircraft {
+        Aircraft::new_departure(
+            "BAW123".to_string(),
+            "A320".to_string(),
+            "2201".to_string(),
+            "EGSS".to_string(),
+            "EHAM".to_string(),
+            "CLN2E/22 CLN P44 RATLO".to_string(),
+            250,
+            "22".to_string(),
+            (51.885, 0.235),
+            220.0,
+        )
+    }
+
+    #[test]
+    fn test_heavy_climb_profile() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut heavy = test_departure();
+        heavy.aircraft_type = "B744".to_string();
+        let mut medium = test_departure();
+
+        for aircraft in [&mut heavy, &mut medium] {
+            aircraft.phase = FlightPhase::Climbing;
+            aircraft.takeoff_time = Some(aircraft.flight_time);
+            aircraft.altitude = 3000;
+        }
+        let initial = heavy.climb_rate(&sim_config) / medium.climb_rate(&sim_config);
+
+        // Half an hour later, at cruise altitude, the heavy has burnt off its fuel
+        for aircraft in [&mut heavy, &mut medium] {
+            aircraft.flight_time += 30.0 * 60.0;
+            aircraft.altitude = 30000;
+        }
+        let high = heavy.climb_rate(&sim_config) / medium.climb_rate(&sim_config);
+
+        assert!(initial < 1.0);
+        assert!(initial < high, "heavy climbs at {:.2} of a medium initially, {:.2} later", initial, high);
+    }
+
+    #[test]
+    fn test_climb_follows_performance_data() {
+        use crate::utils::performance::PerformanceLine;
+
+        let sim_config = crate::config::SimulationConfig::default();
+        let performance = |aircraft_type: &str, rate_of_climb, climb_speed, climb_mach| {
+            let line = |flight_level| PerformanceLine {
+                flight_level,
+                climb_speed,
+                cruise_speed: climb_speed,
+                descent_speed: climb_speed,
+                climb_mach,
+                cruise_mach: climb_mach,
+                descent_mach: climb_mach,
+                rate_of_climb,
+                rate_of_descent: 2000,
+            };
+            Arc::new(AircraftPerformance {
+                aircraft_type: aircraft_type.to_string(),
+                performance_lines: vec![line(0), line(400)],
+            })
+        };
+
+        let mut b738 = test_departure();
+        b738.performance = Some(performance("B738", 2500, 290, 0.78));
+        let mut a388 = test_departure();
+        a388.performance = Some(performance("A388", 1200, 300, 0.85));
+        let mut unknown = test_departure();
+
+        for aircraft in [&mut b738, &mut a388, &mut unknown] {
+            aircraft.phase = FlightPhase::Climbing;
+            aircraft.altitude = 12000;
+            aircraft.target_altitude = 25000;
+            aircraft.airspeed = 300;
+            for _ in 0..60 {
+                aircraft.update(1.0, &FixDatabase::new(), &sim_config);
+            }
+        }
+
+        // A minute's climb at each type's own rate, or the fallback rate without data
+        assert!((b738.altitude - 14500).abs() <= 60, "{}", b738.altitude);
+        assert!((a388.altitude - 13200).abs() <= 60, "{}", a388.altitude);
+        assert!((unknown.altitude - 13800).abs() <= 60, "{}", unknown.altitude);
+
+        // Predicted from the same rate
+        let predicted = a388.predicted_altitude(60.0, &sim_config);
+        assert!((predicted - a388.altitude - 1200).abs() <= 10, "{} from {}", predicted, a388.altitude);
+
+        // Climbing at the type's speed in knots below the crossover, as a true airspeed
+        let expected = SpeedTarget::Ias(290).tas(b738.altitude as f64).round() as u32;
+        assert_eq!(b738.target_speed, expected);
+        assert_eq!(unknown.target_speed, 300);
+    }
+
+    #[test]
+    fn test_step_descents_level_off() {
+        let fix_db = FixDatabase::new();
+        let sim_config = crate::config::SimulationConfig::default();
+
+        let mut aircraft = test_departure();
+        aircraft.phase = FlightPhase::Cruise;
+        aircraft.altitude = 35000;
+        aircraft.airspeed = 450;
+
+        for step in [25000, 15000, 5000] {
+            aircraft.set_altitude(step);
+            assert_eq!(aircraft.phase, FlightPhase::Descending);
+
+            // Descend to the step, never exceeding the configured descent rate
+            let mut ticks = 0;
+            while aircraft.altitude != step {
+                let before = aircraft.altitude;
+                aircraft.update(1.0, &fix_db, &sim_config);
+                let max_rate = if before > 10000 {
+                    sim_config.high_descent_rate
+                } else {
+                    sim_config.descent_rate
+                };
+                assert!(aircraft.altitude < before);
+                assert!((before - aircraft.altitude) as f64 <= max_rate.abs() / 60.0 + 1.0);
+                assert!(aircraft.altitude >= step, "descended through {}", step);
+
+                ticks += 1;
+                assert!(ticks < 1000, "never reached {}", step);
+            }
+
+            // Level at the step until the next clearance
+            for _ in 0..120 {
+                aircraft.update(1.0, &fix_db, &sim_config);
+                assert_eq!(aircraft.altitude, step);
+            }
+        }
+    }
+
+    #[test]
+    fn test_continuous_descent_to_faf() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("ENTRY".to_string(), (52.0, 2.0));
+        fix_db.insert("FAF".to_string(), position_bearing_distance(52.0, 2.0, 270.0, 150.0));
+
+        let mut aircraft = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "2201".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "ENTRY DCT FAF".to_string(),
+            (52.0, 2.0), 270.0, 30000, 280,
+        );
+        aircraft.route_fixes = vec!["ENTRY".to_string(), "FAF".to_string()];
+        aircraft.star_start = 0;
+        aircraft.continuous_descent = true;
+
+        let mut previous = aircraft.altitude;
+        let mut descending = false;
+        for _ in 0..3600 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            if aircraft.is_route_complete() {
+                break;
+            }
+
+            // Once the descent starts, every update loses height: no level-offs on the way
+            if descending {
+                assert!(aircraft.altitude < previous, "levelled at {}ft", aircraft.altitude);
+            }
+            descending |= aircraft.altitude < previous;
+            previous = aircraft.altitude;
+        }
+
+        assert!(aircraft.is_route_complete(), "never reached the FAF");
+        let target = sim_config.arrival_descent_altitude;
+        assert!((aircraft.altitude - target).abs() <= 300, "at {}ft over the FAF", aircraft.altitude);
+    }
+
+    #[test]
+    fn test_intercepts_leg_from_offset() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut fix_db = FixDatabase::new();
+        let from = (52.0, 0.0);
+        let to = position_bearing_distance(52.0, 0.0, 90.0, 60.0);
+        fix_db.insert("ALPHA".to_string(), from);
+        fix_db.insert("BRAVO".to_string(), to);
+
+        // 6nm south of the leg, heading north
+        let mut aircraft = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "2201".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "ALPHA DCT BRAVO".to_string(),
+            position_bearing_distance(52.0, 0.0, 160.0, 6.4), 0.0, 20000, 280,
+        );
+        aircraft.route_fixes = vec!["ALPHA".to_string(), "BRAVO".to_string()];
+
+        let heading = aircraft.intercept_leg("ALPHA", "BRAVO", 30.0, &fix_db).unwrap();
+        assert!((heading - 60.0).abs() < 1.0, "intercept heading {}", heading);
+        assert!(aircraft.intercept_leg("ALPHA", "CHARLIE", 30.0, &fix_db).is_err());
+
+        // Converges on the leg without going direct to BRAVO
+        let mut ticks = 0;
+        while aircraft.nav_mode != NavMode::FlightPlan {
+            let offset = cross_track_nm(aircraft.latitude, aircraft.longitude, from, to);
+            aircraft.update(1.0, &fix_db, &sim_config);
+            assert!(cross_track_nm(aircraft.latitude, aircraft.longitude, from, to) <= offset + 0.01);
+            ticks += 1;
+            assert!(ticks < 600, "never established, {:.1}nm off", offset);
+        }
+        assert_eq!(aircraft.current_fix(), Some("BRAVO"));
+
+        // Then tracks it
+        for _ in 0..120 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            let offset = cross_track_nm(aircraft.latitude, aircraft.longitude, from, to);
+            assert!(offset.abs() < 0.5, "{:.2}nm off the leg", offset);
+        }
+        assert!(heading_difference(aircraft.heading, 90.0).abs() < 5.0, "heading {}", aircraft.heading);
+    }
+
+    #[test]
+    fn test_hold_exits_at_efc() {
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("LAM".to_string(), (51.646, 0.151));
+        let sim_config = crate::config::SimulationConfig::default();
+
+        // Level at FL100, 10nm south of LAM heading north
+        let mut aircraft = test_departure();
+        aircraft.phase = FlightPhase::Cruise;
+        aircraft.altitude = 10000;
+        aircraft.airspeed = 220;
+        aircraft.heading = 0.0;
+        let (lat, lon) = position_bearing_distance(51.646, 0.151, 180.0, 10.0);
+        aircraft.latitude = lat;
+        aircraft.longitude = lon;
+
+        aircraft.hold_at("LAM", Some(10.0), &HoldDatabase::new());
+        let efc = aircraft.flight_time + 600.0;
+
+        let mut flew_outbound = false;
+        while aircraft.flight_time + 1.0 < efc {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            match &aircraft.nav_mode {
+                NavMode::Hold(hold) => {
+                    flew_outbound |= hold.leg == HoldLeg::Outbound && hold.leg_timer > 0.0;
+                }
+                _ => panic!("left the hold before the EFC"),
+            }
+        }
+        assert!(flew_outbound, "never flew the outbound leg");
+
+        // Still within a few miles of the fix after ten minutes of holding
+        let distance = haversine_nm(aircraft.latitude, aircraft.longitude, 51.646, 0.151);
+        assert!(distance < 8.0, "drifted {:.1}nm from the fix", distance);
+
+        aircraft.update(1.0, &fix_db, &sim_config);
+        assert_eq!(aircraft.nav_mode, NavMode::FlightPlan);
+    }
+
+    #[test]
+    fn test_hold_released_before_efc() {
+        let mut aircraft = test_departure();
+        aircraft.hold_at("LAM", Some(10.0), &HoldDatabase::new());
+        assert!(matches!(aircraft.nav_mode, NavMode::Hold(_)));
+
+        aircraft.release_hold();
+        assert_eq!(aircraft.nav_mode, NavMode::FlightPlan);
+    }
+
+    #[test]
+    fn test_hold_uses_published_parameters() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("sweatbox-holds-{}.txt", std::process::id()));
+        std::fs::write(&path, "; test holds\nHOLD:WILLO:277:L:1.5\n")?;
+        let holds = crate::aircraft::load_holds(&path)?;
+        std::fs::remove_file(&path)?;
+
+        let mut aircraft = test_departure();
+        aircraft.hold_at("WILLO", None, &holds);
+        let NavMode::Hold(hold) = &aircraft.nav_mode else {
+            panic!("not holding: {:?}", aircraft.nav_mode);
+        };
+        assert_eq!(hold.inbound_course, Some(277.0));
+        assert_eq!(hold.turn_direction, TurnDirection::Left);
+        assert_eq!(hold.leg_minutes, 1.5);
+
+        // Unpublished: standard right-hand hold
+        aircraft.hold_at("LAM", None, &holds);
+        let NavMode::Hold(hold) = &aircraft.nav_mode else {
+            panic!("not holding: {:?}", aircraft.nav_mode);
+        };
+        assert_eq!(hold.inbound_course, None);
+        assert_eq!(hold.turn_direction, TurnDirection::Right);
+        Ok(())
+    }
+
+    #[test]
+    fn test_assigned_approach_overrides_request() {
+        let mut aircraft = Aircraft::new_departure(
+            "BAW456".to_string(),
+            "A320".to_string(),
+            "2202".to_string(),
+            "EGLL".to_string(),
+            "EGLL".to_string(),
+            "BPK5K/09L DVR UL9 KONAN ALESO1H".to_string(),
+            250,
+            "09L".to_string(),
+            (51.4775, -0.4614),
+            90.0,
+        );
+
+        aircraft.request_approach(Approach::parse("RNAV 27R").unwrap());
+        assert!(aircraft.route_fixes.ends_with(&["CF27R".to_string(), "FI27R".to_string(), "RW27R".to_string()]));
+
+        // Established on the STAR, heading for the 27R final approach fix
+        aircraft.current_fix_index = aircraft.route_fixes.iter().position(|f| f == "CF27R").unwrap();
+        aircraft.phase = FlightPhase::Descending;
+
+        aircraft.assign_approach(Approach::parse("ILS 27L").unwrap());
+        assert_eq!(aircraft.active_approach(), Some(&Approach::parse("ILS 27L").unwrap()));
+        assert!(!aircraft.route_fixes[aircraft.star_start..].iter().any(|f| f.ends_with("27R")));
+        assert!(aircraft.route_fixes.ends_with(&["CF27L".to_string(), "FI27L".to_string(), "RW27L".to_string()]));
+        assert_eq!(aircraft.route_fixes.iter().filter(|f| *f == "ALESO").count(), 1);
+        assert_eq!(aircraft.current_fix(), Some("CF27L"));
+
+        // The assigned runway's fixes drive navigation
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("CF27L".to_string(), (51.4647, -0.0999));
+        fix_db.insert("CF27R".to_string(), (51.4868, -0.0953));
+        aircraft.latitude = 51.4647;
+        aircraft.longitude = 0.05;
+        aircraft.heading = 0.0;
+        aircraft.update(1.0, &fix_db, &crate::config::SimulationConfig::default());
+        assert!(aircraft.heading > 180.0, "turned towards CF27L, heading {}", aircraft.heading);
+        let bearing = bearing_from_to(aircraft.latitude, aircraft.longitude, 51.4647, -0.0999);
+        aircraft.heading = bearing;
+        aircraft.update(1.0, &fix_db, &crate::config::SimulationConfig::default());
+        assert_eq!(aircraft.heading, bearing);
+    }
+
+    #[test]
+    fn test_flies_approach_speed_schedule() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let runway = crate::utils::procedures::RunwayEnd {
+            designator: "27R".to_string(),
+            threshold: (51.4775, -0.4332),
+            course: 270.0,
+        };
+
+        for (aircraft_type, threshold_speed) in [("A320", 140), ("B744", 155)] {
+            let mut aircraft = Aircraft::new_ils_arrival(
+                "BAW123".to_string(),
+                aircraft_type.to_string(),
+                "1234".to_string(),
+                "EHAM".to_string(),
+                "EGLL".to_string(),
+                "REDFA BARMI1H".to_string(),
+                Localizer::new(&runway, 80),
+                14.0,
+                4500,
+            );
+            aircraft.airspeed = 250;
+
+            // Airspeed as each gate is passed, then crossing the threshold
+            let mut speeds = Vec::new();
+            let mut gates = vec![10.0, 6.0];
+            while aircraft.phase == FlightPhase::Approach {
+                let airspeed = aircraft.airspeed;
+                aircraft.update(0.5, &FixDatabase::new(), &sim_config);
+                let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+                    panic!("left the ILS");
+                };
+                let (along, _) = localizer.offsets_nm(aircraft.latitude, aircraft.longitude);
+                if gates.first().is_some_and(|gate| along < *gate) {
+                    speeds.push((gates.remove(0), airspeed));
+                }
+            }
+            speeds.push((0.0, aircraft.airspeed));
+
+            let expected = [(10.0, 220), (6.0, 180), (0.0, threshold_speed)];
+            for ((gate, speed), (_, scheduled)) in speeds.iter().zip(expected) {
+                assert!(speed.abs_diff(scheduled) <= 3, "{} at {}kt by {}nm, scheduled {}kt",
+                        aircraft_type, speed, gate, scheduled);
+            }
+            assert_eq!(speeds.len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_descends_onto_glideslope_from_above() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let runway = crate::utils::procedures::RunwayEnd {
+            designator: "27R".to_string(),
+            threshold: (51.4775, -0.4332),
+            course: 270.0,
+        };
+        let localizer = Localizer::new(&runway, 80);
+        let glideslope = localizer.glideslope_altitude(10.0);
+
+        // 900ft high at 10nm
+        let mut aircraft = Aircraft::new_ils_arrival(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "REDFA BARMI1H".to_string(),
+            localizer, 10.0, glideslope + 900,
+        );
+
+        let max_step_ft = (-sim_config.descent_rate / 60.0).round() as i32;
+        let mut captured = false;
+        for _ in 0..120 {
+            let altitude = aircraft.altitude;
+            aircraft.update(1.0, &FixDatabase::new(), &sim_config);
+            assert!(altitude - aircraft.altitude <= max_step_ft, "dropped {}ft in a second", altitude - aircraft.altitude);
+
+            let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+                panic!("left the ILS: {:?}", aircraft.nav_mode);
+            };
+            let (along_nm, _) = localizer.offsets_nm(aircraft.latitude, aircraft.longitude);
+            captured = (aircraft.altitude - localizer.glideslope_altitude(along_nm)).abs() <= GLIDESLOPE_CAPTURE_FT;
+            if captured {
+                break;
+            }
+        }
+        assert!(captured, "still {}ft", aircraft.altitude);
+    }
+
+    #[test]
+    fn test_flies_to_coordinate_waypoint() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGLL".to_string(), "KJFK".to_string(), "5130N00100W 51N002W".to_string(),
+            (51.5, 0.0), 270.0, 30000, 300,
+        );
+        assert_eq!(aircraft.route_fixes, vec!["5130N00100W", "51N002W"]);
+
+        // Neither waypoint is in the database
+        let fix_db = FixDatabase::new();
+        for _ in 0..1200 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            if aircraft.is_route_complete() {
+                break;
+            }
+        }
+        assert!(aircraft.is_route_complete());
+        assert!(haversine_nm(aircraft.latitude, aircraft.longitude, 51.0, -2.0) < 1.0);
+    }
+
+    #[test]
+    fn test_filed_restriction_steps_descent() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "KJFK".to_string(), "EGLL".to_string(),
+            "5130N00030W 5130N00100W/N0272F180 5130N00200W".to_string(),
+            (51.5, 0.0), 270.0, 25000, 300,
+        );
+        assert_eq!(aircraft.level_changes[0].fix, "5130N00100W");
+        let fix_db = FixDatabase::new();
+
+        // Already descending on the arrival when it passes the restricted fix
+        aircraft.phase = FlightPhase::Descending;
+        aircraft.target_altitude = 20000;
+        while aircraft.current_fix_index < 2 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        assert_eq!(aircraft.target_altitude, 18000);
+        assert_eq!(aircraft.target_speed, 272);
+
+        // Holds FL180 once there
+        for _ in 0..300 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        assert_eq!(aircraft.altitude, 18000);
+        assert_eq!(aircraft.phase, FlightPhase::Cruise);
+    }
+
+    #[test]
+    fn test_filed_level_change_keeps_sid_level_off() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGLL".to_string(), "KJFK".to_string(),
+            "5130N00030W/N0450F370 5130N00500W".to_string(),
+            (51.5, -0.45), 270.0, 4000, 250,
+        );
+        // Climbing to a 6000ft SID stop, filed to cruise at FL250
+        aircraft.flight_plan.cruise_altitude = 250;
+        aircraft.phase = FlightPhase::Climbing;
+        aircraft.target_altitude = 6000;
+
+        let fix_db = FixDatabase::new();
+        while aircraft.current_fix_index < 1 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        assert_eq!(aircraft.flight_plan.cruise_altitude_feet(), 37000);
+        assert_eq!(aircraft.target_altitude, 6000);
+        assert_eq!(aircraft.phase, FlightPhase::Climbing);
+    }
+
+    #[test]
+    fn test_filed_level_change() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGLL".to_string(), "KJFK".to_string(),
+            "5130N00030W/N0460F350 5130N00200W/N0450F310 5130N00500W".to_string(),
+            (51.5, 0.0), 270.0, 33000, 450,
+        );
+        let fix_db = FixDatabase::new();
+
+        let fly_past = |aircraft: &mut Aircraft, fix_index: usize| {
+            while aircraft.current_fix_index < fix_index {
+                aircraft.update(1.0, &fix_db, &sim_config);
+            }
+        };
+
+        // Level at FL330 until the first fix, then climbs to FL350
+        fly_past(&mut aircraft, 0);
+        assert_eq!(aircraft.altitude, 33000);
+        fly_past(&mut aircraft, 1);
+        assert_eq!(aircraft.phase, FlightPhase::Climbing);
+        assert_eq!(aircraft.target_altitude, 35000);
+        assert!(haversine_nm(aircraft.latitude, aircraft.longitude, 51.5, -0.5) < 1.0);
+
+        // Cruises at FL350 until the second fix, then descends to FL310
+        fly_past(&mut aircraft, 2);
+        assert_eq!(aircraft.altitude, 35000);
+        assert_eq!(aircraft.phase, FlightPhase::Descending);
+        assert_eq!(aircraft.target_altitude, 31000);
+        for _ in 0..300 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        assert_eq!(aircraft.altitude, 31000);
+        assert_eq!(aircraft.phase, FlightPhase::Cruise);
+        assert_eq!(aircraft.target_speed, 450);
+    }
+
+    #[test]
+    fn test_flies_by_route_fixes() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("EAST".to_string(), (51.5, 0.0));
+        fix_db.insert("NORTH".to_string(), position_bearing_distance(51.5, 0.0, 0.0, 20.0));
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGLL".to_string(), "EGPH".to_string(), "EAST NORTH".to_string(),
+            position_bearing_distance(51.5, 0.0, 270.0, 20.0), 90.0, 20000, 300,
+        );
+        aircraft.ground_speed = 300;
+
+        // A 90 degree turn at 300kt and 3 degrees a second starts about 1.6nm before EAST
+        let mut passed_east_at = None;
+        for _ in 0..600 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            if passed_east_at.is_none() && aircraft.current_fix_index == 1 {
+                passed_east_at = Some(haversine_nm(aircraft.latitude, aircraft.longitude, 51.5, 0.0));
+            }
+            if aircraft.is_route_complete() {
+                break;
+            }
+        }
+
+        let passed_east_at = passed_east_at.expect("never sequenced EAST");
+        assert!((passed_east_at - 1.6).abs() < 0.2, "turned {:.2}nm before EAST", passed_east_at);
+        assert!(aircraft.is_route_complete());
+        let (north_lat, north_lon) = position_bearing_distance(51.5, 0.0, 0.0, 20.0);
+        assert!(haversine_nm(aircraft.latitude, aircraft.longitude, north_lat, north_lon) < 1.0);
+    }
+
+    #[test]
+    fn test_assigned_heading_and_speed() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("EAST".to_string(), (51.5, 1.0));
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGLL".to_string(), "EHAM".to_string(), "EAST".to_string(),
+            (51.5, 0.0), 90.0, 20000, 300,
+        );
+
+        // Turns off the route onto the heading and slows to 250kt IAS
+        aircraft.fly_heading(180.0);
+        aircraft.assign_speed(250);
+        for _ in 0..120 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        assert_eq!(aircraft.heading, 180.0);
+        let ias = aircraft.indicated_airspeed();
+        assert!((ias - 250.0).abs() < 2.0, "{:.0}kt IAS", ias);
+
+        // Resumes own navigation back towards EAST
+        aircraft.resume_navigation();
+        for _ in 0..120 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        let to_east = bearing_from_to(aircraft.latitude, aircraft.longitude, 51.5, 1.0);
+        assert!(heading_difference(aircraft.heading, to_east).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_intercepts_localizer_from_vector() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let localizer = Localizer {
+            runway: "27R".to_string(),
+            course: 270.0,
+            threshold: (51.4775, -0.4333),
+            elevation: 83,
+            established: false,
+            join_nm: None,
+            intercept_heading: None,
+        };
+
+        // Base leg 12nm out, 10nm south of the centerline, vectored north onto it
+        let start = position_bearing_distance(51.4775, -0.4333, 90.0, 12.0);
+        let start = position_bearing_distance(start.0, start.1, 180.0, 10.0);
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGKK".to_string(), "EGLL".to_string(), "DCT".to_string(),
+            start, 0.0, 3000, 180,
+        );
+        aircraft.fly_heading(0.0);
+        aircraft.clear_ils(localizer);
+
+        let mut captured_offset = None;
+        for _ in 0..600 {
+            aircraft.update(1.0, &FixDatabase::new(), &sim_config);
+            let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+                break;
+            };
+            if captured_offset.is_none() && localizer.intercept_heading.is_none() {
+                captured_offset = Some(localizer.offsets_nm(aircraft.latitude, aircraft.longitude).1);
+            }
+            if localizer.established && (aircraft.heading - 270.0).abs() < 1.0 {
+                break;
+            }
+        }
+
+        // Held north until about the turn radius (1nm at 180kt) from the centerline,
+        // then rolled out on it
+        let captured_offset = captured_offset.expect("never captured the localizer");
+        assert!((0.8..1.1).contains(&captured_offset.abs()), "captured {:.2}nm out", captured_offset);
+        let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+            panic!("left the ILS: {:?}", aircraft.nav_mode);
+        };
+        assert!(localizer.established);
+        assert!((aircraft.heading - 270.0).abs() < 1.0, "heading {:.0}", aircraft.heading);
+        assert!(localizer.offsets_nm(aircraft.latitude, aircraft.longitude).1.abs() < 0.2);
+    }
+
+    #[test]
+    fn test_long_leg_follows_great_circle() {
+        // A 600nm leg flown in a strong crosswind stays close to the leg's great circle,
+        // where homing on the fix would drift over 20nm off it
+        let sim_config = crate::config::SimulationConfig {
+            wind: WindField::uniform(crate::utils::wind::Wind::new(180.0, 60.0)),
+            ..crate::config::SimulationConfig::default()
+        };
+        let (from, to) = ((52.0, 0.0), (58.0, 14.0));
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("WEST".to_string(), from);
+        fix_db.insert("EAST".to_string(), to);
+
+        let mut aircraft = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "2201".to_string(),
+            "EGLL".to_string(), "ESSA".to_string(), "WEST DCT EAST".to_string(),
+            from, bearing_from_to(from.0, from.1, to.0, to.1), 35000, 450,
+        );
+        aircraft.route_fixes = vec!["WEST".to_string(), "EAST".to_string()];
+        aircraft.current_fix_index = 1;
+
+        let mut worst: f64 = 0.0;
+        for _ in 0..7200 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            if haversine_nm(aircraft.latitude, aircraft.longitude, to.0, to.1) < 30.0 {
+                break;
+            }
+            worst = worst.max(cross_track_nm(aircraft.latitude, aircraft.longitude, from, to).abs());
+        }
+        assert!(haversine_nm(aircraft.latitude, aircraft.longitude, to.0, to.1) < 30.0);
+        assert!(worst < 3.0, "{:.1}nm off the leg", worst);
+    }
+
+    #[test]
+    fn test_joins_final_from_abeam() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let localizer = Localizer {
+            runway: "27R".to_string(),
+            course: 270.0,
+            threshold: (51.4775, -0.4333),
+            elevation: 83,
+            established: false,
+            join_nm: Some(10.0),
+            intercept_heading: None,
+        };
+
+        // 12nm out but 14nm south of the centerline
+        let start = position_bearing_distance(51.4775, -0.4333, 90.0, 12.0);
+        let start = position_bearing_distance(start.0, start.1, 180.0, 14.0);
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGKK".to_string(), "EGLL".to_string(), "DCT".to_string(),
+            start, 0.0, 3000, 180,
+        );
+        aircraft.clear_ils(localizer);
+
+        // Heads for the joining point rather than cutting in at 30 degrees
+        let mut joined_at = None;
+        for _ in 0..600 {
+            aircraft.update(1.0, &FixDatabase::new(), &sim_config);
+            let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+                panic!("left the ILS: {:?}", aircraft.nav_mode);
+            };
+            if localizer.join_nm.is_none() {
+                joined_at = Some(localizer.offsets_nm(aircraft.latitude, aircraft.longitude));
+                break;
+            }
+        }
+
+        let (along_nm, offset_nm) = joined_at.expect("never reached the joining point");
+        assert!(offset_nm.abs() <= 2.0, "joined {:.1}nm off the centerline", offset_nm);
+        assert!(along_nm > 8.0, "joined {:.1}nm out", along_nm);
+    }
+
+    #[test]
+    fn test_too_high_goes_around() {
+        let mut sim_config = crate::config::SimulationConfig::default();
+        sim_config.missed_approaches.insert("EGLL 27R".to_string(), vec!["MAPWP".to_string()]);
+        let localizer = Localizer {
+            runway: "27R".to_string(),
+            course: 270.0,
+            threshold: (51.4775, -0.4333),
+            elevation: 83,
+            established: false,
+            join_nm: None,
+            intercept_heading: None,
+        };
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("MAPWP".to_string(), position_bearing_distance(51.4775, -0.4333, 240.0, 8.0));
+
+        // 3nm out on the centerline at 2800ft, over 1000ft above the 3 degree path
+        let start = position_bearing_distance(51.4775, -0.4333, 90.0, 3.0);
+        let mut aircraft = Aircraft::new_enroute(
+            "BAW123".to_string(), "A320".to_string(), "1234".to_string(),
+            "EGKK".to_string(), "EGLL".to_string(), "DCT".to_string(),
+            start, 270.0, 2800, 160,
+        );
+        aircraft.clear_ils(localizer);
+        for _ in 0..5 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+
+        assert!(matches!(aircraft.nav_mode, NavMode::MissedApproach(_)), "{:?}", aircraft.nav_mode);
+        assert_eq!(aircraft.phase, FlightPhase::Climbing);
+        assert_eq!(aircraft.target_altitude, 3000);
+        assert!(aircraft.altitude > 2800);
+
+        // Flies the published track and holds at its end
+        for _ in 0..600 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            if matches!(aircraft.nav_mode, NavMode::Hold(_)) {
+                break;
+            }
+        }
+        let NavMode::Hold(hold) = &aircraft.nav_mode else {
+            panic!("not holding: {:?}", aircraft.nav_mode);
+        };
+        assert_eq!(hold.fix, "MAPWP");
+        assert_eq!(aircraft.altitude, 3000);
+    }
+
+    #[test]
+    fn test_mode_s_registration() {
+        let aircraft = Aircraft::new_departure(
+            "EZY1234".to_string(),
+            "A320".to_string(),
+            "2203".to_string(),
+            "EGSS".to_string(),
+            "EHAM".to_string(),
+            "CLN2E/22 CLN P44 RATLO".to_string(),
+            250,
+            "22".to_string(),
+            (51.885, 0.235),
+            220.0,
+        );
+
+        let mode_s = aircraft.mode_s.as_ref().expect("EZY is UK registered");
+        let uk = crate::utils::registration::Registry::for_airline("EZY").unwrap();
+        assert!(uk.matches(&mode_s.registration), "{}", mode_s.registration);
+
+        let fsd = aircraft.flight_plan.to_fsd_string();
+        assert!(fsd.contains(&format!("REG/{}", mode_s.registration)));
+        assert!(fsd.contains(&format!("CODE/{}", mode_s.address_hex())));
+    }
+
+    #[test]
+    fn test_ground_speed_changes_between_wind_zones() {
+        use crate::utils::wind::{Wind, WindZone};
+
+        // Westerly jet north of 52N, calm to the south
+        let mut sim_config = crate::config::SimulationConfig::default();
+        sim_config.wind.add_zone(WindZone {
+            min_lat: 52.0,
+            max_lat: 60.0,
+            min_lon: -10.0,
+            max_lon: 10.0,
+            min_altitude: 0,
+            max_altitude: 45000,
+            wind: Wind::new(270.0, 100.0),
+        });
+
+        // Cruising due north towards the jet, off the end of the route so the heading holds
+        let mut aircraft = test_departure();
+        aircraft.phase = FlightPhase::Cruise;
+        aircraft.altitude = 25000;
+        aircraft.airspeed = 400;
+        aircraft.target_speed = 400;
+        aircraft.heading = 0.0;
+        aircraft.current_fix_index = aircraft.route_fixes.len();
+        aircraft.latitude = 51.9;
+        aircraft.longitude = 0.0;
+
+        let fix_db = FixDatabase::new();
+        aircraft.update(1.0, &fix_db, &sim_config);
+        assert_eq!(aircraft.ground_speed, 400);
+
+        while aircraft.latitude < 52.05 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        // 100kt crosswind: same airspeed, faster over the ground, drifting east
+        assert_eq!(aircraft.airspeed, 400);
+        assert_eq!(aircraft.ground_speed, 412);
+        assert!(aircraft.longitude > 0.0);
+    }
+
+    #[test]
+    fn test_departs_only_when_cleared_for_takeoff() {
+        let fix_db = FixDatabase::new();
+        let sim_config = crate::config::SimulationConfig {
+            require_takeoff_clearance: true,
+            ..crate::config::SimulationConfig::default()
+        };
+
+        let mut aircraft = test_departure();
+        aircraft.heading = 130.0;
+
+        aircraft.hold_short();
+        for _ in 0..30 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            assert_eq!(aircraft.phase, FlightPhase::HoldingShort);
+            assert_eq!(aircraft.airspeed, 0);
+        }
+
+        aircraft.line_up_and_wait();
+        let (lat, lon) = (aircraft.latitude, aircraft.longitude);
+        for _ in 0..30 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+            assert_eq!(aircraft.phase, FlightPhase::LinedUp);
+            assert_eq!(aircraft.heading, 220.0);
+            assert_eq!((aircraft.latitude, aircraft.longitude), (lat, lon));
+        }
+
+        aircraft.clear_for_takeoff();
+        assert_eq!(aircraft.phase, FlightPhase::Departing);
+        for _ in 0..30 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+        assert_eq!(aircraft.phase, FlightPhase::Climbing);
+        assert!(aircraft.altitude > 0);
+
+        // Can't go back to the holding point once airborne
+        aircraft.line_up_and_wait();
+        assert_eq!(aircraft.phase, FlightPhase::Climbing);
+    }
+
+    #[test]
+    fn test_mach_assignment() {
+        let fix_db = FixDatabase::new();
+        let sim_config = crate::config::SimulationConfig::default();
+
+        let mut aircraft = test_departure();
+        aircraft.phase = FlightPhase::Cruise;
+        aircraft.altitude = 36000;
+        aircraft.airspeed = 430;
+        aircraft.target_speed = 430;
+        aircraft.route_fixes.clear();
+
+        aircraft.assign_mach(0.80, &sim_config).unwrap();
+        for _ in 0..60 {
+            aircraft.update(1.0, &fix_db, &sim_config);
+        }
+
+        // M0.80 at FL360 is about 459kt true, 266kt indicated
+        assert!((aircraft.mach() - 0.80).abs() < 0.005, "flying M{:.3}", aircraft.mach());
+        let expected_ias = mach_to_ias(0.80, 36000.0);
+        assert!((aircraft.indicated_airspeed() - expected_ias).abs() < 2.0,
+                "IAS {:.0}, expected {:.0}", aircraft.indicated_airspeed(), expected_ias);
+
+        // Too low for Mach
+        aircraft.altitude = 20000;
+        aircraft.assigned_mach = None;
+        let err = aircraft.assign_mach(0.70, &sim_config).unwrap_err();
+        assert!(err.to_string().contains("unable"));
+        assert_eq!(aircraft.assigned_mach, None);
+    }
+
+    #[test]
+    fn test_slow_turn_at_high_update_rate() {
+        let sim_config = crate::config::SimulationConfig::default();
+        let delta_time = 0.02; // 50Hz, well under a degree of turn per update
+
+        // Cruising due north with the next fix 40nm due east
+        let mut aircraft = test_departure();
+        aircraft.phase = FlightPhase::Cruise;
+        aircraft.altitude = 35000;
+        aircraft.airspeed = 450;
+        aircraft.target_speed = 450;
+        aircraft.heading = 0.0;
+        aircraft.latitude = 51.0;
+        aircraft.longitude = 0.0;
+        aircraft.route_fixes = vec!["EAST".to_string()];
+        aircraft.current_fix_index = 0;
+        aircraft.star_start = 1;
+
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("EAST".to_string(), position_bearing_distance(51.0, 0.0, 90.0, 40.0));
+
+        let step = sim_config.turn_rate * delta_time;
+        let mut ticks = 0;
+        loop {
+            let previous = aircraft.heading;
+            aircraft.update(delta_time, &fix_db, &sim_config);
+            ticks += 1;
+
+            let turned = heading_difference(previous, aircraft.heading);
+            assert!(turned <= step + 1e-9, "turned {} degrees in one update", turned);
+            if turned < step - 1e-9 {
+                break; // rolled out on the fix
+            }
+            assert!(ticks < 10_000, "turn stalled at heading {}", aircraft.heading);
+        }
+
+        // Standard rate through roughly 90 degrees takes about 30 seconds
+        let seconds = ticks as f64 * delta_time;
+        assert!((25.0..35.0).contains(&seconds), "turn took {} seconds", seconds);
+        assert!((aircraft.heading - 90.0).abs() < 5.0, "rolled out on {}", aircraft.heading);
+    }
+
+    #[test]
+    fn test_slows_before_top_of_descent() {
+        let sim_config = crate::config::SimulationConfig::default();
+
+        // Arrival at FL350 with 120nm to run due north
+        let mut aircraft = test_departure();
+        aircraft.phase = FlightPhase::Cruise;
+        aircraft.altitude = 35000;
+        aircraft.airspeed = 450;
+        aircraft.target_speed = 450;
+        aircraft.heading = 0.0;
+        aircraft.latitude = 51.0;
+        aircraft.longitude = 0.0;
+        aircraft.route_fixes = vec!["ARRIV".to_string(), "FINAL".to_string()];
+        aircraft.current_fix_index = 0;
+        aircraft.star_start = 1;
+
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("ARRIV".to_string(), position_bearing_distance(51.0, 0.0, 0.0, 60.0));
+        fix_db.insert("FINAL".to_string(), position_bearing_distance(51.0, 0.0, 0.0, 120.0));
+
+        let mut slowing_at = None;
+        let mut descending_at = None;
+        for tick in 0..1200 {
+            let (speed, altitude) = (aircraft.airspeed, aircraft.altitude);
+            aircraft.update(1.0, &fix_db, &sim_config);
+
+            if slowing_at.is_none() && aircraft.airspeed < speed {
+                slowing_at = Some(tick);
+            }
+            if descending_at.is_none() && aircraft.altitude < altitude {
+                descending_at = Some(tick);
+                assert_eq!(aircraft.airspeed, sim_config.descent_speed, "crossed TOD above the descent speed");
+            }
+        }
+
+        let slowing_at = slowing_at.expect("never slowed down");
+        let descending_at = descending_at.expect("never descended");
+        assert!(slowing_at < descending_at, "slowed at {}s, descended at {}s", slowing_at, descending_at);
+    }
+
+    #[test]
+    fn test_display_summary() {
+        let mut aircraft = test_departure();
+        aircraft.altitude = 6500;
+        aircraft.airspeed = 250;
+        aircraft.ground_speed = 250;
+        aircraft.phase = FlightPhase::Climbing;
+
+        let summary = aircraft.to_string();
+        println!("{}", summary);
+
+        assert!(summary.contains("BAW123"));
+        assert!(summary.contains("A320"));
+        assert!(summary.contains("Climbing"));
+        assert!(summary.contains("51.88500,0.23500"));
+        assert!(summary.contains("FL065"));
+        assert!(summary.contains("HDG220"));
+        assert!(summary.contains("GS250kt"));
+        assert!(summary.contains("-> SSW01"));
+    }
+
+    #[test]
+    fn test_display_airspeed_and_ground_speed() {
+        use crate::utils::wind::{Wind, WindField};
+
+        // 250kt TAS at FL100 into a 50kt headwind
+        let sim_config = crate::config::SimulationConfig {
+            wind: WindField::uniform(Wind::new(0.0, 50.0)),
+            ..crate::config::SimulationConfig::default()
+        };
+
+        let mut aircraft = test_departure();
+        aircraft.phase = FlightPhase::Cruise;
+        aircraft.altitude = 10000;
+        aircraft.target_altitude = 10000;
+        aircraft.airspeed = 250;
+        aircraft.target_speed = 250;
+        aircraft.heading = 0.0;
+        aircraft.current_fix_index = aircraft.route_fixes.len();
+        aircraft.update(1.0, &FixDatabase::new(), &sim_config);
+
+        let ias = aircraft.indicated_airspeed().round();
+        assert_eq!(aircraft.ground_speed, 200);
+        assert!((ias - 215.0).abs() < 5.0, "IAS {}", ias);
+
+        let summary = aircraft.to_string();
+        assert!(summary.contains(&format!("IAS{}kt GS200kt", ias)), "{}", summary);
     }
 }
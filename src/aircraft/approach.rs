@@ -0,0 +1,208 @@
+use crate::utils::navigation::{haversine_nm, bearing_from_to, position_bearing_distance};
+use crate::utils::procedures::RunwayEnd;
+
+/// Feet of glideslope per NM for a 3 degree path
+const GLIDESLOPE_FT_PER_NM: f64 = 318.0;
+
+/// Height above the glideslope (feet) at which an established aircraft goes around
+pub const GO_AROUND_ABOVE_GLIDESLOPE_FT: i32 = 1000;
+
+/// Height above the threshold (feet) to climb straight ahead to before turning
+/// onto the missed-approach track
+const MISSED_APPROACH_TURN_HEIGHT_FT: i32 = 500;
+
+/// Type of instrument approach
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApproachType {
+    Ils,
+    Rnav,
+}
+
+/// An approach to a specific runway, e.g. "ILS 27L"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approach {
+    pub approach_type: ApproachType,
+    pub runway: String,
+}
+
+impl Approach {
+    pub fn new(approach_type: ApproachType, runway: &str) -> Self {
+        Self {
+            approach_type,
+            runway: runway.to_uppercase(),
+        }
+    }
+
+    /// Parse an approach such as "ILS 27L" or "RNAV 09R"
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split_whitespace();
+        let approach_type = match parts.next()?.to_uppercase().as_str() {
+            "ILS" => ApproachType::Ils,
+            "RNAV" | "RNP" => ApproachType::Rnav,
+            _ => return None,
+        };
+
+        let runway = parts.next()?;
+        if parts.next().is_some() || !runway.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        Some(Self::new(approach_type, runway))
+    }
+}
+
+impl std::fmt::Display for Approach {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let approach_type = match self.approach_type {
+            ApproachType::Ils => "ILS",
+            ApproachType::Rnav => "RNAV",
+        };
+        write!(f, "{} {}", approach_type, self.runway)
+    }
+}
+
+/// Speeds flown on final as the flaps come out: slowed to each gate's speed by its
+/// distance from the threshold, then to Vref plus an additive at the threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApproachSchedule {
+    /// (distance from the threshold in NM, speed in knots), outermost first
+    pub gates: Vec<(f64, u32)>,
+    /// Reference landing speed (knots)
+    pub vref: u32,
+    /// Knots above Vref to cross the threshold at
+    pub additive: u32,
+}
+
+impl Default for ApproachSchedule {
+    fn default() -> Self {
+        Self { gates: vec![(10.0, 220), (6.0, 180)], vref: 135, additive: 5 }
+    }
+}
+
+impl ApproachSchedule {
+    /// Target speed (knots) at a distance from the threshold, slowing evenly from
+    /// one gate to the next so each gate's speed is reached as it is passed
+    pub fn speed_at(&self, distance_nm: f64) -> f64 {
+        let threshold = (0.0, self.vref + self.additive);
+        let mut outer = match self.gates.first() {
+            Some(&gate) if distance_nm < gate.0 => gate,
+            Some(&(_, speed)) => return speed as f64,
+            None => return threshold.1 as f64,
+        };
+
+        for &inner in self.gates.iter().skip(1).chain(std::iter::once(&threshold)) {
+            if distance_nm >= inner.0 {
+                let progress = (outer.0 - distance_nm) / (outer.0 - inner.0);
+                return outer.1 as f64 + (inner.1 as f64 - outer.1 as f64) * progress;
+            }
+            outer = inner;
+        }
+        threshold.1 as f64
+    }
+}
+
+/// ILS localizer and glideslope for a runway
+#[derive(Debug, Clone, PartialEq)]
+pub struct Localizer {
+    pub runway: String,
+    /// Final approach course (degrees true)
+    pub course: f64,
+    pub threshold: (f64, f64),
+    /// Threshold elevation (feet)
+    pub elevation: i32,
+    /// Set once the aircraft has captured the localizer
+    pub established: bool,
+    /// Distance out on the extended centerline (NM) to fly to before turning in,
+    /// for aircraft joining from overhead or the wrong side
+    pub join_nm: Option<f64>,
+    /// Heading being flown to intercept, held until the localizer captures
+    pub intercept_heading: Option<f64>,
+}
+
+impl Localizer {
+    pub fn new(runway: &RunwayEnd, elevation: i32) -> Self {
+        Self {
+            runway: runway.designator.clone(),
+            course: runway.course,
+            threshold: runway.threshold,
+            elevation,
+            established: false,
+            join_nm: None,
+            intercept_heading: None,
+        }
+    }
+
+    /// Distance from the threshold along the extended centerline (NM, positive on the approach side)
+    /// and offset from the centerline (NM, positive to the right of the final approach course)
+    pub fn offsets_nm(&self, lat: f64, lon: f64) -> (f64, f64) {
+        let distance = haversine_nm(self.threshold.0, self.threshold.1, lat, lon);
+        let bearing = bearing_from_to(self.threshold.0, self.threshold.1, lat, lon);
+        let angle = (bearing - (self.course + 180.0)).to_radians();
+
+        (distance * angle.cos(), -distance * angle.sin())
+    }
+
+    /// Point on the extended centerline a distance from the threshold
+    pub fn centerline_point(&self, distance_nm: f64) -> (f64, f64) {
+        position_bearing_distance(self.threshold.0, self.threshold.1, (self.course + 180.0) % 360.0, distance_nm)
+    }
+
+    /// Glideslope altitude (feet) at a distance from the threshold
+    pub fn glideslope_altitude(&self, distance_nm: f64) -> i32 {
+        self.elevation + (distance_nm.max(0.0) * GLIDESLOPE_FT_PER_NM) as i32
+    }
+}
+
+/// A missed approach from the ILS: climb straight ahead on the runway course, then
+/// fly the published track to its hold, or hold at the end of the arrival route
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissedApproach {
+    /// The approach that was abandoned, kept to fly it again
+    pub localizer: Localizer,
+    /// Published missed-approach fixes, the last being the hold
+    pub track: Vec<String>,
+    /// Index in `track` of the fix being flown to
+    pub next: usize,
+}
+
+impl MissedApproach {
+    pub fn new(localizer: Localizer, track: Vec<String>) -> Self {
+        Self { localizer, track, next: 0 }
+    }
+
+    /// Whether the aircraft is high enough above the threshold to turn off the runway course
+    pub fn turn_height_reached(&self, altitude: i32) -> bool {
+        altitude >= self.localizer.elevation + MISSED_APPROACH_TURN_HEIGHT_FT
+    }
+
+    /// Next fix on the published track, or None once it has all been flown
+    pub fn next_fix(&self) -> Option<&str> {
+        self.track.get(self.next).map(|s| s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_approach() {
+        assert_eq!(Approach::parse("ILS 27L"), Some(Approach::new(ApproachType::Ils, "27L")));
+        assert_eq!(Approach::parse("rnav 09r"), Some(Approach::new(ApproachType::Rnav, "09R")));
+        assert_eq!(Approach::parse("ILS"), None);
+        assert_eq!(Approach::parse("VOR 27L"), None);
+        assert_eq!(Approach::parse("ILS LAM"), None);
+        assert_eq!(Approach::new(ApproachType::Ils, "27l").to_string(), "ILS 27L");
+    }
+
+    #[test]
+    fn test_approach_schedule_speeds() {
+        let schedule = ApproachSchedule::default();
+        assert_eq!(schedule.speed_at(15.0), 220.0);
+        assert_eq!(schedule.speed_at(10.0), 220.0);
+        assert_eq!(schedule.speed_at(8.0), 200.0);
+        assert_eq!(schedule.speed_at(6.0), 180.0);
+        assert_eq!(schedule.speed_at(3.0), 160.0);
+        assert_eq!(schedule.speed_at(0.0), 140.0);
+    }
+}
@@ -1,13 +1,19 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use crate::utils::registration::ModeS;
 
 /// Flight plan information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlightPlan {
+    /// Flight rules: I (IFR), V (VFR), Y or Z
+    #[serde(default = "default_flight_rules")]
+    pub flight_rules: String,
     pub aircraft_type: String,
     pub cruise_speed: u32,
     pub departure: String,
     pub arrival: String,
     pub alternate: String,
+    /// Planned cruise level as a flight level (e.g. 360)
     pub cruise_altitude: u32,
     pub route: String,
     pub remarks: String,
@@ -15,6 +21,10 @@ pub struct FlightPlan {
     pub fuel_minutes: u32,
 }
 
+fn default_flight_rules() -> String {
+    "I".to_string()
+}
+
 impl FlightPlan {
     pub fn new(
         aircraft_type: String,
@@ -24,6 +34,7 @@ impl FlightPlan {
         route: String,
     ) -> Self {
         Self {
+            flight_rules: default_flight_rules(),
             aircraft_type: aircraft_type.clone(),
             cruise_speed: 450, // Default, will be updated based on aircraft performance
             departure,
@@ -37,15 +48,44 @@ impl FlightPlan {
         }
     }
 
-    /// Format as FSD flight plan string
+    /// Add mode-S details to the remarks so clients can show the tail number
+    pub fn set_mode_s(&mut self, mode_s: &ModeS) {
+        self.remarks = format!("{} REG/{} CODE/{}", self.remarks, mode_s.registration, mode_s.address_hex());
+    }
+
+    /// Planned cruise level in feet
+    pub fn cruise_altitude_feet(&self) -> i32 {
+        self.cruise_altitude as i32 * 100
+    }
+
+    /// Read a filed altitude field as a flight level: "F360", "FL360" and
+    /// "36000" are all FL360, and "A045" is 4500ft. Bare numbers under 1000
+    /// are taken as levels already.
+    pub fn parse_level(field: &str) -> Option<u32> {
+        let field = field.trim().to_uppercase();
+        if let Some(level) = field
+            .strip_prefix("FL")
+            .or_else(|| field.strip_prefix('F'))
+            .or_else(|| field.strip_prefix('A'))
+        {
+            return level.parse().ok();
+        }
+
+        let value: u32 = field.parse().ok()?;
+        Some(if value >= 1000 { value / 100 } else { value })
+    }
+
+    /// Format as FSD flight plan string, with the cruise level in feet
+    /// (e.g. FL360 is filed as 36000)
     /// Format: *A:RULES:ACFT/EQUIP:TAS:DEP:DEPTIME:ACTUALTIME:ALT:DEST:HRS:MINS:ENDURANCE_HRS:ENDURANCE_MINS:ALT_AIRPORT:REMARKS:ROUTE
     pub fn to_fsd_string(&self) -> String {
         format!(
-            "*A:I:{}/H-S/C:{}:{}:0:0:{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            "*A:{}:{}/H-S/C:{}:{}:0:0:{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            self.flight_rules,
             self.aircraft_type,
             self.cruise_speed,
             self.departure,
-            self.cruise_altitude,
+            self.cruise_altitude_feet(),
             self.arrival,
             self.fuel_hours,
             self.fuel_minutes,
@@ -56,4 +96,96 @@ impl FlightPlan {
             self.route
         )
     }
+
+    /// Read a flight plan back from its FSD string, with or without the leading
+    /// `$FP<callsign>` of the message it came in. Missing trailing fields, as
+    /// EuroScope leaves off, are left empty.
+    pub fn from_fsd_string(filed: &str) -> Result<Self> {
+        let mut fields: Vec<&str> = filed.trim_end_matches(['\r', '\n']).split(':').collect();
+        if fields.first().is_some_and(|f| f.starts_with("$FP")) {
+            fields.remove(0);
+        }
+        anyhow::ensure!(fields.len() >= 9, "Flight plan has {} fields, needs at least 9: {}", fields.len(), filed);
+        let field = |index: usize| fields.get(index).map(|f| f.trim()).unwrap_or_default();
+        let number = |index: usize| field(index).parse::<u32>().unwrap_or(0);
+
+        // Type with its equipment, and possibly a wake prefix: A320/H-S/C, H/B744/L
+        let aircraft_type = field(2)
+            .split('/')
+            .find(|part| part.len() > 1)
+            .ok_or_else(|| anyhow::anyhow!("No aircraft type in flight plan: {}", filed))?;
+        let cruise_altitude = Self::parse_level(field(7))
+            .ok_or_else(|| anyhow::anyhow!("Can't read cruise level {:?}", field(7)))?;
+
+        Ok(Self {
+            flight_rules: if field(1).is_empty() { default_flight_rules() } else { field(1).to_string() },
+            aircraft_type: aircraft_type.to_string(),
+            cruise_speed: number(3),
+            departure: field(4).to_string(),
+            arrival: field(8).to_string(),
+            alternate: field(13).to_string(),
+            cruise_altitude,
+            route: field(15).to_string(),
+            remarks: field(14).to_string(),
+            fuel_hours: number(11),
+            fuel_minutes: number(12),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cruise_level_filed_in_feet() {
+        let plan = FlightPlan::new("A320".to_string(), "EGKK".to_string(), "EGPH".to_string(), 360, "DCT".to_string());
+        let filed = plan.to_fsd_string();
+        let fields: Vec<&str> = filed.split(':').collect();
+
+        assert_eq!(fields[7], "36000");
+        assert_eq!(FlightPlan::parse_level(fields[7]), Some(360));
+        assert_eq!(plan.cruise_altitude_feet(), 36000);
+
+        for filed in ["F360", "FL360", "fl360", "36000", "360"] {
+            assert_eq!(FlightPlan::parse_level(filed), Some(360), "{}", filed);
+        }
+        assert_eq!(FlightPlan::parse_level("A045"), Some(45));
+        assert_eq!(FlightPlan::parse_level("VFR"), None);
+    }
+
+    #[test]
+    fn test_fsd_string_round_trip() {
+        let mut plan = FlightPlan::new("A320".to_string(), "EGKK".to_string(), "EGPH".to_string(), 360, "LAM2X/26L LAM UN57 POL".to_string());
+        plan.cruise_speed = 438;
+        plan.alternate = "EGPF".to_string();
+
+        let parsed = FlightPlan::from_fsd_string(&plan.to_fsd_string()).unwrap();
+        assert_eq!(parsed.flight_rules, "I");
+        assert_eq!(parsed.aircraft_type, "A320");
+        assert_eq!(parsed.cruise_speed, 438);
+        assert_eq!(parsed.departure, "EGKK");
+        assert_eq!(parsed.arrival, "EGPH");
+        assert_eq!(parsed.alternate, "EGPF");
+        assert_eq!(parsed.cruise_altitude, 360);
+        assert_eq!(parsed.route, plan.route);
+        assert_eq!(parsed.remarks, plan.remarks);
+        assert_eq!((parsed.fuel_hours, parsed.fuel_minutes), (2, 30));
+        assert_eq!(parsed.to_fsd_string(), plan.to_fsd_string());
+    }
+
+    #[test]
+    fn test_fsd_string_from_client() {
+        // A controller's amendment, trailing fields left off
+        let parsed = FlightPlan::from_fsd_string("$FPBAW123:*A:V:H/B744/L:480:EGLL:1200:0:FL350:KJFK").unwrap();
+        assert_eq!(parsed.flight_rules, "V");
+        assert_eq!(parsed.aircraft_type, "B744");
+        assert_eq!(parsed.cruise_speed, 480);
+        assert_eq!((parsed.departure.as_str(), parsed.arrival.as_str()), ("EGLL", "KJFK"));
+        assert_eq!(parsed.cruise_altitude, 350);
+        assert!(parsed.route.is_empty() && parsed.alternate.is_empty());
+
+        assert!(FlightPlan::from_fsd_string("*A:I:A320:450:EGKK").is_err());
+        assert!(FlightPlan::from_fsd_string("*A:I:A320:450:EGKK:0:0:high:EGPH").is_err());
+    }
 }
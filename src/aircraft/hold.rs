@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{Result, Context};
+use crate::utils::navigation::TurnDirection;
+
+/// Published holds keyed by fix name
+pub type HoldDatabase = HashMap<String, Hold>;
+
+pub const DEFAULT_HOLDS_FILE: &str = "data/Navaids/HOLDS.txt";
+
+/// Which part of the racetrack the aircraft is flying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldLeg {
+    /// Proceeding to the holding fix
+    Entry,
+    /// Turning onto and flying the outbound leg
+    Outbound,
+    /// Turning back and flying inbound to the fix
+    Inbound,
+}
+
+/// A holding pattern at a fix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hold {
+    pub fix: String,
+    /// Inbound course in degrees. `None` uses the track flown into the fix.
+    pub inbound_course: Option<f64>,
+    pub turn_direction: TurnDirection,
+    /// Outbound leg length in minutes
+    pub leg_minutes: f64,
+    /// Expect further clearance, in aircraft flight time (seconds)
+    pub efc: Option<f64>,
+    pub leg: HoldLeg,
+    /// Seconds flown on the current outbound leg
+    pub leg_timer: f64,
+}
+
+impl Hold {
+    /// Standard right-hand, 1-minute hold at a fix
+    pub fn standard(fix: &str) -> Self {
+        Self {
+            fix: fix.to_uppercase(),
+            inbound_course: None,
+            turn_direction: TurnDirection::Right,
+            leg_minutes: 1.0,
+            efc: None,
+            leg: HoldLeg::Entry,
+            leg_timer: 0.0,
+        }
+    }
+
+    /// The published hold at a fix, or a standard hold if it has none
+    pub fn published_or_standard(fix: &str, holds: &HoldDatabase) -> Self {
+        holds
+            .get(&fix.to_uppercase())
+            .cloned()
+            .unwrap_or_else(|| Self::standard(fix))
+    }
+
+    /// Outbound heading (reciprocal of the inbound course)
+    pub fn outbound_course(&self) -> Option<f64> {
+        self.inbound_course.map(|c| (c + 180.0).rem_euclid(360.0))
+    }
+
+    /// Check whether the EFC has been reached at the given flight time
+    pub fn efc_reached(&self, flight_time: f64) -> bool {
+        self.efc.is_some_and(|efc| flight_time >= efc)
+    }
+}
+
+/// Load published holds.
+///
+/// Format: HOLD:FIX:INBOUND_COURSE:TURN(L/R):LEG_MINUTES, with ';' comments
+pub fn load_holds<P: AsRef<Path>>(path: P) -> Result<HoldDatabase> {
+    let content = std::fs::read_to_string(path.as_ref())
+        .with_context(|| format!("Failed to read holds file: {:?}", path.as_ref()))?;
+
+    let mut holds = HoldDatabase::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split(':').collect();
+        if parts.len() < 5 || parts[0] != "HOLD" {
+            tracing::warn!("[HOLDS] Skipping malformed line: {}", line);
+            continue;
+        }
+
+        let turn_direction = match parts[3] {
+            "L" => TurnDirection::Left,
+            "R" => TurnDirection::Right,
+            other => {
+                tracing::warn!("[HOLDS] Bad turn direction {} for {}", other, parts[1]);
+                continue;
+            }
+        };
+        let (Ok(inbound_course), Ok(leg_minutes)) = (parts[2].parse::<f64>(), parts[4].parse::<f64>()) else {
+            tracing::warn!("[HOLDS] Skipping malformed line: {}", line);
+            continue;
+        };
+
+        let mut hold = Hold::standard(parts[1]);
+        hold.inbound_course = Some(inbound_course);
+        hold.turn_direction = turn_direction;
+        hold.leg_minutes = leg_minutes;
+        holds.insert(hold.fix.clone(), hold);
+    }
+
+    Ok(holds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_holds() -> Result<()> {
+        let holds = load_holds(DEFAULT_HOLDS_FILE)?;
+
+        let lam = Hold::published_or_standard("lam", &holds);
+        assert_eq!(lam.inbound_course, Some(264.0));
+        assert_eq!(lam.turn_direction, TurnDirection::Left);
+        assert_eq!(lam.outbound_course(), Some(84.0));
+
+        // No published hold: standard right-hand, 1-minute
+        let other = Hold::published_or_standard("ZZZZZ", &holds);
+        assert_eq!(other, Hold::standard("ZZZZZ"));
+        Ok(())
+    }
+}
@@ -1,5 +1,12 @@
+#[allow(clippy::module_inception)]
 pub mod aircraft;
+pub mod approach;
 pub mod flight_plan;
+pub mod hold;
+pub mod route;
 
-pub use aircraft::Aircraft;
+pub use aircraft::{Aircraft, FlightPhase, NavMode};
+pub use approach::{Approach, ApproachSchedule, ApproachType, Localizer, MissedApproach};
 pub use flight_plan::FlightPlan;
+pub use hold::{Hold, HoldDatabase, load_holds};
+pub use route::{Route, RouteExplanation, TokenKind, FixSource, LevelChange, parse_route_level};
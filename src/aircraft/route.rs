@@ -0,0 +1,529 @@
+use std::fmt;
+use crate::utils::airways::{AirwayDatabase, airway_segment, default_airways};
+use crate::utils::navigation::parse_coordinate_fix;
+use crate::utils::atmosphere::mach_to_tas;
+
+/// What a route token was read as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Sid,
+    Star,
+    Airway,
+    Fix,
+    /// DCT: straight to the next fix, never a fix or airway itself
+    Direct,
+    /// Speed/level change, e.g. N0450F350
+    Restriction,
+    Unknown,
+}
+
+/// Where a fix in the expanded route came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixSource {
+    Sid(String),
+    /// Named directly in the route string
+    Route,
+    Airway(String),
+    Star(String),
+}
+
+impl fmt::Display for FixSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixSource::Sid(name) => write!(f, "SID {}", name),
+            FixSource::Route => write!(f, "route"),
+            FixSource::Airway(name) => write!(f, "airway {}", name),
+            FixSource::Star(name) => write!(f, "STAR {}", name),
+        }
+    }
+}
+
+/// A speed/level change filed at a fix, e.g. TOLKA/N0450F370
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelChange {
+    pub fix: String,
+    /// True airspeed (kt), converted from a Mach number at the new level if filed as one
+    pub speed: u32,
+    /// New level (feet)
+    pub altitude: i32,
+}
+
+/// The steps of a route expansion, for debugging profiles
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteExplanation {
+    pub route_string: String,
+    pub tokens: Vec<(String, TokenKind)>,
+    pub fixes: Vec<(String, FixSource)>,
+}
+
+impl fmt::Display for RouteExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Route: {}", self.route_string)?;
+        for (token, kind) in &self.tokens {
+            writeln!(f, "  {:<12} {:?}", token, kind)?;
+        }
+        writeln!(f, "Expanded:")?;
+        for (fix, source) in &self.fixes {
+            writeln!(f, "  {:<12} {}", fix, source)?;
+        }
+        Ok(())
+    }
+}
+
+/// A filed route expanded into the fixes to fly: SID, enroute fixes and airways, then STAR
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub route_string: String,
+    pub departure: String,
+    pub arrival: Option<String>,
+    pub fixes: Vec<String>,
+    /// Index into `fixes` where the STAR begins (`fixes.len()` without one)
+    pub star_start: usize,
+    /// Speed/level changes filed at fixes along the route, in order
+    pub level_changes: Vec<LevelChange>,
+    tokens: Vec<(String, TokenKind)>,
+    sources: Vec<FixSource>,
+}
+
+impl Route {
+    /// Expand a route, taking SID/STAR runways from the route string (e.g. "BPK5K/09L")
+    pub fn new(route_string: String, departure: String, arrival: Option<String>) -> Self {
+        Self::with_runways(route_string, departure, arrival, None, None)
+    }
+
+    /// Expand a route, with runways overriding any given in the route string
+    pub fn with_runways(
+        route_string: String,
+        departure: String,
+        arrival: Option<String>,
+        departure_runway: Option<&str>,
+        arrival_runway: Option<&str>,
+    ) -> Self {
+        let mut route = Self {
+            route_string,
+            departure,
+            arrival,
+            fixes: Vec::new(),
+            star_start: 0,
+            level_changes: Vec::new(),
+            tokens: Vec::new(),
+            sources: Vec::new(),
+        };
+        route.expand(default_airways(), departure_runway, arrival_runway);
+        route
+    }
+
+    /// The original string, how each token was read, and where each fix came from
+    pub fn explain(&self) -> RouteExplanation {
+        RouteExplanation {
+            route_string: self.route_string.clone(),
+            tokens: self.tokens.clone(),
+            fixes: self.fixes.iter().cloned().zip(self.sources.iter().cloned()).collect(),
+        }
+    }
+
+    /// The SID and STAR tokens of a route string, without expanding them: the kind,
+    /// procedure name and any runway given after a '/'
+    pub fn procedures(route_string: &str) -> Vec<(TokenKind, String, Option<String>)> {
+        let airways = default_airways();
+        let parts: Vec<&str> = route_string.split_whitespace().collect();
+        parts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, part)| {
+                let kind = Self::classify(part, i, parts.len(), airways);
+                if !matches!(kind, TokenKind::Sid | TokenKind::Star) {
+                    return None;
+                }
+                let (name, runway) = match part.split_once('/') {
+                    Some((name, rwy)) => (name, Some(rwy.to_string())),
+                    None => (*part, None),
+                };
+                Some((kind, name.to_string(), runway))
+            })
+            .collect()
+    }
+
+    fn expand(&mut self, airways: &AirwayDatabase, departure_runway: Option<&str>, arrival_runway: Option<&str>) {
+        let route_string = self.route_string.clone();
+        let parts: Vec<&str> = route_string.split_whitespace().collect();
+        let mut pending_airway: Option<&str> = None;
+        let mut star_start = None;
+
+        for (i, part) in parts.iter().enumerate() {
+            let kind = Self::classify(part, i, parts.len(), airways);
+            self.tokens.push((part.to_string(), kind));
+
+            let (name, runway) = match part.split_once('/') {
+                Some((name, rwy)) => (name, Some(rwy)),
+                None => (*part, None),
+            };
+
+            let (fixes, source) = match kind {
+                TokenKind::Sid => {
+                    let runway = departure_runway.or(runway).unwrap_or("");
+                    (extract_sid_waypoints(&self.departure, name, runway), FixSource::Sid(name.to_string()))
+                }
+                TokenKind::Star => {
+                    let fixes = match &self.arrival {
+                        Some(arrival) => extract_star_waypoints(arrival, name, arrival_runway.or(runway)),
+                        None => Vec::new(),
+                    };
+                    (fixes, FixSource::Star(name.to_string()))
+                }
+                TokenKind::Fix => {
+                    if let Some((speed, altitude)) = runway.and_then(parse_speed_level) {
+                        self.level_changes.push(LevelChange { fix: name.to_uppercase(), speed, altitude });
+                    }
+                    (vec![name.to_uppercase()], FixSource::Route)
+                }
+                TokenKind::Airway => {
+                    pending_airway = Some(part);
+                    continue;
+                }
+                TokenKind::Direct => {
+                    // Straight between the fixes either side, so no airway to fill in
+                    if let Some(airway) = pending_airway.take() {
+                        tracing::debug!("[ROUTE] Ignoring {} before DCT", airway);
+                    }
+                    continue;
+                }
+                TokenKind::Restriction | TokenKind::Unknown => continue,
+            };
+
+            // Fill in the airway between the last fix and this one
+            if let (Some(airway), Some(from), Some(to)) = (pending_airway.take(), self.fixes.last(), fixes.first()) {
+                match airways.get(airway).and_then(|fixes| airway_segment(fixes, from, to)) {
+                    Some(segment) => {
+                        let segment_source = FixSource::Airway(airway.to_string());
+                        self.append(segment, &segment_source);
+                    }
+                    None => tracing::debug!("[ROUTE] Can't expand {} between {} and {}", airway, from, to),
+                }
+            }
+
+            if kind == TokenKind::Star {
+                star_start = Some(self.fixes.len());
+            }
+            self.append(fixes, &source);
+        }
+
+        self.star_start = star_start.unwrap_or(self.fixes.len());
+    }
+
+    /// Append fixes, skipping a repeat of the last fix (e.g. a SID ending where the route starts)
+    fn append(&mut self, fixes: Vec<String>, source: &FixSource) {
+        for fix in fixes {
+            if self.fixes.last() != Some(&fix) {
+                self.fixes.push(fix);
+                self.sources.push(source.clone());
+            }
+        }
+    }
+
+    fn classify(token: &str, index: usize, count: usize, airways: &AirwayDatabase) -> TokenKind {
+        if token == "DCT" {
+            return TokenKind::Direct;
+        }
+
+        let (name, suffix) = match token.split_once('/') {
+            Some((name, suffix)) => (name, Some(suffix)),
+            None => (token, None),
+        };
+
+        if index == 0 && suffix.is_some() && is_procedure_name(name) {
+            return TokenKind::Sid;
+        }
+        if index == count - 1 && count >= 2 && is_procedure_name(name) {
+            return TokenKind::Star;
+        }
+        if is_speed_level(token) {
+            return TokenKind::Restriction;
+        }
+        if suffix.is_none() && (airways.contains_key(token) || looks_like_airway(token)) {
+            return TokenKind::Airway;
+        }
+        // A fix or coordinate waypoint, possibly with a speed/level change (e.g. DVR/N0450F350)
+        let is_fix = (3..=6).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphabetic());
+        if (is_fix || parse_coordinate_fix(name).is_some()) && suffix.is_none_or(is_speed_level) {
+            return TokenKind::Fix;
+        }
+        TokenKind::Unknown
+    }
+
+    /// Fixes of the STAR at the end of a route, for `runway` if given
+    pub fn star_fixes(arrival: &str, route: &str, runway: Option<&str>) -> Vec<String> {
+        let parts: Vec<&str> = route.split_whitespace().collect();
+        if parts.len() < 2 {
+            return Vec::new();
+        }
+
+        let star_part = parts[parts.len() - 1];
+        let (star_name, route_runway) = match star_part.split_once('/') {
+            Some((name, rwy)) => (name, Some(rwy)),
+            None => (star_part, None),
+        };
+        if !is_procedure_name(star_name) {
+            return Vec::new();
+        }
+        extract_star_waypoints(arrival, star_name, runway.or(route_runway))
+    }
+}
+
+/// Extract SID waypoints from the SID file
+fn extract_sid_waypoints(departure: &str, sid_name: &str, runway: &str) -> Vec<String> {
+    let sid_file = format!("data/Airports/{}/Sids.txt", departure);
+    if let Ok(content) = std::fs::read_to_string(&sid_file) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            // Format: SID:ICAO:RUNWAY:SIDNAME:FIXES...
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() >= 5 && parts[0] == "SID" && parts[3] == sid_name && parts[2] == runway {
+                let waypoints: Vec<String> = parts[4]
+                    .split_whitespace()
+                    .map(|s| s.to_uppercase())
+                    .collect();
+
+                tracing::debug!("[ROUTE] Found SID {} for runway {}: {} waypoints",
+                               sid_name, runway, waypoints.len());
+                return waypoints;
+            }
+        }
+        tracing::warn!("[ROUTE] SID {} not found for runway {} at {}", sid_name, runway, departure);
+    } else {
+        tracing::warn!("[ROUTE] Could not read SID file: {}", sid_file);
+    }
+
+    Vec::new()
+}
+
+/// Extract STAR waypoints from the STAR file, using the first runway listed
+/// for the STAR if none is given
+fn extract_star_waypoints(arrival: &str, star_name: &str, runway: Option<&str>) -> Vec<String> {
+    let star_file = format!("data/Airports/{}/Stars.txt", arrival);
+    if let Ok(content) = std::fs::read_to_string(&star_file) {
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            // Format: STAR:ICAO:RUNWAY:STARNAME:FIXES...
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() >= 5 && parts[0] == "STAR" && parts[3] == star_name
+                && runway.is_none_or(|rwy| parts[2] == rwy)
+            {
+                let waypoints: Vec<String> = parts[4]
+                    .split_whitespace()
+                    .map(|s| s.to_uppercase())
+                    .collect();
+
+                tracing::debug!("[ROUTE] Found STAR {} for runway {}: {} waypoints",
+                               star_name, parts[2], waypoints.len());
+                return waypoints;
+            }
+        }
+        tracing::warn!("[ROUTE] STAR {} not found for runway {} at {}",
+                      star_name, runway.unwrap_or("-"), arrival);
+    } else {
+        tracing::warn!("[ROUTE] Could not read STAR file: {}", star_file);
+    }
+
+    Vec::new()
+}
+
+/// Check whether a route token looks like a SID/STAR name (e.g. LOGAN2H, ALESO1H, CLN2E)
+fn is_procedure_name(token: &str) -> bool {
+    let letters = token.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    let rest = &token[letters..];
+    let mut rest_chars = rest.chars();
+
+    (3..=5).contains(&letters)
+        && rest_chars.next().is_some_and(|c| c.is_ascii_digit())
+        && rest_chars.all(|c| c.is_ascii_alphabetic())
+        && rest.len() <= 2
+}
+
+/// Airway designators: up to two letters then a number, e.g. P44, M197, UL9
+fn looks_like_airway(token: &str) -> bool {
+    let letters = token.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    (2..=5).contains(&token.len())
+        && (1..=2).contains(&letters)
+        && token[letters..].chars().next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Parse a speed/level group (e.g. N0450F370, M078S1130) into a true airspeed (kt)
+/// and level (feet)
+pub fn parse_speed_level(token: &str) -> Option<(u32, i32)> {
+    if !is_speed_level(token) {
+        return None;
+    }
+    let speed_len = if token.starts_with('M') { 4 } else { 5 };
+    let (speed, level) = token.split_at(speed_len);
+    let level_value: f64 = level[1..].parse().ok()?;
+
+    let altitude = match &level[..1] {
+        // Flight level or altitude in hundreds of feet
+        "F" | "A" => level_value * 100.0,
+        // Standard metric level or altitude in tens of metres
+        _ => metric_level(level_value) * 100.0,
+    };
+    let speed_value: f64 = speed[1..].parse().ok()?;
+    let tas = match &speed[..1] {
+        "N" => speed_value,
+        "K" => speed_value / 1.852,
+        _ => mach_to_tas(speed_value / 100.0, altitude),
+    };
+
+    Some((tas.round() as u32, altitude.round() as i32))
+}
+
+/// Parse a level (hundreds of feet) from a route token: a speed/level group, or a
+/// bare FL370, F370, A050 or metric S1130
+pub fn parse_route_level(token: &str) -> Option<u32> {
+    if let Some((_, altitude)) = parse_speed_level(token) {
+        return Some((altitude as f64 / 100.0).round() as u32);
+    }
+
+    let (digits, metric) = if let Some(level) = token.strip_prefix("FL") {
+        ((2..=3).contains(&level.len()).then_some(level)?, false)
+    } else if let Some(level) = token.strip_prefix('F').or_else(|| token.strip_prefix('A')) {
+        ((level.len() == 3).then_some(level)?, false)
+    } else {
+        ((token.len() == 5).then(|| token.strip_prefix('S'))??, true)
+    };
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let value: f64 = digits.parse().ok()?;
+    let level = if metric { metric_level(value) } else { value };
+    Some(level as u32)
+}
+
+/// Level (hundreds of feet) for a metric level in tens of metres, to the nearest 100ft
+fn metric_level(tens_of_metres: f64) -> f64 {
+    (tens_of_metres * 10.0 / 0.3048 / 100.0).round()
+}
+
+/// Speed/level groups: N0450F350, M078F350, K0830S1130, N0250A045
+fn is_speed_level(token: &str) -> bool {
+    let speed_len = match token.chars().next() {
+        Some('N') | Some('K') => 5,
+        Some('M') => 4,
+        _ => return false,
+    };
+    let Some(level) = token.get(speed_len..) else {
+        return false;
+    };
+
+    token[1..speed_len].chars().all(|c| c.is_ascii_digit())
+        && matches!(level.chars().next(), Some('F' | 'A' | 'S' | 'M'))
+        && (4..=5).contains(&level.len())
+        && level[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_classifies_tokens() {
+        let route = Route::new(
+            "BPK5K/09L N0450F350 DVR UL9 KONAN DCT ALESO1H/27R".to_string(),
+            "EGLL".to_string(),
+            Some("EGLL".to_string()),
+        );
+        let explanation = route.explain();
+
+        let kinds: Vec<TokenKind> = explanation.tokens.iter().map(|(_, kind)| *kind).collect();
+        assert_eq!(kinds, vec![
+            TokenKind::Sid,
+            TokenKind::Restriction,
+            TokenKind::Fix,
+            TokenKind::Airway,
+            TokenKind::Fix,
+            TokenKind::Direct,
+            TokenKind::Star,
+        ]);
+
+        let source_of = |fix: &str| explanation.fixes.iter().find(|(f, _)| f == fix).map(|(_, s)| s.clone());
+        assert_eq!(source_of("BAPAG"), Some(FixSource::Sid("BPK5K".to_string())));
+        assert_eq!(source_of("DVR"), Some(FixSource::Route));
+        assert_eq!(source_of("TIGER"), Some(FixSource::Star("ALESO1H".to_string())));
+        assert_eq!(explanation.fixes.len(), route.fixes.len());
+        assert_eq!(route.fixes[route.star_start], "ALESO");
+    }
+
+    #[test]
+    fn test_procedure_tokens() {
+        let procedures = Route::procedures("BPK5K/09L DVR UL9 KONAN ALESO1H");
+        assert_eq!(procedures, vec![
+            (TokenKind::Sid, "BPK5K".to_string(), Some("09L".to_string())),
+            (TokenKind::Star, "ALESO1H".to_string(), None),
+        ]);
+        assert!(Route::procedures("DVR UL9 KONAN").is_empty());
+    }
+
+    #[test]
+    fn test_coordinate_waypoints() {
+        let route = Route::new("DVR 5130N00020W 51N020W/N0450F350 KONAN".to_string(), "EGLL".to_string(), None);
+        assert_eq!(route.fixes, vec!["DVR", "5130N00020W", "51N020W", "KONAN"]);
+        assert_eq!(route.explain().tokens[1], ("5130N00020W".to_string(), TokenKind::Fix));
+    }
+
+    #[test]
+    fn test_level_changes() {
+        let route = Route::new(
+            "BPK5K/09L N0450F350 DVR UL9 TOLKA/N0460F370 51N020W/M080F390 KONAN".to_string(),
+            "EGLL".to_string(),
+            None,
+        );
+        assert_eq!(route.level_changes, vec![
+            LevelChange { fix: "TOLKA".to_string(), speed: 460, altitude: 37000 },
+            LevelChange { fix: "51N020W".to_string(), speed: 459, altitude: 39000 },
+        ]);
+
+        assert_eq!(parse_speed_level("K0830S1130"), Some((448, 37100)));
+        assert_eq!(parse_speed_level("N0450A045"), Some((450, 4500)));
+        assert_eq!(parse_speed_level("TOLKA"), None);
+    }
+
+    #[test]
+    fn test_route_levels() {
+        assert_eq!(parse_route_level("FL370"), Some(370));
+        assert_eq!(parse_route_level("F370"), Some(370));
+        assert_eq!(parse_route_level("A050"), Some(50));
+        assert_eq!(parse_route_level("S1130"), Some(371));
+        assert_eq!(parse_route_level("N0450F370"), Some(370));
+        assert_eq!(parse_route_level("K0830S1130"), Some(371));
+
+        // Fixes and airways are not levels
+        for token in ["FLEET", "A1", "A34", "SAM", "SFD", "L9", "DCT"] {
+            assert_eq!(parse_route_level(token), None, "{}", token);
+        }
+    }
+
+    #[test]
+    fn test_direct_segment() {
+        let route = Route::new("BRAIN DCT RATLO".to_string(), "EGLL".to_string(), None);
+        assert_eq!(route.fixes, vec!["BRAIN", "RATLO"]);
+        assert_eq!(route.explain().tokens[1], ("DCT".to_string(), TokenKind::Direct));
+
+        // DCT overrides an airway left dangling before it
+        let route = Route::new("BRAIN P44 DCT RATLO".to_string(), "EGLL".to_string(), None);
+        assert_eq!(route.fixes, vec!["BRAIN", "RATLO"]);
+    }
+
+    #[test]
+    fn test_airway_expanded_between_fixes() {
+        let route = Route::new("BRAIN P44 RATLO".to_string(), "EGLL".to_string(), None);
+
+        assert_eq!(route.fixes, vec!["BRAIN", "DAGGA", "CLN", "RATLO"]);
+        assert_eq!(route.explain().fixes[1].1, FixSource::Airway("P44".to_string()));
+    }
+}
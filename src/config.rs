@@ -2,19 +2,38 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use anyhow::{Result, Context};
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+use crate::aircraft::ApproachSchedule;
+use crate::utils::atmosphere::STANDARD_QNH;
+use crate::utils::performance::ClimbProfile;
+use crate::utils::wake::WakeCategory;
+use crate::utils::wind::WindField;
 
 /// Configuration for a single departure route
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DepartureRoute {
     pub route: String,
     pub arriving: String,
+    /// Relative likelihood of this route being picked (default 1)
+    #[serde(default = "default_route_weight")]
+    pub weight: f64,
+}
+
+fn default_route_weight() -> f64 {
+    1.0
 }
 
 /// Configuration for standard departures from an airport
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct StandardDeparture {
     pub departing: String,
     pub interval: u64, // seconds between spawns
+    /// Intervals that take over from `interval` as the session goes on:
+    /// (minutes since start, seconds between spawns), in order
+    #[serde(default)]
+    pub interval_schedule: Vec<(u32, u64)>,
     pub routes: Vec<DepartureRoute>,
 }
 
@@ -24,10 +43,42 @@ pub struct StandardDeparture {
 pub struct TransitRoute {
     pub departing: String,
     pub arriving: String,
+    /// Level to enter at (feet), or a level such as "FL370" or metric "S1130"
+    #[serde(deserialize_with = "deserialize_level")]
     pub current_level: u32,
+    /// Cruise level (feet), or a level such as "FL370" or metric "S1130"
+    #[serde(deserialize_with = "deserialize_level")]
     pub cruise_level: u32,
     pub route: String,
     pub first_controller: String,
+    /// Relative likelihood of this route being picked (default 1)
+    #[serde(default = "default_route_weight")]
+    pub weight: f64,
+}
+
+/// Altitude (feet) from a profile level: plain feet, FL370, A050, or metric S1130
+/// (tens of metres, to the nearest 100ft as in a route)
+pub fn parse_level_feet(level: &str) -> Option<u32> {
+    let level = level.trim().to_uppercase();
+    if !level.is_empty() && level.chars().all(|c| c.is_ascii_digit()) {
+        return level.parse().ok();
+    }
+    crate::aircraft::parse_route_level(&level).map(|level| level * 100)
+}
+
+fn deserialize_level<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<u32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Level {
+        Feet(u32),
+        Text(String),
+    }
+
+    match Level::deserialize(deserializer)? {
+        Level::Feet(feet) => Ok(feet),
+        Level::Text(text) => parse_level_feet(&text)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid level \"{}\"", text))),
+    }
 }
 
 /// Configuration for standard transits
@@ -37,6 +88,119 @@ pub struct StandardTransit {
     pub routes: Vec<TransitRoute>,
 }
 
+/// A continuous stream of aircraft entering at one fix on the same route,
+/// staggered through a level band
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryStream {
+    pub entry_fix: String,
+    pub departing: String,
+    pub arriving: String,
+    /// Route from the entry fix onwards
+    pub route: String,
+    /// Lowest and highest levels of the band (feet)
+    pub min_level: u32,
+    pub max_level: u32,
+    /// Level change between successive aircraft (feet)
+    #[serde(default = "default_level_step")]
+    pub level_step: u32,
+    /// In-trail spacing between successive aircraft (NM)
+    pub spacing_nm: f64,
+    /// Speed the stream flies at (knots)
+    #[serde(default = "default_stream_speed")]
+    pub speed: u32,
+}
+
+fn default_level_step() -> u32 {
+    1000
+}
+
+fn default_stream_speed() -> u32 {
+    420
+}
+
+impl EntryStream {
+    /// Seconds between aircraft to keep the spacing at the stream's speed
+    pub fn interval_seconds(&self) -> f64 {
+        self.spacing_nm / self.speed.max(1) as f64 * 3600.0
+    }
+
+    /// Level of the `n`th aircraft, working up through the band and starting again
+    pub fn level_for(&self, n: usize) -> u32 {
+        let step = self.level_step.max(1);
+        let levels = (self.max_level.saturating_sub(self.min_level) / step + 1) as usize;
+        self.min_level + (n % levels) as u32 * step
+    }
+}
+
+/// Standing agreement for the AI master controller to descend arrivals for an
+/// airport so they are level by a boundary fix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DescentAgreement {
+    pub arriving: String,
+    /// Fix the arrivals must be level by
+    pub boundary_fix: String,
+    /// Agreed level (feet)
+    pub level: i32,
+}
+
+/// A link in the handoff chain: aircraft worked by the `from` controller are handed
+/// to the `to` controller as they pass the release fix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandoffPoint {
+    pub from: String,
+    pub to: String,
+    pub release_fix: String,
+}
+
+/// The published missed approach for one runway, the last fix being the hold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissedApproach {
+    pub aerodrome: String,
+    pub runway: String,
+    pub fixes: Vec<String>,
+}
+
+/// Local traffic levels for one aerodrome
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AerodromeTraffic {
+    /// Seconds between departures, overriding the standard departure interval
+    #[serde(default)]
+    pub departure_interval: Option<u64>,
+    /// Seconds between arrivals spawned on final
+    #[serde(default)]
+    pub arrival_interval: Option<u64>,
+    /// Airports the arrivals come from, picked at random
+    #[serde(default)]
+    pub arrival_origins: Vec<String>,
+}
+
+/// A specific aircraft a scripted scenario spawns, instead of a randomly generated one.
+/// With a `level` it enters at the first fix of its route; otherwise it departs from
+/// `departing`'s active runway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptedSpawn {
+    pub callsign: String,
+    pub aircraft_type: String,
+    pub departing: String,
+    pub arriving: String,
+    pub route: String,
+    /// Seconds after the start of the session to spawn at (default straight away)
+    #[serde(default)]
+    pub at: u64,
+    /// Level to enter at (feet), for aircraft that aren't departures
+    #[serde(default)]
+    pub level: Option<u32>,
+    /// Speed to enter at (knots)
+    #[serde(default = "default_stream_speed")]
+    pub speed: u32,
+}
+
 /// Main profile configuration loaded from JSON
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -45,10 +209,20 @@ pub struct ProfileConfig {
     pub std_departures: Vec<StandardDeparture>,
     #[serde(default)]
     pub std_transits: Vec<StandardTransit>,
+    #[serde(default)]
+    pub entry_streams: Vec<EntryStream>,
     
     // Profile-specific settings
     pub active_aerodromes: Vec<String>,
     pub active_runways: HashMap<String, String>,
+    /// Runway departures use at aerodromes running segregated parallels, in place of
+    /// the active runway
+    #[serde(default)]
+    pub departure_runways: HashMap<String, String>,
+    /// Runway arrivals use at aerodromes running segregated parallels, in place of
+    /// the active runway
+    #[serde(default)]
+    pub arrival_runways: HashMap<String, String>,
     pub active_controllers: Vec<String>,
     pub master_controller: String,
     pub master_controller_freq: String,
@@ -56,6 +230,36 @@ pub struct ProfileConfig {
     pub inactive_sectors: Vec<String>,
     #[serde(default)]
     pub other_controllers: Vec<(String, String)>,
+    /// Cross-coupling: frequency to the extra controller callsigns that also receive it
+    #[serde(default)]
+    pub coupled_frequencies: HashMap<String, Vec<String>>,
+    /// Descents the AI master controller gives arrivals on its own
+    #[serde(default)]
+    pub descent_agreements: Vec<DescentAgreement>,
+    /// Specific aircraft to spawn, alongside the randomly generated traffic
+    #[serde(default)]
+    pub scripted_spawns: Vec<ScriptedSpawn>,
+    /// Where AI controllers hand aircraft on to the next controller
+    #[serde(default)]
+    pub handoffs: Vec<HandoffPoint>,
+    /// Tracks flown after a go-around, for runways the simulation config doesn't cover
+    #[serde(default)]
+    pub missed_approaches: Vec<MissedApproach>,
+    /// Arrivals fly a continuous descent from top of descent to the end of their route
+    #[serde(default)]
+    pub continuous_descent: bool,
+    /// Per-aerodrome departure and arrival rates
+    #[serde(default)]
+    pub aerodrome_traffic: HashMap<String, AerodromeTraffic>,
+    /// Transition level (flight level, e.g. 70), for the session briefing
+    #[serde(default)]
+    pub transition_level: Option<u32>,
+    /// Special procedures and other notes for the session briefing
+    #[serde(default)]
+    pub session_notes: Vec<String>,
+    /// METARs by station, reported to controllers and setting the AI pilots' QNH
+    #[serde(default)]
+    pub metars: HashMap<String, String>,
 }
 
 impl ProfileConfig {
@@ -68,6 +272,16 @@ impl ProfileConfig {
     }
 }
 
+/// What happens to an arrival that reaches the end of its route with no STAR or approach
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StarlessArrivals {
+    /// Remove it at the field and disconnect its pilot
+    #[default]
+    Remove,
+    /// Descend and fly a straight-in ILS to the active runway
+    StraightIn,
+}
+
 /// Simulation constants (from Constants.py)
 #[derive(Debug, Clone)]
 pub struct SimulationConfig {
@@ -80,6 +294,88 @@ pub struct SimulationConfig {
     pub high_descent_rate: f64,
     pub time_multiplier: f64,
     pub radar_update_rate: f64,
+    /// Maximum seconds between position updates for a pilot, even when stationary.
+    /// Only comes into play when radar reports are further apart than this: with
+    /// adaptive radar updates (parked aircraft report at 3x the interval) or a
+    /// radar report interval above 10s
+    pub pilot_keepalive_interval: f64,
+    /// Real name AI pilots log in with (shown in controller tags via RN queries)
+    pub pilot_name: String,
+    /// QNH (hPa) AI pilots set their altimeters to
+    pub qnh: f64,
+    /// Minimum in-trail spacing between arrivals on final (NM)
+    pub arrival_spacing_nm: f64,
+    /// Open arrival spacing up to the ICAO wake minima where they're larger
+    pub wake_spacing: bool,
+    /// Slow arrivals on final that are closer than the arrival spacing to the one ahead
+    pub approach_spacing: bool,
+    /// Spawn arrivals already established on the ILS instead of flying the STAR
+    pub ils_arrivals: bool,
+    /// Distance from the threshold at which ILS arrivals are spawned (NM)
+    pub ils_spawn_range_nm: f64,
+    /// Altitude at which ILS arrivals are spawned (feet)
+    pub ils_spawn_altitude: i32,
+    /// Cruise altitude (feet) for local flights that return to their departure aerodrome
+    pub local_flight_altitude: i32,
+    /// Start with transit arrivals already spread along their routes, as if the
+    /// session had been running
+    pub prefill_transit_arrivals: bool,
+    /// Pilots squawk conspicuity until told their assigned code, rather than from spawn
+    pub squawk_on_instruction: bool,
+    /// Delay between starting successive AI controller logins (ms)
+    pub controller_login_delay_ms: u64,
+    /// Maximum number of AI controller logins in flight at once
+    pub controller_login_concurrency: usize,
+    /// Wind by position and altitude
+    pub wind: WindField,
+    /// Departures hold short until cleared for takeoff instead of departing on their own
+    pub require_takeoff_clearance: bool,
+    /// Altitude arrivals descend to at top of descent (feet)
+    pub arrival_descent_altitude: i32,
+    /// Speed arrivals descend at (knots)
+    pub descent_speed: u32,
+    /// How far before top of descent to start slowing to the descent speed (NM)
+    pub descent_anticipation_nm: f64,
+    /// Chance (0-1) that a new aircraft has a failed transponder and shows primary-only
+    pub primary_only_chance: f64,
+    /// How strongly departures favour routes whose SID heads towards their destination
+    /// (0 ignores direction and uses the route weights alone)
+    pub sid_direction_bias: f64,
+    /// Simulate aircraft without connecting anything to the FSD server, for
+    /// exercising spawn and navigation logic offline
+    pub ghost_mode: bool,
+    /// Maximum arrivals per hour entering the arrival stream; extra arrivals
+    /// wait in a queue (0 = unmetered)
+    pub arrival_flow_per_hour: f64,
+    /// Lowest altitude (feet) at which aircraft accept a Mach speed assignment
+    pub min_mach_altitude: i32,
+    /// Handling of arrivals whose route ends at the airport without a STAR
+    pub starless_arrivals: StarlessArrivals,
+    /// Seconds between radar position reports for each aircraft
+    pub radar_report_interval: f64,
+    /// Random variation (seconds, either way) in the time between reports
+    pub radar_jitter_secs: f64,
+    /// Delay (seconds) between a position being taken and it reaching the server
+    pub radar_latency_secs: f64,
+    /// Report fast or turning aircraft twice as often, and aircraft parked on the
+    /// ground a third as often, as the report interval
+    pub adaptive_radar_updates: bool,
+    /// Warn when a departure and an arrival are predicted at a shared fix within this
+    /// many seconds of each other (0 disables the check)
+    pub conflict_window_secs: f64,
+    /// Vertical separation (feet) below which a predicted meeting at a fix is a conflict
+    pub conflict_vertical_ft: i32,
+    /// Climb performance that varies with time airborne, by wake category
+    pub climb_profiles: HashMap<WakeCategory, ClimbProfile>,
+    /// Speeds flown on final, by wake category (types without one fly the default)
+    pub approach_schedules: HashMap<WakeCategory, ApproachSchedule>,
+    /// Altitude (feet) climbed to on a missed approach, unless already higher
+    pub missed_approach_altitude: i32,
+    /// Published missed-approach tracks by "<ICAO> <runway>", e.g. "EGLL 27R", the
+    /// last fix being the hold
+    pub missed_approaches: HashMap<String, Vec<String>>,
+    /// Fly the ILS again after a missed approach instead of holding
+    pub rejoin_after_missed_approach: bool,
     
     pub airport_elevations: HashMap<String, u32>,
 }
@@ -113,16 +409,137 @@ impl Default for SimulationConfig {
             high_descent_rate: -3000.0,
             time_multiplier: 1.0,
             radar_update_rate: 5.0,
+            pilot_keepalive_interval: 10.0,
+            pilot_name: "AI Pilot".to_string(),
+            qnh: STANDARD_QNH,
+            arrival_spacing_nm: 3.0,
+            wake_spacing: true,
+            approach_spacing: false,
+            ils_arrivals: false,
+            ils_spawn_range_nm: 10.0,
+            ils_spawn_altitude: 3000,
+            local_flight_altitude: 5000,
+            prefill_transit_arrivals: false,
+            squawk_on_instruction: false,
+            controller_login_delay_ms: 200,
+            controller_login_concurrency: 1,
+            wind: WindField::default(),
+            require_takeoff_clearance: false,
+            arrival_descent_altitude: 7000,
+            descent_speed: 280,
+            descent_anticipation_nm: 5.0,
+            primary_only_chance: 0.0,
+            sid_direction_bias: 0.0,
+            ghost_mode: false,
+            arrival_flow_per_hour: 0.0,
+            min_mach_altitude: 24500,
+            starless_arrivals: StarlessArrivals::default(),
+            radar_report_interval: 5.0,
+            radar_jitter_secs: 0.0,
+            radar_latency_secs: 0.0,
+            adaptive_radar_updates: false,
+            conflict_window_secs: 120.0,
+            conflict_vertical_ft: 1000,
+            climb_profiles: HashMap::from([
+                (WakeCategory::Super, ClimbProfile { initial_factor: 0.6, recovery_minutes: 25.0 }),
+                (WakeCategory::Heavy, ClimbProfile { initial_factor: 0.7, recovery_minutes: 20.0 }),
+            ]),
+            approach_schedules: HashMap::from([
+                (WakeCategory::Super, ApproachSchedule { gates: vec![(10.0, 220), (6.0, 180)], vref: 145, additive: 5 }),
+                (WakeCategory::Heavy, ApproachSchedule { gates: vec![(10.0, 220), (6.0, 180)], vref: 150, additive: 5 }),
+                (WakeCategory::Medium, ApproachSchedule::default()),
+                (WakeCategory::Light, ApproachSchedule { gates: vec![(10.0, 140), (6.0, 110)], vref: 75, additive: 5 }),
+            ]),
+            missed_approach_altitude: 3000,
+            missed_approaches: HashMap::new(),
+            rejoin_after_missed_approach: false,
             airport_elevations,
         }
     }
 }
 
+impl SimulationConfig {
+    /// In-trail spacing target (NM) for `follower_type` behind `leader_type` on final
+    pub fn arrival_spacing(&self, leader_type: &str, follower_type: &str) -> f64 {
+        crate::utils::wake::in_trail_spacing_nm(
+            leader_type,
+            follower_type,
+            self.arrival_spacing_nm,
+            self.wake_spacing,
+        )
+    }
+}
+
 /// Fleet configuration (which airlines fly which aircraft)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FleetConfig {
+    /// Airline to the aircraft types it flies
     pub airlines: HashMap<String, Vec<String>>,
+    /// Airport to the airlines that fly from it
     pub airports: HashMap<String, Vec<String>>,
+    /// Relative weights of airlines at an airport, by airport then airline (default 1)
+    #[serde(default)]
+    pub airport_weights: HashMap<String, HashMap<String, f64>>,
+    /// Relative weights of an airline's aircraft types, by airline then type (default 1)
+    #[serde(default)]
+    pub airline_weights: HashMap<String, HashMap<String, f64>>,
+}
+
+impl FleetConfig {
+    /// Load a fleet from a JSON file with `airlines` and `airports` maps. Every airline
+    /// an airport lists must have its aircraft types given under `airlines`.
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read fleet: {}", path))?;
+        let fleet: FleetConfig = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse fleet JSON: {}", path))?;
+
+        let mut unknown: Vec<String> = fleet.airports
+            .iter()
+            .flat_map(|(airport, airlines)| {
+                airlines.iter()
+                    .filter(|airline| !fleet.airlines.contains_key(*airline))
+                    .map(move |airline| format!("{} at {}", airline, airport))
+            })
+            .collect();
+        unknown.sort();
+        anyhow::ensure!(unknown.is_empty(), "Fleet {} has airlines with no aircraft: {}", path, unknown.join(", "));
+
+        Ok(fleet)
+    }
+
+    /// Pick an airline flying from an airport, in proportion to its weight there
+    pub fn pick_airline<R: Rng + ?Sized>(&self, airport: &str, rng: &mut R) -> Option<&str> {
+        let airlines = self.airports.get(airport)?;
+        pick_weighted(airlines, self.airport_weights.get(airport), rng)
+    }
+
+    /// Pick one of an airline's aircraft types, in proportion to its weight
+    pub fn pick_type<R: Rng + ?Sized>(&self, airline: &str, rng: &mut R) -> Option<&str> {
+        let types = self.airlines.get(airline)?;
+        pick_weighted(types, self.airline_weights.get(airline), rng)
+    }
+}
+
+/// Pick from a list by weight, with unlisted entries weighted 1 and uniform choice
+/// when the weights are unusable (e.g. all zero)
+fn pick_weighted<'a, R: Rng + ?Sized>(
+    choices: &'a [String],
+    weights: Option<&HashMap<String, f64>>,
+    rng: &mut R,
+) -> Option<&'a str> {
+    if choices.is_empty() {
+        return None;
+    }
+    let index = weights
+        .and_then(|weights| {
+            let weights = choices.iter().map(|choice| weights.get(choice).copied().unwrap_or(1.0));
+            WeightedIndex::new(weights).ok()
+        })
+        .map(|weighted| weighted.sample(rng))
+        .unwrap_or_else(|| rng.gen_range(0..choices.len()));
+    Some(choices[index].as_str())
 }
 
 impl Default for FleetConfig {
@@ -185,14 +602,27 @@ impl Default for FleetConfig {
         Self {
             airlines,
             airports,
+            airport_weights: HashMap::new(),
+            airline_weights: HashMap::new(),
         }
     }
 }
 
-/// CCAMS squawk ranges
+/// Emergency codes (hijack, radio failure, general emergency), never assigned
+pub const RESERVED_SQUAWKS: [u16; 3] = [7500, 7600, 7700];
+
+/// Whether a code, written in decimal as it reads (e.g. 4521), is a squawk: four octal digits
+pub fn is_valid_squawk(code: u16) -> bool {
+    code <= 7777 && format!("{:04}", code).chars().all(|c| c <= '7')
+}
+
+/// Whether a code can be given to an aircraft: a valid squawk other than an emergency code
+pub fn is_assignable_squawk(code: u16) -> bool {
+    is_valid_squawk(code) && !RESERVED_SQUAWKS.contains(&code)
+}
+
+/// CCAMS squawk ranges, as a sorted set of valid codes
 pub fn get_ccams_squawks() -> Vec<u16> {
-    let mut squawks = Vec::new();
-    
     let ranges = [
         (201, 277), (301, 377), (470, 477), (501, 577),
         (730, 767), (1070, 1077), (1140, 1176), (1410, 1477),
@@ -207,10 +637,23 @@ pub fn get_ccams_squawks() -> Vec<u16> {
         (6001, 6037),
     ];
     
-    for (start, end) in ranges {
-        squawks.extend(start..=end);
+    squawks_in_ranges(&ranges)
+}
+
+/// Every code in a list of inclusive ranges, sorted with no duplicates. Inverted
+/// ranges are skipped, as are numbers that aren't assignable squawks.
+fn squawks_in_ranges(ranges: &[(u16, u16)]) -> Vec<u16> {
+    let mut squawks = Vec::new();
+    for &(start, end) in ranges {
+        if start > end {
+            tracing::warn!("[CONFIG] Ignoring inverted squawk range {:04}-{:04}", start, end);
+            continue;
+        }
+        squawks.extend((start..=end).filter(|&code| is_assignable_squawk(code)));
     }
-    
+
+    squawks.sort_unstable();
+    squawks.dedup();
     squawks
 }
 
@@ -237,10 +680,96 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_metric_transit_levels() {
+        assert_eq!(parse_level_feet("S1130"), Some(37100));
+        assert_eq!(parse_level_feet("S1130"), crate::aircraft::parse_route_level("S1130").map(|level| level * 100));
+        assert_eq!(parse_level_feet("FL370"), Some(37000));
+        assert_eq!(parse_level_feet("A050"), Some(5000));
+        assert_eq!(parse_level_feet("24000"), Some(24000));
+        assert_eq!(parse_level_feet("S11"), None);
+
+        let route: TransitRoute = serde_json::from_str(r#"{
+            "departing": "UUEE", "arriving": "EGLL", "currentLevel": "S1130", "cruiseLevel": 37000,
+            "route": "KONAN L607 REDFA", "firstController": "LON_E_CTR"
+        }"#).unwrap();
+        assert_eq!((route.current_level, route.cruise_level), (37100, 37000));
+        assert!(serde_json::from_str::<TransitRoute>(r#"{
+            "departing": "UUEE", "arriving": "EGLL", "currentLevel": "high", "cruiseLevel": 37000,
+            "route": "KONAN", "firstController": "LON_E_CTR"
+        }"#).is_err());
+    }
+
+    #[test]
+    fn test_arrival_spacing() {
+        let config = SimulationConfig::default();
+
+        let heavy_light = config.arrival_spacing("B772", "C172");
+        let medium_medium = config.arrival_spacing("A320", "A320");
+        assert_eq!(heavy_light, 6.0);
+        assert_eq!(medium_medium, config.arrival_spacing_nm);
+        assert!(heavy_light > medium_medium);
+    }
+
+    #[test]
+    fn test_load_fleet() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("sweatbox-fleet-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        fs::write(path, r#"{
+            "airlines": {"BAW": ["A320", "B772"], "EZY": ["A319"]},
+            "airports": {"EGLL": ["BAW"], "EGKK": ["BAW", "EZY"]}
+        }"#)?;
+        let fleet = FleetConfig::load(path)?;
+        assert_eq!(fleet.airports["EGKK"], vec!["BAW", "EZY"]);
+        assert_eq!(fleet.airlines["BAW"], vec!["A320", "B772"]);
+
+        // An airline with no aircraft listed
+        fs::write(path, r#"{"airlines": {"BAW": ["A320"]}, "airports": {"EGLL": ["BAW", "VIR"]}}"#)?;
+        let error = FleetConfig::load(path).unwrap_err().to_string();
+        fs::remove_file(path)?;
+        assert!(error.ends_with("VIR at EGLL"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn test_weighted_fleet_selection() {
+        use rand::SeedableRng;
+        let fleet: FleetConfig = serde_json::from_str(r#"{
+            "airlines": {"BAW": ["A320", "A388"], "EZY": ["A319"]},
+            "airports": {"EGLL": ["BAW", "EZY"]},
+            "airportWeights": {"EGLL": {"BAW": 3}},
+            "airlineWeights": {"BAW": {"A320": 9, "A388": 1}}
+        }"#).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+        let a320s = (0..1000).filter(|_| fleet.pick_type("BAW", &mut rng) == Some("A320")).count();
+        assert!((850..950).contains(&a320s), "{}", a320s);
+        let baws = (0..1000).filter(|_| fleet.pick_airline("EGLL", &mut rng) == Some("BAW")).count();
+        assert!((700..800).contains(&baws), "{}", baws);
+
+        // No weights: uniform
+        let default = FleetConfig::default();
+        let ryanair = (0..3000).filter(|_| default.pick_airline("EGSS", &mut rng) == Some("RYR")).count();
+        assert!((900..1100).contains(&ryanair), "{}", ryanair);
+        assert_eq!(default.pick_type("XXX", &mut rng), None);
+    }
+
     #[test]
     fn test_ccams_squawks() {
         let squawks = get_ccams_squawks();
         assert!(!squawks.is_empty());
         println!("Generated {} CCAMS squawks", squawks.len());
+
+        // Sorted and unique, with every code a valid squawk
+        assert!(squawks.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(squawks.iter().all(|&code| is_assignable_squawk(code)));
+
+        let squawks = squawks_in_ranges(&[(201, 207), (205, 210), (300, 290)]);
+        assert_eq!(squawks, vec![201, 202, 203, 204, 205, 206, 207, 210]);
+
+        assert!(is_valid_squawk(4521) && is_valid_squawk(7));
+        assert!(!is_valid_squawk(6789) && !is_valid_squawk(10000));
+        assert!(is_valid_squawk(7700) && !is_assignable_squawk(7700));
     }
 }
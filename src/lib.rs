@@ -4,3 +4,4 @@ pub mod utils;
 pub mod scenario;
 pub mod simulation;
 pub mod aircraft;
+pub mod replay;
@@ -1,20 +1,16 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use tracing::{info, Level};
+use clap::{Parser, Subcommand, ValueEnum};
+use tracing::{info, warn, Level};
 use std::sync::Arc;
 
-mod server;
-mod utils;
-mod config;
-mod scenario;
-mod simulation;
-mod aircraft;
-
-use utils::navigation::load_navigation_data;
-use utils::performance::load_performance_data;
-use config::{SimulationConfig, FleetConfig};
-use scenario::Scenario;
-use simulation::Simulator;
+use custom_sweatbox_rust::aircraft::{load_holds, HoldDatabase};
+use custom_sweatbox_rust::replay;
+use custom_sweatbox_rust::server;
+use custom_sweatbox_rust::utils::navigation::load_navigation_data;
+use custom_sweatbox_rust::utils::performance::load_performance_from_dir;
+use custom_sweatbox_rust::config::{SimulationConfig, FleetConfig};
+use custom_sweatbox_rust::scenario::Scenario;
+use custom_sweatbox_rust::simulation::{Simulator, stdin_commands};
 
 
 #[derive(Parser)]
@@ -23,6 +19,20 @@ use simulation::Simulator;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Log level (trace, debug, info, warn, error)
+    #[arg(long, global = true, default_value = "info")]
+    log_level: Level,
+
+    /// Log output format
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Plain)]
+    log_format: LogFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    Plain,
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -33,6 +43,18 @@ enum Commands {
 
         #[arg(short = 'H', long, default_value = "127.0.0.1")]
         host: String,
+
+        /// Profile to take frequency cross-coupling from
+        #[arg(short = 'P', long)]
+        profile: Option<String>,
+
+        /// Brief controllers on the profile's session as they log in
+        #[arg(long, requires = "profile")]
+        session_info: bool,
+
+        /// Record all traffic to a file, which `replay` can play back
+        #[arg(long)]
+        record: Option<String>,
     },
 
     Simulator {
@@ -41,29 +63,90 @@ enum Commands {
 
         #[arg(short, long)]
         profile: Option<String>,
+
+        /// Read ATC commands for the traffic from stdin, e.g. "BAW123 FH 270"
+        #[arg(short, long)]
+        interactive: bool,
+
+        /// JSON file of the airlines and aircraft types to fly (default: built-in fleet)
+        #[arg(short, long)]
+        fleet: Option<String>,
+
+        /// Write every aircraft's state to a file each tick, as JSON lines
+        #[arg(long)]
+        snapshots: Option<String>,
+    },
+
+    /// Check a profile against the navigation data without running it
+    Validate {
+        /// Profile to check
+        profile: String,
+    },
+
+    /// Re-send the client traffic in a server log or recording to a live server
+    Replay {
+        /// Server log or `server --record` recording to replay
+        logfile: String,
+
+        #[arg(short, long, default_value = "127.0.0.1:6809")]
+        server: String,
+
+        /// Playback speed (2.0 replays twice as fast)
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+    }
+}
+
+/// Build the tracing subscriber for the requested level and format
+fn build_subscriber(level: Level, format: LogFormat) -> Box<dyn tracing::Subscriber + Send + Sync> {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_target(false);
+
+    match format {
+        LogFormat::Plain => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(builder.json().finish()),
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
-        .with_target(false)
-        .init();
-
     let cli = Cli::parse();
 
+    // Initialize tracing
+    tracing::subscriber::set_global_default(build_subscriber(cli.log_level, cli.log_format))?;
+
     match cli.command {
-        Commands::Server { port, host } => {
+        Commands::Server { port, host, profile, session_info, record } => {
             info!("Starting FSD Server on {}:{}", host, port);
-            let fsd_server = server::FsdServer::new(host, port);
+            let mut fsd_server = server::FsdServer::new(host, port);
+            if let Some(path) = record {
+                info!("Recording traffic to {}", path);
+                fsd_server = fsd_server.with_recorder(server::Recorder::create(&path)?);
+            }
+            if let Some(profile) = profile {
+                let scenario = Scenario::load(&profile)?;
+                let coupled = scenario.coupled_frequencies().clone();
+                info!("Cross-coupling {} frequencies from {}", coupled.len(), profile);
+                fsd_server = fsd_server.with_coupled_frequencies(coupled);
+                let weather = server::StaticWeather::for_scenario(&scenario);
+                info!("Reporting {} METARs from {}", scenario.metars().len(), profile);
+                let atis = server::Atis::for_scenario(&scenario, &weather);
+                info!("Broadcasting ATIS for {} aerodromes", atis.len());
+                fsd_server = fsd_server.with_atis(atis).with_weather(Arc::new(weather));
+                if session_info {
+                    fsd_server = fsd_server.with_session_info(scenario.session_info());
+                }
+            }
             fsd_server.start().await?;
         }
 
         Commands::Simulator {
             server,
             profile,
+            interactive,
+            fleet,
+            snapshots,
         } => {
             info!("Starting Simulator connecting to {}", server);
             
@@ -76,20 +159,32 @@ async fn main() -> Result<()> {
                 }
                 Err(e) => {
                     eprintln!("Failed to load navigation data: {}", e);
-                    return Err(e.into());
+                    return Err(e);
                 }
             };
             
             // Load performance data
             info!("Loading aircraft performance data...");
-            let perf_db = match load_performance_data("data/AircraftPerformace.txt") {
+            let perf_db = match load_performance_from_dir("data") {
                 Ok(db) => {
                     info!("Loaded performance data for {} aircraft types", db.len());
                     Arc::new(db)
                 }
                 Err(e) => {
                     eprintln!("Failed to load performance data: {}", e);
-                    return Err(e.into());
+                    return Err(e);
+                }
+            };
+            
+            // Load published holds, falling back to standard holds
+            let holds = match load_holds("data/Navaids/HOLDS.txt") {
+                Ok(holds) => {
+                    info!("Loaded {} published holds", holds.len());
+                    holds
+                }
+                Err(e) => {
+                    warn!("Failed to load holds, using standard holds: {}", e);
+                    HoldDatabase::new()
                 }
             };
             
@@ -101,10 +196,23 @@ async fn main() -> Result<()> {
             let scenario = Scenario::load(&profile_path)?;
             let stats = scenario.statistics();
             info!("{}", stats);
+            for warning in scenario.validate(&fix_db, "data") {
+                warn!("{}", warning);
+            }
 
             // Create configuration
-            let sim_config = SimulationConfig::default();
-            let fleet_config = FleetConfig::default();
+            let mut sim_config = SimulationConfig::default();
+            if let Some(qnh) = scenario.qnh() {
+                info!("Setting AI pilot altimeters to QNH {:.0}", qnh);
+                sim_config.qnh = qnh;
+            }
+            let fleet_config = match fleet {
+                Some(path) => {
+                    info!("Loading fleet: {}", path);
+                    FleetConfig::load(&path)?
+                }
+                None => FleetConfig::default(),
+            };
 
             // Create simulator
             let mut simulator = Simulator::new(
@@ -114,7 +222,15 @@ async fn main() -> Result<()> {
                 fix_db,
                 perf_db,
                 server,
-            );
+            ).with_holds(holds);
+            if interactive {
+                info!("Reading ATC commands from stdin");
+                simulator = simulator.with_command_input(stdin_commands());
+            }
+            if let Some(path) = snapshots {
+                info!("Writing aircraft snapshots to {}", path);
+                simulator = simulator.with_snapshot_log(&path)?;
+            }
 
             // Initialize and run simulation
             info!("Initializing simulation...");
@@ -132,15 +248,151 @@ async fn main() -> Result<()> {
             }).expect("Error setting Ctrl-C handler");
             
             // Run simulation loop
-            simulator.run(shutdown_rx).await?;
+            let result = simulator.run(shutdown_rx).await;
             
-            // Stop simulation
+            // Stop simulation, logging everyone out even if the loop failed
             info!("Stopping simulation...");
             simulator.stop().await?;
+            result?;
             
             info!("Simulation stopped cleanly");
         }
+
+        Commands::Validate { profile } => {
+            let scenario = Scenario::load(&profile)?;
+            info!("{}", scenario.statistics());
+
+            let warnings = scenario.validate(&load_navigation_data("data")?, "data");
+            for warning in &warnings {
+                eprintln!("{}", warning);
+            }
+            let problems = warnings.iter().filter(|w| w.blocks_navigation()).count();
+            anyhow::ensure!(problems == 0, "{} has {} routes that can't be flown", profile, problems);
+            info!("{} is valid", profile);
+        }
+
+        Commands::Replay { logfile, server, speed } => {
+            let messages = replay::load_log(&logfile)?;
+            info!("Replaying {} messages from {} to {} at {}x", messages.len(), logfile, server, speed);
+            replay::replay(&messages, &server, speed).await?;
+        }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_flags() {
+        let cli = Cli::try_parse_from([
+            "custom-sweatbox", "--log-level", "debug", "--log-format", "json", "server",
+        ]).unwrap();
+        assert_eq!(cli.log_level, Level::DEBUG);
+        assert_eq!(cli.log_format, LogFormat::Json);
+
+        // Defaults, with the flags also accepted after the subcommand
+        let cli = Cli::try_parse_from(["custom-sweatbox", "server"]).unwrap();
+        assert_eq!(cli.log_level, Level::INFO);
+        assert_eq!(cli.log_format, LogFormat::Plain);
+
+        let cli = Cli::try_parse_from(["custom-sweatbox", "server", "--log-level", "trace"]).unwrap();
+        assert_eq!(cli.log_level, Level::TRACE);
+    }
+
+    #[test]
+    fn test_replay_args() {
+        let cli = Cli::try_parse_from(["custom-sweatbox", "replay", "server.log", "--speed", "2"]).unwrap();
+        let Commands::Replay { logfile, server, speed } = cli.command else {
+            panic!("not a replay command");
+        };
+        assert_eq!(logfile, "server.log");
+        assert_eq!(server, "127.0.0.1:6809");
+        assert_eq!(speed, 2.0);
+    }
+
+    #[test]
+    fn test_validate_args() {
+        let cli = Cli::try_parse_from(["custom-sweatbox", "validate", "profiles/TCE + TCNE.json"]).unwrap();
+        let Commands::Validate { profile } = cli.command else {
+            panic!("not a validate command");
+        };
+        assert_eq!(profile, "profiles/TCE + TCNE.json");
+        assert!(Cli::try_parse_from(["custom-sweatbox", "validate"]).is_err());
+    }
+
+    #[test]
+    fn test_server_address_args() {
+        let cli = Cli::try_parse_from(["custom-sweatbox", "server", "--host", "0.0.0.0", "--port", "7000"]).unwrap();
+        let Commands::Server { host, port, .. } = cli.command else {
+            panic!("not a server command");
+        };
+        assert_eq!((host.as_str(), port), ("0.0.0.0", 7000));
+
+        let cli = Cli::try_parse_from(["custom-sweatbox", "server"]).unwrap();
+        assert!(matches!(cli.command, Commands::Server { port: 6809, record: None, .. }));
+
+        let cli = Cli::try_parse_from(["custom-sweatbox", "server", "--record", "session.tsv"]).unwrap();
+        assert!(matches!(cli.command, Commands::Server { record: Some(path), .. } if path == "session.tsv"));
+    }
+
+    #[test]
+    fn test_session_info_needs_profile() {
+        let cli = Cli::try_parse_from(["custom-sweatbox", "server", "-P", "profile.json", "--session-info"]).unwrap();
+        assert!(matches!(cli.command, Commands::Server { session_info: true, .. }));
+        assert!(Cli::try_parse_from(["custom-sweatbox", "server", "--session-info"]).is_err());
+    }
+
+    #[test]
+    fn test_interactive_simulator_args() {
+        let cli = Cli::try_parse_from(["custom-sweatbox", "simulator", "--interactive"]).unwrap();
+        assert!(matches!(cli.command, Commands::Simulator { interactive: true, .. }));
+        let cli = Cli::try_parse_from(["custom-sweatbox", "simulator"]).unwrap();
+        assert!(matches!(cli.command, Commands::Simulator { interactive: false, .. }));
+    }
+
+    #[test]
+    fn test_fleet_arg() {
+        let cli = Cli::try_parse_from(["custom-sweatbox", "simulator", "--fleet", "fleet.json"]).unwrap();
+        let Commands::Simulator { fleet, .. } = cli.command else {
+            panic!("not a simulator command");
+        };
+        assert_eq!(fleet.as_deref(), Some("fleet.json"));
+    }
+
+    #[test]
+    fn test_snapshots_arg() {
+        let cli = Cli::try_parse_from(["custom-sweatbox", "simulator", "--snapshots", "tracks.jsonl"]).unwrap();
+        let Commands::Simulator { snapshots, .. } = cli.command else {
+            panic!("not a simulator command");
+        };
+        assert_eq!(snapshots.as_deref(), Some("tracks.jsonl"));
+
+        let cli = Cli::try_parse_from(["custom-sweatbox", "simulator"]).unwrap();
+        assert!(matches!(cli.command, Commands::Simulator { snapshots: None, .. }));
+    }
+
+    #[test]
+    fn test_invalid_log_level() {
+        assert!(Cli::try_parse_from(["custom-sweatbox", "--log-level", "verbose", "server"]).is_err());
+        assert!(Cli::try_parse_from(["custom-sweatbox", "--log-format", "xml", "server"]).is_err());
+    }
+
+    #[test]
+    fn test_subscriber_levels() {
+        for format in [LogFormat::Plain, LogFormat::Json] {
+            tracing::subscriber::with_default(build_subscriber(Level::DEBUG, format), || {
+                info!("subscriber configured");
+                assert!(tracing::enabled!(Level::DEBUG));
+                assert!(!tracing::enabled!(Level::TRACE));
+            });
+
+            tracing::subscriber::with_default(build_subscriber(Level::WARN, format), || {
+                assert!(tracing::enabled!(Level::WARN));
+                assert!(!tracing::enabled!(Level::INFO));
+            });
+        }
+    }
+}
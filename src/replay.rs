@@ -0,0 +1,209 @@
+//! Replay a recorded FSD session into a live server
+//!
+//! The recording is either a `server --record` file or the server's own log, where
+//! every message a client sends is logged as `[RECV] <client address>: <message>` in
+//! either the plain or JSON log format. Each recorded client gets its own connection
+//! and its messages are re-sent at their original timing, optionally sped up or
+//! slowed down.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+use crate::server::recorder::{parse_recorded_line, Direction};
+
+/// One message sent by a client in the recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedMessage {
+    /// Time since the first recorded message
+    pub offset: Duration,
+    /// Address the client connected from, identifying its connection
+    pub client: String,
+    pub message: String,
+}
+
+/// Parse a server log or recording into the messages clients sent, skipping anything else
+pub fn parse_log(contents: &str) -> Vec<RecordedMessage> {
+    let mut start = None;
+    let mut recording_start = None;
+    let mut messages = Vec::new();
+
+    for line in contents.lines() {
+        if let Some((elapsed, direction, client, message)) = parse_recorded_line(line) {
+            if direction == Direction::Recv {
+                let start = *recording_start.get_or_insert(elapsed);
+                messages.push(RecordedMessage {
+                    offset: elapsed.saturating_sub(start),
+                    client: client.to_string(),
+                    message: message.to_string(),
+                });
+            }
+            continue;
+        }
+
+        let Some((timestamp, text)) = split_log_line(line) else {
+            continue;
+        };
+        let Some((client, message)) = text.strip_prefix("[RECV] ").and_then(|rest| rest.split_once(": ")) else {
+            continue;
+        };
+        let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(&timestamp) else {
+            continue;
+        };
+
+        let start = *start.get_or_insert(timestamp);
+        let offset = (timestamp - start).to_std().unwrap_or_default();
+        messages.push(RecordedMessage {
+            offset,
+            client: client.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    messages
+}
+
+/// Timestamp and message text of a plain or JSON log line
+fn split_log_line(line: &str) -> Option<(String, String)> {
+    if line.starts_with('{') {
+        let json: serde_json::Value = serde_json::from_str(line).ok()?;
+        let timestamp = json.get("timestamp")?.as_str()?;
+        let message = json.get("fields")?.get("message")?.as_str()?;
+        return Some((timestamp.to_string(), message.to_string()));
+    }
+
+    // Plain lines may carry colour codes if the log was captured from a terminal
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let ansi = ANSI.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m").expect("valid regex"));
+    let line = ansi.replace_all(line, "");
+
+    let (timestamp, rest) = line.trim().split_once(char::is_whitespace)?;
+    let text = rest.trim_start().split_once(char::is_whitespace)?.1.trim_start();
+    Some((timestamp.to_string(), text.to_string()))
+}
+
+/// Read and parse a recorded session
+pub fn load_log(path: &str) -> Result<Vec<RecordedMessage>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read FSD log: {}", path))?;
+    Ok(parse_log(&contents))
+}
+
+/// Re-send recorded messages to a server, one connection per recorded client.
+/// `speed` scales the timing: 2.0 replays twice as fast.
+pub async fn replay(messages: &[RecordedMessage], server_addr: &str, speed: f64) -> Result<()> {
+    anyhow::ensure!(speed > 0.0, "Replay speed must be positive, got {}", speed);
+
+    let mut connections: HashMap<&str, TcpStream> = HashMap::new();
+    let started = tokio::time::Instant::now();
+
+    for recorded in messages {
+        tokio::time::sleep_until(started + recorded.offset.div_f64(speed)).await;
+
+        let stream = match connections.entry(recorded.client.as_str()) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                info!("[REPLAY] Connecting client {}", recorded.client);
+                let stream = TcpStream::connect(server_addr).await
+                    .with_context(|| format!("Failed to connect to {}", server_addr))?;
+                entry.insert(stream)
+            }
+        };
+
+        if let Err(e) = stream.write_all(format!("{}\r\n", recorded.message).as_bytes()).await {
+            warn!("[REPLAY] Failed to send for {}: {}", recorded.client, e);
+        }
+    }
+
+    for (client, mut stream) in connections {
+        if let Err(e) = stream.shutdown().await {
+            warn!("[REPLAY] Failed to close {}: {}", client, e);
+        }
+    }
+
+    info!("[REPLAY] Replayed {} messages", messages.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::AsyncReadExt;
+    use crate::server::FsdServer;
+
+    const SESSION: &str = "\
+2026-10-16T10:00:00.000000Z  INFO [LISTENING] Server is listening on 127.0.0.1:6809
+2026-10-16T10:00:00.000000Z  INFO [RECV] 127.0.0.1:50001: #APBAW123:SERVER:1000001:123456:1:100:1:Test Pilot
+\x1b[2m2026-10-16T10:00:00.300000Z\x1b[0m \x1b[32m INFO\x1b[0m [RECV] 127.0.0.1:50001: @N:BAW123:2201:1:51.470000:-0.460000:0:0:1234:0
+not a log line
+2026-10-16T10:00:00.350000Z  INFO [RECV] truncated
+{\"timestamp\":\"2026-10-16T10:00:00.800000Z\",\"level\":\"INFO\",\"fields\":{\"message\":\"[RECV] 127.0.0.1:50001: @N:BAW123:2201:1:51.480000:-0.460000:100:0:1234:0\"}}
+";
+
+    #[test]
+    fn test_parse_log() {
+        let messages = parse_log(SESSION);
+        let summary: Vec<(u128, &str, &str)> = messages
+            .iter()
+            .map(|m| (m.offset.as_millis(), m.client.as_str(), &m.message[..3]))
+            .collect();
+        assert_eq!(summary, vec![
+            (0, "127.0.0.1:50001", "#AP"),
+            (300, "127.0.0.1:50001", "@N:"),
+            (800, "127.0.0.1:50001", "@N:"),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_into_server() -> Result<()> {
+        let server = Arc::new(FsdServer::new("127.0.0.1".to_string(), 0));
+        let listener = server.bind().await?;
+        let addr = listener.local_addr()?.to_string();
+        let serving = server.clone();
+        tokio::spawn(async move { serving.serve(listener).await });
+
+        // A controller watching the replayed traffic
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let messages = parse_log(SESSION);
+        let replaying = tokio::spawn(async move { replay(&messages, &addr, 1.0).await });
+
+        let mut received = Vec::new();
+        let mut pending = String::new();
+        let mut buffer = vec![0u8; 4096];
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while received.len() < 2 {
+                let n = controller.read(&mut buffer).await?;
+                anyhow::ensure!(n > 0, "server closed the connection");
+                pending.push_str(&String::from_utf8_lossy(&buffer[..n]));
+                while let Some(end) = pending.find("\r\n") {
+                    let line: String = pending.drain(..end + 2).collect();
+                    if line.starts_with("@N:BAW123") {
+                        received.push((tokio::time::Instant::now(), line.trim_end().to_string()));
+                    }
+                }
+            }
+            Ok(())
+        })
+        .await??;
+        replaying.await??;
+
+        assert!(received[0].1.contains(":51.470000:"));
+        assert!(received[1].1.contains(":51.480000:"));
+        let spacing = received[1].0 - received[0].0;
+        assert!(
+            (350..=700).contains(&spacing.as_millis()),
+            "positions {}ms apart, recorded 500ms apart",
+            spacing.as_millis()
+        );
+        Ok(())
+    }
+}
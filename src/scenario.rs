@@ -1,7 +1,12 @@
 use anyhow::Result;
 use std::path::Path;
-use crate::config::{ProfileConfig, DepartureRoute, StandardDeparture, TransitRoute, StandardTransit};
+use crate::config::{ProfileConfig, DepartureRoute, StandardDeparture, TransitRoute, StandardTransit, EntryStream, DescentAgreement, HandoffPoint, MissedApproach, ScriptedSpawn, AerodromeTraffic};
 use rand::seq::SliceRandom;
+use std::collections::HashMap;
+use crate::aircraft::{Route, TokenKind};
+use crate::utils::navigation::{FixDatabase, parse_coordinate_fix};
+use crate::utils::procedures::{load_procedures, ProcedureDatabase};
+use crate::server::weather::qnh_from_metar;
 
 /// Represents a loaded scenario with utility methods for simulation
 #[derive(Debug, Clone)]
@@ -38,6 +43,29 @@ impl Scenario {
         self.config.active_runways.get(aerodrome).map(|s| s.as_str())
     }
 
+    /// Runway departures use at an aerodrome: its departure runway, else the active runway
+    pub fn active_departure_runway(&self, aerodrome: &str) -> Option<&str> {
+        self.config.departure_runways.get(aerodrome).map(|s| s.as_str())
+            .or_else(|| self.active_runway(aerodrome))
+    }
+
+    /// Runway arrivals use at an aerodrome: its arrival runway, else the active runway
+    pub fn active_arrival_runway(&self, aerodrome: &str) -> Option<&str> {
+        self.config.arrival_runways.get(aerodrome).map(|s| s.as_str())
+            .or_else(|| self.active_runway(aerodrome))
+    }
+
+    /// Runways in use at an aerodrome for briefings, e.g. "27R", or "27L arrivals 27R
+    /// departures" when it runs segregated parallels
+    pub fn runways_in_use(&self, aerodrome: &str) -> Option<String> {
+        match (self.active_arrival_runway(aerodrome), self.active_departure_runway(aerodrome)) {
+            (Some(arrival), Some(departure)) if arrival != departure => {
+                Some(format!("{} arrivals {} departures", arrival, departure))
+            }
+            (arrival, departure) => arrival.or(departure).map(str::to_string),
+        }
+    }
+
     /// Get all departure configurations
     pub fn departure_configs(&self) -> &[StandardDeparture] {
         &self.config.std_departures
@@ -48,14 +76,25 @@ impl Scenario {
         &self.config.std_transits
     }
 
+    /// Get all entry streams
+    pub fn entry_streams(&self) -> &[EntryStream] {
+        &self.config.entry_streams
+    }
+
     /// Get a random departure route for a specific aerodrome
     pub fn random_departure_route(&self, aerodrome: &str) -> Option<&DepartureRoute> {
+        self.random_departure_route_by(aerodrome, |r| r.weight)
+    }
+
+    /// Get a random departure route for a specific aerodrome, with each route's
+    /// likelihood given by `weight`
+    pub fn random_departure_route_by(&self, aerodrome: &str, weight: impl Fn(&DepartureRoute) -> f64) -> Option<&DepartureRoute> {
         let mut rng = rand::thread_rng();
         
         self.config.std_departures
             .iter()
             .find(|d| d.departing == aerodrome)
-            .and_then(|d| d.routes.choose(&mut rng))
+            .and_then(|d| d.routes.choose_weighted(&mut rng, weight).ok())
     }
 
     /// Get a random transit route from a specific configuration index
@@ -64,7 +103,7 @@ impl Scenario {
         
         self.config.std_transits
             .get(transit_index)
-            .and_then(|t| t.routes.choose(&mut rng))
+            .and_then(|t| t.routes.choose_weighted(&mut rng, |r| r.weight).ok())
     }
 
     /// Get all departure aerodromes
@@ -75,12 +114,29 @@ impl Scenario {
             .collect()
     }
 
-    /// Get departure interval for a specific aerodrome
+    /// Get departure interval for a specific aerodrome, taking any per-aerodrome override
     pub fn departure_interval(&self, aerodrome: &str) -> Option<u64> {
-        self.config.std_departures
+        let standard = self.config.std_departures
             .iter()
             .find(|d| d.departing == aerodrome)
-            .map(|d| d.interval)
+            .map(|d| d.interval)?;
+
+        Some(self.aerodrome_traffic(aerodrome).and_then(|t| t.departure_interval).unwrap_or(standard))
+    }
+
+    /// Departure interval for an aerodrome `elapsed` simulated seconds into the session: the latest
+    /// scheduled interval that has started, else the standard interval
+    pub fn departure_interval_at(&self, aerodrome: &str, elapsed: f64) -> Option<u64> {
+        let scheduled = self.config.std_departures
+            .iter()
+            .find(|d| d.departing == aerodrome)?
+            .interval_schedule
+            .iter()
+            .filter(|(minutes, _)| *minutes as f64 * 60.0 <= elapsed)
+            .max_by_key(|(minutes, _)| *minutes)
+            .map(|(_, interval)| *interval);
+
+        scheduled.or_else(|| self.departure_interval(aerodrome))
     }
 
     /// Get all transit intervals
@@ -106,42 +162,202 @@ impl Scenario {
         &self.config.other_controllers
     }
 
+    /// Cross-coupled frequencies and the extra controllers on each
+    pub fn coupled_frequencies(&self) -> &std::collections::HashMap<String, Vec<String>> {
+        &self.config.coupled_frequencies
+    }
+
+    /// Descents the AI master controller issues to arrivals
+    pub fn descent_agreements(&self) -> &[DescentAgreement] {
+        &self.config.descent_agreements
+    }
+
+    /// The handoff chain between controllers
+    pub fn handoffs(&self) -> &[HandoffPoint] {
+        &self.config.handoffs
+    }
+
+    /// Missed-approach tracks published in the profile
+    pub fn missed_approaches(&self) -> &[MissedApproach] {
+        &self.config.missed_approaches
+    }
+
+    /// METARs published in the profile, by station
+    pub fn metars(&self) -> &HashMap<String, String> {
+        &self.config.metars
+    }
+
+    /// QNH (hPa) from the METAR of the first active aerodrome that has one
+    pub fn qnh(&self) -> Option<f64> {
+        self.active_aerodromes()
+            .iter()
+            .filter_map(|aerodrome| self.metars().get(aerodrome))
+            .find_map(|metar| qnh_from_metar(metar))
+    }
+
+    /// Specific aircraft to spawn, in profile order
+    pub fn scripted_spawns(&self) -> &[ScriptedSpawn] {
+        &self.config.scripted_spawns
+    }
+
+    /// Local traffic levels configured for an aerodrome, if any
+    pub fn aerodrome_traffic(&self, aerodrome: &str) -> Option<&AerodromeTraffic> {
+        self.config.aerodrome_traffic.get(aerodrome)
+    }
+
+    /// Aerodromes with local arrivals, and the seconds between them
+    pub fn arrival_intervals(&self) -> Vec<(&str, u64)> {
+        let mut intervals: Vec<(&str, u64)> = self.config.aerodrome_traffic
+            .iter()
+            .filter_map(|(aerodrome, traffic)| Some((aerodrome.as_str(), traffic.arrival_interval?)))
+            .collect();
+        intervals.sort();
+        intervals
+    }
+
+    /// Whether arrivals fly continuous descents
+    pub fn continuous_descent(&self) -> bool {
+        self.config.continuous_descent
+    }
+
+    /// Briefing for controllers joining the session: active runways, transition level,
+    /// standing agreements and notes. Each line goes out as FSD text, so has no colons.
+    pub fn session_info(&self) -> Vec<String> {
+        let mut lines = vec![format!("Session {}", self.name)];
+
+        let runways: Vec<String> = self.config.active_aerodromes
+            .iter()
+            .filter_map(|aerodrome| Some(format!("{} {}", aerodrome, self.runways_in_use(aerodrome)?)))
+            .collect();
+        if !runways.is_empty() {
+            lines.push(format!("Active runways {}", runways.join(", ")));
+        }
+        if let Some(level) = self.config.transition_level {
+            lines.push(format!("Transition level FL{:03}", level));
+        }
+        for agreement in &self.config.descent_agreements {
+            lines.push(format!("{} arrivals level FL{:03} by {}",
+                               agreement.arriving, agreement.level / 100, agreement.boundary_fix));
+        }
+        if self.config.continuous_descent {
+            lines.push("Arrivals fly continuous descents".to_string());
+        }
+        lines.extend(self.config.session_notes.iter().map(|note| note.replace(':', " ")));
+        lines
+    }
+
     /// Check if a specific controller is active
     pub fn is_controller_active(&self, controller: &str) -> bool {
         self.config.active_controllers.iter().any(|c| c == controller)
     }
 
     /// Get all unique arriving aerodromes from departures
-    pub fn departure_destinations(&self) -> Vec<&str> {
-        let mut destinations: Vec<&str> = self.config.std_departures
+    pub fn departure_destinations(&self) -> Vec<String> {
+        sorted_unique(self.config.std_departures
             .iter()
-            .flat_map(|d| d.routes.iter().map(|r| r.arriving.as_str()))
-            .collect();
-        destinations.sort_unstable();
-        destinations.dedup();
-        destinations
+            .flat_map(|d| d.routes.iter().map(|r| r.arriving.as_str())))
     }
 
     /// Get all unique arriving aerodromes from transits
-    pub fn transit_destinations(&self) -> Vec<&str> {
-        let mut destinations: Vec<&str> = self.config.std_transits
+    pub fn transit_destinations(&self) -> Vec<String> {
+        sorted_unique(self.config.std_transits
             .iter()
-            .flat_map(|t| t.routes.iter().map(|r| r.arriving.as_str()))
-            .collect();
-        destinations.sort_unstable();
-        destinations.dedup();
-        destinations
+            .flat_map(|t| t.routes.iter().map(|r| r.arriving.as_str())))
     }
 
     /// Get all unique departing aerodromes from transits
-    pub fn transit_origins(&self) -> Vec<&str> {
-        let mut origins: Vec<&str> = self.config.std_transits
+    pub fn transit_origins(&self) -> Vec<String> {
+        sorted_unique(self.config.std_transits
             .iter()
-            .flat_map(|t| t.routes.iter().map(|r| r.departing.as_str()))
-            .collect();
-        origins.sort_unstable();
-        origins.dedup();
-        origins
+            .flat_map(|t| t.routes.iter().map(|r| r.departing.as_str())))
+    }
+
+    /// Every SID and STAR named in the profile's routes that isn't in the airport data
+    /// under `data_dir` for the runway it would be flown from. SIDs are checked against
+    /// the departure aerodrome's departure runway, STARs against the runway given in the
+    /// route or else the arrival aerodrome's arrival runway; with neither, any runway will do.
+    pub fn missing_procedures<P: AsRef<Path>>(&self, data_dir: P) -> Result<Vec<MissingProcedure>> {
+        let mut procedures: HashMap<String, (ProcedureDatabase, ProcedureDatabase)> = HashMap::new();
+        let mut missing = Vec::new();
+
+        for (departing, arriving, route) in self.all_routes() {
+            for (kind, name, route_runway) in Route::procedures(route) {
+                let (aerodrome, runway) = match kind {
+                    TokenKind::Sid => (departing, self.active_departure_runway(departing).map(str::to_string).or(route_runway)),
+                    _ => (arriving, route_runway.or_else(|| self.active_arrival_runway(arriving).map(str::to_string))),
+                };
+
+                if !procedures.contains_key(aerodrome) {
+                    procedures.insert(aerodrome.to_string(), load_procedures(data_dir.as_ref(), aerodrome)?);
+                }
+                let (sids, stars) = &procedures[aerodrome];
+                let database = if kind == TokenKind::Sid { sids } else { stars };
+                let found = database
+                    .get(&name)
+                    .is_some_and(|runways| runway.as_ref().is_none_or(|rwy| runways.contains_key(rwy)));
+
+                let procedure = MissingProcedure { kind, name, aerodrome: aerodrome.to_string(), runway };
+                if !found && !missing.contains(&procedure) {
+                    missing.push(procedure);
+                }
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// Check the profile's routes against the navigation data: every route fix, every
+    /// destination and every aerodrome departures start from should be known, and every
+    /// SID and STAR in `data_dir` published for the runway in use. Transits start at
+    /// their first fix, so where they came from doesn't matter.
+    pub fn validate(&self, fix_db: &FixDatabase, data_dir: impl AsRef<Path>) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+        let mut add = |warning: ValidationWarning| {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        };
+
+        let origins = self.config.std_departures.iter().map(|d| d.departing.as_str());
+        for aerodrome in origins.chain(self.all_routes().map(|(_, arriving, _)| arriving)) {
+            if fix_db.get(aerodrome).is_none() {
+                add(ValidationWarning::UnknownAirport(aerodrome.to_string()));
+            }
+        }
+
+        for (departing, arriving, route) in self.all_routes() {
+            let expanded = Route::new(route.to_string(), departing.to_string(), Some(arriving.to_string()));
+            for fix in &expanded.fixes {
+                if fix_db.get(fix).is_none() && parse_coordinate_fix(fix).is_none() {
+                    add(ValidationWarning::UnknownFix { fix: fix.clone(), route: route.to_string() });
+                }
+            }
+        }
+
+        match self.missing_procedures(data_dir) {
+            Ok(missing) => missing.into_iter().for_each(|procedure| add(ValidationWarning::MissingProcedure(procedure))),
+            Err(e) => tracing::warn!("Couldn't check procedures for {}: {}", self.name, e),
+        }
+        warnings
+    }
+
+    /// Every (departing, arriving, route) the profile can spawn: departures, transits,
+    /// entry streams and scripted aircraft
+    fn all_routes(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        let departures = self.config.std_departures
+            .iter()
+            .flat_map(|d| d.routes.iter().map(|r| (d.departing.as_str(), r.arriving.as_str(), r.route.as_str())));
+        let transits = self.config.std_transits
+            .iter()
+            .flat_map(|t| t.routes.iter().map(|r| (r.departing.as_str(), r.arriving.as_str(), r.route.as_str())));
+        let streams = self.config.entry_streams
+            .iter()
+            .map(|s| (s.departing.as_str(), s.arriving.as_str(), s.route.as_str()));
+        let scripted = self.config.scripted_spawns
+            .iter()
+            .map(|s| (s.departing.as_str(), s.arriving.as_str(), s.route.as_str()));
+
+        departures.chain(transits).chain(streams).chain(scripted)
     }
 
     /// Get statistics about the scenario
@@ -169,6 +385,66 @@ impl Scenario {
     }
 }
 
+/// Collect codes into an owned list, sorted case-insensitively with
+/// case-insensitive duplicates removed (the first spelling in sort order is kept)
+fn sorted_unique<'a>(codes: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut codes: Vec<String> = codes.map(|c| c.to_string()).collect();
+    codes.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then_with(|| a.cmp(b)));
+    codes.dedup_by(|a, b| a.to_lowercase() == b.to_lowercase());
+    codes
+}
+
+/// A SID or STAR a profile route names that the airport data doesn't have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingProcedure {
+    /// `TokenKind::Sid` or `TokenKind::Star`
+    pub kind: TokenKind,
+    pub name: String,
+    pub aerodrome: String,
+    /// The runway it was looked up for, if any
+    pub runway: Option<String>,
+}
+
+impl std::fmt::Display for MissingProcedure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = if self.kind == TokenKind::Sid { "SID" } else { "STAR" };
+        write!(f, "{} {} at {}", kind, self.name, self.aerodrome)?;
+        if let Some(runway) = &self.runway {
+            write!(f, " for runway {}", runway)?;
+        }
+        Ok(())
+    }
+}
+
+/// Something in a profile the navigation data can't support
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// A fix in a route that isn't in the navigation data
+    UnknownFix { fix: String, route: String },
+    /// A departure or arrival aerodrome that isn't in the navigation data
+    UnknownAirport(String),
+    /// A SID or STAR that isn't published for the runway it would be flown from
+    MissingProcedure(MissingProcedure),
+}
+
+impl ValidationWarning {
+    /// Whether aircraft on the route would be unable to fly it. Without a position for
+    /// a destination only the descent planning towards it is affected.
+    pub fn blocks_navigation(&self) -> bool {
+        !matches!(self, ValidationWarning::UnknownAirport(_))
+    }
+}
+
+impl std::fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationWarning::UnknownFix { fix, route } => write!(f, "Unknown fix {} in route {}", fix, route),
+            ValidationWarning::UnknownAirport(icao) => write!(f, "Unknown aerodrome {}", icao),
+            ValidationWarning::MissingProcedure(procedure) => write!(f, "Missing {}", procedure),
+        }
+    }
+}
+
 /// Statistics about a loaded scenario
 #[derive(Debug, Clone)]
 pub struct ScenarioStats {
@@ -186,10 +462,18 @@ impl std::fmt::Display for ScenarioStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Scenario: {}", self.name)?;
         writeln!(f, "  Active Aerodromes: {}", self.active_aerodromes)?;
-        writeln!(f, "  Departure Configurations: {} ({} routes)", 
-                 self.departure_configs, self.total_departure_routes)?;
-        writeln!(f, "  Transit Configurations: {} ({} routes)", 
-                 self.transit_configs, self.total_transit_routes)?;
+        if self.total_departure_routes == 0 {
+            writeln!(f, "  Departure Configurations: none")?;
+        } else {
+            writeln!(f, "  Departure Configurations: {} ({} routes)",
+                     self.departure_configs, self.total_departure_routes)?;
+        }
+        if self.total_transit_routes == 0 {
+            writeln!(f, "  Transit Configurations: none")?;
+        } else {
+            writeln!(f, "  Transit Configurations: {} ({} routes)",
+                     self.transit_configs, self.total_transit_routes)?;
+        }
         writeln!(f, "  Active Controllers: {}", self.active_controllers)?;
         writeln!(f, "  Other Controllers: {}", self.other_controllers)?;
         Ok(())
@@ -201,12 +485,25 @@ impl std::fmt::Display for ScenarioStats {
 pub struct ScenarioBuilder {
     active_aerodromes: Vec<String>,
     active_runways: std::collections::HashMap<String, String>,
+    departure_runways: std::collections::HashMap<String, String>,
+    arrival_runways: std::collections::HashMap<String, String>,
     active_controllers: Vec<String>,
     master_controller: String,
     master_controller_freq: String,
     other_controllers: Vec<(String, String)>,
+    coupled_frequencies: std::collections::HashMap<String, Vec<String>>,
     std_departures: Vec<StandardDeparture>,
     std_transits: Vec<StandardTransit>,
+    entry_streams: Vec<EntryStream>,
+    descent_agreements: Vec<DescentAgreement>,
+    handoffs: Vec<HandoffPoint>,
+    missed_approaches: Vec<MissedApproach>,
+    scripted_spawns: Vec<ScriptedSpawn>,
+    continuous_descent: bool,
+    aerodrome_traffic: std::collections::HashMap<String, AerodromeTraffic>,
+    transition_level: Option<u32>,
+    session_notes: Vec<String>,
+    metars: std::collections::HashMap<String, String>,
 }
 
 impl ScenarioBuilder {
@@ -220,6 +517,16 @@ impl ScenarioBuilder {
         self
     }
 
+    /// Add an aerodrome running segregated parallels: one runway for departures and
+    /// another for arrivals
+    pub fn add_aerodrome_with_runways(mut self, icao: String, departure_runway: String, arrival_runway: String) -> Self {
+        self.active_aerodromes.push(icao.clone());
+        self.active_runways.insert(icao.clone(), departure_runway.clone());
+        self.departure_runways.insert(icao.clone(), departure_runway);
+        self.arrival_runways.insert(icao, arrival_runway);
+        self
+    }
+
     pub fn master_controller(mut self, callsign: String, freq: String) -> Self {
         self.master_controller = callsign;
         self.master_controller_freq = freq;
@@ -236,6 +543,11 @@ impl ScenarioBuilder {
         self
     }
 
+    pub fn couple_frequency(mut self, freq: String, callsign: String) -> Self {
+        self.coupled_frequencies.entry(freq).or_default().push(callsign);
+        self
+    }
+
     pub fn add_departure_config(mut self, config: StandardDeparture) -> Self {
         self.std_departures.push(config);
         self
@@ -246,19 +558,82 @@ impl ScenarioBuilder {
         self
     }
 
+    pub fn add_entry_stream(mut self, stream: EntryStream) -> Self {
+        self.entry_streams.push(stream);
+        self
+    }
+
+    pub fn add_descent_agreement(mut self, agreement: DescentAgreement) -> Self {
+        self.descent_agreements.push(agreement);
+        self
+    }
+
+    pub fn add_handoff(mut self, handoff: HandoffPoint) -> Self {
+        self.handoffs.push(handoff);
+        self
+    }
+
+    pub fn add_missed_approach(mut self, missed_approach: MissedApproach) -> Self {
+        self.missed_approaches.push(missed_approach);
+        self
+    }
+
+    pub fn add_scripted_spawn(mut self, spawn: ScriptedSpawn) -> Self {
+        self.scripted_spawns.push(spawn);
+        self
+    }
+
+    pub fn continuous_descent(mut self, enabled: bool) -> Self {
+        self.continuous_descent = enabled;
+        self
+    }
+
+    pub fn aerodrome_traffic(mut self, aerodrome: String, traffic: AerodromeTraffic) -> Self {
+        self.aerodrome_traffic.insert(aerodrome, traffic);
+        self
+    }
+
+    pub fn transition_level(mut self, level: u32) -> Self {
+        self.transition_level = Some(level);
+        self
+    }
+
+    pub fn add_session_note(mut self, note: String) -> Self {
+        self.session_notes.push(note);
+        self
+    }
+
+    pub fn add_metar(mut self, icao: String, metar: String) -> Self {
+        self.metars.insert(icao, metar);
+        self
+    }
+
     pub fn build(self) -> Scenario {
         Scenario {
             name: "Built Scenario".to_string(),
             config: ProfileConfig {
                 active_aerodromes: self.active_aerodromes,
                 active_runways: self.active_runways,
+                departure_runways: self.departure_runways,
+                arrival_runways: self.arrival_runways,
                 active_controllers: self.active_controllers,
                 master_controller: self.master_controller,
                 master_controller_freq: self.master_controller_freq,
                 other_controllers: self.other_controllers,
                 inactive_sectors: vec![],
+                coupled_frequencies: self.coupled_frequencies,
                 std_departures: self.std_departures,
                 std_transits: self.std_transits,
+                entry_streams: self.entry_streams,
+                descent_agreements: self.descent_agreements,
+                handoffs: self.handoffs,
+                missed_approaches: self.missed_approaches,
+                scripted_spawns: self.scripted_spawns,
+                continuous_descent: self.continuous_descent,
+                aerodrome_traffic: self.aerodrome_traffic,
+                transition_level: self.transition_level,
+                session_notes: self.session_notes,
+                metars: self.metars,
             },
         }
     }
@@ -319,6 +694,36 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_weighted_route_selection() {
+        let route = |arriving: &str, weight: f64| DepartureRoute {
+            route: "CLN2E/22 CLN".to_string(),
+            arriving: arriving.to_string(),
+            weight,
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_departure_config(StandardDeparture {
+                departing: "EGSS".to_string(),
+                interval: 180,
+                interval_schedule: Vec::new(),
+                routes: vec![route("EHAM", 6.0), route("EDDF", 3.0), route("LFPG", 1.0), route("EBBR", 0.0)],
+            })
+            .build();
+
+        let draws = 20_000;
+        let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for _ in 0..draws {
+            let picked = scenario.random_departure_route("EGSS").unwrap();
+            *counts.entry(picked.arriving.clone()).or_default() += 1;
+        }
+
+        let frequency = |icao: &str| counts.get(icao).copied().unwrap_or(0) as f64 / draws as f64;
+        assert!((frequency("EHAM") - 0.6).abs() < 0.02, "EHAM {}", frequency("EHAM"));
+        assert!((frequency("EDDF") - 0.3).abs() < 0.02, "EDDF {}", frequency("EDDF"));
+        assert!((frequency("LFPG") - 0.1).abs() < 0.02, "LFPG {}", frequency("LFPG"));
+        assert_eq!(frequency("EBBR"), 0.0);
+    }
+
     #[test]
     fn test_departure_intervals() -> Result<()> {
         let scenario = Scenario::load("profiles/TCE + TCNE.json")?;
@@ -331,6 +736,8 @@ mod tests {
         
         let eglc_interval = scenario.departure_interval("EGLC");
         assert_eq!(eglc_interval, Some(400));
+        // No schedule in the profile: the standard interval all session
+        assert_eq!(scenario.departure_interval_at("EGLC", 7200.0), Some(400));
         
         let egll_interval = scenario.departure_interval("EGLL");
         assert_eq!(egll_interval, Some(200));
@@ -371,32 +778,207 @@ mod tests {
         
         let dep_destinations = scenario.departure_destinations();
         println!("Departure destinations: {:?}", dep_destinations);
-        assert!(dep_destinations.contains(&"EHAM"));
-        assert!(dep_destinations.contains(&"EDDF"));
+        assert!(dep_destinations.contains(&"EHAM".to_string()));
+        assert!(dep_destinations.contains(&"EDDF".to_string()));
         
         let transit_destinations = scenario.transit_destinations();
         println!("Transit destinations: {:?}", transit_destinations);
-        assert!(transit_destinations.contains(&"EGKK"));
-        assert!(transit_destinations.contains(&"EGSS"));
+        assert!(transit_destinations.contains(&"EGKK".to_string()));
+        assert!(transit_destinations.contains(&"EGSS".to_string()));
         
         let transit_origins = scenario.transit_origins();
         println!("Transit origins: {:?}", transit_origins);
-        assert!(transit_origins.contains(&"EHAM"));
-        assert!(transit_origins.contains(&"EBBR"));
+        assert!(transit_origins.contains(&"EHAM".to_string()));
+        assert!(transit_origins.contains(&"EBBR".to_string()));
         
         Ok(())
     }
 
+    #[test]
+    fn test_destinations_sorted_and_unique() {
+        let route = |arriving: &str| DepartureRoute {
+            route: "CLN2E/22 CLN".to_string(),
+            arriving: arriving.to_string(),
+            weight: 1.0,
+        };
+        let departures = StandardDeparture {
+            departing: "EGSS".to_string(),
+            interval: 180,
+            interval_schedule: Vec::new(),
+            routes: vec![route("EHAM"), route("ebbr"), route("EDDF"), route("eham"), route("EBBR"), route("ÉLLX")],
+        };
+
+        // Owned: outlives the scenario it was built from
+        let destinations: Vec<String> = {
+            let scenario = ScenarioBuilder::new().add_departure_config(departures).build();
+            scenario.departure_destinations()
+        };
+
+        assert_eq!(destinations, vec!["EBBR", "EDDF", "EHAM", "ÉLLX"]);
+        let mut lowered: Vec<String> = destinations.iter().map(|d| d.to_lowercase()).collect();
+        assert!(lowered.windows(2).all(|w| w[0] < w[1]));
+        lowered.dedup();
+        assert_eq!(lowered.len(), destinations.len());
+    }
+
+    #[test]
+    fn test_missing_procedures_reported() -> Result<()> {
+        let departures = StandardDeparture {
+            departing: "EGLL".to_string(),
+            interval: 120,
+            interval_schedule: Vec::new(),
+            routes: vec![
+                DepartureRoute { route: "CPT3G/27L CPT".to_string(), arriving: "EGSS".to_string(), weight: 1.0 },
+                DepartureRoute { route: "CPT5J/09R CPT".to_string(), arriving: "EGGD".to_string(), weight: 1.0 },
+            ],
+        };
+        let route = |route: &str| TransitRoute {
+            departing: "EHAM".to_string(),
+            arriving: "EGLL".to_string(),
+            current_level: 25000,
+            cruise_level: 25000,
+            route: route.to_string(),
+            first_controller: "LON_S_CTR".to_string(),
+            weight: 1.0,
+        };
+        let transits = StandardTransit {
+            interval: 300,
+            routes: vec![route("KONAN DVR ALESO1H"), route("KONAN DVR XXXXX1A"), route("REDFA XXXXX1A")],
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27L".to_string())
+            .add_departure_config(departures)
+            .add_transit_config(transits)
+            .build();
+
+        // The SID isn't published for the active runway, the STAR isn't published at all,
+        // and the STAR is only reported once however many routes use it
+        let missing: Vec<String> = scenario.missing_procedures("data")?.iter().map(|m| m.to_string()).collect();
+        assert_eq!(missing, vec![
+            "SID CPT5J at EGLL for runway 27L",
+            "STAR XXXXX1A at EGLL for runway 27L",
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_split_runways() {
+        let departures = StandardDeparture {
+            departing: "EGLL".to_string(),
+            interval: 120,
+            interval_schedule: Vec::new(),
+            routes: vec![
+                DepartureRoute { route: "CPT3F/27R CPT".to_string(), arriving: "EGSS".to_string(), weight: 1.0 },
+                DepartureRoute { route: "CPT3G/27L CPT".to_string(), arriving: "EGSS".to_string(), weight: 1.0 },
+            ],
+        };
+        let route = |route: &str| TransitRoute {
+            departing: "EGLL".to_string(),
+            arriving: "EIDW".to_string(),
+            current_level: 25000,
+            cruise_level: 25000,
+            route: route.to_string(),
+            first_controller: "LON_W_CTR".to_string(),
+            weight: 1.0,
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome_with_runways("EGLL".to_string(), "27R".to_string(), "27L".to_string())
+            .add_aerodrome_with_runways("EIDW".to_string(), "28L".to_string(), "34".to_string())
+            .add_departure_config(departures)
+            .add_transit_config(StandardTransit {
+                interval: 300,
+                routes: vec![route("LIPGO LIPGO1S"), route("LIPGO LIPGO2U")],
+            })
+            .build();
+
+        // SIDs are checked against the departure runway and STARs against the arrival runway
+        let missing: Vec<String> = scenario.validate(&FixDatabase::new(), "data")
+            .iter()
+            .filter(|w| matches!(w, ValidationWarning::MissingProcedure(_)))
+            .map(|w| w.to_string())
+            .collect();
+        assert_eq!(missing, vec![
+            "Missing SID CPT3G at EGLL for runway 27R",
+            "Missing STAR LIPGO2U at EIDW for runway 34",
+        ]);
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_fixes() {
+        let route = |route: &str, arriving: &str| TransitRoute {
+            departing: "EHAM".to_string(),
+            arriving: arriving.to_string(),
+            current_level: 25000,
+            cruise_level: 25000,
+            route: route.to_string(),
+            first_controller: "LON_S_CTR".to_string(),
+            weight: 1.0,
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_transit_config(StandardTransit {
+                interval: 300,
+                routes: vec![route("REDFA DCT NOSUCH DCT BARMI", "EGLL"), route("REDFA DCT BARMI", "ZZZZ")],
+            })
+            .build();
+
+        let mut fix_db = FixDatabase::new();
+        for (name, coords) in [("EGLL", (51.47, -0.46)), ("REDFA", (51.9, 2.5)), ("BARMI", (51.7, 1.5))] {
+            fix_db.insert(name.to_string(), coords);
+        }
+
+        let warnings = scenario.validate(&fix_db, "data");
+        assert_eq!(warnings, vec![
+            ValidationWarning::UnknownAirport("ZZZZ".to_string()),
+            ValidationWarning::UnknownFix { fix: "NOSUCH".to_string(), route: "REDFA DCT NOSUCH DCT BARMI".to_string() },
+        ]);
+        assert_eq!(warnings[1].to_string(), "Unknown fix NOSUCH in route REDFA DCT NOSUCH DCT BARMI");
+        assert!(!warnings[0].blocks_navigation() && warnings[1].blocks_navigation());
+    }
+
+    #[test]
+    fn test_bundled_profile_validates() -> Result<()> {
+        let scenario = Scenario::load("profiles/TCE + TCNE.json")?;
+        let fix_db = crate::utils::navigation::load_navigation_data("data")?;
+
+        let problems: Vec<_> = scenario.validate(&fix_db, "data")
+            .into_iter()
+            .filter(|w| w.blocks_navigation())
+            .collect();
+        assert!(problems.is_empty(), "{:?}", problems);
+        Ok(())
+    }
+
     #[test]
     fn test_scenario_builder() {
         let scenario = ScenarioBuilder::new()
             .add_aerodrome("EGLL".to_string(), "27L".to_string())
             .master_controller("LON_S_CTR".to_string(), "29430".to_string())
             .add_controller("LON_S_CTR".to_string())
+            .couple_frequency("29430".to_string(), "LON_D_CTR".to_string())
             .build();
         
         assert_eq!(scenario.active_aerodromes().len(), 1);
         assert_eq!(scenario.active_runway("EGLL"), Some("27L"));
+        assert_eq!(scenario.active_departure_runway("EGLL"), Some("27L"));
+        assert_eq!(scenario.active_arrival_runway("EGLL"), Some("27L"));
+        assert_eq!(scenario.runways_in_use("EGLL").as_deref(), Some("27L"));
         assert_eq!(scenario.master_controller(), ("LON_S_CTR", "29430"));
+        assert_eq!(scenario.coupled_frequencies().get("29430"), Some(&vec!["LON_D_CTR".to_string()]));
+    }
+
+    #[test]
+    fn test_qnh_from_profile_metars() {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27L".to_string())
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .add_metar("EGKK".to_string(), "EGKK 161250Z 24010KT 9999 SCT030 13/07 Q0998".to_string())
+            .build();
+        // EGLL has no METAR, so the QNH comes from EGKK's
+        assert_eq!(scenario.qnh(), Some(998.0));
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27L".to_string())
+            .build();
+        assert_eq!(scenario.qnh(), None);
     }
 }
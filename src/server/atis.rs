@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+use crate::scenario::Scenario;
+use super::weather::WeatherSource;
+
+/// How long each ATIS information letter stays current
+pub const ATIS_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Text ATIS for an aerodrome: the active runway and the weather, under an
+/// information letter that moves on every `ATIS_INTERVAL`
+#[derive(Debug, Clone)]
+pub struct Atis {
+    pub aerodrome: String,
+    /// Runway in use, or the arrival runway when departures use another
+    pub runway: String,
+    /// Runway departures use, when it isn't `runway`
+    pub departure_runway: Option<String>,
+    pub metar: String,
+    /// Letter of the first information issued
+    first_letter: char,
+    issued: Instant,
+}
+
+impl Atis {
+    pub fn new(aerodrome: &str, runway: &str, metar: &str) -> Self {
+        Self {
+            aerodrome: aerodrome.to_uppercase(),
+            runway: runway.to_string(),
+            departure_runway: None,
+            metar: metar.to_string(),
+            first_letter: 'A',
+            issued: Instant::now(),
+        }
+    }
+
+    /// Start from a given information letter
+    pub fn with_letter(mut self, letter: char) -> Self {
+        if letter.is_ascii_alphabetic() {
+            self.first_letter = letter.to_ascii_uppercase();
+        }
+        self
+    }
+
+    /// Departures use a different runway from arrivals
+    pub fn with_departure_runway(mut self, runway: &str) -> Self {
+        if runway != self.runway {
+            self.departure_runway = Some(runway.to_string());
+        }
+        self
+    }
+
+    /// One ATIS for each of a scenario's active aerodromes with a runway in use
+    pub fn for_scenario(scenario: &Scenario, weather: &dyn WeatherSource) -> Vec<Atis> {
+        scenario.active_aerodromes()
+            .iter()
+            .filter_map(|aerodrome| {
+                let runway = scenario.active_arrival_runway(aerodrome)?;
+                let metar = weather.metar(aerodrome).unwrap_or_default();
+                let atis = Atis::new(aerodrome, runway, &metar);
+                Some(match scenario.active_departure_runway(aerodrome) {
+                    Some(departure_runway) => atis.with_departure_runway(departure_runway),
+                    None => atis,
+                })
+            })
+            .collect()
+    }
+
+    /// Callsign of the ATIS station, e.g. EGLL_ATIS
+    pub fn callsign(&self) -> String {
+        format!("{}_ATIS", self.aerodrome)
+    }
+
+    /// Information letter current `elapsed` after the first was issued
+    pub fn letter_after(&self, elapsed: Duration) -> char {
+        let issues = (elapsed.as_secs() / ATIS_INTERVAL.as_secs() % 26) as u8;
+        (b'A' + (self.first_letter as u8 - b'A' + issues) % 26) as char
+    }
+
+    /// Current information letter
+    pub fn letter(&self) -> char {
+        self.letter_after(self.issued.elapsed())
+    }
+
+    /// Lines of ATIS text
+    pub fn text(&self) -> Vec<String> {
+        let mut lines = vec![format!("{} information {}", self.aerodrome, self.letter())];
+        match &self.departure_runway {
+            Some(departure_runway) => {
+                lines.push(format!("Arrival runway {}, departure runway {}", self.runway, departure_runway));
+            }
+            None => lines.push(format!("Runway in use {}", self.runway)),
+        }
+        if !self.metar.is_empty() {
+            lines.push(self.metar.clone());
+        }
+        lines.push(format!("Acknowledge receipt of information {}", self.letter()));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::ScenarioBuilder;
+    use crate::server::StaticWeather;
+
+    #[test]
+    fn test_atis_for_scenario() {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .build();
+        let weather = StaticWeather::new().with_metar("EGLL", "EGLL 161250Z 24012KT 9999 FEW035 14/08 Q1002");
+        let atis = Atis::for_scenario(&scenario, &weather);
+
+        let heathrow = atis.iter().find(|a| a.callsign() == "EGLL_ATIS").unwrap();
+        let text = heathrow.text();
+        assert_eq!(text[0], "EGLL information A");
+        assert!(text.contains(&"Runway in use 27R".to_string()), "{:?}", text);
+        assert!(text.contains(&"EGLL 161250Z 24012KT 9999 FEW035 14/08 Q1002".to_string()));
+        assert!(atis.iter().any(|a| a.callsign() == "EGKK_ATIS" && a.runway == "26L"));
+    }
+
+    #[test]
+    fn test_atis_split_runways() {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome_with_runways("EGLL".to_string(), "27R".to_string(), "27L".to_string())
+            .build();
+        let atis = Atis::for_scenario(&scenario, &StaticWeather::new());
+
+        let text = atis[0].text();
+        assert!(text.contains(&"Arrival runway 27L, departure runway 27R".to_string()), "{:?}", text);
+    }
+
+    #[test]
+    fn test_atis_letter_rotates() {
+        let atis = Atis::new("EGLL", "27R", "").with_letter('y');
+        assert_eq!(atis.letter_after(Duration::ZERO), 'Y');
+        assert_eq!(atis.letter_after(ATIS_INTERVAL), 'Z');
+        assert_eq!(atis.letter_after(ATIS_INTERVAL * 2 + Duration::from_secs(60)), 'A');
+        // Still counting after 256 issues, about five days in
+        assert_eq!(atis.letter_after(ATIS_INTERVAL * 256), 'U');
+    }
+}
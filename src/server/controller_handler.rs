@@ -4,11 +4,14 @@ use tokio::net::tcp::OwnedWriteHalf;
 use tokio::sync::Mutex;
 use std::sync::Arc;
 
-use super::message_handler::{MessageHandler, MessageStatus, ClientType, es_convert, parse_message};
+use super::recorder::{Direction, Recorder};
+use super::message_handler::{MessageHandler, MessageStatus, ClientType, es_convert, login_type, parse_message, pong_for};
 
 /// Handler for controller connections
 pub struct ControllerHandler {
     stream: Arc<Mutex<OwnedWriteHalf>>,
+    /// Where to record what is sent to this controller, if recording
+    recorder: Option<Arc<Recorder>>,
     pub callsign: String,
     server: String,
     cid: String,
@@ -25,6 +28,7 @@ impl ControllerHandler {
     pub fn new(stream: Arc<Mutex<OwnedWriteHalf>>) -> Self {
         Self {
             stream,
+            recorder: None,
             callsign: String::new(),
             server: String::new(),
             cid: String::new(),
@@ -37,9 +41,23 @@ impl ControllerHandler {
         }
     }
 
+    /// Frequency from the controller's last position update
+    pub fn frequency(&self) -> &str {
+        &self.freq
+    }
+
+    /// Record everything sent to this controller
+    pub fn with_recorder(mut self, recorder: Option<Arc<Recorder>>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
     /// Send a message to this controller
     pub async fn send_message(&self, parts: &[&str]) -> Result<()> {
         let data = es_convert(parts);
+        if let Some(recorder) = &self.recorder {
+            recorder.record(Direction::Send, &self.callsign, &String::from_utf8_lossy(&data));
+        }
         let mut stream = self.stream.lock().await;
         stream.write_all(&data).await?;
         Ok(())
@@ -55,32 +73,39 @@ impl MessageHandler for ControllerHandler {
         }
 
         // Handle controller login (#AA)
-        if parts[0].starts_with("#AA") {
-            if parts.len() >= 12 {
-                self.callsign = parts[0][3..].to_string();
-                self.server = parts.get(1).map(|s| s.to_string()).unwrap_or_default();
-                self.name = parts.get(2).map(|s| s.to_string()).unwrap_or_default();
-                self.cid = parts.get(3).map(|s| s.to_string()).unwrap_or_default();
-                self.password = parts.get(4).map(|s| s.to_string()).unwrap_or_default();
-                self.lat = parts.get(9).map(|s| s.to_string()).unwrap_or_default();
-                self.lon = parts.get(10).map(|s| s.to_string()).unwrap_or_default();
-                self.range = parts.get(11).map(|s| s.to_string()).unwrap_or_default();
-
-                // Send welcome message
-                let callsign = self.callsign.clone();
-                let stream = self.stream.clone();
-                tokio::spawn(async move {
-                    let msg_parts = vec![
-                        "#TMserver",
-                        &callsign,
-                        "Custom FSD server",
-                    ];
-                    let data = es_convert(&msg_parts);
-                    if let Ok(_) = stream.lock().await.try_write(&data) {
-                        // Message sent
-                    }
-                });
-            }
+        if login_type(message) == Some(ClientType::Controller) {
+            self.callsign = parts[0][3..].to_string();
+            self.server = parts.get(1).map(|s| s.to_string()).unwrap_or_default();
+            self.name = parts.get(2).map(|s| s.to_string()).unwrap_or_default();
+            self.cid = parts.get(3).map(|s| s.to_string()).unwrap_or_default();
+            self.password = parts.get(4).map(|s| s.to_string()).unwrap_or_default();
+            self.lat = parts.get(9).map(|s| s.to_string()).unwrap_or_default();
+            self.lon = parts.get(10).map(|s| s.to_string()).unwrap_or_default();
+            self.range = parts.get(11).map(|s| s.to_string()).unwrap_or_default();
+
+            // Send welcome message
+            let callsign = self.callsign.clone();
+            let stream = self.stream.clone();
+            tokio::spawn(async move {
+                let msg_parts = vec![
+                    "#TMserver",
+                    &callsign,
+                    "Custom FSD server",
+                ];
+                let data = es_convert(&msg_parts);
+                let _ = stream.lock().await.try_write(&data);
+            });
+
+            return Ok(MessageStatus::Handled);
+        }
+
+        // Answer keepalive pings addressed to the server ($PI)
+        if let Some(pong) = pong_for(message) {
+            let stream = self.stream.clone();
+            tokio::spawn(async move {
+                let data = es_convert(&[pong.as_str()]);
+                let _ = stream.lock().await.try_write(&data);
+            });
             return Ok(MessageStatus::Handled);
         }
 
@@ -113,9 +138,7 @@ impl MessageHandler for ControllerHandler {
                                 &callsign,
                             ];
                             let data = es_convert(&msg_parts);
-                            if let Ok(_) = stream.lock().await.try_write(&data) {
-                                // Message sent
-                            }
+                            let _ = stream.lock().await.try_write(&data);
                         });
                         
                         return Ok(MessageStatus::Handled);
@@ -124,6 +147,14 @@ impl MessageHandler for ControllerHandler {
                         // Flight plan query - will be handled by server with pilot list
                         return Ok(MessageStatus::Handled);
                     }
+                    "RN" => {
+                        // Real name query - answered by server for pilots, else forwarded
+                        return Ok(MessageStatus::Handled);
+                    }
+                    "ATC" => {
+                        // ATC validity query - answered by server from the controller list
+                        return Ok(MessageStatus::Handled);
+                    }
                     _ => {}
                 }
             }
@@ -3,11 +3,16 @@ use tokio::net::{TcpListener, TcpStream};
 use tokio::io::AsyncReadExt;
 use tokio::sync::Mutex;
 use std::sync::Arc;
+use std::collections::HashMap;
 use tracing::{info, warn, error};
 
+use super::atis::Atis;
 use super::controller_handler::ControllerHandler;
 use super::pilot_handler::PilotHandler;
-use super::message_handler::{MessageHandler, MessageStatus, ClientType};
+use super::recorder::{Direction, Recorder};
+use super::tracks::{TrackMessage, Tracks};
+use super::weather::{StaticWeather, WeatherSource};
+use super::message_handler::{MessageHandler, MessageStatus, ClientType, frequency_key, atis_query, login_type, metar_request, text_recipient, TextRecipient};
 
 /// Main FSD server
 pub struct FsdServer {
@@ -15,6 +20,18 @@ pub struct FsdServer {
     host: String,
     controllers: Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
     pilots: Arc<Mutex<Vec<Arc<Mutex<PilotHandler>>>>>,
+    /// Frequency to the controllers cross-coupled onto it
+    coupled_frequencies: Arc<HashMap<String, Vec<String>>>,
+    /// Lines of session information sent to each controller as they log in
+    session_info: Arc<Vec<String>>,
+    /// Who owns each aircraft's track
+    tracks: Arc<Mutex<Tracks>>,
+    /// METARs given to clients that ask for the weather
+    weather: Arc<dyn WeatherSource>,
+    /// ATIS stations the server runs, one per active aerodrome
+    atis: Arc<Vec<Atis>>,
+    /// Recording of all traffic, if enabled
+    recorder: Option<Arc<Recorder>>,
 }
 
 impl FsdServer {
@@ -25,17 +42,81 @@ impl FsdServer {
             host,
             controllers: Arc::new(Mutex::new(Vec::new())),
             pilots: Arc::new(Mutex::new(Vec::new())),
+            coupled_frequencies: Arc::new(HashMap::new()),
+            session_info: Arc::new(Vec::new()),
+            tracks: Arc::new(Mutex::new(Tracks::new())),
+            weather: Arc::new(StaticWeather::new()),
+            atis: Arc::new(Vec::new()),
+            recorder: None,
         }
     }
 
+    /// Cross-couple controllers onto frequencies, so text sent on a frequency
+    /// also reaches the controllers coupled to it
+    pub fn with_coupled_frequencies(mut self, coupled: HashMap<String, Vec<String>>) -> Self {
+        self.coupled_frequencies = Arc::new(
+            coupled.into_iter().map(|(freq, callsigns)| (frequency_key(&freq), callsigns)).collect()
+        );
+        self
+    }
+
+    /// Brief controllers on the session as they log in, one text message per line
+    pub fn with_session_info(mut self, lines: Vec<String>) -> Self {
+        self.session_info = Arc::new(lines);
+        self
+    }
+
+    /// Answer weather requests from a weather source
+    pub fn with_weather(mut self, weather: Arc<dyn WeatherSource>) -> Self {
+        self.weather = weather;
+        self
+    }
+
+    /// Run ATIS stations, answering queries to them and advertising them to controllers
+    pub fn with_atis(mut self, atis: Vec<Atis>) -> Self {
+        self.atis = Arc::new(atis);
+        self
+    }
+
+    /// Record every line received from and sent to clients
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(Arc::new(recorder));
+        self
+    }
+
     /// Start the server
     pub async fn start(&self) -> Result<()> {
+        let listener = self.bind().await?;
+        self.serve(listener).await
+    }
+
+    /// Bind the listening socket (port 0 picks a free port)
+    pub async fn bind(&self) -> Result<TcpListener> {
         let addr = format!("{}:{}", self.host, self.port);
         let listener = TcpListener::bind(&addr).await
             .context(format!("Failed to bind to {}", addr))?;
 
-        info!("[LISTENING] Server is listening on {}", addr);
+        info!("[LISTENING] Server is listening on {}", listener.local_addr()?);
+        Ok(listener)
+    }
+
+    /// Callsigns of the controllers currently logged in
+    pub async fn controller_callsigns(&self) -> Vec<String> {
+        let controllers = self.controllers.lock().await;
+        let mut callsigns = Vec::new();
+        for controller in controllers.iter() {
+            callsigns.push(controller.lock().await.callsign().to_string());
+        }
+        callsigns
+    }
+
+    /// Controller that owns an aircraft's track
+    pub async fn track_owner(&self, aircraft: &str) -> Option<String> {
+        self.tracks.lock().await.owner(aircraft).map(|s| s.to_string())
+    }
 
+    /// Accept and handle clients on a bound listener
+    pub async fn serve(&self, listener: TcpListener) -> Result<()> {
         loop {
             match listener.accept().await {
                 Ok((stream, addr)) => {
@@ -43,9 +124,15 @@ impl FsdServer {
                     
                     let controllers = self.controllers.clone();
                     let pilots = self.pilots.clone();
+                    let coupled_frequencies = self.coupled_frequencies.clone();
+                    let session_info = self.session_info.clone();
+                    let tracks = self.tracks.clone();
+                    let weather = self.weather.clone();
+                    let atis = self.atis.clone();
+                    let recorder = self.recorder.clone();
                     
                     tokio::spawn(async move {
-                        if let Err(e) = Self::handle_client(stream, addr.to_string(), controllers, pilots).await {
+                        if let Err(e) = Self::handle_client(stream, addr.to_string(), controllers, pilots, coupled_frequencies, session_info, tracks, weather, atis, recorder).await {
                             error!("[ERROR] Client handler error: {}", e);
                         }
                     });
@@ -58,11 +145,18 @@ impl FsdServer {
     }
 
     /// Handle a client connection
+    #[allow(clippy::too_many_arguments)]
     async fn handle_client(
         stream: TcpStream,
         addr: String,
         controllers: Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
         pilots: Arc<Mutex<Vec<Arc<Mutex<PilotHandler>>>>>,
+        coupled_frequencies: Arc<HashMap<String, Vec<String>>>,
+        session_info: Arc<Vec<String>>,
+        tracks: Arc<Mutex<Tracks>>,
+        weather: Arc<dyn WeatherSource>,
+        atis: Arc<Vec<Atis>>,
+        recorder: Option<Arc<Recorder>>,
     ) -> Result<()> {
         let mut buffer = vec![0u8; 262144];
         let mut first_message = true;
@@ -102,43 +196,71 @@ impl FsdServer {
                             continue;
                         }
                         info!("[RECV] {}: {}", addr, message);
+                        if let Some(recorder) = &recorder {
+                            recorder.record(Direction::Recv, &addr, message);
+                        }
 
                         // Determine client type on first message
                         if first_message {
                             first_message = false;
                             
-                            if message.contains("AA") {
-                                // Controller login
-                                if let Some(s) = stream_opt.take() {
-                                    let (read_half, write_half) = s.into_split();
-                                    let stream_arc = Arc::new(Mutex::new(write_half));
-                                    let handler = Arc::new(Mutex::new(ControllerHandler::new(stream_arc)));
-                                    controllers.lock().await.push(handler.clone());
-                                    controller_handler = Some(handler.clone());
-                                    handler_type = Some(ClientType::Controller);
-                                    read_stream = Some(read_half);
+                            match login_type(message) {
+                                Some(ClientType::Controller) => {
+                                    // Controller login
+                                    if let Some(s) = stream_opt.take() {
+                                        let (read_half, write_half) = s.into_split();
+                                        let stream_arc = Arc::new(Mutex::new(write_half));
+                                        let handler = Arc::new(Mutex::new(ControllerHandler::new(stream_arc).with_recorder(recorder.clone())));
+                                        controllers.lock().await.push(handler.clone());
+                                        controller_handler = Some(handler.clone());
+                                        handler_type = Some(ClientType::Controller);
+                                        read_stream = Some(read_half);
                                     
-                                    // Process the login message to get callsign
-                                    let _ = handler.lock().await.handle(message);
-                                    let callsign = handler.lock().await.callsign().to_string();
-                                    info!("[CONTROLLER LOGIN] {} from {}", callsign, addr);
+                                        // Process the login message to get callsign
+                                        let _ = handler.lock().await.handle(message);
+                                        let callsign = handler.lock().await.callsign().to_string();
+                                        info!("[CONTROLLER LOGIN] {} from {}", callsign, addr);
+
+                                        for line in session_info.iter() {
+                                            handler.lock().await.send_message(&["#TMserver", &callsign, line]).await?;
+                                        }
+
+                                        // Controllers opening an ATIS position get its text; others
+                                        // are told which stations are broadcasting
+                                        match atis.iter().find(|station| station.callsign() == callsign) {
+                                            Some(station) => {
+                                                for line in station.text() {
+                                                    handler.lock().await.send_message(&["#TMserver", &callsign, &line]).await?;
+                                                }
+                                            }
+                                            None => {
+                                                for station in atis.iter() {
+                                                    let advert = format!("{} information {}, runway {}",
+                                                                         station.callsign(), station.letter(), station.runway);
+                                                    handler.lock().await.send_message(&["#TMserver", &callsign, &advert]).await?;
+                                                }
+                                            }
+                                        }
+                                    }
                                 }
-                            } else if message.contains("AP") {
-                                // Pilot login
-                                if let Some(s) = stream_opt.take() {
-                                    let (read_half, write_half) = s.into_split();
-                                    let stream_arc = Arc::new(Mutex::new(write_half));
-                                    let handler = Arc::new(Mutex::new(PilotHandler::new(stream_arc)));
-                                    pilots.lock().await.push(handler.clone());
-                                    pilot_handler = Some(handler.clone());
-                                    handler_type = Some(ClientType::Pilot);
-                                    read_stream = Some(read_half);
+                                Some(ClientType::Pilot) => {
+                                    // Pilot login
+                                    if let Some(s) = stream_opt.take() {
+                                        let (read_half, write_half) = s.into_split();
+                                        let stream_arc = Arc::new(Mutex::new(write_half));
+                                        let handler = Arc::new(Mutex::new(PilotHandler::new(stream_arc).with_recorder(recorder.clone())));
+                                        pilots.lock().await.push(handler.clone());
+                                        pilot_handler = Some(handler.clone());
+                                        handler_type = Some(ClientType::Pilot);
+                                        read_stream = Some(read_half);
                                     
-                                    // Process the login message to get callsign
-                                    let _ = handler.lock().await.handle(message);
-                                    let callsign = handler.lock().await.callsign().to_string();
-                                    info!("[PILOT LOGIN] {} from {}", callsign, addr);
+                                        // Process the login message to get callsign
+                                        let _ = handler.lock().await.handle(message);
+                                        let callsign = handler.lock().await.callsign().to_string();
+                                        info!("[PILOT LOGIN] {} from {}", callsign, addr);
+                                    }
                                 }
+                                None => warn!("[LOGIN] {} sent {} before logging in", addr, message),
                             }
                         }
 
@@ -166,6 +288,61 @@ impl FsdServer {
                             None => continue,
                         };
 
+                        // Weather requests are answered by the server
+                        if let Some((client, station)) = metar_request(message) {
+                            match weather.metar(&station) {
+                                Some(metar) => {
+                                    let reply = format!("$ARserver:{}:METAR:{}", client, metar);
+                                    Self::reply(controller_handler.as_ref(), pilot_handler.as_ref(), &[&reply]).await?;
+                                }
+                                None => warn!("[WEATHER] No METAR for {}", station),
+                            }
+                            continue;
+                        }
+
+                        // So are queries to its ATIS stations
+                        if let Some((client, station)) = atis_query(message) {
+                            if let Some(station) = atis.iter().find(|a| a.callsign() == station) {
+                                let cr_msg = format!("$CR{}", station.callsign());
+                                let text = station.text();
+                                for line in &text {
+                                    Self::reply(controller_handler.as_ref(), pilot_handler.as_ref(), &[&cr_msg, &client, "ATIS", "T", line]).await?;
+                                }
+                                let count = text.len().to_string();
+                                Self::reply(controller_handler.as_ref(), pilot_handler.as_ref(), &[&cr_msg, &client, "ATIS", "E", &count]).await?;
+                                continue;
+                            }
+                        }
+
+                        // Track changes from a controller must be valid for who owns the track,
+                        // and handoffs go only to the controller they're addressed to
+                        if let (Some(ClientType::Controller), Some(track)) = (handler_type, TrackMessage::parse(message)) {
+                            if let Some(ref handler) = controller_handler {
+                                let sender = handler.lock().await.callsign().to_string();
+                                Self::handle_track_message(message, &track, &sender, handler, &controllers, &pilots, &tracks).await?;
+                            }
+                            continue;
+                        }
+
+                        // Text goes only to its recipient: the controllers on (or coupled to) a
+                        // frequency, a single client, or everyone
+                        if status != MessageStatus::Handled {
+                            if let Some(recipient) = text_recipient(message) {
+                                match recipient {
+                                    TextRecipient::Frequency(frequency) => {
+                                        Self::forward_to_frequency(message, &controllers, &coupled_frequencies, &frequency).await?;
+                                    }
+                                    TextRecipient::Callsign(callsign) => {
+                                        Self::forward_to_callsign(message, &controllers, &pilots, &callsign).await?;
+                                    }
+                                    TextRecipient::Broadcast => {
+                                        Self::forward_to_everyone(message, &controllers, &pilots).await?;
+                                    }
+                                }
+                                continue;
+                            }
+                        }
+
                         // Forward messages based on status
                         match status {
                             MessageStatus::Handled => {
@@ -178,6 +355,22 @@ impl FsdServer {
                                         controller_handler.as_ref(),
                                     ).await?;
                                 }
+                                if message.starts_with("$CQ") && message.split(':').nth(2) == Some("ATC") {
+                                    Self::handle_atc_query(
+                                        message,
+                                        &controllers,
+                                        &atis,
+                                        controller_handler.as_ref(),
+                                    ).await?;
+                                }
+                                if message.starts_with("$CQ") && message.split(':').nth(2) == Some("RN") {
+                                    Self::handle_real_name_query(
+                                        message,
+                                        &controllers,
+                                        &pilots,
+                                        controller_handler.as_ref(),
+                                    ).await?;
+                                }
                             }
                             MessageStatus::ForwardToControllers => {
                                 // Forward to other controllers (not sender)
@@ -211,6 +404,7 @@ impl FsdServer {
         // Cleanup: remove handler from lists
         if let Some(ClientType::Controller) = handler_type {
             if let Some(handler) = controller_handler {
+                tracks.lock().await.release(handler.lock().await.callsign());
                 let mut ctrl_list = controllers.lock().await;
                 ctrl_list.retain(|h| !Arc::ptr_eq(h, &handler));
             }
@@ -271,6 +465,210 @@ impl FsdServer {
         Ok(())
     }
 
+    /// Handle real name query: answer for pilots, forward to controllers otherwise
+    /// Format: $CQ<controller>:<target>:RN -> $CR<target>:<controller>:RN:<name>::<rating>
+    async fn handle_real_name_query(
+        message: &str,
+        controllers: &Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
+        pilots: &Arc<Mutex<Vec<Arc<Mutex<PilotHandler>>>>>,
+        requesting_controller: Option<&Arc<Mutex<ControllerHandler>>>,
+    ) -> Result<()> {
+        let parts: Vec<&str> = message.split(':').collect();
+        if parts.len() < 3 {
+            return Ok(());
+        }
+
+        let requester = &parts[0][3..];
+        let target = parts[1];
+
+        let real_name = {
+            let pilots_lock = pilots.lock().await;
+            let mut real_name = None;
+            for pilot in pilots_lock.iter() {
+                let pilot_guard = pilot.lock().await;
+                if pilot_guard.callsign == target {
+                    real_name = Some(pilot_guard.real_name().to_string());
+                    break;
+                }
+            }
+            real_name
+        };
+
+        match (real_name, requesting_controller) {
+            (Some(real_name), Some(controller)) => {
+                let cr_msg = format!("$CR{}", target);
+                let response = [cr_msg.as_str(), requester, "RN", &real_name, "", "1"];
+                controller.lock().await.send_message(&response).await?;
+            }
+            (None, _) => Self::forward_to_controllers(message, controllers, requester).await?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Handle ATC validity query: Y if the callsign is a connected controller, N for
+    /// pilots and anyone unknown
+    /// Format: $CQ<controller>:SERVER:ATC:<callsign> -> $CRSERVER:<controller>:ATC:<Y|N>:<callsign>
+    async fn handle_atc_query(
+        message: &str,
+        controllers: &Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
+        atis: &[Atis],
+        requesting_controller: Option<&Arc<Mutex<ControllerHandler>>>,
+    ) -> Result<()> {
+        let parts: Vec<&str> = message.split(':').collect();
+        let (Some(requesting_controller), true) = (requesting_controller, parts.len() >= 3) else {
+            return Ok(());
+        };
+
+        let requester = &parts[0][3..];
+        let recipient = parts[1];
+        // Without a callsign, the query is about the recipient
+        let target = parts.get(3).copied().unwrap_or(recipient);
+
+        // The server's ATIS stations count as controllers
+        let mut is_controller = atis.iter().any(|station| station.callsign() == target);
+        for controller in controllers.lock().await.iter() {
+            if controller.lock().await.callsign() == target {
+                is_controller = true;
+                break;
+            }
+        }
+
+        let cr_msg = format!("$CR{}", recipient);
+        let valid = if is_controller { "Y" } else { "N" };
+        let response = [cr_msg.as_str(), requester, "ATC", valid, target];
+        requesting_controller.lock().await.send_message(&response).await?;
+
+        Ok(())
+    }
+
+    /// Forward a frequency-addressed text message to the controllers on that
+    /// frequency and those cross-coupled to it, except the sender
+    async fn forward_to_frequency(
+        message: &str,
+        controllers: &Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
+        coupled_frequencies: &HashMap<String, Vec<String>>,
+        frequency: &str,
+    ) -> Result<()> {
+        let sender = message.split(':').next().unwrap_or_default().trim_start_matches("#TM");
+        let coupled = coupled_frequencies.get(frequency);
+        let controllers_lock = controllers.lock().await;
+
+        for controller in controllers_lock.iter() {
+            let ctrl = controller.lock().await;
+            let listening = frequency_key(ctrl.frequency()) == frequency
+                || coupled.is_some_and(|callsigns| callsigns.iter().any(|c| c == ctrl.callsign()));
+
+            if listening && ctrl.callsign() != sender {
+                if let Err(e) = ctrl.send_message(&[message]).await {
+                    warn!("[ERROR] Failed to send to controller {}: {}", ctrl.callsign(), e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record a track claim, drop, handoff or accept and pass it on, or tell the
+    /// sending controller why it was rejected
+    async fn handle_track_message(
+        message: &str,
+        track: &TrackMessage,
+        sender: &str,
+        sender_handler: &Arc<Mutex<ControllerHandler>>,
+        controllers: &Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
+        pilots: &Arc<Mutex<Vec<Arc<Mutex<PilotHandler>>>>>,
+        tracks: &Arc<Mutex<Tracks>>,
+    ) -> Result<()> {
+        let (controller, aircraft) = match track {
+            TrackMessage::Claim { controller, aircraft } | TrackMessage::Drop { controller, aircraft } => (controller, aircraft),
+            TrackMessage::Handoff { from, aircraft, .. } => (from, aircraft),
+            TrackMessage::Accept { to, aircraft, .. } => (to, aircraft),
+        };
+
+        let accepted = controller == sender && tracks.lock().await.apply(track);
+        if !accepted {
+            let owner = tracks.lock().await.owner(aircraft).unwrap_or("nobody").to_string();
+            warn!("[TRACK] Rejected {} from {}: {} is tracked by {}", message, sender, aircraft, owner);
+            let notice = format!("{} is tracked by {}", aircraft, owner);
+            return sender_handler.lock().await.send_message(&["#TMserver", sender, &notice]).await;
+        }
+
+        info!("[TRACK] {} {:?}", sender, track);
+        match track {
+            TrackMessage::Handoff { to, .. } => Self::forward_to_callsign(message, controllers, pilots, to).await,
+            TrackMessage::Accept { from, .. } => Self::forward_to_callsign(message, controllers, pilots, from).await,
+            _ => Self::forward_to_controllers(message, controllers, sender).await,
+        }
+    }
+
+    /// Send a message back to the client that sent the one being handled
+    async fn reply(
+        controller_handler: Option<&Arc<Mutex<ControllerHandler>>>,
+        pilot_handler: Option<&Arc<Mutex<PilotHandler>>>,
+        parts: &[&str],
+    ) -> Result<()> {
+        if let Some(handler) = controller_handler {
+            handler.lock().await.send_message(parts).await
+        } else if let Some(handler) = pilot_handler {
+            handler.lock().await.send_message(parts).await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Deliver a message to the one client with a callsign
+    async fn forward_to_callsign(
+        message: &str,
+        controllers: &Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
+        pilots: &Arc<Mutex<Vec<Arc<Mutex<PilotHandler>>>>>,
+        callsign: &str,
+    ) -> Result<()> {
+        for controller in controllers.lock().await.iter() {
+            let ctrl = controller.lock().await;
+            if ctrl.callsign() == callsign {
+                if let Err(e) = ctrl.send_message(&[message]).await {
+                    warn!("[ERROR] Failed to send to controller {}: {}", callsign, e);
+                }
+                return Ok(());
+            }
+        }
+        for pilot in pilots.lock().await.iter() {
+            let pilot = pilot.lock().await;
+            if pilot.callsign == callsign {
+                if let Err(e) = pilot.send_message(&[message]).await {
+                    warn!("[ERROR] Failed to send to pilot {}: {}", callsign, e);
+                }
+                return Ok(());
+            }
+        }
+
+        warn!("[TEXT] No client {} to deliver {}", callsign, message);
+        Ok(())
+    }
+
+    /// Deliver a message to every client but its sender
+    async fn forward_to_everyone(
+        message: &str,
+        controllers: &Arc<Mutex<Vec<Arc<Mutex<ControllerHandler>>>>>,
+        pilots: &Arc<Mutex<Vec<Arc<Mutex<PilotHandler>>>>>,
+    ) -> Result<()> {
+        let sender = message.split(':').next().unwrap_or_default().trim_start_matches("#TM");
+        Self::forward_to_controllers(message, controllers, sender).await?;
+
+        for pilot in pilots.lock().await.iter() {
+            let pilot = pilot.lock().await;
+            if pilot.callsign != sender {
+                if let Err(e) = pilot.send_message(&[message]).await {
+                    warn!("[ERROR] Failed to send to pilot {}: {}", pilot.callsign, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Forward message to controllers
     async fn forward_to_controllers(
         message: &str,
@@ -291,3 +689,396 @@ impl FsdServer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use crate::simulation::ai_pilot::AiPilot;
+
+    /// Start serving on a free local port, returning the address to connect to
+    async fn start_server(server: Arc<FsdServer>) -> Result<String> {
+        let listener = server.bind().await?;
+        let addr = listener.local_addr()?.to_string();
+        tokio::spawn(async move { server.serve(listener).await });
+        Ok(addr)
+    }
+
+    /// Read lines from a client stream until `count` of them start with `prefix`
+    async fn read_lines(stream: &mut TcpStream, prefix: &str, count: usize) -> Result<Vec<String>> {
+        let mut received = String::new();
+        let mut buffer = vec![0u8; 4096];
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let lines: Vec<String> = received
+                    .split_terminator("\r\n")
+                    .filter(|line| line.starts_with(prefix))
+                    .map(|line| line.to_string())
+                    .collect();
+                if lines.len() >= count {
+                    return Ok(lines);
+                }
+                let n = stream.read(&mut buffer).await?;
+                anyhow::ensure!(n > 0, "server closed the connection");
+                received.push_str(&String::from_utf8_lossy(&buffer[..n]));
+            }
+        })
+        .await?
+    }
+
+    /// Read lines from a client stream until one starts with `prefix`
+    async fn read_until(stream: &mut TcpStream, prefix: &str) -> Result<String> {
+        Ok(read_lines(stream, prefix, 1).await?.swap_remove(0))
+    }
+
+    /// Whether nothing starting with `prefix` arrives for a while
+    async fn quiet(stream: &mut TcpStream, prefix: &str) -> bool {
+        tokio::time::timeout(std::time::Duration::from_millis(300), read_until(stream, prefix)).await.is_err()
+    }
+
+    #[tokio::test]
+    async fn test_binds_configured_address() -> Result<()> {
+        // Find a free port, then have the server take it
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let server = Arc::new(FsdServer::new("127.0.0.1".to_string(), port));
+        let listener = server.bind().await?;
+        assert_eq!(listener.local_addr()?.port(), port);
+        let serving = server.clone();
+        tokio::spawn(async move { serving.serve(listener).await });
+
+        let mut pilot = AiPilot::new("BAW123".to_string());
+        pilot.connect(&format!("127.0.0.1:{}", port)).await?;
+        pilot.login("A320", "2201").await?;
+        pilot.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_records_session() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("sweatbox-record-{}.tsv", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+
+        let server = Arc::new(FsdServer::new("127.0.0.1".to_string(), 0).with_recorder(Recorder::create(&path)?));
+        let addr = start_server(server.clone()).await?;
+
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        read_until(&mut controller, "#TMserver:TEST_CTR:").await?;
+
+        let mut pilot = TcpStream::connect(&addr).await?;
+        pilot.write_all(b"#APBAW123:SERVER:1000001:123456:1:100:1:Test Pilot\r\n").await?;
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        pilot.write_all(b"@N:BAW123:2201:1:51.470000:-0.460000:0:0:1024:0\r\n").await?;
+        read_until(&mut controller, "@N:BAW123:2201:1:51.47").await?;
+        pilot.write_all(b"@N:BAW123:2201:1:51.480000:-0.460000:100:0:1024:0\r\n").await?;
+        read_until(&mut controller, "@N:BAW123:2201:1:51.48").await?;
+        server.recorder.as_ref().expect("recording").flush().await;
+
+        let contents = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let lines: Vec<_> = contents.lines().filter_map(crate::server::recorder::parse_recorded_line).collect();
+        assert_eq!(lines.len(), contents.lines().count(), "{}", contents);
+
+        // Both logins and the two positions in, and the positions out to the controller
+        let received: Vec<&str> = lines.iter().filter(|l| l.1 == Direction::Recv).map(|l| l.3).collect();
+        assert_eq!(received.len(), 4, "{:?}", received);
+        let forwarded = lines.iter().filter(|l| l.1 == Direction::Send && l.2 == "TEST_CTR" && l.3.starts_with("@N:BAW123")).count();
+        assert_eq!(forwarded, 2, "{}", contents);
+
+        // The recording replays the pilot's traffic in order
+        let replayable = crate::replay::parse_log(&contents);
+        assert_eq!(replayable.len(), 4);
+        assert!(replayable[3].message.contains(":51.480000:"));
+        assert!(replayable.windows(2).all(|pair| pair[0].offset <= pair[1].offset));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_real_name_query() -> Result<()> {
+        let addr = start_server(Arc::new(FsdServer::new("127.0.0.1".to_string(), 0))).await?;
+
+        let mut pilot = AiPilot::new("BAW123".to_string()).with_name("Test Pilot");
+        pilot.connect(&addr).await?;
+        pilot.login("A320", "2201").await?;
+
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        read_until(&mut controller, "#TMserver:TEST_CTR:").await?;
+
+        controller.write_all(b"$CQTEST_CTR:BAW123:RN\r\n").await?;
+        let response = read_until(&mut controller, "$CR").await?;
+        assert_eq!(response, "$CRBAW123:TEST_CTR:RN:Test Pilot::1");
+
+        pilot.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_login_classified_by_fields() -> Result<()> {
+        let server = Arc::new(FsdServer::new("127.0.0.1".to_string(), 0));
+        let addr = start_server(server.clone()).await?;
+
+        // Callsigns that contain the other prefix, and pilots logging in with #AA
+        let logins: [&[u8]; 4] = [
+            b"#APAAL123:SERVER:1000001:123456:1:100:1:Test Pilot\r\n",
+            b"#AAAPP_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n",
+            b"#AALON_S_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n",
+            b"#AAEZY12AB:SERVER:1000002:123456:1:100:1:Test Pilot\r\n",
+        ];
+        let mut clients = Vec::new();
+        for login in logins {
+            let mut client = TcpStream::connect(&addr).await?;
+            client.write_all(login).await?;
+            clients.push(client);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        let mut controllers = Vec::new();
+        for controller in server.controllers.lock().await.iter() {
+            controllers.push(controller.lock().await.callsign().to_string());
+        }
+        controllers.sort();
+        let mut pilots = Vec::new();
+        for pilot in server.pilots.lock().await.iter() {
+            pilots.push(pilot.lock().await.callsign().to_string());
+        }
+        assert_eq!(controllers, vec!["APP_CTR", "LON_S_CTR"]);
+        pilots.sort();
+        assert_eq!(pilots, vec!["AAL123", "EZY12AB"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_info_on_login() -> Result<()> {
+        let scenario = crate::scenario::ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .transition_level(70)
+            .add_session_note("Heathrow: westerly preference".to_string())
+            .build();
+        let server = Arc::new(FsdServer::new("127.0.0.1".to_string(), 0).with_session_info(scenario.session_info()));
+        let addr = start_server(server.clone()).await?;
+
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+
+        // After the welcome banner, which may arrive before or after them
+        let mut lines = read_lines(&mut controller, "#TMserver:TEST_CTR:", 5).await?;
+        lines.retain(|line| !line.ends_with(":Custom FSD server"));
+        assert_eq!(lines, vec![
+            "#TMserver:TEST_CTR:Session Built Scenario",
+            "#TMserver:TEST_CTR:Active runways EGLL 27R, EGKK 26L",
+            "#TMserver:TEST_CTR:Transition level FL070",
+            "#TMserver:TEST_CTR:Heathrow  westerly preference",
+        ]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_atc_validity_query() -> Result<()> {
+        let addr = start_server(Arc::new(FsdServer::new("127.0.0.1".to_string(), 0))).await?;
+
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        read_until(&mut controller, "#TMserver:TEST_CTR:").await?;
+
+        let mut other = TcpStream::connect(&addr).await?;
+        other.write_all(b"#AALON_S_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        read_until(&mut other, "#TMserver:LON_S_CTR:").await?;
+
+        let mut pilot = TcpStream::connect(&addr).await?;
+        pilot.write_all(b"#APBAW123:SERVER:1000001:123456:1:100:1:Test Pilot\r\n").await?;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        controller.write_all(b"$CQTEST_CTR:SERVER:ATC:LON_S_CTR\r\n").await?;
+        assert_eq!(read_until(&mut controller, "$CRSERVER").await?, "$CRSERVER:TEST_CTR:ATC:Y:LON_S_CTR");
+
+        controller.write_all(b"$CQTEST_CTR:SERVER:ATC:BAW123\r\n").await?;
+        assert_eq!(read_until(&mut controller, "$CRSERVER").await?, "$CRSERVER:TEST_CTR:ATC:N:BAW123");
+
+        controller.write_all(b"$CQTEST_CTR:SERVER:ATC:EZY99\r\n").await?;
+        assert_eq!(read_until(&mut controller, "$CRSERVER").await?, "$CRSERVER:TEST_CTR:ATC:N:EZY99");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ping_answered_with_pong() -> Result<()> {
+        let addr = start_server(Arc::new(FsdServer::new("127.0.0.1".to_string(), 0))).await?;
+
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        read_until(&mut controller, "#TMserver:TEST_CTR:").await?;
+
+        let mut pilot = TcpStream::connect(&addr).await?;
+        pilot.write_all(b"#APBAW123:SERVER:1000001:123456:1:100:1:Test Pilot\r\n").await?;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        controller.write_all(b"$PITEST_CTR:SERVER:84236\r\n").await?;
+        assert_eq!(read_until(&mut controller, "$PO").await?, "$POSERVER:TEST_CTR:84236");
+
+        pilot.write_all(b"$PIBAW123:SERVER:19721\r\n").await?;
+        assert_eq!(read_until(&mut pilot, "$PO").await?, "$POSERVER:BAW123:19721");
+
+        // The pilot's ping isn't passed on to controllers
+        let forwarded = tokio::time::timeout(
+            std::time::Duration::from_millis(300),
+            read_until(&mut controller, "$PIBAW123"),
+        ).await;
+        assert!(forwarded.is_err(), "controller got {:?}", forwarded);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_text_reaches_cross_coupled_controllers() -> Result<()> {
+        let coupled = HashMap::from([("20025".to_string(), vec!["LON_D_CTR".to_string()])]);
+        let addr = start_server(Arc::new(FsdServer::new("127.0.0.1".to_string(), 0).with_coupled_frequencies(coupled))).await?;
+
+        let mut controllers = Vec::new();
+        for (callsign, freq) in [("LON_M_CTR", "20025"), ("LON_D_CTR", "34905"), ("LON_NW_CTR", "35580")] {
+            let mut controller = TcpStream::connect(&addr).await?;
+            let login = format!("#AA{}:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n", callsign);
+            controller.write_all(login.as_bytes()).await?;
+            read_until(&mut controller, &format!("#TMserver:{}:", callsign)).await?;
+            let position = format!("%{}:{}:4:300:5:51.5:-0.5:0\r\n", callsign, freq);
+            controller.write_all(position.as_bytes()).await?;
+            controllers.push(controller);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let mut pilot = TcpStream::connect(&addr).await?;
+        pilot.write_all(b"#APBAW123:SERVER:1000001:123456:1:100:1:Test Pilot\r\n").await?;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        pilot.write_all(b"#TMBAW123:@20025:request descent\r\n").await?;
+
+        // The controller on the frequency and the one coupled to it both get it
+        for controller in &mut controllers[..2] {
+            let text = read_until(controller, "#TMBAW123:").await?;
+            assert_eq!(text, "#TMBAW123:@20025:request descent");
+        }
+
+        // Others don't
+        let other = tokio::time::timeout(
+            std::time::Duration::from_millis(300),
+            read_until(&mut controllers[2], "#TMBAW123:"),
+        ).await;
+        assert!(other.is_err(), "uncoupled controller got {:?}", other);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_text_delivered_to_recipient() -> Result<()> {
+        let addr = start_server(Arc::new(FsdServer::new("127.0.0.1".to_string(), 0))).await?;
+
+        let mut controllers = Vec::new();
+        for (callsign, freq) in [("LON_S_CTR", "29430"), ("LTC_S_CTR", "34605")] {
+            let mut controller = TcpStream::connect(&addr).await?;
+            let login = format!("#AA{}:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n", callsign);
+            controller.write_all(login.as_bytes()).await?;
+            read_until(&mut controller, &format!("#TMserver:{}:", callsign)).await?;
+            let position = format!("%{}:{}:4:300:5:51.5:-0.5:0\r\n", callsign, freq);
+            controller.write_all(position.as_bytes()).await?;
+            controllers.push(controller);
+        }
+        let mut pilot = TcpStream::connect(&addr).await?;
+        pilot.write_all(b"#APBAW123:SERVER:1000001:123456:1:100:1:Test Pilot\r\n").await?;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // To a callsign: only that controller
+        pilot.write_all(b"#TMBAW123:LTC_S_CTR:request direct\r\n").await?;
+        assert_eq!(read_until(&mut controllers[1], "#TMBAW123:").await?, "#TMBAW123:LTC_S_CTR:request direct");
+        assert!(quiet(&mut controllers[0], "#TMBAW123:").await);
+
+        // On a frequency: only the controller tuned to it
+        pilot.write_all(b"#TMBAW123:@29430:request descent\r\n").await?;
+        assert_eq!(read_until(&mut controllers[0], "#TMBAW123:").await?, "#TMBAW123:@29430:request descent");
+        assert!(quiet(&mut controllers[1], "#TMBAW123:").await);
+
+        // A controller can reach the pilot directly, and everyone with a broadcast
+        controllers[0].write_all(b"#TMLON_S_CTR:BAW123:contact LTC\r\n").await?;
+        assert_eq!(read_until(&mut pilot, "#TMLON_S_CTR:").await?, "#TMLON_S_CTR:BAW123:contact LTC");
+        controllers[0].write_all(b"#TMLON_S_CTR:*:closing in 5\r\n").await?;
+        assert_eq!(read_until(&mut pilot, "#TMLON_S_CTR:*").await?, "#TMLON_S_CTR:*:closing in 5");
+        assert_eq!(read_until(&mut controllers[1], "#TMLON_S_CTR:*").await?, "#TMLON_S_CTR:*:closing in 5");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metar_query_answered() -> Result<()> {
+        let weather = StaticWeather::new().with_metar("EGLL", "EGLL 161250Z 24012KT 9999 FEW035 14/08 Q1002");
+        let addr = start_server(Arc::new(FsdServer::new("127.0.0.1".to_string(), 0).with_weather(Arc::new(weather)))).await?;
+
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        read_until(&mut controller, "#TMserver:TEST_CTR:").await?;
+
+        controller.write_all(b"$AXTEST_CTR:SERVER:METAR:EGLL\r\n").await?;
+        let response = read_until(&mut controller, "$AR").await?;
+        assert_eq!(response, "$ARserver:TEST_CTR:METAR:EGLL 161250Z 24012KT 9999 FEW035 14/08 Q1002");
+
+        controller.write_all(b"$CQTEST_CTR:SERVER:METAR:EGKK\r\n").await?;
+        let response = read_until(&mut controller, "$AR").await?;
+        assert_eq!(response, "$ARserver:TEST_CTR:METAR:EGKK 000000Z 00000KT CAVOK 15/05 Q1013");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_atis_served() -> Result<()> {
+        let scenario = crate::scenario::ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .build();
+        let atis = Atis::for_scenario(&scenario, &StaticWeather::new());
+        let addr = start_server(Arc::new(FsdServer::new("127.0.0.1".to_string(), 0).with_atis(atis))).await?;
+
+        let mut controller = TcpStream::connect(&addr).await?;
+        controller.write_all(b"#AATEST_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n").await?;
+        let advert = read_until(&mut controller, "#TMserver:TEST_CTR:EGLL_ATIS").await?;
+        assert_eq!(advert, "#TMserver:TEST_CTR:EGLL_ATIS information A, runway 27R");
+
+        controller.write_all(b"$CQTEST_CTR:EGLL_ATIS:ATIS\r\n").await?;
+        let runway = read_until(&mut controller, "$CREGLL_ATIS:TEST_CTR:ATIS:T:Runway").await?;
+        assert_eq!(runway, "$CREGLL_ATIS:TEST_CTR:ATIS:T:Runway in use 27R");
+        controller.write_all(b"$CQTEST_CTR:EGLL_ATIS:ATIS\r\n").await?;
+        let end = read_until(&mut controller, "$CREGLL_ATIS:TEST_CTR:ATIS:E:").await?;
+        assert_eq!(end, "$CREGLL_ATIS:TEST_CTR:ATIS:E:4");
+
+        // Valid as an ATC station
+        controller.write_all(b"$CQTEST_CTR:SERVER:ATC:EGLL_ATIS\r\n").await?;
+        assert_eq!(read_until(&mut controller, "$CRSERVER").await?, "$CRSERVER:TEST_CTR:ATC:Y:EGLL_ATIS");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handoff_transfers_ownership_on_accept() -> Result<()> {
+        let server = Arc::new(FsdServer::new("127.0.0.1".to_string(), 0));
+        let addr = start_server(server.clone()).await?;
+
+        let mut controllers = Vec::new();
+        for callsign in ["LON_S_CTR", "LTC_S_CTR"] {
+            let mut controller = TcpStream::connect(&addr).await?;
+            let login = format!("#AA{}:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300\r\n", callsign);
+            controller.write_all(login.as_bytes()).await?;
+            read_until(&mut controller, &format!("#TMserver:{}:", callsign)).await?;
+            controllers.push(controller);
+        }
+
+        controllers[0].write_all(b"$CQLON_S_CTR:@94835:IT:BAW123\r\n").await?;
+        read_until(&mut controllers[1], "$CQLON_S_CTR:").await?;
+        assert_eq!(server.track_owner("BAW123").await.as_deref(), Some("LON_S_CTR"));
+
+        // A controller that doesn't own the track can't hand it off
+        controllers[1].write_all(b"$HOLTC_S_CTR:LON_S_CTR:BAW123\r\n").await?;
+        let notice = read_until(&mut controllers[1], "#TMserver:").await?;
+        assert_eq!(notice, "#TMserver:LTC_S_CTR:BAW123 is tracked by LON_S_CTR");
+        assert!(quiet(&mut controllers[0], "$HO").await);
+
+        // The offer reaches the other controller, but ownership waits for the accept
+        controllers[0].write_all(b"$HOLON_S_CTR:LTC_S_CTR:BAW123\r\n").await?;
+        assert_eq!(read_until(&mut controllers[1], "$HO").await?, "$HOLON_S_CTR:LTC_S_CTR:BAW123");
+        assert_eq!(server.track_owner("BAW123").await.as_deref(), Some("LON_S_CTR"));
+
+        controllers[1].write_all(b"$HALTC_S_CTR:LON_S_CTR:BAW123\r\n").await?;
+        assert_eq!(read_until(&mut controllers[0], "$HA").await?, "$HALTC_S_CTR:LON_S_CTR:BAW123");
+        assert_eq!(server.track_owner("BAW123").await.as_deref(), Some("LTC_S_CTR"));
+        Ok(())
+    }
+}
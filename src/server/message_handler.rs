@@ -30,6 +30,26 @@ pub trait MessageHandler: Send + Sync {
     fn client_type(&self) -> ClientType;
 }
 
+/// Fields in a controller's `#AA` login, up to its visibility range
+pub const CONTROLLER_LOGIN_FIELDS: usize = 12;
+
+/// Client type from a login message: `#AP` adds a pilot, and so does a short `#AA`
+/// (some pilot clients log in with it). An `#AA` with the full controller field count
+/// adds a controller. Anything else isn't a login.
+pub fn login_type(message: &str) -> Option<ClientType> {
+    if message.starts_with("#AA") {
+        if message.split(':').count() >= CONTROLLER_LOGIN_FIELDS {
+            Some(ClientType::Controller)
+        } else {
+            Some(ClientType::Pilot)
+        }
+    } else if message.starts_with("#AP") {
+        Some(ClientType::Pilot)
+    } else {
+        None
+    }
+}
+
 /// Convert arguments to FSD format (colon-separated with \r\n)
 pub fn es_convert(parts: &[&str]) -> Vec<u8> {
     let mut result = parts.join(":");
@@ -41,3 +61,126 @@ pub fn es_convert(parts: &[&str]) -> Vec<u8> {
 pub fn parse_message(message: &str) -> Vec<String> {
     message.split(':').map(|s| s.to_string()).collect()
 }
+
+/// Normalise a frequency to FSD's short form: "129.430", "129430" and "@29430" are all "29430"
+pub fn frequency_key(freq: &str) -> String {
+    let digits: String = freq.trim_start_matches('@').chars().filter(|c| *c != '.').collect();
+    match digits.strip_prefix('1') {
+        Some(short) if digits.len() == 6 => short.to_string(),
+        _ => digits,
+    }
+}
+
+/// Who a text message (#TM) is addressed to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextRecipient {
+    /// Everyone tuned to a frequency, in short form ("@18480" -> "18480")
+    Frequency(String),
+    /// A single client
+    Callsign(String),
+    /// Everyone connected ("*" and its variants)
+    Broadcast,
+}
+
+/// Recipient of a text message, or None if it isn't one
+pub fn text_recipient(message: &str) -> Option<TextRecipient> {
+    let mut parts = message.strip_prefix("#TM")?.split(':');
+    let recipient = parts.nth(1)?;
+    Some(if recipient.starts_with('@') {
+        TextRecipient::Frequency(frequency_key(recipient))
+    } else if recipient.starts_with('*') {
+        TextRecipient::Broadcast
+    } else {
+        TextRecipient::Callsign(recipient.to_string())
+    })
+}
+
+/// Client and station of a weather request: `$AX<client>:SERVER:METAR:<icao>`,
+/// or the same as a `$CQ` query
+pub fn metar_request(message: &str) -> Option<(String, String)> {
+    let rest = message.strip_prefix("$AX").or_else(|| message.strip_prefix("$CQ"))?;
+    match rest.split(':').collect::<Vec<_>>().as_slice() {
+        [client, _, "METAR", station, ..] if !station.is_empty() => {
+            Some((client.to_string(), station.to_uppercase()))
+        }
+        _ => None,
+    }
+}
+
+/// Client and station of an ATIS query: `$CQ<client>:<station>:ATIS`
+pub fn atis_query(message: &str) -> Option<(String, String)> {
+    let mut parts = message.strip_prefix("$CQ")?.split(':');
+    let client = parts.next()?;
+    let station = parts.next()?;
+    (parts.next() == Some("ATIS")).then(|| (client.to_string(), station.to_string()))
+}
+
+/// Pong answering a keepalive ping addressed to the server, with the ping's data echoed back:
+/// `$PI<client>:SERVER:<data>` -> `$POSERVER:<client>:<data>`
+pub fn pong_for(message: &str) -> Option<String> {
+    let mut parts = message.strip_prefix("$PI")?.splitn(3, ':');
+    let client = parts.next()?;
+    let server = parts.next()?;
+    if !server.eq_ignore_ascii_case("SERVER") {
+        return None;
+    }
+    let data = parts.next().unwrap_or_default();
+    Some(format!("$PO{}:{}:{}", server, client, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_recipient() {
+        assert_eq!(frequency_key("129.430"), "29430");
+        assert_eq!(frequency_key("29430"), "29430");
+        assert_eq!(text_recipient("#TMBAW123:@29430:hello"), Some(TextRecipient::Frequency("29430".to_string())));
+        assert_eq!(text_recipient("%LON_S_CTR:29430:4:300:5:51.5:-0.5:0"), None);
+        assert_eq!(text_recipient("#TMBAW123:@18480:hello"), Some(TextRecipient::Frequency("18480".to_string())));
+        assert_eq!(text_recipient("#TMBAW123:LON_S_CTR:hello"), Some(TextRecipient::Callsign("LON_S_CTR".to_string())));
+        assert_eq!(text_recipient("#TMLON_S_CTR:*:hello all"), Some(TextRecipient::Broadcast));
+        assert_eq!(text_recipient("#TMLON_S_CTR:*S:help"), Some(TextRecipient::Broadcast));
+        assert_eq!(text_recipient("#TMBAW123"), None);
+        assert_eq!(text_recipient("$CQBAW123:SERVER:ATC"), None);
+    }
+
+    #[test]
+    fn test_login_type() {
+        assert_eq!(login_type("#AALON_S_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300"), Some(ClientType::Controller));
+        // Pilots logging in with #AA send fewer fields
+        assert_eq!(login_type("#AALON_S_CTR:SERVER"), Some(ClientType::Pilot));
+        assert_eq!(login_type("#AABAW123:SERVER:1000001:123456:1:100:1:Test Pilot"), Some(ClientType::Pilot));
+        assert_eq!(login_type("#APBAW123:SERVER:1000001:123456:1:100:1:Test Pilot"), Some(ClientType::Pilot));
+        // Callsigns containing the other prefix don't confuse it
+        assert_eq!(login_type("#APAAL123:SERVER:1000001:123456:1:100:1:Test Pilot"), Some(ClientType::Pilot));
+        assert_eq!(login_type("#AAAPP_CTR:SERVER:Test:1000000:123456:5:100:1:100:51.5:-0.5:300"), Some(ClientType::Controller));
+        assert_eq!(login_type("$CQAAL123:SERVER:ATC"), None);
+        assert_eq!(login_type("@N:AAL123:2201:1:51.5:-0.5:0:0:0:0"), None);
+    }
+
+    #[test]
+    fn test_pong_for() {
+        assert_eq!(pong_for("$PIBAW123:SERVER:84236"), Some("$POSERVER:BAW123:84236".to_string()));
+        assert_eq!(pong_for("$PILON_S_CTR:SERVER"), Some("$POSERVER:LON_S_CTR:".to_string()));
+        // Pings between clients are passed on, not answered
+        assert_eq!(pong_for("$PILON_S_CTR:LON_E_CTR:84236"), None);
+        assert_eq!(pong_for("$POSERVER:BAW123:84236"), None);
+    }
+
+    #[test]
+    fn test_atis_query() {
+        assert_eq!(atis_query("$CQLON_S_CTR:EGLL_ATIS:ATIS"), Some(("LON_S_CTR".to_string(), "EGLL_ATIS".to_string())));
+        assert_eq!(atis_query("$CQLON_S_CTR:EGLL_ATIS:RN"), None);
+        assert_eq!(atis_query("$AXLON_S_CTR:SERVER:METAR:EGLL"), None);
+    }
+
+    #[test]
+    fn test_metar_request() {
+        assert_eq!(metar_request("$AXLON_S_CTR:SERVER:METAR:EGLL"), Some(("LON_S_CTR".to_string(), "EGLL".to_string())));
+        assert_eq!(metar_request("$CQBAW123:SERVER:METAR:egkk"), Some(("BAW123".to_string(), "EGKK".to_string())));
+        assert_eq!(metar_request("$CQLON_S_CTR:BAW123:FP:BAW123"), None);
+        assert_eq!(metar_request("$AXLON_S_CTR:SERVER:METAR:"), None);
+    }
+}
@@ -1,6 +1,14 @@
+pub mod atis;
 pub mod fsd_server;
 pub mod controller_handler;
 pub mod pilot_handler;
 pub mod message_handler;
+pub mod recorder;
+pub mod tracks;
+pub mod weather;
 
+pub use atis::Atis;
 pub use fsd_server::FsdServer;
+pub use recorder::{Direction, Recorder};
+pub use tracks::{TrackMessage, Tracks};
+pub use weather::{StaticWeather, WeatherSource};
@@ -1,14 +1,17 @@
 use anyhow::Result;
+use tokio::io::AsyncWriteExt;
 use tokio::net::tcp::OwnedWriteHalf;
 use tokio::sync::Mutex;
 use std::sync::Arc;
 
-use super::message_handler::{MessageHandler, MessageStatus, ClientType, parse_message};
+use super::recorder::{Direction, Recorder};
+use super::message_handler::{MessageHandler, MessageStatus, ClientType, es_convert, login_type, parse_message, pong_for};
 
 /// Handler for pilot connections
 pub struct PilotHandler {
-    #[allow(dead_code)]
     stream: Arc<Mutex<OwnedWriteHalf>>,
+    /// Where to record what is sent to this pilot, if recording
+    recorder: Option<Arc<Recorder>>,
     pub callsign: String,
     server: String,
     cid: String,
@@ -27,6 +30,7 @@ impl PilotHandler {
     pub fn new(stream: Arc<Mutex<OwnedWriteHalf>>) -> Self {
         Self {
             stream,
+            recorder: None,
             callsign: String::new(),
             server: String::new(),
             cid: String::new(),
@@ -38,6 +42,28 @@ impl PilotHandler {
             fp_message: Vec::new(),
         }
     }
+
+    /// Real name given at login
+    pub fn real_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Record everything sent to this pilot
+    pub fn with_recorder(mut self, recorder: Option<Arc<Recorder>>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    /// Send a message to this pilot
+    pub async fn send_message(&self, parts: &[&str]) -> Result<()> {
+        let data = es_convert(parts);
+        if let Some(recorder) = &self.recorder {
+            recorder.record(Direction::Send, &self.callsign, &String::from_utf8_lossy(&data));
+        }
+        let mut stream = self.stream.lock().await;
+        stream.write_all(&data).await?;
+        Ok(())
+    }
 }
 
 impl MessageHandler for PilotHandler {
@@ -48,15 +74,23 @@ impl MessageHandler for PilotHandler {
             return Ok(MessageStatus::Handled);
         }
 
-        // Handle pilot login (#AP)
-        if parts[0].starts_with("#AP") {
-            if parts.len() >= 8 {
-                self.callsign = parts[0][3..].to_string();
-                self.server = parts.get(1).map(|s| s.to_string()).unwrap_or_default();
-                self.cid = parts.get(2).map(|s| s.to_string()).unwrap_or_default();
-                self.password = parts.get(3).map(|s| s.to_string()).unwrap_or_default();
-                self.name = parts.get(7).map(|s| s.to_string()).unwrap_or_default();
-            }
+        // Handle pilot login (#AP, or a short #AA)
+        if login_type(message) == Some(ClientType::Pilot) {
+            self.callsign = parts[0][3..].to_string();
+            self.server = parts.get(1).map(|s| s.to_string()).unwrap_or_default();
+            self.cid = parts.get(2).map(|s| s.to_string()).unwrap_or_default();
+            self.password = parts.get(3).map(|s| s.to_string()).unwrap_or_default();
+            self.name = parts.get(7).map(|s| s.to_string()).unwrap_or_default();
+            return Ok(MessageStatus::Handled);
+        }
+
+        // Answer keepalive pings addressed to the server ($PI)
+        if let Some(pong) = pong_for(message) {
+            let stream = self.stream.clone();
+            tokio::spawn(async move {
+                let data = es_convert(&[pong.as_str()]);
+                let _ = stream.lock().await.try_write(&data);
+            });
             return Ok(MessageStatus::Handled);
         }
 
@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+/// Which way a recorded line went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from a client
+    Recv,
+    /// Sent to a client
+    Send,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Recv => "RECV",
+            Direction::Send => "SEND",
+        }
+    }
+}
+
+/// What the writer thread is asked to do
+enum Record {
+    Line(String),
+    /// Signal once everything sent before has been written
+    Flush(oneshot::Sender<()>),
+}
+
+/// Records every line the server receives and sends, one per line as
+/// `<ms since start>\t<RECV|SEND>\t<client>\t<message>`. Received lines are keyed by
+/// the client's address, sent lines by the callsign they went to.
+pub struct Recorder {
+    started: Instant,
+    records: mpsc::UnboundedSender<Record>,
+}
+
+impl Recorder {
+    /// Start a new recording, replacing any file already at `path`. Lines are written
+    /// on a thread of the recorder's own, which stops once the recorder is dropped.
+    pub fn create(path: &str) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create recording: {}", path))?;
+        let (records, mut pending) = mpsc::unbounded_channel();
+
+        std::thread::spawn(move || {
+            let mut file = LineWriter::new(file);
+            while let Some(record) = pending.blocking_recv() {
+                match record {
+                    Record::Line(line) => {
+                        if let Err(e) = file.write_all(line.as_bytes()) {
+                            warn!("[RECORD] Failed to record: {}", e);
+                        }
+                    }
+                    Record::Flush(done) => {
+                        let _ = file.flush();
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            started: Instant::now(),
+            records,
+        })
+    }
+
+    /// Record a line received from or sent to `client`, stamped with the time since the
+    /// recording started. The line is queued for the writer thread, so this never
+    /// blocks on the file.
+    pub fn record(&self, direction: Direction, client: &str, message: &str) {
+        let line = format!(
+            "{}\t{}\t{}\t{}\n",
+            self.started.elapsed().as_millis(),
+            direction.as_str(),
+            client,
+            message.trim_end()
+        );
+        if self.records.send(Record::Line(line)).is_err() {
+            warn!("[RECORD] Writer stopped, dropping line for {}", client);
+        }
+    }
+
+    /// Wait until every line recorded so far is in the file
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.records.send(Record::Flush(done)).is_ok() {
+            let _ = written.await;
+        }
+    }
+}
+
+/// Parse a recorded line into its time since the start, direction, client and message
+pub fn parse_recorded_line(line: &str) -> Option<(Duration, Direction, &str, &str)> {
+    let mut fields = line.splitn(4, '\t');
+    let millis: u64 = fields.next()?.parse().ok()?;
+    let direction = match fields.next()? {
+        "RECV" => Direction::Recv,
+        "SEND" => Direction::Send,
+        _ => return None,
+    };
+    Some((Duration::from_millis(millis), direction, fields.next()?, fields.next()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_parse() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("sweatbox-recorder-{}.tsv", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let recorder = Recorder::create(path)?;
+        recorder.record(Direction::Recv, "127.0.0.1:50001", "#APBAW123:SERVER:1000001");
+        recorder.record(Direction::Send, "LON_S_CTR", "@N:BAW123:2201:1:51.47:-0.46:0:0:1234:0\r\n");
+        recorder.flush().await;
+
+        let contents = std::fs::read_to_string(path)?;
+        std::fs::remove_file(path)?;
+        let lines: Vec<_> = contents.lines().filter_map(parse_recorded_line).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].1, lines[0].2, lines[0].3), (Direction::Recv, "127.0.0.1:50001", "#APBAW123:SERVER:1000001"));
+        assert_eq!((lines[1].1, lines[1].2), (Direction::Send, "LON_S_CTR"));
+        assert!(lines[1].3.ends_with(":1234:0"));
+        assert_eq!(parse_recorded_line("2026-10-16T10:00:00Z INFO [RECV] x: y"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// A message that changes who controls an aircraft
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackMessage {
+    /// Start tracking: `$CQ<controller>:<to>:IT:<aircraft>`
+    Claim { controller: String, aircraft: String },
+    /// Stop tracking: `$CQ<controller>:<to>:DR:<aircraft>`
+    Drop { controller: String, aircraft: String },
+    /// Offer a handoff: `$HO<from>:<to>:<aircraft>`
+    Handoff { from: String, to: String, aircraft: String },
+    /// Accept a handoff: `$HA<to>:<from>:<aircraft>`
+    Accept { to: String, from: String, aircraft: String },
+}
+
+impl TrackMessage {
+    /// Parse a track claim, drop, handoff or accept, or None for any other message
+    pub fn parse(message: &str) -> Option<Self> {
+        let (prefix, rest) = message.split_at_checked(3)?;
+        let parts: Vec<&str> = rest.split(':').collect();
+        match (prefix, parts.as_slice()) {
+            ("$CQ", [controller, _, "IT", aircraft, ..]) => Some(TrackMessage::Claim {
+                controller: controller.to_string(),
+                aircraft: aircraft.to_string(),
+            }),
+            ("$CQ", [controller, _, "DR", aircraft, ..]) => Some(TrackMessage::Drop {
+                controller: controller.to_string(),
+                aircraft: aircraft.to_string(),
+            }),
+            ("$HO", [from, to, aircraft, ..]) => Some(TrackMessage::Handoff {
+                from: from.to_string(),
+                to: to.to_string(),
+                aircraft: aircraft.to_string(),
+            }),
+            ("$HA", [to, from, aircraft, ..]) => Some(TrackMessage::Accept {
+                to: to.to_string(),
+                from: from.to_string(),
+                aircraft: aircraft.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TrackMessage {
+    /// The FSD line for the message, without the line ending. Claims and drops go to
+    /// everyone (`@94835`).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackMessage::Claim { controller, aircraft } => write!(f, "$CQ{}:@94835:IT:{}", controller, aircraft),
+            TrackMessage::Drop { controller, aircraft } => write!(f, "$CQ{}:@94835:DR:{}", controller, aircraft),
+            TrackMessage::Handoff { from, to, aircraft } => write!(f, "$HO{}:{}:{}", from, to, aircraft),
+            TrackMessage::Accept { to, from, aircraft } => write!(f, "$HA{}:{}:{}", to, from, aircraft),
+        }
+    }
+}
+
+/// Which controller owns each aircraft's track, and the handoffs awaiting acceptance
+#[derive(Debug, Default)]
+pub struct Tracks {
+    /// Aircraft callsign to owning controller
+    owners: HashMap<String, String>,
+    /// Aircraft callsign to the controller it has been offered to
+    offered: HashMap<String, String>,
+}
+
+impl Tracks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Controller that owns an aircraft's track
+    pub fn owner(&self, aircraft: &str) -> Option<&str> {
+        self.owners.get(aircraft).map(|s| s.as_str())
+    }
+
+    /// Apply a track message, returning whether it is valid and should be passed on.
+    /// Only an untracked aircraft can be claimed, only its owner can drop it or hand
+    /// it off, and ownership moves only when the controller offered it accepts.
+    pub fn apply(&mut self, message: &TrackMessage) -> bool {
+        match message {
+            TrackMessage::Claim { controller, aircraft } => {
+                match self.owners.get(aircraft) {
+                    Some(owner) => owner == controller,
+                    None => {
+                        self.owners.insert(aircraft.clone(), controller.clone());
+                        true
+                    }
+                }
+            }
+            TrackMessage::Drop { controller, aircraft } => {
+                if self.owner(aircraft) != Some(controller) {
+                    return false;
+                }
+                self.owners.remove(aircraft);
+                self.offered.remove(aircraft);
+                true
+            }
+            TrackMessage::Handoff { from, to, aircraft } => {
+                if self.owner(aircraft) != Some(from) {
+                    return false;
+                }
+                self.offered.insert(aircraft.clone(), to.clone());
+                true
+            }
+            TrackMessage::Accept { to, from, aircraft } => {
+                if self.owner(aircraft) != Some(from) || self.offered.get(aircraft) != Some(to) {
+                    return false;
+                }
+                self.offered.remove(aircraft);
+                self.owners.insert(aircraft.clone(), to.clone());
+                true
+            }
+        }
+    }
+
+    /// Release every track a controller owns, e.g. when it disconnects
+    pub fn release(&mut self, controller: &str) {
+        self.owners.retain(|_, owner| owner != controller);
+        self.offered.retain(|_, offered| offered != controller);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(tracks: &mut Tracks, message: &str) -> bool {
+        tracks.apply(&TrackMessage::parse(message).unwrap())
+    }
+
+    #[test]
+    fn test_parse_track_messages() {
+        assert_eq!(TrackMessage::parse("$CQLON_S_CTR:@94835:IT:BAW123"), Some(TrackMessage::Claim {
+            controller: "LON_S_CTR".to_string(),
+            aircraft: "BAW123".to_string(),
+        }));
+        assert_eq!(TrackMessage::parse("$HOLON_S_CTR:LTC_S_CTR:BAW123"), Some(TrackMessage::Handoff {
+            from: "LON_S_CTR".to_string(),
+            to: "LTC_S_CTR".to_string(),
+            aircraft: "BAW123".to_string(),
+        }));
+        assert_eq!(TrackMessage::parse("$HALTC_S_CTR:LON_S_CTR:BAW123"), Some(TrackMessage::Accept {
+            to: "LTC_S_CTR".to_string(),
+            from: "LON_S_CTR".to_string(),
+            aircraft: "BAW123".to_string(),
+        }));
+        for message in ["$CQLON_S_CTR:@94835:IT:BAW123", "$HOLON_S_CTR:LTC_S_CTR:BAW123", "$HALTC_S_CTR:LON_S_CTR:BAW123"] {
+            assert_eq!(TrackMessage::parse(message).unwrap().to_string(), message);
+        }
+        assert_eq!(TrackMessage::parse("$CQLON_S_CTR:BAW123:FP:BAW123"), None);
+        assert_eq!(TrackMessage::parse("$HOLON_S_CTR"), None);
+        assert_eq!(TrackMessage::parse("#T"), None);
+    }
+
+    #[test]
+    fn test_handoff_transfers_on_accept() {
+        let mut tracks = Tracks::new();
+        assert!(apply(&mut tracks, "$CQLON_S_CTR:@94835:IT:BAW123"));
+        assert!(!apply(&mut tracks, "$CQLTC_S_CTR:@94835:IT:BAW123"));
+        assert_eq!(tracks.owner("BAW123"), Some("LON_S_CTR"));
+
+        // Only the owner can hand off, and only the controller offered it can accept
+        assert!(!apply(&mut tracks, "$HOLTC_S_CTR:LON_S_CTR:BAW123"));
+        assert!(apply(&mut tracks, "$HOLON_S_CTR:LTC_S_CTR:BAW123"));
+        assert_eq!(tracks.owner("BAW123"), Some("LON_S_CTR"));
+        assert!(!apply(&mut tracks, "$HALTC_E_CTR:LON_S_CTR:BAW123"));
+        assert!(apply(&mut tracks, "$HALTC_S_CTR:LON_S_CTR:BAW123"));
+        assert_eq!(tracks.owner("BAW123"), Some("LTC_S_CTR"));
+
+        // The old owner has let it go
+        assert!(!apply(&mut tracks, "$CQLON_S_CTR:@94835:DR:BAW123"));
+        tracks.release("LTC_S_CTR");
+        assert_eq!(tracks.owner("BAW123"), None);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use crate::scenario::Scenario;
+
+/// Where the server gets the weather it gives clients
+pub trait WeatherSource: Send + Sync {
+    /// Current METAR for a station
+    fn metar(&self, icao: &str) -> Option<String>;
+
+    /// Current QNH (hPa) at a station, from its METAR
+    fn qnh(&self, icao: &str) -> Option<f64> {
+        self.metar(icao).as_deref().and_then(qnh_from_metar)
+    }
+}
+
+/// Fixed METARs by station, with calm ISA weather for stations without one
+#[derive(Debug, Clone, Default)]
+pub struct StaticWeather {
+    metars: HashMap<String, String>,
+}
+
+impl StaticWeather {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the METAR reported for a station
+    pub fn with_metar(mut self, icao: &str, metar: &str) -> Self {
+        self.metars.insert(icao.to_uppercase(), metar.to_string());
+        self
+    }
+
+    /// Weather from the METARs published in a scenario's profile
+    pub fn for_scenario(scenario: &Scenario) -> Self {
+        scenario.metars()
+            .iter()
+            .fold(Self::new(), |weather, (icao, metar)| weather.with_metar(icao, metar))
+    }
+}
+
+impl WeatherSource for StaticWeather {
+    fn metar(&self, icao: &str) -> Option<String> {
+        let icao = icao.to_uppercase();
+        if let Some(metar) = self.metars.get(&icao) {
+            return Some(metar.clone());
+        }
+        let is_station = icao.len() == 4 && icao.chars().all(|c| c.is_ascii_alphanumeric());
+        is_station.then(|| format!("{} 000000Z 00000KT CAVOK 15/05 Q1013", icao))
+    }
+}
+
+/// QNH (hPa) from a METAR's altimeter group: Q1013, or A2992 in inches of mercury
+pub fn qnh_from_metar(metar: &str) -> Option<f64> {
+    metar.split_whitespace().find_map(|group| {
+        let (unit, value) = group.split_at_checked(1)?;
+        if value.len() != 4 {
+            return None;
+        }
+        let value: f64 = value.parse().ok()?;
+        match unit {
+            "Q" => Some(value),
+            "A" => Some(value / 100.0 * 33.8639),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::ScenarioBuilder;
+
+    #[test]
+    fn test_static_weather() {
+        let weather = StaticWeather::new().with_metar("EGLL", "EGLL 161250Z 24012KT 9999 FEW035 14/08 Q1002");
+        assert_eq!(weather.metar("egll").as_deref(), Some("EGLL 161250Z 24012KT 9999 FEW035 14/08 Q1002"));
+        assert_eq!(weather.qnh("EGLL"), Some(1002.0));
+
+        // Calm standard weather elsewhere
+        assert_eq!(weather.qnh("EGKK"), Some(1013.0));
+        assert_eq!(weather.metar("LONDON"), None);
+
+        let inches = qnh_from_metar("KJFK 161251Z 31010KT 10SM FEW250 18/04 A2992 RMK AO2").unwrap();
+        assert!((inches - 1013.2).abs() < 0.1, "{}", inches);
+    }
+
+    #[test]
+    fn test_weather_for_scenario() {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27L".to_string())
+            .add_metar("egll".to_string(), "EGLL 161250Z 24012KT 9999 FEW035 14/08 Q1002".to_string())
+            .build();
+        let weather = StaticWeather::for_scenario(&scenario);
+        assert_eq!(weather.qnh("EGLL"), Some(1002.0));
+        assert_eq!(weather.qnh("EGKK"), Some(1013.0));
+    }
+}
@@ -1,13 +1,21 @@
 use anyhow::{Result, Context};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::net::TcpStream;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::mpsc;
 use tracing::{info, debug, warn, error};
 
+use crate::server::TrackMessage;
+
 /// AI Controller client that connects to the FSD server
 pub struct AiController {
     stream: Option<TcpStream>,
     tx: Option<mpsc::UnboundedSender<String>>,
+    /// Track messages the server has passed on to this controller
+    tracks: Option<mpsc::UnboundedReceiver<TrackMessage>>,
+    /// Cleared when the server connection ends, from either side
+    connected: Arc<AtomicBool>,
     callsign: String,
     freq: String,
     name: String,
@@ -30,6 +38,8 @@ impl AiController {
         Self {
             stream: None,
             tx: None,
+            tracks: None,
+            connected: Arc::new(AtomicBool::new(false)),
             callsign,
             freq,
             name: "AI Controller".to_string(),
@@ -50,6 +60,7 @@ impl AiController {
             .context(format!("Failed to connect to {}", server_addr))?;
         
         self.stream = Some(stream);
+        self.connected.store(true, Ordering::SeqCst);
         
         info!("[AI CONTROLLER] Connected to FSD server");
         Ok(())
@@ -79,8 +90,8 @@ impl AiController {
         
         info!("[AI CONTROLLER] Login message sent for {}", self.callsign);
 
-        // Wait for server response
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        // Wait for the server's welcome to confirm the login was accepted
+        self.wait_for_welcome(tokio::time::Duration::from_secs(5)).await?;
 
         // Send initial position update
         self.send_position_update().await?;
@@ -90,6 +101,32 @@ impl AiController {
         Ok(())
     }
 
+    /// Read from the server until the `#TM` welcome for this callsign arrives
+    async fn wait_for_welcome(&mut self, timeout: tokio::time::Duration) -> Result<()> {
+        let welcome = format!("#TMserver:{}:", self.callsign);
+        let stream = self.stream.as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Not connected to server"))?;
+
+        let mut received = String::new();
+        let mut buffer = vec![0u8; 4096];
+        let read_welcome = async {
+            loop {
+                let n = stream.read(&mut buffer).await?;
+                if n == 0 {
+                    return Err(anyhow::anyhow!("Server closed the connection"));
+                }
+                received.push_str(&String::from_utf8_lossy(&buffer[..n]));
+                if received.split("\r\n").any(|m| m.starts_with(&welcome)) {
+                    return Ok(());
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, read_welcome)
+            .await
+            .map_err(|_| anyhow::anyhow!("No login response for {} within {:?}", self.callsign, timeout))?
+    }
+
     /// Send a position update
     pub async fn send_position_update(&mut self) -> Result<()> {
         // FSD controller position format: %<callsign>:<frequency>:<facilitytype>:<visrange>:<rating>:<lat>:<lon>:<elevation>
@@ -127,6 +164,14 @@ impl AiController {
         }
     }
 
+    /// Queue a message to the server, once the message loop is running
+    pub fn send(&self, message: &str) -> Result<()> {
+        let tx = self.tx.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("{} is not running", self.callsign))?;
+        tx.send(format!("{}\r\n", message))
+            .map_err(|_| anyhow::anyhow!("{} has disconnected", self.callsign))
+    }
+
     /// Start listening for messages from the server
     pub async fn start_message_loop(&mut self) -> Result<()> {
         if self.stream.is_none() {
@@ -143,6 +188,7 @@ impl AiController {
         let latitude = self.latitude;
         let longitude = self.longitude;
         let range = self.range;
+        let connected = self.connected.clone();
 
         // Create channel for sending messages
         let (tx, mut rx) = mpsc::unbounded_channel::<String>();
@@ -163,6 +209,9 @@ impl AiController {
             info!("[AI CONTROLLER] Write loop ended for {}", callsign_write);
         });
 
+        let (tracks_tx, tracks_rx) = mpsc::unbounded_channel::<TrackMessage>();
+        self.tracks = Some(tracks_rx);
+
         // Spawn a task to handle incoming messages
         tokio::spawn(async move {
             let mut buffer = vec![0u8; 8192];
@@ -180,6 +229,9 @@ impl AiController {
                                     continue;
                                 }
                                 debug!("[AI CONTROLLER] {} received: {}", callsign, message);
+                                if let Some(track) = TrackMessage::parse(message) {
+                                    let _ = tracks_tx.send(track);
+                                }
                             }
                         }
                     }
@@ -189,6 +241,7 @@ impl AiController {
                     }
                 }
             }
+            connected.store(false, Ordering::SeqCst);
             info!("[AI CONTROLLER] Read loop ended for {}", callsign);
         });
 
@@ -215,6 +268,18 @@ impl AiController {
         Ok(())
     }
 
+    /// Track messages received since the last call, e.g. accepts of handoffs this
+    /// controller offered
+    pub fn received_tracks(&mut self) -> Vec<TrackMessage> {
+        let mut messages = Vec::new();
+        if let Some(tracks) = &mut self.tracks {
+            while let Ok(message) = tracks.try_recv() {
+                messages.push(message);
+            }
+        }
+        messages
+    }
+
     /// Disconnect from the server
     pub async fn disconnect(&mut self) -> Result<()> {
         info!("[AI CONTROLLER] Disconnecting {}", self.callsign);
@@ -230,6 +295,7 @@ impl AiController {
         
         // Drop the channel sender to close the write loop
         self.tx = None;
+        self.connected.store(false, Ordering::SeqCst);
         
         Ok(())
     }
@@ -239,6 +305,11 @@ impl AiController {
         &self.callsign
     }
 
+    /// Whether the controller is still connected to the server
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
     /// Get the frequency
     pub fn frequency(&self) -> &str {
         &self.freq
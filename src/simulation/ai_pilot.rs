@@ -1,13 +1,21 @@
 use anyhow::{Result, Context};
 use tokio::net::TcpStream;
 use tokio::io::{AsyncWriteExt};
-use tracing::{info, debug, warn};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::utils::atmosphere::pressure_altitude_offset;
 
 /// AI Pilot client that connects to the FSD server
 pub struct AiPilot {
     stream: Option<TcpStream>,
     callsign: String,
     cid: String,
+    name: String,
+    /// Pressure altitude minus true altitude (feet), from the QNH
+    pressure_offset: i32,
+    last_position: Option<String>,
+    last_position_sent: Option<Instant>,
 }
 
 impl AiPilot {
@@ -17,9 +25,26 @@ impl AiPilot {
             stream: None,
             callsign,
             cid: "1000001".to_string(),
+            name: "AI Pilot".to_string(),
+            pressure_offset: 0,
+            last_position: None,
+            last_position_sent: None,
         }
     }
 
+    /// Set the real name sent at login
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set the QNH (hPa) the altimeter is set to, so positions report the pressure
+    /// altitude controllers' displays correct back to altitude below the transition level
+    pub fn with_qnh(mut self, qnh: f64) -> Self {
+        self.pressure_offset = pressure_altitude_offset(qnh).round() as i32;
+        self
+    }
+
     /// Connect to the FSD server
     pub async fn connect(&mut self, server_addr: &str) -> Result<()> {
         debug!("[AI PILOT] {} connecting to FSD server at {}", self.callsign, server_addr);
@@ -35,16 +60,17 @@ impl AiPilot {
     }
 
     /// Login to the FSD server as a pilot
-    pub async fn login(&mut self, aircraft_type: &str, squawk: &str) -> Result<()> {
+    pub async fn login(&mut self, _aircraft_type: &str, squawk: &str) -> Result<()> {
         if self.stream.is_none() {
             return Err(anyhow::anyhow!("Not connected to server"));
         }
 
         // FSD pilot login format: #AP<callsign>:<server>:<cid>:<password>:<rating>:<protocol>:<simulator>:<realname>
         let login_message = format!(
-            "#AP{}:SERVER:{}:123456:1:100:1:AI Pilot\r\n",
+            "#AP{}:SERVER:{}:123456:1:100:1:{}\r\n",
             self.callsign,
-            self.cid
+            self.cid,
+            self.name
         );
 
         self.send_raw(&login_message).await?;
@@ -65,31 +91,79 @@ impl AiPilot {
         lon: f64, 
         altitude: i32, 
         ground_speed: u32, 
-        heading: i32,
+        heading: f64,
         squawk: &str
     ) -> Result<()> {
-        // FSD pilot position format: @<transponder flag>:<callsign>:<squawk code>:1:<latitude>:<longitude>:<altitude>:0:<heading>:0
-        // Heading encoding: ((heading * 2.88 + 0.5) * 4) as integer
+        // FSD pilot position format: @<transponder flag>:<callsign>:<squawk code>:1:<latitude>:<longitude>:<altitude>:0:<pitch/bank/heading>:<pressure altitude difference>
         // Use @N for Mode C (altitude reporting)
-        let encoded_heading = ((heading as f64 * 2.88 + 0.5) * 4.0) as i32;
+        let encoded_heading = encode_pbh(0.0, 0.0, heading);
         
         let position_message = format!(
-            "@N:{}:{}:1:{:.6}:{:.6}:{}:0:{}:0\r\n",
+            "@N:{}:{}:1:{:.6}:{:.6}:{}:0:{}:{}\r\n",
             self.callsign,
             squawk,
             lat,
             lon,
             altitude,
-            encoded_heading
+            encoded_heading,
+            self.pressure_offset
         );
 
-        self.send_raw(&position_message).await?;
-        debug!("[AI PILOT] Position update sent for {}: lat={:.6}, lon={:.6}, alt={}, spd={}, hdg={} (encoded={})", 
+        self.send_position_message(position_message).await?;
+        debug!("[AI PILOT] Position update sent for {}: lat={:.6}, lon={:.6}, alt={}, spd={}, hdg={:.0} (encoded={})", 
                self.callsign, lat, lon, altitude, ground_speed, heading, encoded_heading);
         
         Ok(())
     }
 
+    /// Send a primary-only position, as if the transponder had failed: standby mode,
+    /// with no squawk or altitude
+    pub async fn send_primary_position(&mut self, lat: f64, lon: f64, ground_speed: u32, heading: f64) -> Result<()> {
+        let encoded_heading = encode_pbh(0.0, 0.0, heading);
+
+        let position_message = format!(
+            "@S:{}:0000:1:{:.6}:{:.6}:0:0:{}:0\r\n",
+            self.callsign,
+            lat,
+            lon,
+            encoded_heading
+        );
+
+        self.send_position_message(position_message).await?;
+        debug!("[AI PILOT] Primary-only position sent for {}: lat={:.6}, lon={:.6}, spd={}, hdg={:.0}",
+               self.callsign, lat, lon, ground_speed, heading);
+
+        Ok(())
+    }
+
+    /// Send a position, remembering it for keep-alives
+    async fn send_position_message(&mut self, position_message: String) -> Result<()> {
+        self.send_raw(&position_message).await?;
+        self.last_position = Some(position_message);
+        self.last_position_sent = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Re-send the last position if nothing has been sent for `min_interval`,
+    /// so stationary aircraft don't time out on the controller's radar.
+    /// Returns whether a keep-alive was sent.
+    pub async fn send_keepalive(&mut self, min_interval: Duration) -> Result<bool> {
+        let due = self.last_position_sent
+            .is_some_and(|sent| sent.elapsed() >= min_interval);
+        if !due {
+            return Ok(false);
+        }
+
+        if let Some(position_message) = self.last_position.clone() {
+            self.send_raw(&position_message).await?;
+            self.last_position_sent = Some(Instant::now());
+            debug!("[AI PILOT] Keep-alive position sent for {}", self.callsign);
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
     /// Send a flight plan
     pub async fn send_flight_plan(&mut self, flight_plan: &str) -> Result<()> {
         let fp_message = format!("$FP{}:{}\r\n", self.callsign, flight_plan);
@@ -135,3 +209,110 @@ impl Drop for AiPilot {
         }
     }
 }
+
+/// Pack attitude (degrees) into the pitch/bank/heading field of a position update.
+/// Each angle takes 10 bits, 1024 steps to the circle: pitch in the top 10, then bank,
+/// then heading above two low flag bits. Pitch and bank are sent negated, so nose up
+/// and right wing down are just below a full circle.
+pub fn encode_pbh(pitch: f64, bank: f64, heading: f64) -> u32 {
+    let steps = |angle: f64| (angle.rem_euclid(360.0) / 360.0 * 1024.0).round() as u32 % 1024;
+    steps(-pitch) << 22 | steps(-bank) << 12 | steps(heading) << 2
+}
+
+/// Unpack the pitch, bank and heading (degrees) from a pitch/bank/heading field
+pub fn decode_pbh(pbh: u32) -> (f64, f64, f64) {
+    let angle = |shift: u32| ((pbh >> shift) & 0x3ff) as f64 / 1024.0 * 360.0;
+    let signed = |angle: f64| if angle > 180.0 { angle - 360.0 } else { angle };
+    (-signed(angle(22)), -signed(angle(12)), angle(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::navigation::heading_difference;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_encode_pbh() {
+        assert_eq!(encode_pbh(0.0, 0.0, 0.0), 0);
+        assert_eq!(encode_pbh(0.0, 0.0, 90.0), 256 << 2);
+        assert_eq!(encode_pbh(0.0, 0.0, 270.0), 768 << 2);
+        assert_eq!(encode_pbh(0.0, 0.0, -90.0), 768 << 2);
+
+        // Wings level, the heading never spills into the bank bits, wrapping to
+        // north just short of 360
+        for tenth in 0..3600 {
+            let heading = tenth as f64 / 10.0;
+            let pbh = encode_pbh(0.0, 0.0, heading);
+            assert!(pbh < 1 << 12, "{}", heading);
+
+            let (_, _, decoded) = decode_pbh(pbh);
+            assert!(heading_difference(heading, decoded).abs() <= 360.0 / 2048.0 + 1e-9, "{} -> {}", heading, decoded);
+        }
+        assert_eq!(encode_pbh(0.0, 0.0, 359.9), 0);
+
+        // Climbing turn to the left
+        let (pitch, bank, heading) = decode_pbh(encode_pbh(10.0, -25.0, 135.0));
+        assert!((pitch - 10.0).abs() < 0.2 && (bank + 25.0).abs() < 0.2, "{} {}", pitch, bank);
+        assert!((heading - 135.0).abs() < 0.2, "{}", heading);
+    }
+
+    #[tokio::test]
+    async fn test_stationary_keepalive() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+
+        let mut pilot = AiPilot::new("BAW123".to_string());
+        pilot.connect(&addr).await?;
+        let (mut server_side, _) = listener.accept().await?;
+
+        let interval = Duration::from_millis(50);
+
+        // Nothing to repeat before the first position
+        assert!(!pilot.send_keepalive(interval).await?);
+
+        pilot.send_position(51.47, -0.46, 0, 0, 270.0, "2201").await?;
+
+        // Not yet due
+        assert!(!pilot.send_keepalive(interval).await?);
+
+        // Parked aircraft: keep-alives continue at the minimum interval
+        for _ in 0..3 {
+            tokio::time::sleep(interval).await;
+            assert!(pilot.send_keepalive(interval).await?);
+        }
+        pilot.disconnect().await?;
+
+        let mut received = String::new();
+        server_side.read_to_string(&mut received).await?;
+        let positions: Vec<&str> = received
+            .split("\r\n")
+            .filter(|m| m.starts_with("@N:BAW123:"))
+            .collect();
+
+        assert_eq!(positions.len(), 4);
+        assert!(positions.iter().all(|p| *p == positions[0]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_position_reports_pressure_altitude() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?.to_string();
+
+        let mut pilot = AiPilot::new("BAW123".to_string()).with_qnh(1002.0);
+        pilot.connect(&addr).await?;
+        let (mut server_side, _) = listener.accept().await?;
+        pilot.send_position(51.47, -0.46, 3000, 180, 270.0, "2201").await?;
+        pilot.disconnect().await?;
+
+        let mut received = String::new();
+        server_side.read_to_string(&mut received).await?;
+        // 3000ft on a QNH of 1002 is about 3300ft pressure altitude
+        let position = received.split("\r\n").find(|m| m.starts_with("@N:")).unwrap();
+        let offset: i32 = position.rsplit(':').next().unwrap().parse()?;
+        assert!((300..320).contains(&offset), "{}", received);
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+/// An instruction a controller can give an aircraft
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    /// Fly heading (degrees)
+    Heading(u32),
+    /// Climb or descend to an altitude (feet)
+    Altitude(i32),
+    /// Fly an indicated airspeed (kt)
+    Speed(u32),
+    /// Fly a Mach number
+    Mach(f64),
+    /// Cleared ILS approach to a runway at the destination
+    Ils(String),
+    /// Resume own navigation along the route
+    Resume,
+    /// Hold at a fix
+    Hold(String),
+    /// Go around and fly the missed approach
+    GoAround,
+    /// Squawk the assigned code
+    Squawk,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Heading(heading) => write!(f, "fly heading {:03}", heading),
+            Instruction::Altitude(altitude) => write!(f, "climb/descend to {}ft", altitude),
+            Instruction::Speed(speed) => write!(f, "speed {}kt", speed),
+            Instruction::Mach(mach) => write!(f, "Mach {:.2}", mach),
+            Instruction::Ils(runway) => write!(f, "cleared ILS {}", runway),
+            Instruction::Resume => write!(f, "resume own navigation"),
+            Instruction::Hold(fix) => write!(f, "hold at {}", fix),
+            Instruction::GoAround => write!(f, "go around"),
+            Instruction::Squawk => write!(f, "squawk assigned code"),
+        }
+    }
+}
+
+/// A typed ATC command, e.g. "BAW123 FH 270", optionally addressed to a callsign
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtcCommand {
+    pub callsign: Option<String>,
+    pub instruction: Instruction,
+}
+
+/// Parse a command: an optional callsign, then one of
+/// `FH <hdg>`, `C|D <alt>`, `SPD <kt>`, `M <mach>`, `ILS <rwy>`, `RESUME`, `HOLD <fix>`,
+/// `GA` or `SQ`. Altitudes below 1000 are flight levels (`C 120`), as is `FL120`; Mach
+/// numbers are hundredths (`M 080`) or decimals (`M.80`, `MACH .80`).
+pub fn parse_command(text: &str) -> Option<AtcCommand> {
+    let tokens: Vec<String> = text.split_whitespace().map(|t| t.to_uppercase()).collect();
+
+    if let Some(instruction) = parse_instruction(&tokens) {
+        return Some(AtcCommand { callsign: None, instruction });
+    }
+
+    let (callsign, rest) = tokens.split_first()?;
+    if !is_callsign(callsign) {
+        return None;
+    }
+    let instruction = parse_instruction(rest)?;
+    Some(AtcCommand { callsign: Some(callsign.clone()), instruction })
+}
+
+fn parse_instruction(tokens: &[String]) -> Option<Instruction> {
+    let strs: Vec<&str> = tokens.iter().map(|t| t.as_str()).collect();
+    match strs.as_slice() {
+        ["FH" | "H", heading] => {
+            let heading: u32 = heading.parse().ok()?;
+            (1..=360).contains(&heading).then_some(Instruction::Heading(heading))
+        }
+        ["C" | "D" | "CM" | "DM", altitude] => parse_altitude(altitude).map(Instruction::Altitude),
+        ["SPD" | "S", speed] => {
+            let speed: u32 = speed.parse().ok()?;
+            (speed > 0).then_some(Instruction::Speed(speed))
+        }
+        ["M" | "MACH", mach] => parse_mach(mach).map(Instruction::Mach),
+        [mach] if mach.starts_with("M.") => parse_mach(&mach[1..]).map(Instruction::Mach),
+        ["ILS", runway] => is_runway(runway).then(|| Instruction::Ils(runway.to_string())),
+        ["RESUME" | "RES"] => Some(Instruction::Resume),
+        ["GA" | "GOAROUND"] => Some(Instruction::GoAround),
+        ["SQ" | "SQUAWK"] => Some(Instruction::Squawk),
+        ["HOLD", fix] => {
+            let fix_like = (2..=11).contains(&fix.len()) && fix.chars().all(|c| c.is_ascii_alphanumeric());
+            fix_like.then(|| Instruction::Hold(fix.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Feet, or a flight level: "5000", "FL120", "F120" or "120"
+fn parse_altitude(token: &str) -> Option<i32> {
+    let (digits, is_level) = match token.strip_prefix("FL").or_else(|| token.strip_prefix('F')) {
+        Some(level) => (level, true),
+        None => (token, false),
+    };
+    let value: i32 = digits.parse().ok()?;
+    if value <= 0 {
+        return None;
+    }
+    Some(if is_level || value < 1000 { value * 100 } else { value })
+}
+
+/// A Mach number: "080" or "80" in hundredths, or ".80" / "0.80"
+fn parse_mach(token: &str) -> Option<f64> {
+    let mach = if token.contains('.') {
+        token.parse::<f64>().ok()?
+    } else {
+        (2..=3).contains(&token.len()).then_some(())?;
+        token.parse::<u32>().ok()? as f64 / 100.0
+    };
+    (mach > 0.0 && mach < 1.0).then_some(mach)
+}
+
+fn is_runway(token: &str) -> bool {
+    let (number, side) = token.split_at(token.len().min(2));
+    number.len() == 2
+        && number.parse::<u32>().is_ok_and(|n| (1..=36).contains(&n))
+        && matches!(side, "" | "L" | "R" | "C")
+}
+
+fn is_callsign(token: &str) -> bool {
+    (3..=10).contains(&token.len())
+        && token.starts_with(|c: char| c.is_ascii_alphabetic())
+        && token.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(text: &str) -> Option<Instruction> {
+        parse_command(text).map(|command| command.instruction)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse_command("BAW123 FH 270"), Some(AtcCommand {
+            callsign: Some("BAW123".to_string()),
+            instruction: Instruction::Heading(270),
+        }));
+        assert_eq!(parse_command("fh 090"), Some(AtcCommand { callsign: None, instruction: Instruction::Heading(90) }));
+
+        assert_eq!(instruction("C 5000"), Some(Instruction::Altitude(5000)));
+        assert_eq!(instruction("EZY12AB D FL120"), Some(Instruction::Altitude(12000)));
+        assert_eq!(instruction("C 80"), Some(Instruction::Altitude(8000)));
+        assert_eq!(instruction("SPD 220"), Some(Instruction::Speed(220)));
+        assert_eq!(instruction("BAW123 M 080"), Some(Instruction::Mach(0.8)));
+        assert_eq!(instruction("M.78"), Some(Instruction::Mach(0.78)));
+        assert_eq!(instruction("BAW123 MACH .80"), Some(Instruction::Mach(0.8)));
+        assert_eq!(instruction("KLM1 HOLD 5130N00030W"), Some(Instruction::Hold("5130N00030W".to_string())));
+        assert_eq!(instruction("BAW123 ILS 27R"), Some(Instruction::Ils("27R".to_string())));
+        assert_eq!(instruction("RESUME"), Some(Instruction::Resume));
+        assert_eq!(instruction("KLM1 HOLD LAM"), Some(Instruction::Hold("LAM".to_string())));
+        assert_eq!(instruction("BAW123 GA"), Some(Instruction::GoAround));
+        assert_eq!(instruction("BAW123 SQ"), Some(Instruction::Squawk));
+        assert_eq!(instruction("squawk"), Some(Instruction::Squawk));
+    }
+
+    #[test]
+    fn test_malformed_commands() {
+        for text in [
+            "",
+            "BAW123",
+            "FH",
+            "FH 400",
+            "FH west",
+            "C -500",
+            "SPD 0",
+            "M 8",
+            "M 1.2",
+            "M.",
+            "MACH FAST",
+            "ILS 40",
+            "ILS 27X",
+            "HOLD",
+            "BAW123 JUMP",
+            "123 FH 270",
+            "BAW123 FH 270 NOW",
+        ] {
+            assert_eq!(parse_command(text), None, "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+/// Things that happen during a simulation, for anything watching it
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulatorEvent {
+    /// A controller went offline
+    ControllerLost { callsign: String },
+    /// An aircraft was handed to another controller
+    AircraftReassigned { callsign: String, from: String, to: String },
+    /// A controller handed an aircraft on at its release point
+    HandoffOffered { callsign: String, from: String, to: String },
+    /// A controller cleared an aircraft to descend (feet)
+    DescentIssued { callsign: String, controller: String, altitude: i32 },
+    /// A departure and an arrival are predicted to meet at a fix without vertical separation
+    ConflictPredicted { departure: String, arrival: String, fix: String },
+}
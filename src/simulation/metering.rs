@@ -0,0 +1,47 @@
+use std::collections::VecDeque;
+
+/// Limits how often arrivals enter the arrival stream, queueing any that
+/// come faster than the flow rate allows
+#[derive(Debug, Clone)]
+pub struct ArrivalMeter<T> {
+    /// Minimum time between releases (seconds)
+    min_interval: f64,
+    last_release: Option<f64>,
+    queue: VecDeque<T>,
+}
+
+impl<T> ArrivalMeter<T> {
+    /// Meter to `flow_per_hour` arrivals (0 or less releases everything immediately)
+    pub fn new(flow_per_hour: f64) -> Self {
+        Self {
+            min_interval: if flow_per_hour > 0.0 { 3600.0 / flow_per_hour } else { 0.0 },
+            last_release: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Queue an arrival for release
+    pub fn push(&mut self, arrival: T) {
+        self.queue.push_back(arrival);
+    }
+
+    /// The next queued arrival, if one is waiting and the flow rate allows it at `now` (seconds)
+    pub fn release(&mut self, now: f64) -> Option<T> {
+        if self.last_release.is_some_and(|last| now - last < self.min_interval) {
+            return None;
+        }
+
+        let arrival = self.queue.pop_front()?;
+        self.last_release = Some(now);
+        Some(arrival)
+    }
+
+    /// Number of arrivals waiting
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
@@ -1,7 +1,14 @@
 pub mod simulator;
 pub mod ai_controller;
 pub mod ai_pilot;
+pub mod command;
+pub mod events;
+pub mod metering;
+pub mod radar;
+pub mod spacing;
 
-pub use simulator::Simulator;
+pub use simulator::{AircraftSnapshot, Simulator, stdin_commands};
 pub use ai_controller::AiController;
 pub use ai_pilot::AiPilot;
+pub use command::{AtcCommand, Instruction, parse_command};
+pub use events::SimulatorEvent;
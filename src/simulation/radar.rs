@@ -0,0 +1,145 @@
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+
+/// Schedules radar position reports for each target: one every `interval` seconds,
+/// varied by up to `jitter` either way, and delivered `latency` seconds after the
+/// position was taken
+#[derive(Debug, Clone)]
+pub struct RadarFeed<T> {
+    interval: f64,
+    jitter: f64,
+    latency: f64,
+    /// Time each target's next report is taken
+    next_report: HashMap<String, f64>,
+    /// Reports taken but not yet delivered, in delivery order
+    pending: VecDeque<(f64, String, T)>,
+}
+
+impl<T> RadarFeed<T> {
+    pub fn new(interval: f64, jitter: f64, latency: f64) -> Self {
+        Self {
+            interval: interval.max(0.0),
+            jitter: jitter.clamp(0.0, interval.max(0.0)),
+            latency: latency.max(0.0),
+            next_report: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Take a report of a target at `now` (seconds) if one is due. A target seen for
+    /// the first time is reported one interval later.
+    pub fn scan(&mut self, callsign: &str, now: f64, report: impl FnOnce() -> T) {
+        self.scan_scaled(callsign, now, 1.0, report);
+    }
+
+    /// Take a report as `scan` does, scheduling the one after at `scale` times the
+    /// usual interval
+    pub fn scan_scaled(&mut self, callsign: &str, now: f64, scale: f64, report: impl FnOnce() -> T) {
+        let next_interval = self.next_interval() * scale.max(0.0);
+        let Some(next) = self.next_report.get_mut(callsign) else {
+            self.next_report.insert(callsign.to_string(), now + next_interval);
+            return;
+        };
+        if *next > now {
+            return;
+        }
+
+        *next = now + next_interval;
+        self.pending.push_back((now + self.latency, callsign.to_string(), report()));
+    }
+
+    /// Reports due for delivery at `now`
+    pub fn ready(&mut self, now: f64) -> Vec<(String, T)> {
+        let mut ready = Vec::new();
+        while self.pending.front().is_some_and(|(deliver_at, _, _)| *deliver_at <= now) {
+            let (_, callsign, report) = self.pending.pop_front().expect("front exists");
+            ready.push((callsign, report));
+        }
+        ready
+    }
+
+    /// Stop tracking a target, dropping any reports of it still to be delivered
+    pub fn forget(&mut self, callsign: &str) {
+        self.next_report.remove(callsign);
+        self.pending.retain(|(_, pending, _)| pending != callsign);
+    }
+
+    fn next_interval(&self) -> f64 {
+        if self.jitter > 0.0 {
+            self.interval + rand::thread_rng().gen_range(-self.jitter..=self.jitter)
+        } else {
+            self.interval
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Times reports of one target are delivered over `duration` seconds of 5Hz scans
+    fn delivery_times(feed: &mut RadarFeed<f64>, duration: f64) -> Vec<f64> {
+        let mut times = Vec::new();
+        for tick in 0..=(duration * 5.0) as u32 {
+            let now = tick as f64 / 5.0;
+            feed.scan("BAW123", now, || now);
+            for (_, taken) in feed.ready(now) {
+                assert!(now >= taken);
+                times.push(now);
+            }
+        }
+        times
+    }
+
+    #[test]
+    fn test_steady_updates() {
+        let mut feed = RadarFeed::new(5.0, 0.0, 0.0);
+        assert_eq!(delivery_times(&mut feed, 20.0), vec![5.0, 10.0, 15.0, 20.0]);
+    }
+
+    #[test]
+    fn test_jitter_varies_intervals_around_mean() {
+        let mut feed = RadarFeed::new(5.0, 1.0, 0.0);
+        let times = delivery_times(&mut feed, 2000.0);
+        let intervals: Vec<f64> = times.windows(2).map(|pair| pair[1] - pair[0]).collect();
+
+        // Each interval is within the jitter, give or take a scan
+        for interval in &intervals {
+            assert!((3.8..=6.2).contains(interval), "{}s between updates", interval);
+        }
+        let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        assert!((mean - 5.0).abs() < 0.25, "mean interval {}s", mean);
+        assert!(intervals.iter().any(|i| (i - 5.0).abs() > 0.5), "{:?}", intervals);
+    }
+
+    #[test]
+    fn test_latency_delays_reports() {
+        let mut feed = RadarFeed::new(5.0, 0.0, 1.4);
+        let mut delays = Vec::new();
+        for tick in 0..=100 {
+            let now = tick as f64 / 5.0;
+            feed.scan("BAW123", now, || now);
+            delays.extend(feed.ready(now).into_iter().map(|(_, taken)| now - taken));
+        }
+        assert_eq!(delays.len(), 3);
+        assert!(delays.iter().all(|delay| (delay - 1.4).abs() < 0.21), "{:?}", delays);
+
+        feed.scan("BAW123", 25.0, || 25.0);
+        feed.forget("BAW123");
+        assert!(feed.ready(30.0).is_empty());
+    }
+
+    #[test]
+    fn test_scaled_intervals() {
+        let mut feed = RadarFeed::new(4.0, 0.0, 0.0);
+        let mut times = Vec::new();
+        for tick in 0..=60 {
+            let now = tick as f64;
+            // Twice as often for the first 10 seconds, then half as often
+            let scale = if now < 10.0 { 0.5 } else { 2.0 };
+            feed.scan_scaled("BAW123", now, scale, || now);
+            times.extend(feed.ready(now).into_iter().map(|(_, taken)| taken));
+        }
+        assert_eq!(times, vec![2.0, 4.0, 6.0, 8.0, 10.0, 18.0, 26.0, 34.0, 42.0, 50.0, 58.0]);
+    }
+}
@@ -1,17 +1,56 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Write;
 use std::sync::Arc;
 use std::collections::HashMap;
 use tracing::{info, debug, warn};
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
 use rand::Rng;
+use rand::seq::SliceRandom;
 
 use crate::scenario::Scenario;
-use crate::config::{SimulationConfig, FleetConfig};
-use crate::utils::navigation::FixDatabase;
+use crate::config::{is_assignable_squawk, SimulationConfig, FleetConfig, DepartureRoute, EntryStream, ScriptedSpawn, StarlessArrivals, TransitRoute};
+use crate::utils::navigation::{FixDatabase, bearing_from_to, haversine_nm, heading_difference, magnetic_to_true, parse_coordinate_fix, position_bearing_distance};
 use crate::utils::performance::PerformanceDatabase;
-use crate::aircraft::Aircraft;
+use crate::utils::procedures::{load_runways, RunwayEnd};
+use crate::aircraft::{Aircraft, Approach, ApproachType, FlightPhase, HoldDatabase, Localizer, NavMode, Route, FixSource, parse_route_level};
+use crate::server::TrackMessage;
 use super::ai_controller::AiController;
 use super::ai_pilot::AiPilot;
+use super::command::{Instruction, parse_command};
+use super::events::SimulatorEvent;
+use super::metering::ArrivalMeter;
+use super::spacing::space_final_approach;
+use super::radar::RadarFeed;
+
+/// Longest simulated step (seconds) a single aircraft update may take, so
+/// high time multipliers don't overshoot turns and level-offs
+const MAX_UPDATE_STEP_SECS: f64 = 2.0;
+
+/// Read typed ATC commands from stdin, a line at a time. The read blocks, so it runs
+/// on its own thread rather than in the runtime, where it would hold up shutdown.
+pub fn stdin_commands() -> tokio::sync::mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// The next command line, or never if there is no command input
+async fn next_command(commands: &mut Option<tokio::sync::mpsc::UnboundedReceiver<String>>) -> Option<String> {
+    match commands {
+        Some(commands) => commands.recv().await,
+        None => std::future::pending().await,
+    }
+}
 
 /// Main simulation controller
 pub struct Simulator {
@@ -26,7 +65,25 @@ pub struct Simulator {
     pilot_clients: HashMap<String, AiPilot>,
     running: bool,
     squawk_pool: Vec<u16>,
+    /// Every CCAMS code, for telling which retired squawks go back in the pool
+    ccams_squawks: std::collections::HashSet<u16>,
     used_callsigns: std::collections::HashSet<String>,
+    events: tokio::sync::broadcast::Sender<SimulatorEvent>,
+    /// Arrivals (departure, arrival, route) waiting to enter the arrival stream
+    arrival_meter: ArrivalMeter<(String, String, String)>,
+    /// Conflicts (departure, arrival, fix) already reported
+    reported_conflicts: std::collections::HashSet<(String, String, String)>,
+    /// Positions waiting to be sent, at the radar's update rate, jitter and latency
+    radar: RadarFeed<Aircraft>,
+    /// Published holds; fixes without one get a standard hold
+    holds: HoldDatabase,
+    /// Typed ATC commands from the operator, applied as they arrive
+    command_input: Option<tokio::sync::mpsc::UnboundedReceiver<String>>,
+    /// Each tick's aircraft snapshots, as a line of JSON, for the thread writing them
+    /// to the snapshot log
+    snapshot_log: Option<(std::sync::mpsc::Sender<String>, std::thread::JoinHandle<()>)>,
+    /// Simulated seconds since the session started, scaled by the time multiplier
+    sim_time: f64,
 }
 
 impl Simulator {
@@ -39,7 +96,21 @@ impl Simulator {
         perf_db: Arc<PerformanceDatabase>,
         server_addr: String,
     ) -> Self {
+        // Profile missed approaches fill in runways the simulation config leaves out
+        let mut sim_config = sim_config;
+        for missed_approach in scenario.missed_approaches() {
+            sim_config.missed_approaches
+                .entry(format!("{} {}", missed_approach.aerodrome, missed_approach.runway))
+                .or_insert_with(|| missed_approach.fixes.clone());
+        }
+
         Self {
+            arrival_meter: ArrivalMeter::new(sim_config.arrival_flow_per_hour),
+            radar: RadarFeed::new(
+                sim_config.radar_report_interval,
+                sim_config.radar_jitter_secs,
+                sim_config.radar_latency_secs,
+            ),
             scenario: Arc::new(scenario),
             sim_config: Arc::new(sim_config),
             fleet_config: Arc::new(fleet_config),
@@ -51,10 +122,53 @@ impl Simulator {
             pilot_clients: HashMap::new(),
             running: false,
             squawk_pool: crate::config::get_ccams_squawks(),
+            ccams_squawks: crate::config::get_ccams_squawks().into_iter().collect(),
             used_callsigns: std::collections::HashSet::new(),
+            events: tokio::sync::broadcast::channel(64).0,
+            reported_conflicts: std::collections::HashSet::new(),
+            holds: HoldDatabase::new(),
+            command_input: None,
+            snapshot_log: None,
+            sim_time: 0.0,
         }
     }
 
+    /// Take typed ATC commands, one per line, while running (see `stdin_commands`)
+    pub fn with_command_input(mut self, commands: tokio::sync::mpsc::UnboundedReceiver<String>) -> Self {
+        self.command_input = Some(commands);
+        self
+    }
+
+    /// Write every aircraft's state to a file each tick, one JSON line per tick. The
+    /// file is written on a thread of its own, off the simulation loop.
+    pub fn with_snapshot_log(mut self, path: &str) -> Result<Self> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create snapshot log: {}", path))?;
+        let (lines, pending) = std::sync::mpsc::channel::<String>();
+        let writer = std::thread::spawn(move || {
+            let mut log = std::io::LineWriter::new(file);
+            for line in pending {
+                if let Err(e) = writeln!(log, "{}", line) {
+                    warn!("[SIMULATOR] Failed to write snapshot, stopping the snapshot log: {}", e);
+                    break;
+                }
+            }
+        });
+        self.snapshot_log = Some((lines, writer));
+        Ok(self)
+    }
+
+    /// Use published holds for aircraft told to hold
+    pub fn with_holds(mut self, holds: HoldDatabase) -> Self {
+        self.holds = holds;
+        self
+    }
+
+    /// Receive events from the simulation
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<SimulatorEvent> {
+        self.events.subscribe()
+    }
+
     /// Initialize the simulation
     pub async fn initialize(&mut self) -> Result<()> {
         info!("[SIMULATOR] Initializing simulation...");
@@ -64,7 +178,11 @@ impl Simulator {
         info!("{}", stats);
         
         // Login AI controllers
-        self.login_ai_controllers().await?;
+        if self.sim_config.ghost_mode {
+            info!("[SIMULATOR] Ghost mode: not connecting to {}", self.server_addr);
+        } else {
+            self.login_ai_controllers().await?;
+        }
         
         info!("[SIMULATOR] Initialization complete");
         Ok(())
@@ -78,65 +196,70 @@ impl Simulator {
         
         // Create and login master controller
         info!("[SIMULATOR] Creating master controller: {} on {}", master_callsign, master_freq);
-        
-        let mut master_controller = AiController::new(
+        let master_controller = Self::login_controller(
             master_callsign.to_string(),
             master_freq.to_string(),
-            51.5,  // Default latitude (central UK)
-            -0.5,  // Default longitude
-            300,   // Range in nautical miles
-        );
-        
-        // Connect and login
-        master_controller.connect(&self.server_addr).await?;
-        master_controller.login().await?;
-        
-        // Wait a bit for the server to process
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        
-        // Send IP query
-        master_controller.send_ip_query().await?;
-        
-        // Start message loop
-        master_controller.start_message_loop().await?;
-        
+            self.server_addr.clone(),
+        ).await?;
         self.ai_controllers.push(master_controller);
         
         info!("[SIMULATOR] Master controller {} logged in", master_callsign);
         
-        // Login other controllers
-        for (callsign, freq) in self.scenario.other_controllers() {
+        // Login other controllers, with at most `controller_login_concurrency` in flight
+        let delay = Duration::from_millis(self.sim_config.controller_login_delay_ms);
+        let permits = Arc::new(tokio::sync::Semaphore::new(self.sim_config.controller_login_concurrency.max(1)));
+        let mut logins = tokio::task::JoinSet::new();
+
+        for (index, (callsign, freq)) in self.scenario.other_controllers().iter().enumerate() {
+            if index > 0 && !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+
             info!("[SIMULATOR] Creating controller: {} on {}", callsign, freq);
-            
-            let mut controller = AiController::new(
-                callsign.clone(),
-                freq.clone(),
-                51.5,
-                -0.5,
-                300,
-            );
-            
-            controller.connect(&self.server_addr).await?;
-            
-            // Wait a bit between logins
-            tokio::time::sleep(Duration::from_millis(200)).await;
-            
-            controller.login().await?;
-            tokio::time::sleep(Duration::from_millis(300)).await;
-            
-            controller.send_ip_query().await?;
-            controller.start_message_loop().await?;
-            
-            self.ai_controllers.push(controller);
-            
-            info!("[SIMULATOR] Controller {} logged in", callsign);
+            let permit = permits.clone().acquire_owned().await?;
+            let (callsign, freq, server_addr) = (callsign.clone(), freq.clone(), self.server_addr.clone());
+
+            logins.spawn(async move {
+                let result = Self::login_controller(callsign, freq, server_addr).await;
+                drop(permit);
+                (index, result)
+            });
+        }
+
+        // Keep the scenario's controller order regardless of which login finished first
+        let mut controllers = Vec::new();
+        while let Some(joined) = logins.join_next().await {
+            let (index, result) = joined?;
+            let controller = result?;
+            info!("[SIMULATOR] Controller {} logged in", controller.callsign());
+            controllers.push((index, controller));
         }
+        controllers.sort_by_key(|(index, _)| *index);
+        self.ai_controllers.extend(controllers.into_iter().map(|(_, c)| c));
         
         info!("[SIMULATOR] {} AI controllers logged in", self.ai_controllers.len());
         
         Ok(())
     }
 
+    /// Connect and log in a single AI controller, failing if the server doesn't accept it
+    async fn login_controller(callsign: String, freq: String, server_addr: String) -> Result<AiController> {
+        let mut controller = AiController::new(
+            callsign,
+            freq,
+            51.5,  // Default latitude (central UK)
+            -0.5,  // Default longitude
+            300,   // Range in nautical miles
+        );
+
+        controller.connect(&server_addr).await?;
+        controller.login().await?;
+        controller.send_ip_query().await?;
+        controller.start_message_loop().await?;
+
+        Ok(controller)
+    }
+
     /// Start the main simulation loop
     pub async fn run(&mut self, shutdown: tokio::sync::broadcast::Receiver<()>) -> Result<()> {
         info!("[SIMULATOR] Starting main simulation loop...");
@@ -145,13 +268,23 @@ impl Simulator {
         // Create timers for different spawn intervals
         let mut departure_timers = self.create_departure_timers();
         let mut transit_timers = self.create_transit_timers();
+        let mut arrival_timers = self.create_arrival_timers();
+        let mut stream_timers = self.create_stream_timers();
+        let mut scripted_spawns = self.create_scripted_queue();
+
+        if self.sim_config.prefill_transit_arrivals {
+            self.prefill_transit_arrivals().await?;
+        }
         
         // Main update loop (runs at radar update rate)
         let radar_update_ms = (1000.0 / self.sim_config.radar_update_rate) as u64;
         let mut update_interval = interval(Duration::from_millis(radar_update_ms));
+        update_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut last_update = Instant::now();
         
         let mut loop_count = 0u64;
         let mut shutdown_rx = shutdown;
+        let mut commands = self.command_input.take();
         
         loop {
             tokio::select! {
@@ -159,27 +292,71 @@ impl Simulator {
                     info!("[SIMULATOR] Shutdown signal received");
                     break;
                 }
+                line = next_command(&mut commands) => match line {
+                    Some(line) => self.handle_command_line(&line),
+                    None => {
+                        info!("[SIMULATOR] Command input closed");
+                        commands = None;
+                    }
+                },
                 _ = update_interval.tick() => {
                     loop_count += 1;
+
+                    // Advance by the wall-clock time actually elapsed, so late ticks don't lose time
+                    let now = Instant::now();
+                    let elapsed = now - last_update;
+                    last_update = now;
                     
-                    let delta_time = (radar_update_ms as f64) / 1000.0;
+                    // Hand aircraft of controllers that went offline to another sector
+                    self.check_controllers();
+
+                    // Descend arrivals the AI master controller is working
+                    self.issue_agreed_descents();
+
+                    // Pass aircraft along the handoff chain, completing handoffs once accepted
+                    let mut track_messages = self.hand_off_at_release_points();
+                    for controller in &mut self.ai_controllers {
+                        track_messages.extend(controller.received_tracks());
+                    }
+                    self.complete_handoffs(&track_messages);
                     
+                    // Spawns run on simulated time, so they speed up with the time multiplier
+                    let spawn_ticks = self.spawn_ticks();
+
                     // Check departure timers
-                    self.check_departure_spawns(&mut departure_timers, loop_count).await?;
+                    self.check_departure_spawns(&mut departure_timers, spawn_ticks).await?;
                     
                     // Check transit timers
-                    self.check_transit_spawns(&mut transit_timers, loop_count).await?;
+                    self.check_transit_spawns(&mut transit_timers, spawn_ticks).await?;
+
+                    // Check local arrival timers
+                    self.check_arrival_spawns(&mut arrival_timers, spawn_ticks).await?;
+
+                    // Check entry stream timers
+                    self.check_stream_spawns(&mut stream_timers, spawn_ticks).await?;
+
+                    // Spawn any scripted aircraft that are due
+                    self.check_scripted_spawns(&mut scripted_spawns, spawn_ticks).await?;
                     
-                    // Update all aircraft
-                    self.update_aircraft(delta_time);
+                    // Update all aircraft, logging out any that have finished
+                    let removed = self.advance(elapsed);
+                    self.disconnect_pilots(&removed).await;
                     
-                    // Send pilot position updates every 5 seconds (25 ticks at 5 Hz)
-                    if loop_count % 25 == 0 {
-                        self.broadcast_pilot_positions().await?;
+                    // Send pilot position updates as the radar reports them, in wall-clock time
+                    let now = loop_count as f64 / self.sim_config.radar_update_rate;
+                    self.broadcast_radar_positions(now).await?;
+                    self.log_snapshots(self.sim_time);
+
+                    // Look for conflicts every 5 seconds (25 ticks at 5 Hz)
+                    if loop_count.is_multiple_of(25) {
+                        self.predict_conflicts();
                     }
+
+                    // Keep pilots visible even if they haven't sent a position recently
+                    self.send_pilot_keepalives().await;
                     
                     // Log status periodically
-                    if loop_count % 50 == 0 {
+                    if loop_count.is_multiple_of(50) {
                         debug!("[SIMULATOR] Loop {}: {} controllers, {} aircraft", 
                                loop_count, self.ai_controllers.len(), self.aircraft.len());
                     }
@@ -192,21 +369,239 @@ impl Simulator {
         Ok(())
     }
     
-    /// Update all aircraft positions and states
-    fn update_aircraft(&mut self, delta_time: f64) {
+    /// Drop controllers that have disconnected, reassigning their aircraft to the
+    /// master controller (or the next controller still online)
+    fn check_controllers(&mut self) {
+        let (lost, online): (Vec<_>, Vec<_>) = std::mem::take(&mut self.ai_controllers)
+            .into_iter()
+            .partition(|c| !c.is_connected());
+        self.ai_controllers = online;
+
+        for controller in lost {
+            let lost_callsign = controller.callsign().to_string();
+            warn!("[SIMULATOR] Controller {} went offline", lost_callsign);
+            let _ = self.events.send(SimulatorEvent::ControllerLost { callsign: lost_callsign.clone() });
+
+            let Some(new_controller) = self.ai_controllers.first().map(|c| c.callsign().to_string()) else {
+                warn!("[SIMULATOR] No controllers online to take {}'s aircraft", lost_callsign);
+                continue;
+            };
+
+            for aircraft in self.aircraft.iter_mut().filter(|a| a.controller.as_ref() == Some(&lost_callsign)) {
+                info!("[SIMULATOR] {} reassigned from {} to {}", aircraft.callsign, lost_callsign, new_controller);
+                aircraft.controller = Some(new_controller.clone());
+                // Any accept of its handoff would have gone to the lost controller
+                aircraft.handoff_to = None;
+                let _ = self.events.send(SimulatorEvent::AircraftReassigned {
+                    callsign: aircraft.callsign.clone(),
+                    from: lost_callsign.clone(),
+                    to: new_controller.clone(),
+                });
+            }
+        }
+    }
+
+    /// Have the AI master controller descend its arrivals so they are at the agreed
+    /// level by the boundary, for sessions where nobody is working the enroute sector
+    fn issue_agreed_descents(&mut self) {
+        let scenario = self.scenario.clone();
+        let master = scenario.master_controller().0;
+
+        for agreement in scenario.descent_agreements() {
+            for aircraft in self.aircraft.iter_mut().filter(|a| {
+                a.controller.as_deref() == Some(master) && a.flight_plan.arrival == agreement.arriving
+            }) {
+                let already_descending = aircraft.cleared_altitude.is_some_and(|alt| alt <= agreement.level);
+                if already_descending || aircraft.altitude <= agreement.level {
+                    continue;
+                }
+                let Some(distance) = aircraft.route_distance_to(&agreement.boundary_fix, &self.nav_db) else {
+                    continue;
+                };
+
+                // 3nm per 1000ft to lose, plus a few miles in hand
+                let needed_nm = (aircraft.altitude - agreement.level) as f64 / 1000.0 * 3.0 + 5.0;
+                if distance > needed_nm {
+                    continue;
+                }
+
+                info!("[SIMULATOR] {} descending {} to {} ft by {}",
+                      master, aircraft.callsign, agreement.level, agreement.boundary_fix);
+                aircraft.set_altitude(agreement.level);
+                let _ = self.events.send(SimulatorEvent::DescentIssued {
+                    callsign: aircraft.callsign.clone(),
+                    controller: master.to_string(),
+                    altitude: agreement.level,
+                });
+            }
+        }
+    }
+
+    /// Hand aircraft that have passed their controller's release fix to the next
+    /// controller in the chain. The releasing controller takes the track if it hasn't
+    /// already and offers it on; an AI controller taking over accepts it straight away.
+    /// The aircraft stays with the releasing controller until `complete_handoffs` sees
+    /// the accept. Returns the track messages, sent through any of the controllers that
+    /// are online.
+    fn hand_off_at_release_points(&mut self) -> Vec<TrackMessage> {
+        let scenario = self.scenario.clone();
+        let mut messages = Vec::new();
+
+        for handoff in scenario.handoffs() {
+            for aircraft in self.aircraft.iter_mut().filter(|a| {
+                a.controller.as_deref() == Some(handoff.from.as_str())
+                    && a.handoff_to.is_none()
+                    && a.route_fixes[..a.current_fix_index].contains(&handoff.release_fix)
+            }) {
+                info!("[SIMULATOR] {} handing {} to {} at {}",
+                      handoff.from, aircraft.callsign, handoff.to, handoff.release_fix);
+                let callsign = aircraft.callsign.clone();
+                let mut handoff_messages = vec![
+                    TrackMessage::Claim { controller: handoff.from.clone(), aircraft: callsign.clone() },
+                    TrackMessage::Handoff { from: handoff.from.clone(), to: handoff.to.clone(), aircraft: callsign.clone() },
+                ];
+                if self.ai_controllers.iter().any(|c| c.callsign() == handoff.to) {
+                    handoff_messages.push(TrackMessage::Accept {
+                        to: handoff.to.clone(),
+                        from: handoff.from.clone(),
+                        aircraft: callsign.clone(),
+                    });
+                }
+
+                for message in &handoff_messages {
+                    let sender = match message {
+                        TrackMessage::Accept { to, .. } => to,
+                        _ => &handoff.from,
+                    };
+                    let Some(controller) = self.ai_controllers.iter().find(|c| c.callsign() == sender) else {
+                        continue;
+                    };
+                    if let Err(e) = controller.send(&message.to_string()) {
+                        warn!("[SIMULATOR] Failed to send {}: {}", message, e);
+                    }
+                }
+                messages.extend(handoff_messages);
+
+                aircraft.handoff_to = Some(handoff.to.clone());
+                let _ = self.events.send(SimulatorEvent::HandoffOffered {
+                    callsign,
+                    from: handoff.from.clone(),
+                    to: handoff.to.clone(),
+                });
+            }
+        }
+        messages
+    }
+
+    /// Move aircraft to the controller they were handed to once that controller accepts
+    fn complete_handoffs(&mut self, messages: &[TrackMessage]) {
+        for message in messages {
+            let TrackMessage::Accept { to, from, aircraft: callsign } = message else {
+                continue;
+            };
+            let Some(aircraft) = self.aircraft.iter_mut().find(|a| {
+                &a.callsign == callsign && a.handoff_to.as_ref() == Some(to)
+            }) else {
+                continue;
+            };
+            info!("[SIMULATOR] {} accepted {} from {}", to, callsign, from);
+            aircraft.controller = aircraft.handoff_to.take();
+        }
+    }
+
+    /// Look for departures and arrivals heading for the same fix at about the same
+    /// time without vertical separation, reporting each conflict once
+    fn predict_conflicts(&mut self) {
+        let window = self.sim_config.conflict_window_secs;
+        let aircraft = &self.aircraft;
+        self.reported_conflicts.retain(|(departure, arrival, _)| {
+            aircraft.iter().any(|a| a.callsign == *departure) && aircraft.iter().any(|a| a.callsign == *arrival)
+        });
+        if window <= 0.0 {
+            return;
+        }
+
+        let active = self.scenario.active_aerodromes();
+        let departures = self.aircraft.iter()
+            .filter(|a| a.phase == FlightPhase::Climbing && active.contains(&a.flight_plan.departure));
+        let arrivals: Vec<&Aircraft> = self.aircraft.iter()
+            .filter(|a| !a.is_on_ground() && active.contains(&a.flight_plan.arrival))
+            .collect();
+
+        let mut conflicts = Vec::new();
+        for departure in departures {
+            for arrival in arrivals.iter().filter(|a| a.callsign != departure.callsign) {
+                let shared = departure.route_fixes[departure.current_fix_index..]
+                    .iter()
+                    .find(|fix| arrival.route_fixes[arrival.current_fix_index..].contains(fix));
+                let Some(fix) = shared else {
+                    continue;
+                };
+
+                let eta = |aircraft: &Aircraft| {
+                    let distance = aircraft.route_distance_to(fix, &self.nav_db)?;
+                    (aircraft.ground_speed > 0).then(|| distance / aircraft.ground_speed as f64 * 3600.0)
+                };
+                let (Some(departure_eta), Some(arrival_eta)) = (eta(departure), eta(arrival)) else {
+                    continue;
+                };
+                if (departure_eta - arrival_eta).abs() > window {
+                    continue;
+                }
+
+                let departure_level = departure.predicted_altitude(departure_eta, &self.sim_config);
+                let arrival_level = arrival.predicted_altitude(arrival_eta, &self.sim_config);
+                if (departure_level - arrival_level).abs() < self.sim_config.conflict_vertical_ft {
+                    conflicts.push((departure.callsign.clone(), arrival.callsign.clone(), fix.clone()));
+                }
+            }
+        }
+
+        for conflict in conflicts {
+            if self.reported_conflicts.insert(conflict.clone()) {
+                let (departure, arrival, fix) = conflict;
+                warn!("[SIMULATOR] Predicted conflict between {} and {} at {}", departure, arrival, fix);
+                let _ = self.events.send(SimulatorEvent::ConflictPredicted { departure, arrival, fix });
+            }
+        }
+    }
+
+    /// Advance the aircraft by `elapsed` wall-clock time scaled by the time multiplier,
+    /// in steps of at most `MAX_UPDATE_STEP_SECS`. Returns the callsigns of those removed.
+    fn advance(&mut self, elapsed: Duration) -> Vec<String> {
+        let mut remaining = elapsed.as_secs_f64() * self.sim_config.time_multiplier;
+        self.sim_time += remaining;
+        let mut removed = Vec::new();
+
+        while remaining > 0.0 {
+            let step = remaining.min(MAX_UPDATE_STEP_SECS);
+            removed.extend(self.update_aircraft(step));
+            remaining -= step;
+        }
+        removed
+    }
+
+    /// Update all aircraft positions and states, returning the callsigns of those removed
+    fn update_aircraft(&mut self, delta_time: f64) -> Vec<String> {
         let sim_config = self.sim_config.clone();
         let nav_db = self.nav_db.clone();
+
+        self.join_final_without_star();
+        if sim_config.approach_spacing {
+            space_final_approach(&mut self.aircraft, &sim_config);
+        }
         
-        // Collect callsigns of aircraft that will be removed
-        let removed_callsigns: Vec<String> = self.aircraft
+        // Collect callsigns and squawks of aircraft that will be removed
+        let removed: Vec<(String, String)> = self.aircraft
             .iter()
             .filter(|a| a.is_route_complete())
-            .map(|a| a.callsign.clone())
+            .map(|a| (a.callsign.clone(), a.squawk.clone()))
             .collect();
         
-        // Remove completed aircraft from used callsigns
-        for callsign in &removed_callsigns {
+        // Free completed aircraft's callsigns and codes
+        for (callsign, squawk) in &removed {
             self.used_callsigns.remove(callsign);
+            self.release_squawk(squawk);
             info!("[SIMULATOR] Aircraft {} completed route and removed", callsign);
         }
         
@@ -217,82 +612,503 @@ impl Simulator {
         for aircraft in &mut self.aircraft {
             aircraft.update(delta_time, &nav_db, &sim_config);
         }
+
+        removed.into_iter().map(|(callsign, _)| callsign).collect()
+    }
+
+    /// Send arrivals that have run out of route at their destination with no STAR
+    /// straight in to the active runway, if configured to, and local flights always
+    fn join_final_without_star(&mut self) {
+        let scenario = self.scenario.clone();
+        let sim_config = self.sim_config.clone();
+        let straight_in = sim_config.starless_arrivals == StarlessArrivals::StraightIn;
+
+        for index in 0..self.aircraft.len() {
+            let aircraft = &self.aircraft[index];
+            if !(straight_in || aircraft.is_local_flight())
+                || !aircraft.is_route_complete()
+                || aircraft.has_star()
+                || aircraft.nav_mode != NavMode::FlightPlan
+                || scenario.active_arrival_runway(&aircraft.flight_plan.arrival).is_none()
+            {
+                continue;
+            }
+
+            let arrival = aircraft.flight_plan.arrival.clone();
+            match self.active_localizer(&arrival) {
+                Ok(localizer) => self.aircraft[index].straight_in(localizer, &sim_config),
+                Err(e) => warn!("[SIMULATOR] {} can't join final at {}: {}", self.aircraft[index].callsign, arrival, e),
+            }
+        }
+    }
+
+    /// Log out the pilots of aircraft that have left the simulation
+    async fn disconnect_pilots(&mut self, callsigns: &[String]) {
+        for callsign in callsigns {
+            self.radar.forget(callsign);
+            if let Some(mut pilot) = self.pilot_clients.remove(callsign) {
+                info!("[SIMULATOR] Disconnecting pilot {}", callsign);
+                if let Err(e) = pilot.disconnect().await {
+                    warn!("[SIMULATOR] Failed to disconnect {}: {}", callsign, e);
+                }
+            }
+        }
     }
 
     /// Create departure spawn timers
     fn create_departure_timers(&self) -> Vec<(String, u64, u64)> {
         self.scenario.departure_configs()
             .iter()
+            .filter(|dep| !dep.routes.is_empty())
             .map(|dep| {
-                let interval_ticks = (dep.interval as f64 / (1.0 / self.sim_config.radar_update_rate)) as u64;
-                (dep.departing.clone(), interval_ticks, 0u64)
+                let interval = self.scenario.departure_interval_at(&dep.departing, 0.0).unwrap_or(dep.interval);
+                (dep.departing.clone(), self.interval_ticks(interval), 0u64)
+            })
+            .collect()
+    }
+
+    /// Simulated time in spawn ticks, at the radar update rate
+    fn spawn_ticks(&self) -> u64 {
+        (self.sim_time * self.sim_config.radar_update_rate).round() as u64
+    }
+
+    /// Spawn ticks in a spawn interval (seconds)
+    fn interval_ticks(&self, interval: u64) -> u64 {
+        ((interval as f64 * self.sim_config.radar_update_rate) as u64).max(1)
+    }
+
+    /// Create local arrival spawn timers
+    fn create_arrival_timers(&self) -> Vec<(String, u64, u64)> {
+        self.scenario.arrival_intervals()
+            .into_iter()
+            .map(|(aerodrome, interval)| {
+                let interval_ticks = (interval as f64 * self.sim_config.radar_update_rate) as u64;
+                (aerodrome.to_string(), interval_ticks.max(1), 0u64)
             })
             .collect()
     }
 
+    /// Check and spawn local arrivals
+    async fn check_arrival_spawns(&mut self, timers: &mut [(String, u64, u64)], spawn_ticks: u64) -> Result<()> {
+        for (aerodrome, interval, last_spawn) in timers.iter_mut() {
+            if spawn_ticks - *last_spawn >= *interval {
+                *last_spawn = spawn_ticks;
+                let aerodrome = aerodrome.clone();
+                // An aerodrome that can't take arrivals shouldn't stop the session
+                if let Err(e) = self.spawn_local_arrival(&aerodrome).await {
+                    warn!("[SIMULATOR] Skipping local arrival for {}: {}", aerodrome, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawn an arrival on final for an aerodrome, flown by one of the airlines based there
+    async fn spawn_local_arrival(&mut self, aerodrome: &str) -> Result<()> {
+        let origin = self.scenario.aerodrome_traffic(aerodrome)
+            .and_then(|traffic| traffic.arrival_origins.choose(&mut rand::thread_rng()))
+            .cloned()
+            .unwrap_or_else(|| "ZZZZ".to_string());
+
+        let airline = self.pick_airline(aerodrome)?;
+        let callsign = self.generate_callsign(&airline)?;
+        let aircraft_type = self.select_aircraft_type(&airline);
+        let aircraft = self.create_ils_arrival_as(&origin, aerodrome, "DCT", callsign, aircraft_type)?;
+
+        info!("[SIMULATOR] Spawned local arrival {} ({}) from {} on final for {}",
+              aircraft.callsign, aircraft.aircraft_type, origin, aerodrome);
+
+        self.add_aircraft(aircraft).await
+    }
+
     /// Create transit spawn timers
     fn create_transit_timers(&self) -> Vec<(usize, u64, u64)> {
         self.scenario.transit_configs()
             .iter()
             .enumerate()
+            .filter(|(_, transit)| !transit.routes.is_empty())
             .map(|(idx, transit)| {
                 let interval_ticks = (transit.interval as f64 / (1.0 / self.sim_config.radar_update_rate)) as u64;
-                (idx, interval_ticks, 0u64)
+                (idx, interval_ticks.max(1), 0u64)
+            })
+            .collect()
+    }
+
+    /// Spawn the transit arrivals that would already be inbound had the session been
+    /// running: one per spawn interval of flying, shared between a transit's routes by
+    /// weight, at levels on a 3nm per 1000ft descent towards the arrival descent altitude.
+    /// Routes to aerodromes that aren't active are left empty
+    async fn prefill_transit_arrivals(&mut self) -> Result<()> {
+        // Arrivals skip straight to final instead
+        if self.sim_config.ils_arrivals {
+            return Ok(());
+        }
+
+        let speed = self.sim_config.descent_speed;
+        for transit in self.scenario.transit_configs().to_vec() {
+            let spacing_nm = speed as f64 * transit.interval as f64 / 3600.0;
+            if spacing_nm <= 0.0 {
+                continue;
+            }
+            let total_weight: f64 = transit.routes.iter().map(|r| r.weight).sum();
+
+            for route in &transit.routes {
+                if route.weight <= 0.0 || self.scenario.active_arrival_runway(&route.arriving).is_none() {
+                    continue;
+                }
+
+                // Each route gets its share of the transit's aircraft
+                let route_spacing_nm = spacing_nm * total_weight / route.weight;
+                let expanded = Route::new(route.route.clone(), route.departing.clone(), Some(route.arriving.clone()));
+                let points = self.route_points(&expanded.fixes);
+                let length: f64 = points
+                    .windows(2)
+                    .map(|leg| {
+                        let (from, to) = (leg[0].1, leg[1].1);
+                        haversine_nm(from.0, from.1, to.0, to.1)
+                    })
+                    .sum();
+
+                for n in 1.. {
+                    let flown = n as f64 * route_spacing_nm;
+                    if flown >= length {
+                        break;
+                    }
+
+                    // Find the leg we're on and how far along it
+                    let mut leg_start = 0.0;
+                    let mut placed = None;
+                    for leg in points.windows(2) {
+                        let (from, (next_index, to)) = (leg[0].1, leg[1]);
+                        let leg = haversine_nm(from.0, from.1, to.0, to.1);
+                        if leg_start + leg > flown {
+                            let heading = bearing_from_to(from.0, from.1, to.0, to.1);
+                            let position = position_bearing_distance(from.0, from.1, heading, flown - leg_start);
+                            placed = Some((position, heading, next_index));
+                            break;
+                        }
+                        leg_start += leg;
+                    }
+                    let Some((position, heading, next_index)) = placed else {
+                        break;
+                    };
+
+                    let descent_altitude = self.sim_config.arrival_descent_altitude;
+                    let profile_altitude = descent_altitude + ((length - flown) / 3.0 * 1000.0) as i32;
+                    let altitude = profile_altitude.min(route.current_level as i32) / 100 * 100;
+
+                    let airline = self.pick_airline(&route.departing)?;
+                    let callsign = self.generate_callsign(&airline)?;
+                    let aircraft_type = self.select_aircraft_type(&airline);
+                    let squawk = self.assign_squawk();
+                    let mut aircraft = Aircraft::new_enroute(
+                        callsign,
+                        aircraft_type,
+                        squawk,
+                        route.departing.clone(),
+                        route.arriving.clone(),
+                        route.route.clone(),
+                        position,
+                        heading,
+                        altitude,
+                        speed,
+                    );
+                    aircraft.current_fix_index = next_index;
+                    if altitude < route.current_level as i32 {
+                        aircraft.phase = FlightPhase::Descending;
+                        aircraft.target_altitude = descent_altitude;
+                    }
+
+                    info!("[SIMULATOR] Prefilled {} {:.0}nm along {} at {}ft",
+                          aircraft.callsign, flown, route.route, altitude);
+                    self.add_aircraft(aircraft).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Positions of the route fixes found in the nav database, with each one's index
+    /// in the route, picking each fix nearest the one before
+    fn route_points(&self, fixes: &[String]) -> Vec<(usize, (f64, f64))> {
+        let mut points: Vec<(usize, (f64, f64))> = Vec::new();
+        for (index, fix) in fixes.iter().enumerate() {
+            let found = match points.last() {
+                Some((_, last)) => self.nav_db.position(fix, *last),
+                None => self.nav_db.get(fix).copied().or_else(|| parse_coordinate_fix(fix)),
+            };
+            if let Some(coords) = found {
+                points.push((index, coords));
+            }
+        }
+        points
+    }
+
+    /// Turn an aircraft just placed at a fix towards the next fix on its route, where
+    /// that can be found
+    fn head_for(&self, aircraft: &mut Aircraft, next_fix: Option<&str>) {
+        let position = (aircraft.latitude, aircraft.longitude);
+        if let Some(next) = next_fix.and_then(|fix| self.nav_db.position(fix, position)) {
+            aircraft.heading = bearing_from_to(position.0, position.1, next.0, next.1);
+            aircraft.target_heading = aircraft.heading;
+        }
+    }
+
+    /// Create entry stream timers: (stream index, interval ticks, last spawn, aircraft spawned)
+    fn create_stream_timers(&self) -> Vec<(usize, u64, u64, usize)> {
+        self.scenario.entry_streams()
+            .iter()
+            .enumerate()
+            .map(|(idx, stream)| {
+                let interval_ticks = (stream.interval_seconds() * self.sim_config.radar_update_rate).round() as u64;
+                (idx, interval_ticks.max(1), 0u64, 0usize)
             })
             .collect()
     }
 
+    /// Queue the profile's scripted aircraft in the order they are due
+    fn create_scripted_queue(&self) -> std::collections::VecDeque<ScriptedSpawn> {
+        let mut spawns = self.scenario.scripted_spawns().to_vec();
+        spawns.sort_by_key(|spawn| spawn.at);
+        spawns.into()
+    }
+
+    /// Spawn the scripted aircraft that are due
+    async fn check_scripted_spawns(&mut self, queue: &mut std::collections::VecDeque<ScriptedSpawn>, spawn_ticks: u64) -> Result<()> {
+        let now = spawn_ticks as f64 / self.sim_config.radar_update_rate;
+        while let Some(spawn) = queue.front() {
+            if spawn.at as f64 > now {
+                break;
+            }
+            let spawn = queue.pop_front().expect("front exists");
+            // One bad entry in the script shouldn't stop the session
+            if let Err(e) = self.spawn_scripted(&spawn).await {
+                warn!("[SIMULATOR] Dropping scripted {}: {}", spawn.callsign, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawn a scripted aircraft, as a departure or at the first fix of its route
+    async fn spawn_scripted(&mut self, spawn: &ScriptedSpawn) -> Result<()> {
+        if self.used_callsigns.contains(&spawn.callsign) {
+            warn!("[SIMULATOR] Scripted {} is already in the session, not spawning it again", spawn.callsign);
+            return Ok(());
+        }
+
+        let Some(level) = spawn.level else {
+            return self.spawn_departure_as(
+                &spawn.departing,
+                &spawn.arriving,
+                &spawn.route,
+                spawn.callsign.clone(),
+                spawn.aircraft_type.clone(),
+            ).await;
+        };
+
+        let route = Route::new(spawn.route.clone(), spawn.departing.clone(), Some(spawn.arriving.clone()));
+        let entry_fix = route.fixes.first()
+            .ok_or_else(|| anyhow::anyhow!("Scripted {} has no route to enter on", spawn.callsign))?;
+
+        let squawk = self.assign_squawk();
+        let mut aircraft = Aircraft::from_fix(
+            spawn.callsign.clone(),
+            spawn.aircraft_type.clone(),
+            squawk.clone(),
+            spawn.departing.clone(),
+            spawn.arriving.clone(),
+            spawn.route.clone(),
+            entry_fix,
+            0.0,
+            level as i32,
+            spawn.speed,
+            Some(&self.nav_db),
+        ).inspect_err(|_| self.release_squawk(&squawk))?;
+        self.head_for(&mut aircraft, route.fixes.get(1).map(String::as_str));
+
+        info!("[SIMULATOR] Spawned scripted {} ({}) at {} at {}ft",
+              aircraft.callsign, aircraft.aircraft_type, entry_fix, level);
+
+        self.add_aircraft(aircraft).await
+    }
+
+    /// Check and spawn aircraft in entry streams
+    async fn check_stream_spawns(&mut self, timers: &mut [(usize, u64, u64, usize)], spawn_ticks: u64) -> Result<()> {
+        for (idx, interval, last_spawn, spawned) in timers.iter_mut() {
+            if spawn_ticks - *last_spawn >= *interval {
+                *last_spawn = spawn_ticks;
+                let stream = self.scenario.entry_streams()[*idx].clone();
+                // A stream that can't be flown shouldn't stop the session
+                match self.spawn_stream_aircraft(&stream, *spawned).await {
+                    Ok(()) => *spawned += 1,
+                    Err(e) => warn!("[SIMULATOR] Skipping entry stream at {}: {}", stream.entry_fix, e),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawn the `n`th aircraft of an entry stream at its entry fix
+    async fn spawn_stream_aircraft(&mut self, stream: &EntryStream, n: usize) -> Result<()> {
+        let airline = self.pick_airline(&stream.departing)?;
+        let callsign = self.generate_callsign(&airline)?;
+        let aircraft_type = self.select_aircraft_type(&airline);
+        let squawk = self.assign_squawk();
+        let level = stream.level_for(n);
+
+        let mut aircraft = Aircraft::from_fix(
+            callsign,
+            aircraft_type,
+            squawk.clone(),
+            stream.departing.clone(),
+            stream.arriving.clone(),
+            stream.route.clone(),
+            &stream.entry_fix,
+            0.0,
+            level as i32,
+            stream.speed,
+            Some(&self.nav_db),
+        ).inspect_err(|_| self.release_squawk(&squawk))?;
+
+        // Head for the first fix after the entry fix
+        let route = Route::new(stream.route.clone(), stream.departing.clone(), Some(stream.arriving.clone()));
+        let next_fix = route.fixes.iter().find(|fix| **fix != stream.entry_fix);
+        self.head_for(&mut aircraft, next_fix.map(String::as_str));
+
+        info!("[SIMULATOR] Spawned {} at {} at {}ft in the {} stream",
+              aircraft.callsign, stream.entry_fix, level, stream.arriving);
+
+        self.add_aircraft(aircraft).await
+    }
+
     /// Check and spawn departures
-    async fn check_departure_spawns(&mut self, timers: &mut [(String, u64, u64)], loop_count: u64) -> Result<()> {
+    async fn check_departure_spawns(&mut self, timers: &mut [(String, u64, u64)], spawn_ticks: u64) -> Result<()> {
+        let now = spawn_ticks as f64 / self.sim_config.radar_update_rate;
         for (aerodrome, interval, last_spawn) in timers.iter_mut() {
-            if loop_count - *last_spawn >= *interval {
-                *last_spawn = loop_count;
+            // Follow the aerodrome's interval schedule as the simulated session goes on
+            if let Some(scheduled) = self.scenario.departure_interval_at(aerodrome, now) {
+                *interval = self.interval_ticks(scheduled);
+            }
+
+            if spawn_ticks - *last_spawn >= *interval {
+                *last_spawn = spawn_ticks;
                 
-                if let Some(route) = self.scenario.random_departure_route(aerodrome) {
+                if let Some(route) = self.select_departure_route(aerodrome) {
                     let departure = aerodrome.clone();
-                    let arrival = route.arriving.clone();
-                    let route_str = route.route.clone();
-                    self.spawn_departure(&departure, &arrival, &route_str).await?;
+                    self.spawn_departure(&departure, &route.arriving, &route.route).await?;
                 }
             }
         }
         Ok(())
     }
     
+    /// Pick a departure route, favouring SIDs that head towards the destination
+    /// if `sid_direction_bias` is set
+    fn select_departure_route(&self, aerodrome: &str) -> Option<DepartureRoute> {
+        let bias = self.sim_config.sid_direction_bias;
+        if bias <= 0.0 {
+            return self.scenario.random_departure_route(aerodrome).cloned();
+        }
+
+        let Ok(origin) = self.get_airport_coords(aerodrome) else {
+            return self.scenario.random_departure_route(aerodrome).cloned();
+        };
+        self.scenario
+            .random_departure_route_by(aerodrome, |r| r.weight * self.sid_direction_factor(aerodrome, origin, r, bias))
+            .cloned()
+    }
+
+    /// How well a route's SID points towards its destination: 1 straight at it,
+    /// falling towards 0 as it turns away (1 if either direction is unknown)
+    fn sid_direction_factor(&self, aerodrome: &str, origin: (f64, f64), route: &DepartureRoute, bias: f64) -> f64 {
+        let Some(destination) = self.nav_db.get_nearest(&route.arriving, origin.0, origin.1) else {
+            return 1.0;
+        };
+
+        // The SID's last fix, or the first fix of the route without a known SID
+        let expanded = Route::with_runways(
+            route.route.clone(),
+            aerodrome.to_string(),
+            None,
+            self.scenario.active_departure_runway(aerodrome),
+            None,
+        );
+        let explanation = expanded.explain();
+        let sid_exit = explanation.fixes
+            .iter()
+            .rev()
+            .find(|(_, source)| matches!(source, FixSource::Sid(_)))
+            .or(explanation.fixes.first())
+            .and_then(|(fix, _)| self.nav_db.get_nearest(fix, origin.0, origin.1));
+        let Some(sid_exit) = sid_exit else {
+            return 1.0;
+        };
+
+        let to_destination = bearing_from_to(origin.0, origin.1, destination.0, destination.1);
+        let along_sid = bearing_from_to(origin.0, origin.1, sid_exit.0, sid_exit.1);
+        let alignment = (1.0 + (to_destination - along_sid).to_radians().cos()) / 2.0;
+
+        alignment.powf(bias).max(0.01)
+    }
+
     /// Spawn a departure aircraft
     async fn spawn_departure(&mut self, departure: &str, arrival: &str, route: &str) -> Result<()> {
-        // Get airport coordinates
-        let airport_coords = self.get_airport_coords(departure)?;
+        // Pick the operator and generate a callsign for it
+        let airline = self.pick_airline(departure)?;
+        let callsign = self.generate_callsign(&airline)?;
         
+        // Select aircraft type
+        let aircraft_type = self.select_aircraft_type(&airline);
+
+        self.spawn_departure_as(departure, arrival, route, callsign, aircraft_type).await
+    }
+
+    /// Spawn a departure with a given callsign and aircraft type
+    async fn spawn_departure_as(
+        &mut self,
+        departure: &str,
+        arrival: &str,
+        route: &str,
+        callsign: String,
+        aircraft_type: String,
+    ) -> Result<()> {
         // Get runway information
-        let runway = match self.scenario.active_runway(departure) {
+        let runway = match self.scenario.active_departure_runway(departure) {
             Some(r) => r.to_string(),
             None => return Err(anyhow::anyhow!("No active runway for {}", departure)),
         };
         
-        // Parse runway heading (e.g., "27R" -> 270 degrees)
-        let runway_heading = self.parse_runway_heading(&runway);
-        
-        // Generate callsign
-        let callsign = self.generate_callsign(departure)?;
-        
-        // Select aircraft type
-        let aircraft_type = self.select_aircraft_type(departure)?;
+        // Line up at the threshold, or at the airport on the designator's heading
+        // (e.g., "27R" -> 270 degrees magnetic) when there is no runway data
+        let (position, runway_heading) = match self.runway_threshold(departure, &runway) {
+            Some(runway_end) => (runway_end.threshold, runway_end.course),
+            None => {
+                let airport_coords = self.get_airport_coords(departure)?;
+                (airport_coords, self.parse_runway_heading(&runway, airport_coords))
+            }
+        };
         
         // Assign squawk
         let squawk = self.assign_squawk();
+
+        // Local flights stay low, with no enroute cruise
+        let cruise_altitude = if departure == arrival {
+            (self.sim_config.local_flight_altitude / 100) as u32
+        } else {
+            Self::get_cruise_altitude(route)
+        };
         
         // Create aircraft
         let aircraft = Aircraft::new_departure(
             callsign.clone(),
-            aircraft_type.clone(),
-            squawk.clone(),
+            aircraft_type,
+            squawk,
             departure.to_string(),
             arrival.to_string(),
             route.to_string(),
-            self.get_cruise_altitude(route),
+            cruise_altitude,
             runway,
-            airport_coords,
+            position,
             runway_heading,
         );
         
@@ -300,22 +1116,159 @@ impl Simulator {
               callsign, aircraft.aircraft_type, departure, arrival, 
               aircraft.current_fix().unwrap_or("route"));
         
-        // Get flight plan before moving aircraft
-        let flight_plan_str = aircraft.flight_plan.to_fsd_string();
-        
-        // Login pilot to FSD server and send flight plan
-        self.login_pilot(&callsign, &aircraft_type, &squawk, &flight_plan_str).await?;
-        
-        // Send initial position immediately after login
-        if let Some(pilot) = self.pilot_clients.get_mut(&callsign) {
-            pilot.send_position(
-                aircraft.latitude,
-                aircraft.longitude,
-                aircraft.altitude,
-                aircraft.ground_speed,
-                aircraft.heading,
-                &aircraft.squawk
-            ).await?;
+        self.add_aircraft(aircraft).await
+    }
+
+    /// Spawn a transit at the first fix of its route, flying towards the next at its current level
+    async fn spawn_transit(&mut self, route: &TransitRoute) -> Result<()> {
+        let expanded = Route::new(route.route.clone(), route.departing.clone(), Some(route.arriving.clone()));
+        let points = self.route_points(&expanded.fixes);
+        let (entry_index, _) = *points.first()
+            .ok_or_else(|| anyhow::anyhow!("Unknown entry fix in transit route {}", route.route))?;
+        let next_index = points.get(1).map(|(index, _)| *index);
+
+        let airline = self.pick_airline(&route.departing)?;
+        let callsign = self.generate_callsign(&airline)?;
+        let aircraft_type = self.select_aircraft_type(&airline);
+        let squawk = self.assign_squawk();
+        let mut aircraft = Aircraft::from_fix(
+            callsign,
+            aircraft_type,
+            squawk.clone(),
+            route.departing.clone(),
+            route.arriving.clone(),
+            route.route.clone(),
+            &expanded.fixes[entry_index],
+            0.0,
+            route.current_level as i32,
+            self.sim_config.descent_speed,
+            Some(&self.nav_db),
+        ).inspect_err(|_| self.release_squawk(&squawk))?;
+        self.head_for(&mut aircraft, next_index.map(|index| expanded.fixes[index].as_str()));
+        aircraft.current_fix_index = next_index.unwrap_or(0);
+        aircraft.flight_plan.cruise_altitude = route.cruise_level / 100;
+
+        // Fly the STAR for the runway in use
+        if let Some(runway) = self.scenario.active_arrival_runway(&route.arriving) {
+            aircraft.request_approach(Approach::new(ApproachType::Ils, runway));
+        }
+
+        info!("[SIMULATOR] Spawned transit {} ({}) {} -> {} at FL{:03} via {}",
+              aircraft.callsign, aircraft.aircraft_type, route.departing, route.arriving,
+              route.current_level / 100, route.route);
+
+        self.add_aircraft(aircraft).await
+    }
+
+    /// Build an arrival established on the ILS for the active runway at `arrival`
+    fn create_ils_arrival(&mut self, departure: &str, arrival: &str, route: &str) -> Result<Aircraft> {
+        let airline = self.pick_airline(departure)?;
+        let callsign = self.generate_callsign(&airline)?;
+        let aircraft_type = self.select_aircraft_type(&airline);
+
+        self.create_ils_arrival_as(departure, arrival, route, callsign, aircraft_type)
+    }
+
+    /// Build an arrival on the ILS with a given callsign and aircraft type
+    fn create_ils_arrival_as(
+        &mut self,
+        departure: &str,
+        arrival: &str,
+        route: &str,
+        callsign: String,
+        aircraft_type: String,
+    ) -> Result<Aircraft> {
+        let localizer = self.active_localizer(arrival)?;
+        let squawk = self.assign_squawk();
+
+        Ok(Aircraft::new_ils_arrival(
+            callsign,
+            aircraft_type,
+            squawk,
+            departure.to_string(),
+            arrival.to_string(),
+            route.to_string(),
+            localizer,
+            self.sim_config.ils_spawn_range_nm,
+            self.sim_config.ils_spawn_altitude,
+        ))
+    }
+
+    /// Localizer for the arrival runway at an airport, from its threshold data
+    fn active_localizer(&self, arrival: &str) -> Result<Localizer> {
+        let runway = self.scenario.active_arrival_runway(arrival)
+            .ok_or_else(|| anyhow::anyhow!("No active runway for {}", arrival))?;
+        self.localizer(arrival, runway)
+    }
+
+    /// Threshold of a runway at an aerodrome, from its runway data
+    fn runway_threshold(&self, aerodrome: &str, runway: &str) -> Option<RunwayEnd> {
+        match load_runways(format!("data/Airports/{}", aerodrome)) {
+            Ok(mut runways) => runways.remove(runway),
+            Err(e) => {
+                warn!("[SIMULATOR] Failed to load runways for {}: {}", aerodrome, e);
+                None
+            }
+        }
+    }
+
+    /// Localizer for a runway at an aerodrome
+    fn localizer(&self, arrival: &str, runway: &str) -> Result<Localizer> {
+        let runways = load_runways(format!("data/Airports/{}", arrival))?;
+        let runway_end = runways.get(runway)
+            .ok_or_else(|| anyhow::anyhow!("No threshold data for {} runway {}", arrival, runway))?;
+        let elevation = self.sim_config.airport_elevations.get(arrival).copied().unwrap_or(0) as i32;
+
+        Ok(Localizer::new(runway_end, elevation))
+    }
+
+    /// Spawn an arrival established on the ILS
+    async fn spawn_ils_arrival(&mut self, departure: &str, arrival: &str, route: &str) -> Result<()> {
+        let aircraft = self.create_ils_arrival(departure, arrival, route)?;
+
+        info!("[SIMULATOR] Spawned ILS arrival {} ({}) from {} on final for {} {}",
+              aircraft.callsign, aircraft.aircraft_type, departure, arrival,
+              aircraft.current_fix().unwrap_or("runway"));
+
+        self.add_aircraft(aircraft).await
+    }
+
+    /// Log a new aircraft's pilot in, file its flight plan and start simulating it
+    async fn add_aircraft(&mut self, mut aircraft: Aircraft) -> Result<()> {
+        let callsign = aircraft.callsign.clone();
+
+        if rand::thread_rng().gen_bool(self.sim_config.primary_only_chance.clamp(0.0, 1.0)) {
+            info!("[SIMULATOR] {} has a failed transponder", callsign);
+            aircraft.primary_only = true;
+        }
+
+        if aircraft.performance.is_none() {
+            aircraft.performance = self.perf_db.get(&aircraft.aircraft_type).cloned();
+        }
+        aircraft.continuous_descent = self.scenario.continuous_descent();
+        if aircraft.destination.is_none() {
+            aircraft.destination = self.get_airport_coords(&aircraft.flight_plan.arrival).ok();
+        }
+        if self.sim_config.squawk_on_instruction {
+            aircraft.squawking_assigned = false;
+        }
+
+        // New aircraft start with the master controller
+        if aircraft.controller.is_none() {
+            aircraft.controller = self.ai_controllers.first().map(|c| c.callsign().to_string());
+        }
+
+        if !self.sim_config.ghost_mode {
+            // Get flight plan before moving aircraft
+            let flight_plan_str = aircraft.flight_plan.to_fsd_string();
+
+            // Login pilot to FSD server and send flight plan
+            self.login_pilot(&callsign, &aircraft.aircraft_type, aircraft.transponder_code(), &flight_plan_str).await?;
+
+            // Send initial position immediately after login
+            if let Some(pilot) = self.pilot_clients.get_mut(&callsign) {
+                Self::send_aircraft_position(pilot, &aircraft).await?;
+            }
         }
         
         // Mark callsign as used
@@ -328,7 +1281,9 @@ impl Simulator {
     
     /// Login a pilot client to the FSD server
     async fn login_pilot(&mut self, callsign: &str, aircraft_type: &str, squawk: &str, flight_plan: &str) -> Result<()> {
-        let mut pilot = AiPilot::new(callsign.to_string());
+        let mut pilot = AiPilot::new(callsign.to_string())
+            .with_name(&self.sim_config.pilot_name)
+            .with_qnh(self.sim_config.qnh);
         pilot.connect(&self.server_addr).await?;
         pilot.login(aircraft_type, squawk).await?;
         
@@ -339,20 +1294,46 @@ impl Simulator {
         Ok(())
     }
     
-    /// Broadcast all pilot positions to FSD server
-    async fn broadcast_pilot_positions(&mut self) -> Result<()> {
+    /// Send the positions the simulated radar reports at `now` (seconds): each aircraft
+    /// every report interval, with the configured jitter and latency
+    async fn broadcast_radar_positions(&mut self, now: f64) -> Result<()> {
+        for aircraft in &self.aircraft {
+            let scale = if self.sim_config.adaptive_radar_updates {
+                Self::report_interval_scale(aircraft)
+            } else {
+                1.0
+            };
+            self.radar.scan_scaled(&aircraft.callsign, now, scale, || aircraft.clone());
+        }
+
+        let reports = self.radar.ready(now);
+        Self::send_positions(&mut self.pilot_clients, reports.iter().map(|(_, aircraft)| aircraft)).await;
+        Ok(())
+    }
+
+    /// How much longer or shorter than usual to wait before an aircraft's next report:
+    /// sooner when it is fast or turning, so clients dead-reckon less, and later when
+    /// it is parked
+    fn report_interval_scale(aircraft: &Aircraft) -> f64 {
+        let turning = heading_difference(aircraft.heading, aircraft.target_heading).abs() > 5.0;
+        if aircraft.is_on_ground() && aircraft.ground_speed == 0 {
+            3.0
+        } else if turning || aircraft.ground_speed >= 350 {
+            0.5
+        } else {
+            1.0
+        }
+    }
+
+    /// Send each aircraft's position through its pilot, dropping pilots that fail
+    async fn send_positions<'a>(pilot_clients: &mut HashMap<String, AiPilot>, aircraft: impl Iterator<Item = &'a Aircraft>) {
         let mut disconnected = Vec::new();
         
-        for aircraft in &self.aircraft {
-            if let Some(pilot) = self.pilot_clients.get_mut(&aircraft.callsign) {
-                if let Err(e) = pilot.send_position(
-                    aircraft.latitude,
-                    aircraft.longitude,
-                    aircraft.altitude,
-                    aircraft.ground_speed,
-                    aircraft.heading,
-                    &aircraft.squawk
-                ).await {
+        for aircraft in aircraft {
+            debug!("[SIMULATOR] {}", aircraft);
+            if let Some(pilot) = pilot_clients.get_mut(&aircraft.callsign) {
+                if let Err(e) = Self::send_aircraft_position(pilot, aircraft).await {
+                    warn!("[SIMULATOR] Failed to send position for {}: {}", aircraft.callsign, e);
                     disconnected.push(aircraft.callsign.clone());
                 }
             }
@@ -360,11 +1341,123 @@ impl Simulator {
         
         // Remove disconnected pilots
         for callsign in disconnected {
-            self.pilot_clients.remove(&callsign);
+            pilot_clients.remove(&callsign);
         }
-        
+    }
+    
+    /// Send an aircraft's position: mode C normally, or a primary-only return
+    /// if its transponder has failed
+    async fn send_aircraft_position(pilot: &mut AiPilot, aircraft: &Aircraft) -> Result<()> {
+        if aircraft.primary_only {
+            pilot.send_primary_position(aircraft.latitude, aircraft.longitude, aircraft.ground_speed, aircraft.heading).await
+        } else {
+            pilot.send_position(
+                aircraft.latitude,
+                aircraft.longitude,
+                aircraft.altitude,
+                aircraft.ground_speed,
+                aircraft.heading,
+                aircraft.transponder_code()
+            ).await
+        }
+    }
+
+    /// Carry out a typed ATC command addressed to an aircraft, e.g. "BAW123 FH 270"
+    pub fn command(&mut self, text: &str) -> Result<()> {
+        let command = parse_command(text)
+            .ok_or_else(|| anyhow::anyhow!("Can't understand \"{}\"", text))?;
+        let callsign = command.callsign
+            .ok_or_else(|| anyhow::anyhow!("No callsign in \"{}\"", text))?;
+        let index = self.aircraft
+            .iter()
+            .position(|a| a.callsign == callsign)
+            .ok_or_else(|| anyhow::anyhow!("Unknown callsign {}", callsign))?;
+
+        info!("[SIMULATOR] {} {}", callsign, command.instruction);
+        match command.instruction {
+            Instruction::Heading(heading) => self.aircraft[index].fly_heading(heading as f64),
+            Instruction::Altitude(altitude) => self.aircraft[index].set_altitude(altitude),
+            Instruction::Speed(speed) => self.aircraft[index].assign_speed(speed),
+            Instruction::Ils(runway) => {
+                let localizer = self.localizer(&self.aircraft[index].flight_plan.arrival, &runway)?;
+                self.aircraft[index].clear_ils(localizer);
+            }
+            Instruction::Resume => self.aircraft[index].resume_navigation(),
+            Instruction::Mach(mach) => self.aircraft[index].assign_mach(mach, &self.sim_config)?,
+            Instruction::Hold(fix) => {
+                let aircraft = &self.aircraft[index];
+                if self.nav_db.position(&fix, (aircraft.latitude, aircraft.longitude)).is_none() {
+                    anyhow::bail!("Unknown fix {}", fix);
+                }
+                self.aircraft[index].hold_at(&fix, None, &self.holds);
+            }
+            Instruction::GoAround => self.force_go_around(&callsign)?,
+            Instruction::Squawk => self.instruct_squawk(&callsign)?,
+        }
+        Ok(())
+    }
+
+    /// Instruct an aircraft on the ILS to go around and fly the missed approach
+    pub fn force_go_around(&mut self, callsign: &str) -> Result<()> {
+        let aircraft = self.aircraft
+            .iter_mut()
+            .find(|a| a.callsign == callsign)
+            .ok_or_else(|| anyhow::anyhow!("No aircraft {}", callsign))?;
+
+        if !aircraft.go_around(&self.sim_config) {
+            anyhow::bail!("{} is not on an approach", callsign);
+        }
+        Ok(())
+    }
+
+    /// Apply a line of operator input, logging whether it was accepted
+    fn handle_command_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        match self.command(line) {
+            Ok(()) => info!("[SIMULATOR] Accepted \"{}\"", line),
+            Err(e) => warn!("[SIMULATOR] Rejected \"{}\": {}", line, e),
+        }
+    }
+
+    /// Fail or restore an aircraft's transponder mid-flight
+    pub fn set_primary_only(&mut self, callsign: &str, primary_only: bool) -> Result<()> {
+        let aircraft = self.aircraft
+            .iter_mut()
+            .find(|a| a.callsign == callsign)
+            .ok_or_else(|| anyhow::anyhow!("No aircraft {}", callsign))?;
+
+        info!("[SIMULATOR] {} transponder {}", callsign, if primary_only { "failed" } else { "restored" });
+        aircraft.primary_only = primary_only;
+        Ok(())
+    }
+    
+    /// Tell an aircraft to squawk its assigned code
+    pub fn instruct_squawk(&mut self, callsign: &str) -> Result<()> {
+        let aircraft = self.aircraft
+            .iter_mut()
+            .find(|a| a.callsign == callsign)
+            .ok_or_else(|| anyhow::anyhow!("No aircraft {}", callsign))?;
+
+        info!("[SIMULATOR] {} squawking {}", callsign, aircraft.squawk);
+        aircraft.squawking_assigned = true;
         Ok(())
     }
+
+    /// Re-send positions for pilots that have been quiet for the keep-alive interval,
+    /// which happens when the radar reports an aircraft less often than that (parked
+    /// aircraft under adaptive radar updates, or a long report interval)
+    async fn send_pilot_keepalives(&mut self) {
+        let min_interval = Duration::from_secs_f64(self.sim_config.pilot_keepalive_interval);
+
+        for (callsign, pilot) in self.pilot_clients.iter_mut() {
+            if let Err(e) = pilot.send_keepalive(min_interval).await {
+                warn!("[SIMULATOR] Failed to send keep-alive for {}: {}", callsign, e);
+            }
+        }
+    }
     
     /// Get airport coordinates from navigation database
     fn get_airport_coords(&self, icao: &str) -> Result<(f64, f64)> {
@@ -386,30 +1479,30 @@ impl Simulator {
         Ok(coords)
     }
     
-    /// Parse runway heading from runway identifier
-    fn parse_runway_heading(&self, runway: &str) -> i32 {
+    /// True runway heading from its identifier, which gives the magnetic heading
+    fn parse_runway_heading(&self, runway: &str, position: (f64, f64)) -> f64 {
         // Extract numeric part (e.g., "27R" -> 27)
         let numeric: String = runway.chars().filter(|c| c.is_numeric()).collect();
-        if let Ok(rwy_num) = numeric.parse::<i32>() {
-            rwy_num * 10
+        if let Ok(rwy_num) = numeric.parse::<u32>() {
+            magnetic_to_true((rwy_num * 10) as f64, position.0, position.1)
         } else {
-            0
+            0.0
         }
     }
     
-    /// Generate a unique callsign for an aircraft
-    fn generate_callsign(&mut self, departure: &str) -> Result<String> {
+    /// Pick the airline operating a flight from `departure`
+    fn pick_airline(&self, departure: &str) -> Result<String> {
+        self.fleet_config.pick_airline(departure, &mut rand::thread_rng())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("No airlines configured for {}", departure))
+    }
+
+    /// Generate a unique callsign for one of an airline's flights
+    fn generate_callsign(&mut self, airline: &str) -> Result<String> {
         let mut rng = rand::thread_rng();
         
-        // Get airline for this airport
-        let airlines = self.fleet_config.airports.get(departure)
-            .ok_or_else(|| anyhow::anyhow!("No airlines configured for {}", departure))?;
-        
         // Try up to 100 times to generate a unique callsign
         for _ in 0..100 {
-            let airline = airlines.get(rng.gen_range(0..airlines.len()))
-                .ok_or_else(|| anyhow::anyhow!("No airline selected"))?;
-            
             // Generate flight number
             let flight_num = rng.gen_range(1..9999);
             let callsign = format!("{}{:04}", airline, flight_num);
@@ -423,72 +1516,95 @@ impl Simulator {
         Err(anyhow::anyhow!("Failed to generate unique callsign after 100 attempts"))
     }
     
-    /// Select an aircraft type for departure
-    fn select_aircraft_type(&self, departure: &str) -> Result<String> {
-        let mut rng = rand::thread_rng();
-        
-        // Get airlines for this airport
-        let airlines = self.fleet_config.airports.get(departure)
-            .ok_or_else(|| anyhow::anyhow!("No airlines for {}", departure))?;
-        
-        let airline = airlines.get(rng.gen_range(0..airlines.len()))
-            .ok_or_else(|| anyhow::anyhow!("No airline selected"))?;
-        
-        // Get aircraft types for this airline
-        let aircraft_types = self.fleet_config.airlines.get(airline);
-        
-        if aircraft_types.is_none() || aircraft_types.unwrap().is_empty() {
-            warn!("[SIMULATOR] No aircraft types configured for airline {}, using default A320", airline);
-            return Ok("A320".to_string());
+    /// Select one of an airline's aircraft types
+    fn select_aircraft_type(&self, airline: &str) -> String {
+        match self.fleet_config.pick_type(airline, &mut rand::thread_rng()) {
+            Some(aircraft_type) => aircraft_type.to_string(),
+            None => {
+                warn!("[SIMULATOR] No aircraft types configured for airline {}, using default A320", airline);
+                "A320".to_string()
+            }
         }
-        
-        let aircraft_types = aircraft_types.unwrap();
-        let aircraft_type = aircraft_types.get(rng.gen_range(0..aircraft_types.len()))
-            .ok_or_else(|| anyhow::anyhow!("No aircraft type selected"))?;
-        
-        Ok(aircraft_type.clone())
     }
     
-    /// Assign a squawk code
+    /// Assign a squawk code that no other aircraft is squawking
     fn assign_squawk(&mut self) -> String {
-        if let Some(squawk) = self.squawk_pool.pop() {
-            format!("{:04}", squawk)
-        } else {
-            // Fallback if pool is empty
-            let mut rng = rand::thread_rng();
-            format!("{:04}", rng.gen_range(2000..7777))
+        // Codes still in use are dropped here, and come back when their aircraft retires
+        while let Some(squawk) = self.squawk_pool.pop() {
+            let code = format!("{:04}", squawk);
+            if is_assignable_squawk(squawk) && !self.squawk_in_use(&code) {
+                return code;
+            }
         }
-    }
-    
-    /// Extract cruise altitude from route
-    fn get_cruise_altitude(&self, route: &str) -> u32 {
-        // Look for FL in route (e.g., FL350)
-        if let Some(fl_pos) = route.find("FL") {
-            let fl_str = &route[fl_pos+2..];
-            if let Some(num_end) = fl_str.find(|c: char| !c.is_numeric()) {
-                if let Ok(fl) = fl_str[..num_end].parse::<u32>() {
-                    return fl;
-                }
+
+        // Fallback if pool is empty
+        let mut rng = rand::thread_rng();
+        loop {
+            let squawk = rng.gen_range(2000..=7777);
+            let code = format!("{:04}", squawk);
+            if is_assignable_squawk(squawk) && !self.squawk_in_use(&code) {
+                return code;
             }
         }
-        
-        // Default cruise altitude
-        360
+    }
+
+    fn squawk_in_use(&self, squawk: &str) -> bool {
+        self.aircraft.iter().any(|a| a.squawk == squawk)
+    }
+
+    /// Return a retired aircraft's CCAMS code to the pool, to be reissued after the rest
+    fn release_squawk(&mut self, squawk: &str) {
+        let Ok(code) = squawk.parse::<u16>() else {
+            return;
+        };
+        if self.ccams_squawks.contains(&code) && !self.squawk_pool.contains(&code) {
+            self.squawk_pool.insert(0, code);
+        }
+    }
+    
+    /// Extract cruise altitude (hundreds of feet) from route
+    fn get_cruise_altitude(route: &str) -> u32 {
+        // First level in the route (e.g., FL350, or N0450F370 at a fix)
+        route
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .find_map(parse_route_level)
+            // Default cruise altitude
+            .unwrap_or(360)
     }
 
     /// Check and spawn transits
-    async fn check_transit_spawns(&self, timers: &mut [(usize, u64, u64)], loop_count: u64) -> Result<()> {
+    async fn check_transit_spawns(&mut self, timers: &mut [(usize, u64, u64)], spawn_ticks: u64) -> Result<()> {
         for (idx, interval, last_spawn) in timers.iter_mut() {
-            if loop_count - *last_spawn >= *interval {
-                *last_spawn = loop_count;
+            if spawn_ticks - *last_spawn >= *interval {
+                *last_spawn = spawn_ticks;
                 
                 if let Some(route) = self.scenario.random_transit_route(*idx) {
-                    info!("[SIMULATOR] Spawning transit: {} -> {} at FL{:03} via {}", 
-                          route.departing, route.arriving, route.current_level / 100, route.route);
-                    // TODO: Create and spawn aircraft
+                    let departure = route.departing.clone();
+                    let arrival = route.arriving.clone();
+                    let route_str = route.route.clone();
+
+                    // For approach training, arrivals skip straight to final (subject to metering)
+                    if self.sim_config.ils_arrivals && self.scenario.active_arrival_runway(&arrival).is_some() {
+                        self.arrival_meter.push((departure, arrival, route_str));
+                        continue;
+                    }
+
+                    // A route that can't be flown shouldn't stop the session
+                    let route = route.clone();
+                    if let Err(e) = self.spawn_transit(&route).await {
+                        warn!("[SIMULATOR] Skipping transit {}: {}", route.route, e);
+                    }
                 }
             }
         }
+
+        let now = spawn_ticks as f64 / self.sim_config.radar_update_rate;
+        if let Some((departure, arrival, route)) = self.arrival_meter.release(now) {
+            if !self.arrival_meter.is_empty() {
+                debug!("[SIMULATOR] {} arrivals held by metering", self.arrival_meter.len());
+            }
+            self.spawn_ils_arrival(&departure, &arrival, &route).await?;
+        }
         Ok(())
     }
 
@@ -497,23 +1613,52 @@ impl Simulator {
         info!("[SIMULATOR] Stopping simulation...");
         self.running = false;
         
-        // Disconnect all pilots
+        // Disconnect all pilots, carrying on past any that fail
         for (callsign, mut pilot) in self.pilot_clients.drain() {
             info!("[SIMULATOR] Disconnecting pilot {}", callsign);
-            pilot.disconnect().await?;
+            if let Err(e) = pilot.disconnect().await {
+                warn!("[SIMULATOR] Failed to disconnect {}: {}", callsign, e);
+            }
         }
         
         // Disconnect all AI controllers
         for controller in &mut self.ai_controllers {
-            controller.disconnect().await?;
+            if let Err(e) = controller.disconnect().await {
+                warn!("[SIMULATOR] Failed to disconnect controller: {}", e);
+            }
         }
         
         self.ai_controllers.clear();
+        self.close_snapshot_log();
         
         info!("[SIMULATOR] Simulation stopped");
         Ok(())
     }
 
+    /// State of every aircraft in the simulation
+    pub fn snapshot(&self) -> Vec<AircraftSnapshot> {
+        self.aircraft.iter().map(AircraftSnapshot::from).collect()
+    }
+
+    /// Queue this tick's snapshots for the snapshot log, if there is one
+    fn log_snapshots(&mut self, now: f64) {
+        let Some((lines, _)) = &self.snapshot_log else {
+            return;
+        };
+        let line = serde_json::json!({ "time": now, "aircraft": self.snapshot() });
+        if lines.send(line.to_string()).is_err() {
+            self.snapshot_log = None;
+        }
+    }
+
+    /// Finish writing the snapshot log and close it
+    fn close_snapshot_log(&mut self) {
+        if let Some((lines, writer)) = self.snapshot_log.take() {
+            drop(lines);
+            let _ = writer.join();
+        }
+    }
+
     /// Get simulation statistics
     pub fn statistics(&self) -> SimulatorStats {
         SimulatorStats {
@@ -525,6 +1670,47 @@ impl Simulator {
     }
 }
 
+/// An aircraft's state at a moment, for logging and post-processing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AircraftSnapshot {
+    pub callsign: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Feet
+    pub altitude: i32,
+    /// Degrees true
+    pub heading: f64,
+    /// Ground speed (knots)
+    pub speed: u32,
+    /// Lateral navigation mode, e.g. FlightPlan or Heading
+    pub mode: String,
+    /// Phase of flight, e.g. Climbing
+    pub phase: String,
+}
+
+impl From<&Aircraft> for AircraftSnapshot {
+    fn from(aircraft: &Aircraft) -> Self {
+        let mode = match &aircraft.nav_mode {
+            NavMode::FlightPlan => "FlightPlan",
+            NavMode::Hold(_) => "Hold",
+            NavMode::Ils(_) => "Ils",
+            NavMode::Intercept(_) => "Intercept",
+            NavMode::Heading(_) => "Heading",
+            NavMode::MissedApproach(_) => "MissedApproach",
+        };
+        Self {
+            callsign: aircraft.callsign.clone(),
+            latitude: aircraft.latitude,
+            longitude: aircraft.longitude,
+            altitude: aircraft.altitude,
+            heading: aircraft.heading,
+            speed: aircraft.ground_speed,
+            mode: mode.to_string(),
+            phase: format!("{:?}", aircraft.phase),
+        }
+    }
+}
+
 /// Statistics about the running simulator
 #[derive(Debug, Clone)]
 pub struct SimulatorStats {
@@ -544,3 +1730,1686 @@ impl std::fmt::Display for SimulatorStats {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aircraft::aircraft::{FlightPhase, NavMode};
+    use crate::scenario::ScenarioBuilder;
+    use crate::utils::navigation::haversine_nm;
+
+    /// A simulator that never connects to a server, for driving aircraft and spawns directly
+    fn ghost_simulator(scenario: Scenario, sim_config: SimulationConfig, fixes: FixDatabase) -> Simulator {
+        Simulator::new(
+            scenario,
+            SimulationConfig { ghost_mode: true, ..sim_config },
+            FleetConfig::default(),
+            Arc::new(fixes),
+            Arc::new(PerformanceDatabase::new()),
+            "127.0.0.1:1".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_controller_logins() -> Result<()> {
+        let server = Arc::new(crate::server::FsdServer::new("127.0.0.1".to_string(), 0));
+        let listener = server.bind().await?;
+        let server_addr = listener.local_addr()?.to_string();
+        let serving = server.clone();
+        tokio::spawn(async move { serving.serve(listener).await });
+
+        let mut builder = ScenarioBuilder::new()
+            .master_controller("LON_E_CTR".to_string(), "118.480".to_string());
+        for i in 1..=6 {
+            builder = builder.add_other_controller(format!("TEST{}_CTR", i), format!("12{}.000", i));
+        }
+
+        let sim_config = SimulationConfig {
+            controller_login_delay_ms: 0,
+            controller_login_concurrency: 3,
+            ..SimulationConfig::default()
+        };
+        let mut simulator = Simulator::new(
+            builder.build(),
+            sim_config,
+            FleetConfig::default(),
+            Arc::new(FixDatabase::new()),
+            Arc::new(PerformanceDatabase::new()),
+            server_addr,
+        );
+        simulator.login_ai_controllers().await?;
+
+        let expected: Vec<String> = std::iter::once("LON_E_CTR".to_string())
+            .chain((1..=6).map(|i| format!("TEST{}_CTR", i)))
+            .collect();
+        let logged_in: Vec<String> = simulator.ai_controllers.iter().map(|c| c.callsign().to_string()).collect();
+        assert_eq!(logged_in, expected);
+
+        let mut on_server = server.controller_callsigns().await;
+        on_server.sort();
+        assert_eq!(on_server, expected);
+
+        simulator.stop().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_offline_controller_aircraft_reassigned_to_master() -> Result<()> {
+        let server = Arc::new(crate::server::FsdServer::new("127.0.0.1".to_string(), 0));
+        let listener = server.bind().await?;
+        let server_addr = listener.local_addr()?.to_string();
+        let serving = server.clone();
+        tokio::spawn(async move { serving.serve(listener).await });
+
+        let scenario = ScenarioBuilder::new()
+            .master_controller("LON_E_CTR".to_string(), "118.480".to_string())
+            .add_other_controller("LON_S_CTR".to_string(), "129.430".to_string())
+            .build();
+        let sim_config = SimulationConfig {
+            controller_login_delay_ms: 0,
+            ..SimulationConfig::default()
+        };
+        let mut simulator = Simulator::new(
+            scenario,
+            sim_config,
+            FleetConfig::default(),
+            Arc::new(FixDatabase::new()),
+            Arc::new(PerformanceDatabase::new()),
+            server_addr,
+        );
+        simulator.login_ai_controllers().await?;
+        let mut events = simulator.subscribe();
+
+        for (callsign, controller) in [("EZY12AB", "LON_S_CTR"), ("BAW34CD", "LON_E_CTR")] {
+            let mut aircraft = Aircraft::new_departure(
+                callsign.to_string(), "A320".to_string(), "1234".to_string(),
+                "EGKK".to_string(), "EGPH".to_string(), "DCT".to_string(),
+                35000, "26L".to_string(), (51.15, -0.19), 257.0,
+            );
+            aircraft.controller = Some(controller.to_string());
+            simulator.aircraft.push(aircraft);
+        }
+
+        simulator.ai_controllers[1].disconnect().await?;
+        simulator.check_controllers();
+
+        let controllers: Vec<Option<&str>> = simulator.aircraft.iter().map(|a| a.controller.as_deref()).collect();
+        assert_eq!(controllers, vec![Some("LON_E_CTR"), Some("LON_E_CTR")]);
+        assert_eq!(simulator.ai_controllers.len(), 1);
+
+        assert_eq!(events.try_recv()?, SimulatorEvent::ControllerLost { callsign: "LON_S_CTR".to_string() });
+        assert_eq!(events.try_recv()?, SimulatorEvent::AircraftReassigned {
+            callsign: "EZY12AB".to_string(),
+            from: "LON_S_CTR".to_string(),
+            to: "LON_E_CTR".to_string(),
+        });
+        assert!(events.try_recv().is_err());
+
+        simulator.stop().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_primary_only_position() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let server_addr = listener.local_addr()?.to_string();
+
+        let mut simulator = Simulator::new(
+            ScenarioBuilder::new().build(),
+            SimulationConfig::default(),
+            FleetConfig::default(),
+            Arc::new(FixDatabase::new()),
+            Arc::new(PerformanceDatabase::new()),
+            server_addr.clone(),
+        );
+        let mut aircraft = Aircraft::new_departure(
+            "EZY12AB".to_string(), "A320".to_string(), "4521".to_string(),
+            "EGKK".to_string(), "EGPH".to_string(), "DCT".to_string(),
+            35000, "26L".to_string(), (51.15, -0.19), 257.0,
+        );
+        aircraft.altitude = 12000;
+        aircraft.primary_only = true;
+        simulator.aircraft.push(aircraft);
+
+        let mut pilot = AiPilot::new("EZY12AB".to_string());
+        pilot.connect(&server_addr).await?;
+        let (mut server_side, _) = listener.accept().await?;
+        simulator.pilot_clients.insert("EZY12AB".to_string(), pilot);
+
+        Simulator::send_positions(&mut simulator.pilot_clients, simulator.aircraft.iter()).await;
+        simulator.set_primary_only("EZY12AB", false)?;
+        Simulator::send_positions(&mut simulator.pilot_clients, simulator.aircraft.iter()).await;
+        assert!(simulator.set_primary_only("BAW1", true).is_err());
+        simulator.stop().await?;
+
+        let mut received = String::new();
+        server_side.read_to_string(&mut received).await?;
+        let positions: Vec<Vec<&str>> = received
+            .split("\r\n")
+            .filter(|m| m.starts_with('@'))
+            .map(|m| m.split(':').collect())
+            .collect();
+
+        // Primary-only: standby, no squawk or altitude
+        assert_eq!(positions[0][0], "@S");
+        assert_eq!(positions[0][2], "0000");
+        assert_eq!(positions[0][6], "0");
+
+        // Mode C restored
+        assert_eq!(positions[1][0], "@N");
+        assert_eq!(positions[1][2], "4521");
+        assert_eq!(positions[1][6], "12000");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_finished_aircraft_logged_out() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let server_addr = listener.local_addr()?.to_string();
+
+        let mut simulator = Simulator::new(
+            ScenarioBuilder::new().build(),
+            SimulationConfig::default(),
+            FleetConfig::default(),
+            Arc::new(FixDatabase::new()),
+            Arc::new(PerformanceDatabase::new()),
+            server_addr.clone(),
+        );
+        let mut aircraft = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "4521".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "DCT".to_string(),
+            (51.5, 0.5), 270.0, 12000, 280,
+        );
+        aircraft.current_fix_index = aircraft.route_fixes.len();
+        simulator.used_callsigns.insert(aircraft.callsign.clone());
+        simulator.aircraft.push(aircraft);
+
+        let mut pilot = AiPilot::new("KLM123".to_string());
+        pilot.connect(&server_addr).await?;
+        let (mut server_side, _) = listener.accept().await?;
+        simulator.pilot_clients.insert("KLM123".to_string(), pilot);
+
+        let removed = simulator.advance(Duration::from_secs(1));
+        assert_eq!(removed, vec!["KLM123".to_string()]);
+        simulator.disconnect_pilots(&removed).await;
+        assert!(simulator.pilot_clients.is_empty());
+        assert!(simulator.aircraft.is_empty());
+
+        // The pilot logged off and closed its session
+        let mut received = String::new();
+        server_side.read_to_string(&mut received).await?;
+        assert!(received.contains("#DPKLM123"), "{:?}", received);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_squawk_on_instruction() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let server_addr = listener.local_addr()?.to_string();
+
+        let sim_config = SimulationConfig {
+            squawk_on_instruction: true,
+            ..Default::default()
+        };
+        let mut simulator = Simulator::new(
+            ScenarioBuilder::new().build(),
+            sim_config,
+            FleetConfig::default(),
+            Arc::new(FixDatabase::new()),
+            Arc::new(PerformanceDatabase::new()),
+            server_addr.clone(),
+        );
+
+        let accept = tokio::spawn(async move { listener.accept().await.map(|(stream, _)| stream) });
+        let aircraft = Aircraft::new_departure(
+            "EZY12AB".to_string(), "A320".to_string(), "4521".to_string(),
+            "EGKK".to_string(), "EGPH".to_string(), "DCT".to_string(),
+            35000, "26L".to_string(), (51.15, -0.19), 257.0,
+        );
+        simulator.add_aircraft(aircraft).await?;
+        let mut server_side = accept.await??;
+
+        Simulator::send_positions(&mut simulator.pilot_clients, simulator.aircraft.iter()).await;
+        simulator.instruct_squawk("EZY12AB")?;
+        Simulator::send_positions(&mut simulator.pilot_clients, simulator.aircraft.iter()).await;
+        assert!(simulator.instruct_squawk("BAW1").is_err());
+        simulator.stop().await?;
+
+        let mut received = String::new();
+        server_side.read_to_string(&mut received).await?;
+        let squawks: Vec<&str> = received
+            .split("\r\n")
+            .filter(|m| m.starts_with('@'))
+            .filter_map(|m| m.split(':').nth(2))
+            .collect();
+
+        // Login squawk and the first two positions show conspicuity, then the assigned code
+        assert_eq!(squawks, vec!["2000", "2000", "2000", "4521"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_eastbound_destinations_prefer_eastbound_sids() {
+        use crate::config::StandardDeparture;
+
+        let route = |route: &str, arriving: &str| DepartureRoute {
+            route: route.to_string(),
+            arriving: arriving.to_string(),
+            weight: 1.0,
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .add_departure_config(StandardDeparture {
+                departing: "EGKK".to_string(),
+                interval: 120,
+                interval_schedule: Vec::new(),
+                routes: vec![
+                    route("EASTA1X/26L EASTA", "EHAM"),
+                    route("WESTA1X/26L WESTA", "EHAM"),
+                    route("EASTA1X/26L EASTA", "EGGD"),
+                    route("WESTA1X/26L WESTA", "EGGD"),
+                ],
+            })
+            .build();
+
+        let mut fixes = FixDatabase::new();
+        fixes.insert("EGKK".to_string(), (51.148, -0.190));
+        fixes.insert("EHAM".to_string(), (52.309, 4.764));
+        fixes.insert("EGGD".to_string(), (51.383, -2.719));
+        fixes.insert("EASTA".to_string(), (51.2, 0.6));
+        fixes.insert("WESTA".to_string(), (51.1, -1.0));
+
+        let sim_config = SimulationConfig {
+            sid_direction_bias: 4.0,
+            ..SimulationConfig::default()
+        };
+        let simulator = ghost_simulator(scenario, sim_config, fixes);
+
+        let mut counts: std::collections::HashMap<(String, bool), usize> = std::collections::HashMap::new();
+        for _ in 0..1000 {
+            let picked = simulator.select_departure_route("EGKK").unwrap();
+            *counts.entry((picked.arriving.clone(), picked.route.starts_with("EASTA"))).or_default() += 1;
+        }
+        let count = |arriving: &str, east_sid: bool| counts.get(&(arriving.to_string(), east_sid)).copied().unwrap_or(0);
+
+        // Eastbound destinations predominantly use the eastbound SID, and vice versa
+        assert!(count("EHAM", true) > 20 * count("EHAM", false), "{:?}", counts);
+        assert!(count("EGGD", false) > 20 * count("EGGD", true), "{:?}", counts);
+    }
+
+    #[tokio::test]
+    async fn test_ghost_mode_simulates_without_connecting() -> Result<()> {
+        let scenario = ScenarioBuilder::new()
+            .master_controller("LON_S_CTR".to_string(), "129.430".to_string())
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .build();
+        // Nothing listens here, so any connection attempt would fail
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), FixDatabase::new());
+
+        simulator.initialize().await?;
+        simulator.spawn_departure("EGKK", "EGPH", "LAM2X/26L LAM").await?;
+        assert!(simulator.ai_controllers.is_empty());
+        assert!(simulator.pilot_clients.is_empty());
+        assert_eq!(simulator.aircraft.len(), 1);
+
+        let start = (simulator.aircraft[0].latitude, simulator.aircraft[0].longitude);
+        for _ in 0..120 {
+            simulator.update_aircraft(1.0);
+        }
+        Simulator::send_positions(&mut simulator.pilot_clients, simulator.aircraft.iter()).await;
+
+        let aircraft = &simulator.aircraft[0];
+        assert!(aircraft.altitude > 0, "still on the ground: {}", aircraft);
+        assert!(haversine_nm(start.0, start.1, aircraft.latitude, aircraft.longitude) > 1.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_time_multiplier_scales_spawns() -> Result<()> {
+        use crate::config::StandardDeparture;
+
+        let departures_in_ten_minutes = |time_multiplier: f64| async move {
+            let scenario = ScenarioBuilder::new()
+                .add_aerodrome("EGLL".to_string(), "27R".to_string())
+                .add_departure_config(StandardDeparture {
+                    departing: "EGLL".to_string(),
+                    interval: 120,
+                    interval_schedule: Vec::new(),
+                    routes: vec![DepartureRoute {
+                        route: "CPT3J/27R CPT".to_string(),
+                        arriving: "EGPH".to_string(),
+                        weight: 1.0,
+                    }],
+                })
+                .build();
+            let mut simulator = ghost_simulator(scenario, SimulationConfig { time_multiplier, ..SimulationConfig::default() }, FixDatabase::new());
+
+            // Ten wall-clock minutes of 5Hz ticks
+            let mut timers = simulator.create_departure_timers();
+            let mut spawned = 0;
+            for _ in 0..3000 {
+                simulator.advance(Duration::from_millis(200));
+                let before = simulator.aircraft.len();
+                let spawn_ticks = simulator.spawn_ticks();
+                simulator.check_departure_spawns(&mut timers, spawn_ticks).await?;
+                spawned += simulator.aircraft.len() - before;
+            }
+            Ok::<_, anyhow::Error>(spawned)
+        };
+
+        assert_eq!(departures_in_ten_minutes(1.0).await?, 5);
+        assert_eq!(departures_in_ten_minutes(2.0).await?, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_multiplier_scales_distance() {
+        let distance_after_minute = |time_multiplier: f64| {
+            let mut fixes = FixDatabase::new();
+            fixes.insert("FAR".to_string(), (52.0, 20.0));
+            let mut simulator = ghost_simulator(ScenarioBuilder::new().build(), SimulationConfig { time_multiplier, ..SimulationConfig::default() }, fixes);
+
+            // Level eastbound towards a distant fix, holding its heading and speed
+            let mut aircraft = Aircraft::new_enroute(
+                "KLM123".to_string(), "B738".to_string(), "2201".to_string(),
+                "EHAM".to_string(), "EGLL".to_string(), "FAR".to_string(),
+                (52.0, 0.0), 90.0, 20000, 300,
+            );
+            aircraft.route_fixes = vec!["FAR".to_string()];
+            let start = (aircraft.latitude, aircraft.longitude);
+            simulator.aircraft.push(aircraft);
+
+            // A minute of 5Hz ticks
+            for _ in 0..300 {
+                simulator.advance(Duration::from_millis(200));
+            }
+            let aircraft = &simulator.aircraft[0];
+            (haversine_nm(start.0, start.1, aircraft.latitude, aircraft.longitude), aircraft.ground_speed)
+        };
+
+        let (real_time, ground_speed) = distance_after_minute(1.0);
+        let (double, _) = distance_after_minute(2.0);
+        let (fast, _) = distance_after_minute(10.0);
+        assert!((real_time - ground_speed as f64 / 60.0).abs() < 0.1, "{}nm at {}kt", real_time, ground_speed);
+        assert!((double / real_time - 2.0).abs() < 0.05, "{}nm at 2x, {}nm at 1x", double, real_time);
+        assert!((fast / real_time - 10.0).abs() < 0.1, "{}nm at 10x, {}nm at 1x", fast, real_time);
+    }
+
+    #[tokio::test]
+    async fn test_arrivals_metered_to_flow_rate() -> Result<()> {
+        use crate::config::{StandardTransit, TransitRoute};
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_transit_config(StandardTransit {
+                interval: 30,
+                routes: vec![TransitRoute {
+                    route: "REDFA DCT BARMI BARMI1H".to_string(),
+                    departing: "EHAM".to_string(),
+                    arriving: "EGLL".to_string(),
+                    current_level: 10000,
+                    cruise_level: 10000,
+                    first_controller: "LON_S_CTR".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .build();
+        let sim_config = SimulationConfig {
+            ils_arrivals: true,
+            arrival_flow_per_hour: 30.0,
+            ..SimulationConfig::default()
+        };
+        let ticks_per_second = sim_config.radar_update_rate;
+        let mut simulator = ghost_simulator(scenario, sim_config, FixDatabase::new());
+
+        // Ten minutes of spawns every 30 seconds, metered to one every two minutes
+        let mut timers = simulator.create_transit_timers();
+        let mut spawn_times = Vec::new();
+        for loop_count in 1..=(600.0 * ticks_per_second) as u64 {
+            let before = simulator.aircraft.len();
+            simulator.check_transit_spawns(&mut timers, loop_count).await?;
+            if simulator.aircraft.len() > before {
+                spawn_times.push(loop_count as f64 / ticks_per_second);
+            }
+        }
+
+        assert_eq!(spawn_times.len(), 5, "{:?}", spawn_times);
+        assert!(spawn_times.windows(2).all(|pair| pair[1] - pair[0] >= 120.0), "{:?}", spawn_times);
+        // The rest are queued rather than dropped
+        assert!(simulator.arrival_meter.len() >= 10);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transit_spawned_at_entry_fix() -> Result<()> {
+        use crate::config::{StandardTransit, TransitRoute};
+        use crate::utils::navigation::position_bearing_distance;
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_transit_config(StandardTransit {
+                interval: 60,
+                routes: vec![TransitRoute {
+                    route: "ENTRY DCT MIDPT DCT FINAL".to_string(),
+                    departing: "EHAM".to_string(),
+                    arriving: "EGLL".to_string(),
+                    current_level: 12000,
+                    cruise_level: 12000,
+                    first_controller: "LON_S_CTR".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .build();
+
+        let entry = (52.0, 2.0);
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), entry);
+        fixes.insert("MIDPT".to_string(), position_bearing_distance(entry.0, entry.1, 270.0, 50.0));
+        fixes.insert("FINAL".to_string(), position_bearing_distance(entry.0, entry.1, 270.0, 100.0));
+        let sim_config = SimulationConfig {
+            ils_arrivals: false,
+            ..SimulationConfig::default()
+        };
+        let ticks_per_second = sim_config.radar_update_rate;
+        let mut simulator = ghost_simulator(scenario, sim_config, fixes);
+
+        let mut timers = simulator.create_transit_timers();
+        let interval_ticks = (60.0 * ticks_per_second) as u64;
+        for loop_count in 1..interval_ticks {
+            simulator.check_transit_spawns(&mut timers, loop_count).await?;
+        }
+        assert!(simulator.aircraft.is_empty());
+
+        simulator.check_transit_spawns(&mut timers, interval_ticks).await?;
+        assert_eq!(simulator.aircraft.len(), 1);
+        let transit = &simulator.aircraft[0];
+        assert_eq!(transit.flight_plan.departure, "EHAM");
+        assert_eq!(transit.flight_plan.arrival, "EGLL");
+        assert_eq!(transit.altitude, 12000);
+        assert_eq!(transit.phase, FlightPhase::Cruise);
+        assert_eq!((transit.latitude, transit.longitude), entry);
+        assert_eq!(transit.current_fix(), Some("MIDPT"));
+        assert!((transit.heading - 270.0).abs() < 2.0, "heading {}", transit.heading);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unflyable_transit_skipped() -> Result<()> {
+        use crate::config::{StandardTransit, TransitRoute};
+
+        let scenario = ScenarioBuilder::new()
+            .add_transit_config(StandardTransit {
+                interval: 60,
+                routes: vec![TransitRoute {
+                    route: "NOWHR DCT FINAL".to_string(),
+                    departing: "EHAM".to_string(),
+                    arriving: "EGLL".to_string(),
+                    current_level: 12000,
+                    cruise_level: 12000,
+                    first_controller: "LON_S_CTR".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .build();
+        let sim_config = SimulationConfig::default();
+        let interval_ticks = (60.0 * sim_config.radar_update_rate) as u64;
+        let mut simulator = ghost_simulator(scenario, sim_config, FixDatabase::new());
+
+        // None of the route's fixes are known: nothing spawns, but the session carries on
+        let mut timers = simulator.create_transit_timers();
+        simulator.check_transit_spawns(&mut timers, interval_ticks).await?;
+        assert!(simulator.aircraft.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_entry_stream_spacing_and_levels() -> Result<()> {
+        let stream = EntryStream {
+            entry_fix: "ENTRY".to_string(),
+            departing: "EHAM".to_string(),
+            arriving: "EGLL".to_string(),
+            route: "ENTRY DCT EXITT".to_string(),
+            min_level: 30000,
+            max_level: 34000,
+            level_step: 2000,
+            spacing_nm: 10.0,
+            speed: 420,
+        };
+        let scenario = ScenarioBuilder::new().add_entry_stream(stream).build();
+
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), (52.0, 2.0));
+        fixes.insert("EXITT".to_string(), (50.0, -3.0));
+        let sim_config = SimulationConfig::default();
+        let ticks_per_second = sim_config.radar_update_rate;
+        let mut simulator = ghost_simulator(scenario, sim_config, fixes);
+
+        let mut timers = simulator.create_stream_timers();
+        for loop_count in 1..=(360.0 * ticks_per_second) as u64 {
+            let before = simulator.aircraft.len();
+            simulator.check_stream_spawns(&mut timers, loop_count).await?;
+            if simulator.aircraft.len() > before {
+                let spawned = simulator.aircraft.last().unwrap();
+                assert_eq!((spawned.latitude, spawned.longitude), (52.0, 2.0));
+            }
+            simulator.update_aircraft(1.0 / ticks_per_second);
+        }
+
+        let levels: Vec<i32> = simulator.aircraft.iter().map(|a| a.flight_plan.cruise_altitude_feet()).collect();
+        assert_eq!(levels, vec![30000, 32000, 34000, 30000]);
+
+        // Each aircraft is the configured spacing behind the one ahead
+        for pair in simulator.aircraft.windows(2) {
+            let spacing = haversine_nm(pair[0].latitude, pair[0].longitude, pair[1].latitude, pair[1].longitude);
+            assert!((spacing - 10.0).abs() < 0.5, "{:.1}nm in trail", spacing);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unflyable_stream_skipped() -> Result<()> {
+        let stream = |entry_fix: &str| EntryStream {
+            entry_fix: entry_fix.to_string(),
+            departing: "EHAM".to_string(),
+            arriving: "EGLL".to_string(),
+            route: format!("{} DCT EXITT", entry_fix),
+            min_level: 30000,
+            max_level: 30000,
+            level_step: 2000,
+            spacing_nm: 10.0,
+            speed: 420,
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_entry_stream(stream("NOWHR"))
+            .add_entry_stream(stream("ENTRY"))
+            .build();
+
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), (52.0, 2.0));
+        fixes.insert("EXITT".to_string(), (50.0, -3.0));
+        let sim_config = SimulationConfig::default();
+        let ticks_per_second = sim_config.radar_update_rate;
+        let mut simulator = ghost_simulator(scenario, sim_config, fixes);
+
+        // The stream with an unknown entry fix spawns nothing, the other carries on
+        let mut timers = simulator.create_stream_timers();
+        for loop_count in 1..=(300.0 * ticks_per_second) as u64 {
+            simulator.check_stream_spawns(&mut timers, loop_count).await?;
+            simulator.update_aircraft(1.0 / ticks_per_second);
+        }
+        assert!(simulator.aircraft.len() >= 2, "{} spawned", simulator.aircraft.len());
+        assert!(simulator.aircraft.iter().all(|a| a.flight_plan.route.starts_with("ENTRY")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scripted_spawns() -> Result<()> {
+        let scripted = |callsign: &str, aircraft_type: &str, route: &str, at: u64, level: Option<u32>| ScriptedSpawn {
+            callsign: callsign.to_string(),
+            aircraft_type: aircraft_type.to_string(),
+            departing: if level.is_some() { "EHAM" } else { "EGKK" }.to_string(),
+            arriving: "EGLL".to_string(),
+            route: route.to_string(),
+            at,
+            level,
+            speed: 300,
+        };
+        // Listed out of order: they spawn by time
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .add_scripted_spawn(scripted("KLM45", "B738", "ENTRY DCT EXITT", 10, Some(24000)))
+            .add_scripted_spawn(scripted("BAW123", "A320", "LAM2X/26L LAM", 0, None))
+            .add_scripted_spawn(scripted("BAW123", "B772", "LAM2X/26L LAM", 20, None))
+            .add_scripted_spawn(scripted("DLH9", "A321", "NOWHR DCT EXITT", 5, Some(30000)))
+            .build();
+
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), (52.0, 2.0));
+        fixes.insert("EXITT".to_string(), (50.0, -3.0));
+        let sim_config = SimulationConfig::default();
+        let ticks_per_second = sim_config.radar_update_rate;
+        // No fleet data, so nothing could be generated at random
+        let mut simulator = ghost_simulator(scenario, sim_config, fixes);
+
+        let mut queue = simulator.create_scripted_queue();
+        for loop_count in 0..=(30.0 * ticks_per_second) as u64 {
+            simulator.check_scripted_spawns(&mut queue, loop_count).await?;
+            if loop_count == (5.0 * ticks_per_second) as u64 {
+                assert_eq!(simulator.aircraft.len(), 1);
+            }
+        }
+        assert!(queue.is_empty());
+
+        // The duplicate BAW123 is skipped rather than spawned twice, and DLH9, entering at
+        // an unknown fix, is dropped
+        let spawned: Vec<(&str, &str)> = simulator.aircraft
+            .iter()
+            .map(|a| (a.callsign.as_str(), a.aircraft_type.as_str()))
+            .collect();
+        assert_eq!(spawned, vec![("BAW123", "A320"), ("KLM45", "B738")]);
+
+        let klm = &simulator.aircraft[1];
+        assert_eq!((klm.latitude, klm.longitude, klm.altitude), (52.0, 2.0, 24000));
+        assert_ne!(simulator.aircraft[0].squawk, klm.squawk);
+        Ok(())
+    }
+
+    /// Run a ghost-mode simulation of `scenario` for a second and a half
+    async fn run_briefly(scenario: Scenario, sim_config: SimulationConfig) -> Result<Simulator> {
+        let mut simulator = ghost_simulator(scenario, sim_config, FixDatabase::new());
+        simulator.initialize().await?;
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(1500)).await;
+            let _ = shutdown_tx.send(());
+        });
+        simulator.run(shutdown_rx).await?;
+        Ok(simulator)
+    }
+
+    #[tokio::test]
+    async fn test_departures_only_profile() -> Result<()> {
+        use crate::config::StandardDeparture;
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_departure_config(StandardDeparture {
+                departing: "EGLL".to_string(),
+                interval: 1,
+                interval_schedule: Vec::new(),
+                routes: vec![DepartureRoute {
+                    route: "CPT3F/27R CPT".to_string(),
+                    arriving: "EGGD".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .build();
+        assert!(scenario.statistics().to_string().contains("Transit Configurations: none"));
+
+        let simulator = run_briefly(scenario, SimulationConfig::default()).await?;
+        assert!(!simulator.aircraft.is_empty());
+        assert!(simulator.aircraft.iter().all(|a| a.flight_plan.departure == "EGLL"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transits_only_profile() -> Result<()> {
+        use crate::config::{StandardTransit, TransitRoute};
+
+        // An empty configuration alongside one with a route
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_transit_config(StandardTransit { interval: 1, routes: vec![] })
+            .add_transit_config(StandardTransit {
+                interval: 1,
+                routes: vec![TransitRoute {
+                    departing: "EHAM".to_string(),
+                    arriving: "EGLL".to_string(),
+                    current_level: 12000,
+                    cruise_level: 12000,
+                    route: "REDFA DCT BARMI BARMI1H".to_string(),
+                    first_controller: "LON_E_CTR".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .build();
+        assert!(scenario.statistics().to_string().contains("Departure Configurations: none"));
+
+        let sim_config = SimulationConfig {
+            ils_arrivals: true,
+            prefill_transit_arrivals: true,
+            ..SimulationConfig::default()
+        };
+        let simulator = run_briefly(scenario, sim_config).await?;
+        assert!(!simulator.aircraft.is_empty());
+        assert!(simulator.aircraft.iter().all(|a| a.flight_plan.departure == "EHAM" && a.phase == FlightPhase::Approach));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_runways() -> Result<()> {
+        use crate::config::TransitRoute;
+
+        // Segregated parallels: departures off 27R, arrivals onto 27L
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome_with_runways("EGLL".to_string(), "27R".to_string(), "27L".to_string())
+            .build();
+        let mut fixes = FixDatabase::new();
+        fixes.insert("BPK".to_string(), (51.7497, -0.1064));
+        fixes.insert("ENTRY".to_string(), (52.0, 1.0));
+        fixes.insert("FINAL".to_string(), (51.6, 0.2));
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), fixes);
+
+        simulator.spawn_departure_as("EGLL", "EGKK", "BPK", "BAW01D".to_string(), "A320".to_string()).await?;
+        simulator.spawn_transit(&TransitRoute {
+            departing: "EHAM".to_string(),
+            arriving: "EGLL".to_string(),
+            current_level: 12000,
+            cruise_level: 12000,
+            route: "ENTRY DCT FINAL".to_string(),
+            first_controller: "LON_E_CTR".to_string(),
+            weight: 1.0,
+        }).await?;
+
+        let departure = &simulator.aircraft[0];
+        assert_eq!(departure.departure_runway, "27R");
+        assert!((departure.latitude - 51.4777).abs() < 0.001, "{}", departure.latitude);
+        let arrival = &simulator.aircraft[1];
+        assert_eq!(arrival.active_approach().map(|a| a.runway.as_str()), Some("27L"));
+        assert_eq!(simulator.active_localizer("EGLL")?.runway, "27L");
+        assert_eq!(simulator.scenario.runways_in_use("EGLL").as_deref(), Some("27L arrivals 27R departures"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefill_transit_arrivals() -> Result<()> {
+        use crate::config::{StandardTransit, TransitRoute};
+        use crate::utils::navigation::{cross_track_nm, position_bearing_distance};
+
+        let transit = |arriving: &str| StandardTransit {
+            interval: 120,
+            routes: vec![TransitRoute {
+                departing: "EHAM".to_string(),
+                arriving: arriving.to_string(),
+                current_level: 30000,
+                cruise_level: 30000,
+                route: "ENTRY DCT MIDPT DCT FINAL".to_string(),
+                first_controller: "LON_E_CTR".to_string(),
+                weight: 1.0,
+            }],
+        };
+        // Only transits into an active aerodrome are arrivals
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_transit_config(transit("EGLL"))
+            .add_transit_config(transit("EGKK"))
+            .build();
+
+        let entry = (52.0, 2.0);
+        let final_fix = position_bearing_distance(entry.0, entry.1, 270.0, 200.0);
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), entry);
+        fixes.insert("MIDPT".to_string(), position_bearing_distance(entry.0, entry.1, 270.0, 100.0));
+        fixes.insert("FINAL".to_string(), final_fix);
+        let sim_config = SimulationConfig {
+            prefill_transit_arrivals: true,
+            ..SimulationConfig::default()
+        };
+        let mut simulator = ghost_simulator(scenario, sim_config.clone(), fixes);
+        simulator.prefill_transit_arrivals().await?;
+
+        // One every two minutes at 280kt over 200nm
+        assert_eq!(simulator.aircraft.len(), 21);
+        assert!(simulator.aircraft.iter().all(|a| a.flight_plan.arrival == "EGLL"));
+
+        let mut inbound: Vec<(f64, &Aircraft)> = simulator.aircraft
+            .iter()
+            .map(|a| (haversine_nm(entry.0, entry.1, a.latitude, a.longitude), a))
+            .collect();
+        inbound.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (flown, aircraft) in &inbound {
+            assert!(cross_track_nm(aircraft.latitude, aircraft.longitude, entry, final_fix).abs() < 0.1,
+                    "{} is off the route", aircraft.callsign);
+            let next_fix = if *flown < 100.0 { "MIDPT" } else { "FINAL" };
+            assert_eq!(aircraft.route_fixes[aircraft.current_fix_index], next_fix);
+        }
+
+        // Level at the start of the route, then lower the further inbound, on the
+        // descent profile
+        let altitudes: Vec<i32> = inbound.iter().map(|(_, a)| a.altitude).collect();
+        assert!(altitudes.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", altitudes);
+        assert_eq!(altitudes[0], 30000);
+        let (flown, last) = inbound.last().expect("aircraft were prefilled");
+        let profile = sim_config.arrival_descent_altitude as f64 + (200.0 - flown) / 3.0 * 1000.0;
+        assert!((last.altitude as f64 - profile).abs() < 200.0, "{}ft {:.0}nm from the end", last.altitude, 200.0 - flown);
+        assert!(inbound.iter().filter(|(_, a)| a.phase == FlightPhase::Descending).all(|(_, a)| a.altitude < 30000));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefill_shares_transit_between_routes() -> Result<()> {
+        use crate::config::{StandardTransit, TransitRoute};
+        use crate::utils::navigation::position_bearing_distance;
+
+        // One transit feeding the active EGLL and the inactive EGKK
+        let route = |arriving: &str| TransitRoute {
+            departing: "EHAM".to_string(),
+            arriving: arriving.to_string(),
+            current_level: 30000,
+            cruise_level: 30000,
+            route: "ENTRY DCT FINAL".to_string(),
+            first_controller: "LON_E_CTR".to_string(),
+            weight: 1.0,
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_transit_config(StandardTransit {
+                interval: 120,
+                routes: vec![route("EGKK"), route("EGLL")],
+            })
+            .build();
+
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), (52.0, 2.0));
+        fixes.insert("FINAL".to_string(), position_bearing_distance(52.0, 2.0, 270.0, 200.0));
+        let mut simulator = ghost_simulator(scenario, SimulationConfig { prefill_transit_arrivals: true, ..SimulationConfig::default() }, fixes);
+        simulator.prefill_transit_arrivals().await?;
+
+        // Half the transit's aircraft, one every four minutes at 280kt over 200nm
+        assert_eq!(simulator.aircraft.len(), 10);
+        assert!(simulator.aircraft.iter().all(|a| a.flight_plan.arrival == "EGLL"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_aerodrome_arrival_departure_balance() -> Result<()> {
+        use crate::config::{AerodromeTraffic, StandardDeparture};
+
+        // Arrivals every minute, departures every five (overriding the standard two)
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_departure_config(StandardDeparture {
+                departing: "EGLL".to_string(),
+                interval: 120,
+                interval_schedule: Vec::new(),
+                routes: vec![DepartureRoute {
+                    route: "CPT3J/27R CPT".to_string(),
+                    arriving: "EGPH".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .aerodrome_traffic("EGLL".to_string(), AerodromeTraffic {
+                departure_interval: Some(300),
+                arrival_interval: Some(60),
+                arrival_origins: vec!["EHAM".to_string(), "LFPG".to_string()],
+            })
+            .build();
+        let sim_config = SimulationConfig::default();
+        let ticks_per_second = sim_config.radar_update_rate;
+        let mut simulator = ghost_simulator(scenario, sim_config, FixDatabase::new());
+
+        let mut departure_timers = simulator.create_departure_timers();
+        let mut arrival_timers = simulator.create_arrival_timers();
+        for loop_count in 1..=(1800.0 * ticks_per_second) as u64 {
+            simulator.check_departure_spawns(&mut departure_timers, loop_count).await?;
+            simulator.check_arrival_spawns(&mut arrival_timers, loop_count).await?;
+        }
+
+        let departures = simulator.aircraft.iter().filter(|a| a.flight_plan.departure == "EGLL").count();
+        let arrivals = simulator.aircraft.iter().filter(|a| a.flight_plan.arrival == "EGLL").count();
+        assert_eq!((departures, arrivals), (6, 30));
+        assert!(simulator.aircraft
+            .iter()
+            .filter(|a| a.flight_plan.arrival == "EGLL")
+            .all(|a| ["EHAM", "LFPG"].contains(&a.flight_plan.departure.as_str()) && a.phase == FlightPhase::Approach));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unflyable_local_arrival_skipped() -> Result<()> {
+        use crate::config::AerodromeTraffic;
+
+        let traffic = || AerodromeTraffic {
+            departure_interval: None,
+            arrival_interval: Some(60),
+            arrival_origins: vec!["EHAM".to_string()],
+        };
+        // There's no runway data for ZZZZ, so nothing can be put on final there
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("ZZZZ".to_string(), "27".to_string())
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .aerodrome_traffic("ZZZZ".to_string(), traffic())
+            .aerodrome_traffic("EGLL".to_string(), traffic())
+            .build();
+        let sim_config = SimulationConfig::default();
+        let ticks_per_second = sim_config.radar_update_rate;
+        let mut simulator = ghost_simulator(scenario, sim_config, FixDatabase::new());
+
+        let mut arrival_timers = simulator.create_arrival_timers();
+        for loop_count in 1..=(300.0 * ticks_per_second) as u64 {
+            simulator.check_arrival_spawns(&mut arrival_timers, loop_count).await?;
+        }
+        assert_eq!(simulator.aircraft.len(), 5);
+        assert!(simulator.aircraft.iter().all(|a| a.flight_plan.arrival == "EGLL"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_departure_interval_schedule() -> Result<()> {
+        use crate::config::StandardDeparture;
+
+        // Every five minutes, then a push with one a minute from ten minutes in
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_departure_config(StandardDeparture {
+                departing: "EGLL".to_string(),
+                interval: 300,
+                interval_schedule: vec![(10, 60)],
+                routes: vec![DepartureRoute {
+                    route: "CPT3J/27R CPT".to_string(),
+                    arriving: "EGPH".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .build();
+        let sim_config = SimulationConfig::default();
+        let ticks_per_second = sim_config.radar_update_rate;
+        let mut simulator = ghost_simulator(scenario, sim_config, FixDatabase::new());
+
+        let mut timers = simulator.create_departure_timers();
+        let mut spawn_times = Vec::new();
+        for loop_count in 1..=(1200.0 * ticks_per_second) as u64 {
+            let before = simulator.aircraft.len();
+            simulator.check_departure_spawns(&mut timers, loop_count).await?;
+            if simulator.aircraft.len() > before {
+                spawn_times.push(loop_count as f64 / ticks_per_second);
+            }
+        }
+
+        let gaps: Vec<f64> = spawn_times.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        assert_eq!(spawn_times[..2], [300.0, 600.0], "{:?}", spawn_times);
+        assert!(gaps[1..].iter().all(|gap| *gap == 60.0), "{:?}", spawn_times);
+        assert_eq!(spawn_times.len(), 12);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_departure_schedule_follows_simulated_time() -> Result<()> {
+        use crate::config::StandardDeparture;
+
+        // Every five minutes, then one a minute from ten simulated minutes in
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .add_departure_config(StandardDeparture {
+                departing: "EGLL".to_string(),
+                interval: 300,
+                interval_schedule: vec![(10, 60)],
+                routes: vec![DepartureRoute {
+                    route: "CPT3J/27R CPT".to_string(),
+                    arriving: "EGPH".to_string(),
+                    weight: 1.0,
+                }],
+            })
+            .build();
+        let mut simulator = ghost_simulator(scenario, SimulationConfig { time_multiplier: 2.0, ..SimulationConfig::default() }, FixDatabase::new());
+
+        // Six wall-clock minutes at 2x
+        let mut timers = simulator.create_departure_timers();
+        let mut spawn_times = Vec::new();
+        for tick in 1..=1800 {
+            simulator.advance(Duration::from_millis(200));
+            let before = simulator.aircraft.len();
+            let spawn_ticks = simulator.spawn_ticks();
+            simulator.check_departure_spawns(&mut timers, spawn_ticks).await?;
+            if simulator.aircraft.len() > before {
+                spawn_times.push(tick as f64 / 5.0);
+            }
+        }
+
+        // The push starts five wall-clock minutes in, then runs every 30 seconds
+        assert_eq!(spawn_times, [150.0, 300.0, 330.0, 360.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_master_descends_arrival_by_boundary() -> Result<()> {
+        use crate::config::DescentAgreement;
+        use crate::utils::navigation::position_bearing_distance;
+
+        let scenario = ScenarioBuilder::new()
+            .master_controller("LON_E_CTR".to_string(), "118.480".to_string())
+            .add_descent_agreement(DescentAgreement {
+                arriving: "EGLL".to_string(),
+                boundary_fix: "BNDRY".to_string(),
+                level: 20000,
+            })
+            .build();
+
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), (52.0, 2.0));
+        fixes.insert("BNDRY".to_string(), position_bearing_distance(52.0, 2.0, 270.0, 120.0));
+        fixes.insert("ARRIV".to_string(), position_bearing_distance(52.0, 2.0, 270.0, 200.0));
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), fixes);
+        let mut events = simulator.subscribe();
+
+        let mut aircraft = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "4521".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "ENTRY DCT BNDRY DCT ARRIV".to_string(),
+            (52.0, 2.0), 270.0, 35000, 420,
+        );
+        aircraft.controller = Some("LON_E_CTR".to_string());
+        simulator.aircraft.push(aircraft);
+
+        // Fly until the boundary is sequenced
+        let boundary_index = simulator.aircraft[0].route_fixes.iter().position(|f| f == "BNDRY").unwrap();
+        let mut ticks = 0;
+        while simulator.aircraft[0].current_fix_index <= boundary_index {
+            simulator.issue_agreed_descents();
+            simulator.update_aircraft(1.0);
+            ticks += 1;
+            if ticks == 30 {
+                // Too far out to need a descent yet
+                assert_eq!(simulator.aircraft[0].cleared_altitude, None);
+            }
+            assert!(ticks < 3600, "never reached the boundary");
+        }
+
+        assert_eq!(simulator.aircraft[0].altitude, 20000);
+        assert_eq!(events.try_recv()?, SimulatorEvent::DescentIssued {
+            callsign: "KLM123".to_string(),
+            controller: "LON_E_CTR".to_string(),
+            altitude: 20000,
+        });
+        assert!(events.try_recv().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_missed_approaches() {
+        use crate::config::MissedApproach;
+
+        let missed_approach = |runway: &str, fixes: &[&str]| MissedApproach {
+            aerodrome: "EGLL".to_string(),
+            runway: runway.to_string(),
+            fixes: fixes.iter().map(|f| f.to_string()).collect(),
+        };
+        let scenario = ScenarioBuilder::new()
+            .add_missed_approach(missed_approach("27R", &["D264L", "BNN"]))
+            .add_missed_approach(missed_approach("27L", &["D265K", "OCK"]))
+            .build();
+        let mut sim_config = SimulationConfig::default();
+        sim_config.missed_approaches.insert("EGLL 27L".to_string(), vec!["LON".to_string()]);
+        let simulator = ghost_simulator(scenario, sim_config, FixDatabase::new());
+
+        // The profile fills in runways without one, but doesn't override the config
+        let tracks = &simulator.sim_config.missed_approaches;
+        assert_eq!(tracks["EGLL 27R"], vec!["D264L", "BNN"]);
+        assert_eq!(tracks["EGLL 27L"], vec!["LON"]);
+    }
+
+    #[test]
+    fn test_handoff_at_release_point() -> Result<()> {
+        use crate::config::HandoffPoint;
+        use crate::utils::navigation::position_bearing_distance;
+
+        let scenario = ScenarioBuilder::new()
+            .add_handoff(HandoffPoint {
+                from: "LON_E_CTR".to_string(),
+                to: "LTC_E_CTR".to_string(),
+                release_fix: "RELSE".to_string(),
+            })
+            .build();
+        let mut fixes = FixDatabase::new();
+        fixes.insert("ENTRY".to_string(), (52.0, 2.0));
+        fixes.insert("RELSE".to_string(), position_bearing_distance(52.0, 2.0, 270.0, 20.0));
+        fixes.insert("ARRIV".to_string(), position_bearing_distance(52.0, 2.0, 270.0, 100.0));
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), fixes);
+        let mut events = simulator.subscribe();
+
+        let mut aircraft = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "4521".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "ENTRY DCT RELSE DCT ARRIV".to_string(),
+            (52.0, 2.0), 270.0, 24000, 400,
+        );
+        aircraft.controller = Some("LON_E_CTR".to_string());
+        simulator.aircraft.push(aircraft);
+
+        // Still with the first controller until the release fix is passed
+        let release_index = simulator.aircraft[0].route_fixes.iter().position(|f| f == "RELSE").unwrap();
+        let mut messages = Vec::new();
+        for _ in 0..600 {
+            messages = simulator.hand_off_at_release_points();
+            if !messages.is_empty() {
+                break;
+            }
+            simulator.update_aircraft(1.0);
+        }
+        assert!(simulator.aircraft[0].current_fix_index > release_index);
+
+        assert!(messages.contains(&TrackMessage::Handoff {
+            from: "LON_E_CTR".to_string(),
+            to: "LTC_E_CTR".to_string(),
+            aircraft: "KLM123".to_string(),
+        }), "{:?}", messages);
+        assert!(messages.iter().any(|m| m.to_string() == "$HOLON_E_CTR:LTC_E_CTR:KLM123"));
+        assert_eq!(events.try_recv()?, SimulatorEvent::HandoffOffered {
+            callsign: "KLM123".to_string(),
+            from: "LON_E_CTR".to_string(),
+            to: "LTC_E_CTR".to_string(),
+        });
+
+        // LTC_E_CTR isn't an AI controller, so the aircraft waits for it to accept
+        simulator.complete_handoffs(&messages);
+        assert_eq!(simulator.aircraft[0].controller.as_deref(), Some("LON_E_CTR"));
+
+        // Handed off only once
+        simulator.update_aircraft(1.0);
+        assert!(simulator.hand_off_at_release_points().is_empty());
+
+        simulator.complete_handoffs(&[TrackMessage::parse("$HALTC_E_CTR:LON_E_CTR:KLM123").unwrap()]);
+        assert_eq!(simulator.aircraft[0].controller.as_deref(), Some("LTC_E_CTR"));
+        assert_eq!(simulator.aircraft[0].handoff_to, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_conflict_predicted_at_shared_fix() -> Result<()> {
+        use crate::utils::navigation::position_bearing_distance;
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .build();
+
+        // SHARD is 20nm ahead of both
+        let mut fixes = FixDatabase::new();
+        fixes.insert("SHARD".to_string(), (51.5, -0.1));
+        fixes.insert("NORTH".to_string(), position_bearing_distance(51.5, -0.1, 0.0, 40.0));
+        fixes.insert("WEST".to_string(), position_bearing_distance(51.5, -0.1, 270.0, 20.0));
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), fixes);
+        let mut events = simulator.subscribe();
+
+        // Departure climbing through 6000ft towards FL150, 4 minutes from SHARD at 2000fpm
+        let mut departure = Aircraft::new_departure(
+            "EZY12AB".to_string(), "A320".to_string(), "4521".to_string(),
+            "EGKK".to_string(), "EGPH".to_string(), "SHARD DCT NORTH".to_string(),
+            35000, "26L".to_string(), position_bearing_distance(51.5, -0.1, 180.0, 20.0), 0.0,
+        );
+        departure.route_fixes = vec!["SHARD".to_string(), "NORTH".to_string()];
+        departure.phase = FlightPhase::Climbing;
+        departure.altitude = 6000;
+        departure.target_altitude = 15000;
+        departure.ground_speed = 300;
+
+        // Arrival level at FL140 through SHARD, also 4 minutes away
+        let mut arrival = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "2201".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "SHARD DCT WEST".to_string(),
+            position_bearing_distance(51.5, -0.1, 90.0, 20.0), 270.0, 14000, 300,
+        );
+        arrival.cleared_altitude = Some(14000);
+        simulator.aircraft.push(departure);
+        simulator.aircraft.push(arrival);
+
+        simulator.predict_conflicts();
+        assert_eq!(events.try_recv()?, SimulatorEvent::ConflictPredicted {
+            departure: "EZY12AB".to_string(),
+            arrival: "KLM123".to_string(),
+            fix: "SHARD".to_string(),
+        });
+
+        // Reported once only
+        simulator.predict_conflicts();
+        assert!(events.try_recv().is_err());
+
+        // Stopping the departure below the arrival resolves it
+        simulator.reported_conflicts.clear();
+        simulator.aircraft[0].target_altitude = 12000;
+        simulator.predict_conflicts();
+        assert!(events.try_recv().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_starless_arrival_at_field() -> Result<()> {
+        use crate::utils::navigation::position_bearing_distance;
+
+        for mode in [StarlessArrivals::Remove, StarlessArrivals::StraightIn] {
+            let scenario = ScenarioBuilder::new()
+                .add_aerodrome("EGLL".to_string(), "27R".to_string())
+                .build();
+
+            // Route ends 3nm north of Heathrow, with no STAR
+            let mut fixes = FixDatabase::new();
+            fixes.insert("ENTRY".to_string(), position_bearing_distance(51.47, -0.45, 30.0, 30.0));
+            fixes.insert("FIELD".to_string(), position_bearing_distance(51.47, -0.45, 0.0, 3.0));
+            let sim_config = SimulationConfig {
+                starless_arrivals: mode,
+                ..SimulationConfig::default()
+            };
+            let mut simulator = ghost_simulator(scenario, sim_config, fixes);
+
+            let aircraft = Aircraft::new_enroute(
+                "KLM123".to_string(), "B738".to_string(), "4521".to_string(),
+                "EHAM".to_string(), "EGLL".to_string(), "ENTRY DCT FIELD".to_string(),
+                position_bearing_distance(51.47, -0.45, 30.0, 30.0), 210.0, 12000, 280,
+            );
+            assert!(!aircraft.has_star());
+            simulator.used_callsigns.insert(aircraft.callsign.clone());
+            simulator.aircraft.push(aircraft);
+
+            let mut last_seen = None;
+            let mut removed = Vec::new();
+            for _ in 0..3600 {
+                removed = simulator.update_aircraft(1.0);
+                match simulator.aircraft.first() {
+                    Some(aircraft) => last_seen = Some((aircraft.phase.clone(), aircraft.altitude)),
+                    None => break,
+                }
+            }
+            assert_eq!(removed, vec!["KLM123".to_string()], "{:?}: never removed", mode);
+            assert!(!simulator.used_callsigns.contains("KLM123"));
+
+            let (phase, altitude) = last_seen.unwrap();
+            match mode {
+                // Leaves the sim overhead the field, still at its level
+                StarlessArrivals::Remove => assert_eq!(altitude, 12000),
+                // Flies the ILS down to the runway
+                StarlessArrivals::StraightIn => {
+                    assert_eq!(phase, FlightPhase::Landing);
+                    assert!(altitude < 500, "landed from {} ft", altitude);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_starless_transit_descends_towards_field() -> Result<()> {
+        use crate::utils::navigation::{haversine_nm, position_bearing_distance};
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .build();
+        let heathrow = (51.47, -0.45);
+        let mut fixes = FixDatabase::new();
+        fixes.insert("EGLL".to_string(), heathrow);
+        fixes.insert("ENTRY".to_string(), position_bearing_distance(heathrow.0, heathrow.1, 60.0, 100.0));
+        fixes.insert("FIELD".to_string(), position_bearing_distance(heathrow.0, heathrow.1, 60.0, 5.0));
+        let sim_config = SimulationConfig {
+            starless_arrivals: StarlessArrivals::StraightIn,
+            ..SimulationConfig::default()
+        };
+        let mut simulator = ghost_simulator(scenario, sim_config, fixes);
+
+        let aircraft = Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "4521".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "ENTRY DCT FIELD".to_string(),
+            position_bearing_distance(heathrow.0, heathrow.1, 60.0, 100.0), 240.0, 25000, 280,
+        );
+        simulator.add_aircraft(aircraft).await?;
+        assert_eq!(simulator.aircraft[0].destination, Some(heathrow));
+
+        // Level until 3nm per 1000ft to lose from the field, then down towards 7000ft
+        let mut altitudes = Vec::new();
+        let mut top_of_descent = None;
+        for _ in 0..15 * 60 {
+            simulator.update_aircraft(1.0);
+            let aircraft = &simulator.aircraft[0];
+            if top_of_descent.is_none() && aircraft.phase == FlightPhase::Descending {
+                top_of_descent = Some(haversine_nm(aircraft.latitude, aircraft.longitude, heathrow.0, heathrow.1));
+            }
+            altitudes.push(aircraft.altitude);
+        }
+
+        let tod_nm = top_of_descent.expect("never started descending");
+        assert!((tod_nm - 54.0).abs() < 2.0, "descended from {:.1}nm", tod_nm);
+        let minutes: Vec<i32> = altitudes.iter().step_by(60).copied().collect();
+        assert_eq!(minutes[0], 25000);
+        assert!(minutes.windows(2).all(|w| w[1] <= w[0]));
+        assert!(*minutes.last().unwrap() < 15000, "{:?}", minutes);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_squawks_recycled() -> Result<()> {
+        use std::collections::HashSet;
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .build();
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), FixDatabase::new());
+        simulator.squawk_pool = vec![201, 202, 203];
+        let pool: HashSet<&str> = HashSet::from(["0201", "0202", "0203"]);
+
+        // Ten departures through a pool of three codes, retiring the oldest to make room
+        for n in 0..10 {
+            if simulator.aircraft.len() == 3 {
+                let oldest = &mut simulator.aircraft[0];
+                oldest.current_fix_index = oldest.route_fixes.len();
+                simulator.update_aircraft(1.0);
+            }
+            simulator.spawn_departure_as("EGKK", "EHAM", "5110N00030W", format!("EZY{}", n), "A320".to_string()).await?;
+
+            let active: Vec<&str> = simulator.aircraft.iter().map(|a| a.squawk.as_str()).collect();
+            let unique: HashSet<&str> = active.iter().copied().collect();
+            assert_eq!(unique.len(), active.len(), "duplicate codes: {:?}", active);
+            assert!(unique.is_subset(&pool), "{:?}", active);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_signal_ends_run() -> Result<()> {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .build();
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), FixDatabase::new());
+        simulator.initialize().await?;
+
+        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let _ = shutdown_tx.send(());
+        });
+        tokio::time::timeout(Duration::from_secs(2), simulator.run(shutdown_rx)).await??;
+        assert!(!simulator.statistics().running);
+
+        simulator.stop().await?;
+        assert!(simulator.pilot_clients.is_empty() && simulator.ai_controllers.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_of_new_departure() -> Result<()> {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGKK".to_string(), "26L".to_string())
+            .build();
+        let path = std::env::temp_dir().join(format!("sweatbox-snapshots-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), FixDatabase::new()).with_snapshot_log(path)?;
+
+        simulator.spawn_departure_as("EGKK", "EHAM", "5110N00030W", "EZY12AB".to_string(), "A320".to_string()).await?;
+        let snapshot = simulator.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].callsign, "EZY12AB");
+        assert_eq!(snapshot[0].altitude, 0);
+        assert_eq!((snapshot[0].mode.as_str(), snapshot[0].phase.as_str()), ("FlightPlan", "OnGround"));
+
+        simulator.log_snapshots(0.2);
+        simulator.close_snapshot_log();
+        let contents = std::fs::read_to_string(path)?;
+        std::fs::remove_file(path)?;
+        let line: serde_json::Value = serde_json::from_str(contents.trim())?;
+        assert_eq!(line["time"], 0.2);
+        assert_eq!(line["aircraft"][0]["callsign"], "EZY12AB");
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_squawks_valid() {
+        use crate::config::{is_valid_squawk, RESERVED_SQUAWKS};
+
+        let mut simulator = ghost_simulator(ScenarioBuilder::new().build(), SimulationConfig::default(), FixDatabase::new());
+        // Emergency and non-octal codes in the pool are passed over, then it runs dry
+        simulator.squawk_pool = vec![7700, 6789];
+
+        for _ in 0..2000 {
+            let squawk: u16 = simulator.assign_squawk().parse().unwrap();
+            assert!(is_valid_squawk(squawk), "{}", squawk);
+            assert!(!RESERVED_SQUAWKS.contains(&squawk), "{}", squawk);
+        }
+    }
+
+    #[test]
+    fn test_cruise_altitude_from_route() {
+        assert_eq!(Simulator::get_cruise_altitude("LAM2X/26L LAM UL9 FL350"), 350);
+        assert_eq!(Simulator::get_cruise_altitude("BPK5K/09L N0450F370 DVR UL9 KONAN"), 370);
+        assert_eq!(Simulator::get_cruise_altitude("DVR/N0450F310 KONAN F390"), 310);
+        assert_eq!(Simulator::get_cruise_altitude("KONAN K0830S1130 REDFA"), 371);
+        assert_eq!(Simulator::get_cruise_altitude("SAM A050 GWC"), 50);
+
+        // Nothing filed
+        assert_eq!(Simulator::get_cruise_altitude("LAM2X/26L LAM L10 BPK"), 360);
+    }
+
+    #[tokio::test]
+    async fn test_departure_lines_up_at_threshold() -> Result<()> {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .build();
+        let field = (51.4775, -0.4614);
+        let mut fixes = FixDatabase::new();
+        fixes.insert("EGLL".to_string(), field);
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), fixes);
+
+        simulator.spawn_departure_as("EGLL", "EHAM", "DCT", "BAW01T".to_string(), "A320".to_string()).await?;
+        let aircraft = &simulator.aircraft[0];
+
+        // The eastern end of the northern runway, pointing down it
+        assert!((aircraft.latitude - 51.4777).abs() < 0.001, "{}", aircraft.latitude);
+        assert!((aircraft.longitude + 0.4333).abs() < 0.001, "{}", aircraft.longitude);
+        assert!(haversine_nm(field.0, field.1, aircraft.latitude, aircraft.longitude) > 1.0);
+        assert!((aircraft.heading - 269.7).abs() < 0.5, "{}", aircraft.heading);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_flight_returns_for_approach() -> Result<()> {
+        use crate::utils::navigation::position_bearing_distance;
+
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .build();
+
+        // The BPK7F fixes around Heathrow, lifting off at the 09L threshold
+        let field = (51.4775, -0.4614);
+        let mut fixes = FixDatabase::new();
+        fixes.insert("EGLL".to_string(), field);
+        fixes.insert("RW09L".to_string(), (51.4775, -0.4849));
+        for (fix, bearing, distance) in [
+            ("D268D", 268.0, 4.0), ("D278F", 278.0, 6.0), ("D304H", 304.0, 8.0),
+            ("D325H", 325.0, 8.0), ("CHT", 340.0, 14.0), ("BPK", 40.0, 18.0),
+        ] {
+            fixes.insert(fix.to_string(), position_bearing_distance(field.0, field.1, bearing, distance));
+        }
+        let sim_config = SimulationConfig::default();
+        let mut simulator = ghost_simulator(scenario, sim_config.clone(), fixes);
+
+        simulator.spawn_departure_as("EGLL", "EGLL", "BPK7F/27R BPK", "BAW01T".to_string(), "A320".to_string()).await?;
+        let aircraft = &simulator.aircraft[0];
+        assert!(aircraft.is_local_flight());
+        assert_eq!(aircraft.flight_plan.cruise_altitude_feet(), sim_config.local_flight_altitude);
+        assert_eq!(aircraft.route_fixes.first().map(String::as_str), Some("RW09L"));
+
+        let mut highest = 0;
+        let mut established = false;
+        let mut last_phase = None;
+        for _ in 0..3600 {
+            let removed = simulator.update_aircraft(1.0);
+            if !removed.is_empty() {
+                assert_eq!(removed, vec!["BAW01T".to_string()]);
+                break;
+            }
+            let aircraft = &simulator.aircraft[0];
+            highest = highest.max(aircraft.altitude);
+            established |= matches!(&aircraft.nav_mode, NavMode::Ils(localizer) if localizer.established);
+            last_phase = Some(aircraft.phase.clone());
+        }
+
+        // Flew the SID and route without an enroute climb, then the ILS back to the field
+        assert!(simulator.aircraft.is_empty(), "never landed");
+        assert!(highest <= sim_config.local_flight_altitude, "climbed to {}ft", highest);
+        assert!(established);
+        assert_eq!(last_phase, Some(FlightPhase::Landing));
+        Ok(())
+    }
+
+    #[test]
+    fn test_atc_commands_dispatched() -> Result<()> {
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("LAM".to_string(), (51.646, 0.151));
+        let mut simulator = ghost_simulator(ScenarioBuilder::new().build(), SimulationConfig::default(), fix_db);
+        simulator.aircraft.push(Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "2201".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "REDFA DCT BARMI".to_string(),
+            (51.8, 0.5), 250.0, 12000, 300,
+        ));
+
+        simulator.command("KLM123 FH 180")?;
+        assert_eq!(simulator.aircraft[0].nav_mode, NavMode::Heading(180.0));
+        simulator.command("klm123 c 8000")?;
+        assert_eq!(simulator.aircraft[0].cleared_altitude, Some(8000));
+        assert_eq!(simulator.aircraft[0].phase, FlightPhase::Descending);
+        simulator.command("KLM123 SPD 220")?;
+        assert_eq!(simulator.aircraft[0].assigned_speed, Some(220));
+        simulator.command("KLM123 HOLD LAM")?;
+        assert!(matches!(&simulator.aircraft[0].nav_mode, NavMode::Hold(hold) if hold.fix == "LAM"));
+        simulator.command("KLM123 HOLD 5130N00030W")?;
+        assert!(matches!(&simulator.aircraft[0].nav_mode, NavMode::Hold(hold) if hold.fix == "5130N00030W"));
+        simulator.command("KLM123 RESUME")?;
+        assert_eq!(simulator.aircraft[0].nav_mode, NavMode::FlightPlan);
+        simulator.command("KLM123 ILS 27R")?;
+        assert!(matches!(&simulator.aircraft[0].nav_mode, NavMode::Ils(localizer) if localizer.runway == "27R"));
+        simulator.aircraft[0].squawking_assigned = false;
+        simulator.command("KLM123 SQ")?;
+        assert!(simulator.aircraft[0].squawking_assigned);
+
+        assert!(simulator.command("BAW1 FH 180").is_err());
+        assert!(simulator.command("FH 180").is_err());
+        assert!(simulator.command("KLM123 FLY AWAY").is_err());
+        assert!(simulator.command("KLM123 ILS 36").is_err());
+        assert!(simulator.command("KLM123 HOLD NOWHR").is_err());
+
+        // Mach only at high level: unable at 12000ft, taken at FL350
+        let unable = simulator.command("KLM123 M 078").unwrap_err();
+        assert!(unable.to_string().contains("unable Mach 0.78"), "{}", unable);
+        simulator.aircraft[0].altitude = 35000;
+        simulator.command("KLM123 MACH .80")?;
+        assert_eq!(simulator.aircraft[0].assigned_mach, Some(0.8));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commands_applied_while_running() -> Result<()> {
+        let (commands, command_input) = tokio::sync::mpsc::unbounded_channel();
+        let mut simulator = ghost_simulator(ScenarioBuilder::new().build(), SimulationConfig::default(), FixDatabase::new())
+            .with_command_input(command_input);
+        simulator.aircraft.push(Aircraft::new_enroute(
+            "KLM123".to_string(), "B738".to_string(), "2201".to_string(),
+            "EHAM".to_string(), "EGLL".to_string(), "REDFA DCT BARMI".to_string(),
+            (51.8, 0.5), 250.0, 12000, 300,
+        ));
+
+        commands.send("KLM123 FH 180".to_string())?;
+        commands.send("BAW1 C 5000".to_string())?;
+        commands.send("nonsense".to_string())?;
+        commands.send("KLM123 C 8000".to_string())?;
+        drop(commands);
+
+        // Input closing doesn't stop the simulation; the shutdown signal does
+        let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let _ = shutdown_tx.send(());
+        });
+        tokio::time::timeout(Duration::from_secs(5), simulator.run(shutdown_rx)).await??;
+
+        assert!(matches!(simulator.aircraft[0].nav_mode, NavMode::Heading(_)));
+        assert_eq!(simulator.aircraft[0].cleared_altitude, Some(8000));
+        assert!(simulator.command_input.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_aircraft_type_from_callsign_airline() -> Result<()> {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGNM".to_string(), "32".to_string())
+            .build();
+        let mut simulator = ghost_simulator(scenario, SimulationConfig::default(), FixDatabase::new());
+        simulator.fleet_config = Arc::new(FleetConfig {
+            airlines: HashMap::from([
+                ("RYR".to_string(), vec!["B738".to_string()]),
+                ("EZY".to_string(), vec!["A319".to_string()]),
+            ]),
+            airports: HashMap::from([("EGLL".to_string(), vec!["RYR".to_string(), "EZY".to_string()])]),
+            airport_weights: HashMap::new(),
+            airline_weights: HashMap::new(),
+        });
+
+        // Each aircraft flies a type from its own operator's fleet
+        for _ in 0..20 {
+            let aircraft = simulator.create_ils_arrival("EGLL", "EGNM", "DCT")?;
+            let expected = if aircraft.callsign.starts_with("RYR") { "B738" } else { "A319" };
+            assert_eq!(aircraft.aircraft_type, expected, "{}", aircraft.callsign);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_glideslope_to_field_elevation() -> Result<()> {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGNM".to_string(), "32".to_string())
+            .build();
+        let sim_config = SimulationConfig {
+            ils_spawn_range_nm: 6.0,
+            ils_spawn_altitude: 2500,
+            ..SimulationConfig::default()
+        };
+        let mut simulator = ghost_simulator(scenario, sim_config.clone(), FixDatabase::new());
+
+        // Leeds Bradford is 681ft above sea level
+        let mut aircraft = simulator.create_ils_arrival("EGLL", "EGNM", "DCT")?;
+        let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+            panic!("not in ILS mode: {:?}", aircraft.nav_mode);
+        };
+        assert_eq!(localizer.elevation, 681);
+        let localizer = localizer.clone();
+
+        let mut lowest = aircraft.altitude;
+        while aircraft.phase != FlightPhase::Landing {
+            aircraft.update(1.0, &FixDatabase::new(), &sim_config);
+            lowest = lowest.min(aircraft.altitude);
+            assert!(aircraft.flight_time < 600.0, "never landed");
+        }
+
+        // On the glideslope down to the runway, never below the field
+        let (along, _) = localizer.offsets_nm(aircraft.latitude, aircraft.longitude);
+        assert!(along < 0.2);
+        assert!((681..=681 + 70).contains(&lowest), "lowest {}ft", lowest);
+        Ok(())
+    }
+
+    #[test]
+    fn test_spawn_established_on_ils() -> Result<()> {
+        let scenario = ScenarioBuilder::new()
+            .add_aerodrome("EGLL".to_string(), "27R".to_string())
+            .build();
+        let sim_config = SimulationConfig {
+            ils_arrivals: true,
+            ils_spawn_range_nm: 8.0,
+            ils_spawn_altitude: 2500,
+            ..SimulationConfig::default()
+        };
+        let mut simulator = ghost_simulator(scenario, sim_config.clone(), FixDatabase::new());
+
+        let mut aircraft = simulator.create_ils_arrival("EHAM", "EGLL", "REDFA DCT BARMI BARMI1H")?;
+        println!("{}", aircraft);
+
+        assert_eq!(aircraft.phase, FlightPhase::Approach);
+        assert_eq!(aircraft.altitude, 2500);
+        let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+            panic!("not in ILS mode: {:?}", aircraft.nav_mode);
+        };
+        assert!(localizer.established);
+        assert_eq!(localizer.runway, "27R");
+
+        let (along, offset) = localizer.offsets_nm(aircraft.latitude, aircraft.longitude);
+        assert!((along - 8.0).abs() < 0.05, "{:.2}nm from the threshold", along);
+        assert!(offset.abs() < 0.05, "{:.2}nm off the centerline", offset);
+
+        // Tracks the localizer down the glideslope
+        for _ in 0..60 {
+            aircraft.update(1.0, &FixDatabase::new(), &sim_config);
+        }
+        let NavMode::Ils(localizer) = &aircraft.nav_mode else {
+            panic!("left the ILS");
+        };
+        let (along, offset) = localizer.offsets_nm(aircraft.latitude, aircraft.longitude);
+        assert!(offset.abs() < 0.1, "{:.2}nm off the centerline", offset);
+        // Captured the glideslope from below and is descending on it
+        assert!(aircraft.altitude < 2500);
+        assert!((aircraft.altitude - localizer.glideslope_altitude(along)).abs() < 50,
+                "{}ft at {:.1}nm", aircraft.altitude, along);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::aircraft::{Aircraft, FlightPhase, NavMode};
+use crate::config::SimulationConfig;
+use crate::utils::navigation::haversine_nm;
+use crate::utils::wake::WakeCategory;
+
+/// Knots below the aircraft ahead that a trailing aircraft slows to while too close
+const CLOSING_MARGIN_KT: u32 = 10;
+
+/// Keep arrivals established on the same ILS apart: an aircraft closer to the one ahead
+/// than the arrival spacing slows below its speed, but no slower than its Vref plus
+/// additive, and goes back to its approach schedule once the gap has opened
+pub fn space_final_approach(aircraft: &mut [Aircraft], sim_config: &SimulationConfig) {
+    // Aircraft on each final, by distance from the threshold
+    let mut finals: HashMap<(String, String), Vec<(f64, usize)>> = HashMap::new();
+    for (index, a) in aircraft.iter_mut().enumerate() {
+        a.spacing_speed = None;
+        let NavMode::Ils(localizer) = &a.nav_mode else {
+            continue;
+        };
+        if a.phase != FlightPhase::Approach {
+            continue;
+        }
+        let (along_nm, _) = localizer.offsets_nm(a.latitude, a.longitude);
+        finals.entry((a.flight_plan.arrival.clone(), localizer.runway.clone()))
+            .or_default()
+            .push((along_nm, index));
+    }
+
+    for sequence in finals.values_mut() {
+        sequence.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for pair in sequence.windows(2) {
+            let (leader, follower) = (&aircraft[pair[0].1], &aircraft[pair[1].1]);
+            let gap_nm = haversine_nm(leader.latitude, leader.longitude, follower.latitude, follower.longitude);
+            let required_nm = sim_config.arrival_spacing(&leader.aircraft_type, &follower.aircraft_type);
+            if gap_nm >= required_nm {
+                continue;
+            }
+
+            let category = WakeCategory::for_type(&follower.aircraft_type);
+            let schedule = sim_config.approach_schedules.get(&category).cloned().unwrap_or_default();
+            let speed = leader.airspeed
+                .saturating_sub(CLOSING_MARGIN_KT)
+                .max(schedule.vref + schedule.additive);
+            tracing::debug!("[{}] {:.1}nm behind {}, slowing to {}kt", follower.callsign, gap_nm, leader.callsign, speed);
+            aircraft[pair[1].1].spacing_speed = Some(speed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aircraft::{ApproachSchedule, Localizer};
+    use crate::utils::navigation::{position_bearing_distance, FixDatabase};
+
+    #[test]
+    fn test_trailing_arrival_slowed() {
+        let sim_config = SimulationConfig {
+            approach_spacing: true,
+            arrival_spacing_nm: 5.0,
+            ..SimulationConfig::default()
+        };
+        let localizer = Localizer {
+            runway: "27R".to_string(),
+            course: 270.0,
+            threshold: (51.4775, -0.4333),
+            elevation: 83,
+            established: true,
+            join_nm: None,
+            intercept_heading: None,
+        };
+
+        // 3nm apart on final, both at 180kt on the glideslope
+        let mut aircraft: Vec<Aircraft> = [("BAW1", 5.0), ("BAW2", 8.0)]
+            .into_iter()
+            .map(|(callsign, distance_nm)| {
+                let position = position_bearing_distance(51.4775, -0.4333, 90.0, distance_nm);
+                let mut a = Aircraft::new_enroute(
+                    callsign.to_string(), "A320".to_string(), "1234".to_string(),
+                    "EGKK".to_string(), "EGLL".to_string(), "DCT".to_string(),
+                    position, 270.0, localizer.glideslope_altitude(distance_nm), 180,
+                );
+                a.clear_ils(localizer.clone());
+                a.phase = FlightPhase::Approach;
+                a
+            })
+            .collect();
+
+        for _ in 0..20 {
+            space_final_approach(&mut aircraft, &sim_config);
+            for a in &mut aircraft {
+                a.update(1.0, &FixDatabase::new(), &sim_config);
+            }
+        }
+
+        let (leader, trailer) = (&aircraft[0], &aircraft[1]);
+        assert_eq!(leader.spacing_speed, None);
+        let slowed_to = trailer.spacing_speed.expect("trailing aircraft not slowed");
+        assert!(slowed_to < leader.airspeed, "{}kt behind {}kt", slowed_to, leader.airspeed);
+        assert!(trailer.airspeed < 180, "{}kt", trailer.airspeed);
+        assert!(trailer.airspeed >= ApproachSchedule::default().vref, "{}kt", trailer.airspeed);
+    }
+}
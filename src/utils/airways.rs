@@ -0,0 +1,93 @@
+//! ATS route (airway) definitions
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use anyhow::{Result, Context};
+use serde::Deserialize;
+
+/// Airway designator to its fixes, in published order
+pub type AirwayDatabase = HashMap<String, Vec<String>>;
+
+pub const DEFAULT_AIRWAYS_FILE: &str = "data/ATS Routes/ats.json";
+
+#[derive(Debug, Deserialize)]
+struct AirwayEntry {
+    waypoints: Vec<AirwayWaypoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AirwayWaypoint {
+    name: String,
+}
+
+/// Load airways from an ats.json file
+pub fn load_airways<P: AsRef<Path>>(path: P) -> Result<AirwayDatabase> {
+    let content = fs::read_to_string(path.as_ref())
+        .with_context(|| format!("Failed to read airways file: {:?}", path.as_ref()))?;
+
+    // The file is written as a Python literal, so swap its quotes to make it JSON
+    let json = content.replace('\'', "\"");
+    let entries: HashMap<String, AirwayEntry> = serde_json::from_str(&json)
+        .with_context(|| format!("Failed to parse airways file: {:?}", path.as_ref()))?;
+
+    Ok(entries
+        .into_iter()
+        .map(|(name, entry)| (name, entry.waypoints.into_iter().map(|w| w.name).collect()))
+        .collect())
+}
+
+/// Airways from the default data file, loaded on first use (empty if it can't be read)
+pub fn default_airways() -> &'static AirwayDatabase {
+    static AIRWAYS: OnceLock<AirwayDatabase> = OnceLock::new();
+    AIRWAYS.get_or_init(|| {
+        load_airways(DEFAULT_AIRWAYS_FILE).unwrap_or_else(|e| {
+            tracing::warn!("[AIRWAYS] {}", e);
+            AirwayDatabase::new()
+        })
+    })
+}
+
+/// Fixes strictly between `from` and `to` along an airway, in flying order,
+/// or `None` if either fix isn't on it
+pub fn airway_segment(airway: &[String], from: &str, to: &str) -> Option<Vec<String>> {
+    let start = airway.iter().position(|f| f == from)?;
+    let end = airway.iter().position(|f| f == to)?;
+
+    if start == end {
+        return Some(Vec::new());
+    }
+
+    let mut between = airway[start.min(end) + 1..start.max(end)].to_vec();
+    if start > end {
+        between.reverse();
+    }
+    Some(between)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_airways() -> Result<()> {
+        let airways = load_airways(DEFAULT_AIRWAYS_FILE)?;
+
+        let ul9 = airways.get("UL9").expect("UL9 missing");
+        assert_eq!(ul9.first().map(|s| s.as_str()), Some("KONAN"));
+        assert!(ul9.contains(&"DVR".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_airway_segment() {
+        let airway: Vec<String> = ["BRAIN", "DAGGA", "CLN", "RATLO", "PIXAM"]
+            .iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(airway_segment(&airway, "BRAIN", "RATLO"), Some(vec!["DAGGA".to_string(), "CLN".to_string()]));
+        assert_eq!(airway_segment(&airway, "RATLO", "BRAIN"), Some(vec!["CLN".to_string(), "DAGGA".to_string()]));
+        assert_eq!(airway_segment(&airway, "CLN", "RATLO"), Some(vec![]));
+        assert_eq!(airway_segment(&airway, "CLN", "LAM"), None);
+    }
+}
@@ -0,0 +1,111 @@
+//! ISA standard atmosphere: speed of sound and Mach/airspeed conversions
+
+/// Speed of sound at sea level (kt)
+const SEA_LEVEL_SPEED_OF_SOUND: f64 = 661.47;
+/// Sea level temperature (K)
+const SEA_LEVEL_TEMPERATURE: f64 = 288.15;
+/// Tropopause altitude (feet), above which the temperature is constant
+const TROPOPAUSE_FT: f64 = 36089.0;
+
+/// Standard sea level pressure (hPa)
+pub const STANDARD_QNH: f64 = 1013.25;
+
+/// ISA temperature (K) at a pressure altitude (feet)
+fn temperature(altitude_ft: f64) -> f64 {
+    SEA_LEVEL_TEMPERATURE - 0.0019812 * altitude_ft.min(TROPOPAUSE_FT)
+}
+
+/// ISA pressure as a fraction of sea level pressure
+fn pressure_ratio(altitude_ft: f64) -> f64 {
+    if altitude_ft <= TROPOPAUSE_FT {
+        (temperature(altitude_ft) / SEA_LEVEL_TEMPERATURE).powf(5.25588)
+    } else {
+        0.22336 * (-(altitude_ft - TROPOPAUSE_FT) / 20806.0).exp()
+    }
+}
+
+/// Speed of sound (kt) at a pressure altitude (feet)
+pub fn speed_of_sound_kt(altitude_ft: f64) -> f64 {
+    SEA_LEVEL_SPEED_OF_SOUND * (temperature(altitude_ft) / SEA_LEVEL_TEMPERATURE).sqrt()
+}
+
+/// True airspeed (kt) for a Mach number at altitude
+pub fn mach_to_tas(mach: f64, altitude_ft: f64) -> f64 {
+    mach * speed_of_sound_kt(altitude_ft)
+}
+
+/// Mach number for a true airspeed (kt) at altitude
+pub fn tas_to_mach(tas: f64, altitude_ft: f64) -> f64 {
+    tas / speed_of_sound_kt(altitude_ft)
+}
+
+/// Indicated (calibrated) airspeed (kt) for a Mach number at altitude
+pub fn mach_to_ias(mach: f64, altitude_ft: f64) -> f64 {
+    // Impact pressure relative to sea level static pressure
+    let qc = pressure_ratio(altitude_ft) * ((1.0 + 0.2 * mach * mach).powf(3.5) - 1.0);
+    SEA_LEVEL_SPEED_OF_SOUND * (5.0 * ((qc + 1.0).powf(2.0 / 7.0) - 1.0)).sqrt()
+}
+
+/// Mach number for an indicated (calibrated) airspeed (kt) at altitude
+pub fn ias_to_mach(ias: f64, altitude_ft: f64) -> f64 {
+    let ratio = ias / SEA_LEVEL_SPEED_OF_SOUND;
+    let qc = (1.0 + 0.2 * ratio * ratio).powf(3.5) - 1.0;
+    (5.0 * ((qc / pressure_ratio(altitude_ft) + 1.0).powf(2.0 / 7.0) - 1.0)).sqrt()
+}
+
+/// Crossover altitude (feet) at which an indicated airspeed (kt) and a Mach number
+/// are the same speed: above it the Mach number is the lower of the two
+pub fn crossover_altitude(ias: f64, mach: f64) -> f64 {
+    let (mut low, mut high) = (0.0, 60000.0);
+    for _ in 0..50 {
+        let mid = (low + high) / 2.0;
+        if ias_to_mach(ias, mid) < mach {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// Pressure altitude (feet) at mean sea level when the QNH is `qnh` hPa, which is
+/// what an altimeter set to standard reads above the aircraft's true altitude
+pub fn pressure_altitude_offset(qnh: f64) -> f64 {
+    145366.45 * (1.0 - (qnh / STANDARD_QNH).powf(0.190284))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mach_conversions() {
+        // Sea level: Mach and IAS line up with TAS
+        assert!((mach_to_ias(0.5, 0.0) - mach_to_tas(0.5, 0.0)).abs() < 0.01);
+
+        // FL360: speed of sound about 573kt, M0.80 is about 459kt TAS, 266kt IAS
+        assert!((speed_of_sound_kt(36000.0) - 573.6).abs() < 0.5);
+        assert!((mach_to_tas(0.80, 36000.0) - 459.0).abs() < 1.0);
+        assert!((mach_to_ias(0.80, 36000.0) - 266.0).abs() < 1.0);
+
+        // Round trips
+        assert!((ias_to_mach(mach_to_ias(0.78, 30000.0), 30000.0) - 0.78).abs() < 1e-9);
+        assert!((tas_to_mach(mach_to_tas(0.82, 39000.0), 39000.0) - 0.82).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pressure_altitude_offset() {
+        assert!(pressure_altitude_offset(STANDARD_QNH).abs() < 0.01);
+        // About 27ft per hPa near the surface: higher than true altitude on a low QNH
+        assert!((pressure_altitude_offset(1002.0) - 308.0).abs() < 2.0);
+        assert!((pressure_altitude_offset(1030.0) + 455.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn test_crossover_altitude() {
+        // 290kt/M0.78 cross over at about FL310
+        let crossover = crossover_altitude(290.0, 0.78);
+        assert!((30000.0..32000.0).contains(&crossover), "{}ft", crossover);
+        assert!((mach_to_ias(0.78, crossover) - 290.0).abs() < 0.01);
+    }
+}
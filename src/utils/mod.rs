@@ -1,3 +1,8 @@
+pub mod airways;
+pub mod atmosphere;
 pub mod navigation;
 pub mod procedures;
 pub mod performance;
+pub mod registration;
+pub mod wake;
+pub mod wind;
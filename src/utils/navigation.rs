@@ -7,7 +7,101 @@ use anyhow::{Result, Context};
 const EARTH_RADIUS_KM: f64 = 6372.8;
 const EARTH_RADIUS_NM: f64 = 3440.065;
 
-pub type FixDatabase = HashMap<String, (f64, f64)>;
+/// Fix/navaid/airport positions by name.
+///
+/// Names aren't globally unique, so every distinct position loaded for a name
+/// is kept and callers that know roughly where they are can pick the nearest.
+#[derive(Debug, Clone, Default)]
+pub struct FixDatabase {
+    fixes: HashMap<String, Vec<(f64, f64)>>,
+}
+
+impl FixDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a position for a fix, keeping any other positions with the same name
+    pub fn insert(&mut self, name: String, coords: (f64, f64)) {
+        let positions = self.fixes.entry(name).or_default();
+        if !positions.contains(&coords) {
+            positions.push(coords);
+        }
+    }
+
+    /// Position of a fix, preferring the most recently loaded one if the name is duplicated
+    pub fn get(&self, name: &str) -> Option<&(f64, f64)> {
+        self.fixes.get(name).and_then(|positions| positions.last())
+    }
+
+    /// Position of the fix with this name nearest to (lat, lon)
+    pub fn get_nearest(&self, name: &str, lat: f64, lon: f64) -> Option<&(f64, f64)> {
+        self.fixes.get(name)?.iter().min_by(|a, b| {
+            haversine(lat, lon, a.0, a.1).total_cmp(&haversine(lat, lon, b.0, b.1))
+        })
+    }
+
+    /// Position of a route fix nearest `near`, including coordinate waypoints such as
+    /// 5130N00020W, which are placed by their name rather than the database
+    pub fn position(&self, name: &str, near: (f64, f64)) -> Option<(f64, f64)> {
+        self.get_nearest(name, near.0, near.1).copied().or_else(|| parse_coordinate_fix(name))
+    }
+
+    /// Every position loaded for a fix name
+    pub fn candidates(&self, name: &str) -> &[(f64, f64)] {
+        self.fixes.get(name).map(|p| p.as_slice()).unwrap_or(&[])
+    }
+
+    /// Resolve a route's fixes in order, picking each one nearest the previous
+    /// (starting from `start`). Unknown fixes are skipped.
+    pub fn resolve_route(&self, fixes: &[String], start: (f64, f64)) -> Vec<(String, (f64, f64))> {
+        let mut last = start;
+        let mut resolved = Vec::new();
+
+        for fix in fixes {
+            if let Some(coords) = self.position(fix, last) {
+                resolved.push((fix.clone(), coords));
+                last = coords;
+            }
+        }
+
+        resolved
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.fixes.contains_key(name)
+    }
+
+    /// Number of distinct fix names
+    pub fn len(&self) -> usize {
+        self.fixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fixes.is_empty()
+    }
+}
+
+impl Extend<(String, (f64, f64))> for FixDatabase {
+    fn extend<I: IntoIterator<Item = (String, (f64, f64))>>(&mut self, iter: I) {
+        for (name, coords) in iter {
+            self.insert(name, coords);
+        }
+    }
+}
+
+impl IntoIterator for FixDatabase {
+    type Item = (String, (f64, f64));
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.fixes
+            .into_iter()
+            .flat_map(|(name, positions)| positions.into_iter().map(move |p| (name.clone(), p)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
 
 pub fn haversine(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     let dlat = (lat2 - lat1).to_radians();
@@ -27,6 +121,11 @@ pub fn haversine_nm(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
 }
 
 pub fn heading_from_to(from_lat: f64, from_lon: f64, to_lat: f64, to_lon: f64) -> i32 {
+    bearing_from_to(from_lat, from_lon, to_lat, to_lon) as i32
+}
+
+/// Initial great-circle bearing (degrees true, 0..360) without rounding to a whole heading
+pub fn bearing_from_to(from_lat: f64, from_lon: f64, to_lat: f64, to_lon: f64) -> f64 {
     let dlon = to_lon - from_lon;
     let y = dlon.to_radians().sin() * to_lat.to_radians().cos();
     let x = from_lat.to_radians().cos() * to_lat.to_radians().sin()
@@ -35,7 +134,7 @@ pub fn heading_from_to(from_lat: f64, from_lon: f64, to_lat: f64, to_lon: f64) -
             * dlon.to_radians().cos();
 
     let bearing = y.atan2(x).to_degrees();
-    ((bearing + 360.0) % 360.0) as i32
+    (bearing + 360.0) % 360.0
 }
 
 pub fn position_bearing_distance(
@@ -61,13 +160,51 @@ pub fn position_bearing_distance(
     (dest_lat_rad.to_degrees(), dest_lon_rad.to_degrees())
 }
 
+/// Point a fraction (0 to 1) of the way along the great circle from one position to another
+pub fn intermediate_point(lat1: f64, lon1: f64, lat2: f64, lon2: f64, fraction: f64) -> (f64, f64) {
+    let (lat1_rad, lon1_rad) = (lat1.to_radians(), lon1.to_radians());
+    let (lat2_rad, lon2_rad) = (lat2.to_radians(), lon2.to_radians());
+    let angular_distance = haversine_nm(lat1, lon1, lat2, lon2) / EARTH_RADIUS_NM;
+    if angular_distance.sin().abs() < 1e-12 {
+        return (lat1, lon1);
+    }
+
+    let a = ((1.0 - fraction) * angular_distance).sin() / angular_distance.sin();
+    let b = (fraction * angular_distance).sin() / angular_distance.sin();
+    let x = a * lat1_rad.cos() * lon1_rad.cos() + b * lat2_rad.cos() * lon2_rad.cos();
+    let y = a * lat1_rad.cos() * lon1_rad.sin() + b * lat2_rad.cos() * lon2_rad.sin();
+    let z = a * lat1_rad.sin() + b * lat2_rad.sin();
+
+    (z.atan2((x * x + y * y).sqrt()).to_degrees(), y.atan2(x).to_degrees())
+}
+
+/// Distance (NM) of a position from the great circle through a leg's two ends,
+/// positive to the right of the leg's direction
+pub fn cross_track_nm(lat: f64, lon: f64, from: (f64, f64), to: (f64, f64)) -> f64 {
+    let angular_distance = haversine_nm(from.0, from.1, lat, lon) / EARTH_RADIUS_NM;
+    let angle = (bearing_from_to(from.0, from.1, lat, lon) - bearing_from_to(from.0, from.1, to.0, to.1)).to_radians();
+
+    (angular_distance.sin() * angle.sin()).asin() * EARTH_RADIUS_NM
+}
+
+/// Heading that intercepts a leg at `intercept_angle` degrees from whichever side
+/// the position is on
+pub fn intercept_heading(lat: f64, lon: f64, from: (f64, f64), to: (f64, f64), intercept_angle: f64) -> f64 {
+    let course = bearing_from_to(from.0, from.1, to.0, to.1);
+    if cross_track_nm(lat, lon, from, to) > 0.0 {
+        normalize_heading(course - intercept_angle)
+    } else {
+        normalize_heading(course + intercept_angle)
+    }
+}
+
 pub fn delta_position(
     lat: f64,
     tas_knots: f64,
-    heading: i32,
+    heading: f64,
     delta_time_seconds: f64,
 ) -> (f64, f64) {
-    let heading_rad = (heading as f64).to_radians();
+    let heading_rad = heading.to_radians();
     let delta_time_hours = delta_time_seconds / 3600.0;
 
     let delta_lat = (tas_knots * heading_rad.cos() * delta_time_hours) / 60.0;
@@ -77,9 +214,8 @@ pub fn delta_position(
     (delta_lat, delta_lon)
 }
 
-pub fn shortest_turn_direction(current: i32, target: i32) -> TurnDirection {
-    let diff = (target - current + 360) % 360;
-    if diff <= 180 {
+pub fn shortest_turn_direction(current: f64, target: f64) -> TurnDirection {
+    if heading_difference(current, target) >= 0.0 {
         TurnDirection::Right
     } else {
         TurnDirection::Left
@@ -92,9 +228,43 @@ pub enum TurnDirection {
     Right,
 }
 
-/// Normalize heading to 0-359 range
-pub fn normalize_heading(heading: i32) -> i32 {
-    ((heading % 360) + 360) % 360
+/// Normalize heading to the 0-360 range
+pub fn normalize_heading(heading: f64) -> f64 {
+    let heading = heading.rem_euclid(360.0);
+    // rem_euclid can round a tiny negative up to exactly 360
+    if heading >= 360.0 { 0.0 } else { heading }
+}
+
+/// Signed angle (degrees, -180..=180) to turn from `current` onto `target`; positive is right
+pub fn heading_difference(current: f64, target: f64) -> f64 {
+    let diff = normalize_heading(target - current);
+    if diff > 180.0 { diff - 360.0 } else { diff }
+}
+
+/// Regions of roughly constant magnetic variation: (south, north, west, east, variation)
+const VARIATION_REGIONS: [(f64, f64, f64, f64, f64); 1] = [
+    // UK and Ireland
+    (49.0, 61.0, -11.0, 2.0, -2.0),
+];
+
+/// Magnetic variation (degrees, east positive) at a position, from a coarse table
+/// of regional offsets, zero outside them
+pub fn magnetic_variation(lat: f64, lon: f64) -> f64 {
+    VARIATION_REGIONS
+        .iter()
+        .find(|(south, north, west, east, _)| (*south..=*north).contains(&lat) && (*west..=*east).contains(&lon))
+        .map(|region| region.4)
+        .unwrap_or(0.0)
+}
+
+/// Convert a true heading to magnetic at a position
+pub fn true_to_magnetic(heading: f64, lat: f64, lon: f64) -> f64 {
+    normalize_heading(heading - magnetic_variation(lat, lon))
+}
+
+/// Convert a magnetic heading to true at a position
+pub fn magnetic_to_true(heading: f64, lat: f64, lon: f64) -> f64 {
+    normalize_heading(heading + magnetic_variation(lat, lon))
 }
 
 /// Convert sector file coordinates to decimal degrees
@@ -128,12 +298,53 @@ pub fn sf_coords_to_decimal(lat: &str, lon: &str) -> Result<(f64, f64)> {
     Ok((lat_decimal, lon_decimal))
 }
 
+/// Position of an ICAO coordinate waypoint, in whole degrees (51N020W) or degrees
+/// and minutes (5130N00020W)
+pub fn parse_coordinate_fix(name: &str) -> Option<(f64, f64)> {
+    let (lat_len, lon_len) = match name.len() {
+        7 => (2, 3),
+        11 => (4, 5),
+        _ => return None,
+    };
+    if !name.is_ascii() {
+        return None;
+    }
+
+    let lat = coordinate_part(&name[..lat_len], &name[lat_len..lat_len + 1], "N", "S", 90.0)?;
+    let lon_start = lat_len + 1;
+    let lon = coordinate_part(&name[lon_start..lon_start + lon_len], &name[lon_start + lon_len..], "E", "W", 180.0)?;
+    Some((lat, lon))
+}
+
+/// Degrees (and minutes, if there are two more digits than `max` needs) with a hemisphere
+fn coordinate_part(digits: &str, hemisphere: &str, positive: &str, negative: &str, max: f64) -> Option<f64> {
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let degree_len = if max > 90.0 { 3 } else { 2 };
+    let degrees: f64 = digits[..degree_len].parse().ok()?;
+    let minutes: f64 = match &digits[degree_len..] {
+        "" => 0.0,
+        minutes => minutes.parse().ok()?,
+    };
+    let value = degrees + minutes / 60.0;
+    if minutes >= 60.0 || value > max {
+        return None;
+    }
+
+    match hemisphere {
+        h if h == positive => Some(value),
+        h if h == negative => Some(-value),
+        _ => None,
+    }
+}
+
 /// Parse a fixes file and return a map of fix name to coordinates
 fn parse_fixes_file<P: AsRef<Path>>(path: P) -> Result<FixDatabase> {
     let content = fs::read_to_string(path.as_ref())
         .with_context(|| format!("Failed to read file: {:?}", path.as_ref()))?;
 
-    let mut fixes = HashMap::new();
+    let mut fixes = FixDatabase::new();
 
     for line in content.lines() {
         let line = line.trim();
@@ -176,7 +387,7 @@ fn parse_fixes_file<P: AsRef<Path>>(path: P) -> Result<FixDatabase> {
 
 /// Parse airport basic data files to get airport reference points
 fn parse_airports<P: AsRef<Path>>(airports_dir: P) -> Result<FixDatabase> {
-    let mut airports = HashMap::new();
+    let mut airports = FixDatabase::new();
     
     let entries = fs::read_dir(airports_dir.as_ref())
         .with_context(|| format!("Failed to read airports directory: {:?}", airports_dir.as_ref()))?;
@@ -219,7 +430,7 @@ fn parse_airports<P: AsRef<Path>>(airports_dir: P) -> Result<FixDatabase> {
 
 /// Load all navigation data (fixes, VORs, NDBs, airports)
 pub fn load_navigation_data<P: AsRef<Path>>(data_dir: P) -> Result<FixDatabase> {
-    let mut all_fixes = HashMap::new();
+    let mut all_fixes = FixDatabase::new();
 
     let data_path = data_dir.as_ref();
     let navaids_dir = data_path.join("Navaids");
@@ -284,7 +495,7 @@ pub fn load_navigation_data<P: AsRef<Path>>(data_dir: P) -> Result<FixDatabase>
 
 /// Parse SID waypoints from airport folders
 fn parse_sid_waypoints<P: AsRef<Path>>(airports_dir: P) -> Result<FixDatabase> {
-    let mut waypoints = HashMap::new();
+    let mut waypoints = FixDatabase::new();
     
     let entries = fs::read_dir(airports_dir.as_ref())
         .with_context(|| format!("Failed to read airports directory: {:?}", airports_dir.as_ref()))?;
@@ -323,10 +534,25 @@ mod tests {
         assert!((dist - 340.0).abs() < 10.0);
     }
 
+    #[test]
+    fn test_intermediate_point() {
+        // Land's End to John o' Groats: midpoint 54 21 44N 004 31 50W
+        let (lat, lon) = intermediate_point(50.0664, -5.7147, 58.6439, -3.0700, 0.5);
+        assert!((lat - 54.3622).abs() < 0.001, "{}", lat);
+        assert!((lon + 4.5306).abs() < 0.001, "{}", lon);
+
+        // Heathrow to JFK: the midpoint lies north of both ends, and each end is exact
+        let (lat, _) = intermediate_point(51.4700, -0.4543, 40.6413, -73.7781, 0.5);
+        assert!(lat > 52.0, "{}", lat);
+        assert_eq!(intermediate_point(51.47, -0.4543, 40.6413, -73.7781, 0.0), (51.47, -0.4543));
+        let (lat, lon) = intermediate_point(51.47, -0.4543, 40.6413, -73.7781, 1.0);
+        assert!((lat - 40.6413).abs() < 1e-9 && (lon + 73.7781).abs() < 1e-9);
+    }
+
     #[test]
     fn test_heading() {
         let hdg = heading_from_to(50.0, 0.0, 51.0, 0.0);
-        assert!((hdg - 0).abs() < 5);
+        assert!(hdg.abs() < 5);
 
         let hdg = heading_from_to(50.0, 0.0, 50.0, 1.0);
         assert!((hdg - 90).abs() < 5);
@@ -334,9 +560,42 @@ mod tests {
 
     #[test]
     fn test_shortest_turn() {
-        assert_eq!(shortest_turn_direction(10, 20), TurnDirection::Right);
-        assert_eq!(shortest_turn_direction(350, 10), TurnDirection::Right);
-        assert_eq!(shortest_turn_direction(20, 350), TurnDirection::Left);
+        assert_eq!(shortest_turn_direction(10.0, 20.0), TurnDirection::Right);
+        assert_eq!(shortest_turn_direction(350.0, 10.0), TurnDirection::Right);
+        assert_eq!(shortest_turn_direction(20.0, 350.0), TurnDirection::Left);
+    }
+
+    #[test]
+    fn test_intercept_heading() {
+        let from = (52.0, 0.0);
+        let to = position_bearing_distance(52.0, 0.0, 90.0, 50.0);
+
+        // 5nm south of an eastbound leg is right of it: turn left to intercept
+        let (south_lat, south_lon) = position_bearing_distance(52.0, 0.0, 180.0, 5.0);
+        assert!((cross_track_nm(south_lat, south_lon, from, to) - 5.0).abs() < 0.01);
+        assert!((intercept_heading(south_lat, south_lon, from, to, 30.0) - 60.0).abs() < 0.01);
+
+        let (north_lat, north_lon) = position_bearing_distance(52.0, 0.0, 0.0, 5.0);
+        assert!((cross_track_nm(north_lat, north_lon, from, to) + 5.0).abs() < 0.01);
+        assert!((intercept_heading(north_lat, north_lon, from, to, 45.0) - 135.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_coordinate_fixes() {
+        assert_eq!(parse_coordinate_fix("51N020W"), Some((51.0, -20.0)));
+        assert_eq!(parse_coordinate_fix("33S151E"), Some((-33.0, 151.0)));
+        assert_eq!(parse_coordinate_fix("5130N00020W"), Some((51.5, -(20.0 / 60.0))));
+        assert_eq!(parse_coordinate_fix("3345S15115E"), Some((-33.75, 151.25)));
+        assert_eq!(parse_coordinate_fix("0030N17930E"), Some((0.5, 179.5)));
+
+        for invalid in ["51X020W", "51N020Q", "91N020W", "51N181E", "5160N00020W", "ABCDEFG", "5130N0020W", "BPK"] {
+            assert_eq!(parse_coordinate_fix(invalid), None, "{}", invalid);
+        }
+
+        // Not in the database, but placed by name
+        let fixes = FixDatabase::new();
+        assert_eq!(fixes.position("5130N00020W", (51.0, 0.0)), Some((51.5, -(20.0 / 60.0))));
+        assert_eq!(fixes.position("NOFIX", (51.0, 0.0)), None);
     }
 
     #[test]
@@ -347,6 +606,37 @@ mod tests {
         assert!((lon - (-3.47601)).abs() < 0.001);
     }
 
+    #[test]
+    fn test_duplicate_fix_names_resolve_nearest() {
+        let mut fix_db = FixDatabase::new();
+        fix_db.insert("DVR".to_string(), (51.1625, 1.3597));
+        fix_db.insert("KONAN".to_string(), (51.3311, 2.0000));
+        // Same identifier on the other side of the world, loaded last
+        fix_db.insert("KONAN".to_string(), (-33.9461, 151.1772));
+
+        assert_eq!(fix_db.len(), 2);
+        assert_eq!(fix_db.candidates("KONAN").len(), 2);
+        assert_eq!(fix_db.get("KONAN"), Some(&(-33.9461, 151.1772)));
+
+        let route = vec!["DVR".to_string(), "KONAN".to_string()];
+        let resolved = fix_db.resolve_route(&route, (51.47, -0.46));
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[1], ("KONAN".to_string(), (51.3311, 2.0000)));
+    }
+
+    #[test]
+    fn test_magnetic_variation() {
+        // Heathrow 27R: magnetic 270 is 268 true with 2 degrees west
+        let (lat, lon) = (51.4775, -0.4614);
+        assert_eq!(magnetic_variation(lat, lon), -2.0);
+        assert_eq!(magnetic_to_true(270.0, lat, lon), 268.0);
+        assert_eq!(true_to_magnetic(268.0, lat, lon), 270.0);
+        assert_eq!(true_to_magnetic(359.0, lat, lon), 1.0);
+
+        // No offset outside the table
+        assert_eq!(magnetic_to_true(270.0, 40.6398, -73.7789), 270.0);
+    }
+
     #[test]
     fn test_southern_western_hemisphere() {
         let (lat, lon) = sf_coords_to_decimal("S010.00.00.000", "W020.00.00.000").unwrap();
@@ -1,7 +1,16 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use anyhow::{Result, Context};
+use crate::utils::atmosphere::{crossover_altitude, ias_to_mach, mach_to_tas};
+
+/// Known names for the performance file, correct spelling first
+pub const PERFORMANCE_FILE_NAMES: &[&str] = &[
+    "AircraftPerformance.txt",
+    "AircraftPerformace.txt",
+    "aircraft_performance.txt",
+];
 
 /// Performance data for a specific altitude band
 #[derive(Debug, Clone)]
@@ -17,6 +26,31 @@ pub struct PerformanceLine {
     pub rate_of_descent: i32,      // Rate of descent in ft/min (positive value)
 }
 
+/// Phase of flight a speed is wanted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedPhase {
+    Climb,
+    Cruise,
+    Descent,
+}
+
+/// Speed to fly: an indicated airspeed below the crossover altitude, a Mach number above it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpeedTarget {
+    Ias(u32),
+    Mach(f64),
+}
+
+impl SpeedTarget {
+    /// Approximate true airspeed (kt) at altitude, for integrating position
+    pub fn tas(&self, altitude_ft: f64) -> f64 {
+        match *self {
+            SpeedTarget::Ias(ias) => mach_to_tas(ias_to_mach(ias as f64, altitude_ft), altitude_ft),
+            SpeedTarget::Mach(mach) => mach_to_tas(mach, altitude_ft),
+        }
+    }
+}
+
 /// Complete performance profile for an aircraft type
 #[derive(Debug, Clone)]
 pub struct AircraftPerformance {
@@ -38,6 +72,61 @@ impl AircraftPerformance {
             .max_by_key(|line| line.flight_level)
     }
 
+    /// Interpolate a value linearly between the performance lines either side of an
+    /// altitude, clamping to the lowest or highest line outside them
+    fn interpolate(&self, altitude_ft: f64, value: impl Fn(&PerformanceLine) -> f64) -> Option<f64> {
+        let mut lines: Vec<&PerformanceLine> = self.performance_lines.iter().collect();
+        lines.sort_by_key(|line| line.flight_level);
+        let altitude_fl = altitude_ft / 100.0;
+
+        let (first, last) = (lines.first()?, lines.last()?);
+        if altitude_fl <= first.flight_level as f64 {
+            return Some(value(first));
+        }
+        if altitude_fl >= last.flight_level as f64 {
+            return Some(value(last));
+        }
+        lines.windows(2).find_map(|pair| {
+            let (lower, upper) = (pair[0], pair[1]);
+            if altitude_fl > upper.flight_level as f64 {
+                return None;
+            }
+            let fraction = (altitude_fl - lower.flight_level as f64)
+                / (upper.flight_level - lower.flight_level) as f64;
+            Some(value(lower) + (value(upper) - value(lower)) * fraction)
+        })
+    }
+
+    /// Rate of climb (ft/min) at altitude, interpolated between performance lines
+    pub fn interpolated_rate_of_climb(&self, altitude_ft: f64) -> f64 {
+        self.interpolate(altitude_ft, |line| line.rate_of_climb as f64)
+            .unwrap_or(2000.0)
+    }
+
+    /// Rate of descent (ft/min, negative) at altitude, interpolated between performance lines
+    pub fn interpolated_rate_of_descent(&self, altitude_ft: f64) -> f64 {
+        self.interpolate(altitude_ft, |line| -line.rate_of_descent as f64)
+            .unwrap_or(-2000.0)
+    }
+
+    /// Climb speed (kt) at altitude, interpolated between performance lines
+    pub fn interpolated_climb_speed(&self, altitude_ft: f64) -> f64 {
+        self.interpolate(altitude_ft, |line| {
+            let speed = if line.climb_speed > 0 { line.climb_speed } else { line.cruise_speed };
+            speed as f64
+        })
+        .unwrap_or(250.0)
+    }
+
+    /// Descent speed (kt) at altitude, interpolated between performance lines
+    pub fn interpolated_descent_speed(&self, altitude_ft: f64) -> f64 {
+        self.interpolate(altitude_ft, |line| {
+            let speed = if line.descent_speed > 0 { line.descent_speed } else { line.cruise_speed };
+            speed as f64
+        })
+        .unwrap_or(250.0)
+    }
+
     /// Get the rate of climb for a specific altitude
     pub fn get_rate_of_climb(&self, altitude_ft: f64) -> i32 {
         self.get_performance_at_altitude(altitude_ft)
@@ -65,6 +154,27 @@ impl AircraftPerformance {
             .unwrap_or(250)
     }
 
+    /// Speed for a phase of flight at altitude: the line's Mach number above the
+    /// altitude where it crosses over with the line's speed in knots, otherwise the knots
+    pub fn target_speed_at_altitude(&self, altitude_ft: f64, phase: SpeedPhase) -> SpeedTarget {
+        let Some(perf) = self.get_performance_at_altitude(altitude_ft) else {
+            return SpeedTarget::Ias(250);
+        };
+        let (ias, mach) = match phase {
+            SpeedPhase::Climb => (perf.climb_speed, perf.climb_mach),
+            SpeedPhase::Cruise => (perf.cruise_speed, perf.cruise_mach),
+            SpeedPhase::Descent => (perf.descent_speed, perf.descent_mach),
+        };
+
+        if mach > 0.0 && (ias == 0 || altitude_ft >= crossover_altitude(ias as f64, mach)) {
+            SpeedTarget::Mach(mach)
+        } else if ias > 0 {
+            SpeedTarget::Ias(ias)
+        } else {
+            SpeedTarget::Ias(perf.cruise_speed)
+        }
+    }
+
     /// Get appropriate speed for descent at altitude
     pub fn get_descent_speed(&self, altitude_ft: f64) -> u32 {
         self.get_performance_at_altitude(altitude_ft)
@@ -79,13 +189,35 @@ impl AircraftPerformance {
     }
 }
 
-pub type PerformanceDatabase = HashMap<String, AircraftPerformance>;
+/// How an aircraft's climb rate changes as it burns fuel: a reduced rate just after
+/// takeoff, recovering to the full rate over the first part of the flight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClimbProfile {
+    /// Fraction of the normal climb rate available at takeoff
+    pub initial_factor: f64,
+    /// Minutes airborne until the full climb rate is available
+    pub recovery_minutes: f64,
+}
+
+impl ClimbProfile {
+    /// Climb rate factor after `minutes` airborne
+    pub fn factor(&self, minutes: f64) -> f64 {
+        if self.recovery_minutes <= 0.0 {
+            return 1.0;
+        }
+        let progress = (minutes / self.recovery_minutes).clamp(0.0, 1.0);
+        self.initial_factor + (1.0 - self.initial_factor) * progress
+    }
+}
+
+/// Performance by aircraft type, shared with every aircraft of the type
+pub type PerformanceDatabase = HashMap<String, Arc<AircraftPerformance>>;
 
 /// Parse a PERFLINE entry
 fn parse_perf_line(line: &str) -> Result<PerformanceLine> {
     let parts: Vec<&str> = line.split(':').collect();
     
-    if parts.len() != 9 || parts[0] != "PERFLINE" {
+    if parts.len() != 10 || parts[0] != "PERFLINE" {
         anyhow::bail!("Invalid PERFLINE format: {}", line);
     }
 
@@ -97,11 +229,31 @@ fn parse_perf_line(line: &str) -> Result<PerformanceLine> {
         climb_mach: if parts[5] == "0" { 0.0 } else { parts[5].parse::<f64>()? / 100.0 },
         cruise_mach: if parts[6] == "0" { 0.0 } else { parts[6].parse::<f64>()? / 100.0 },
         descent_mach: if parts[7] == "0" { 0.0 } else { parts[7].parse::<f64>()? / 100.0 },
-        rate_of_climb: parts[8].split(':').next().unwrap_or(parts[8]).parse()?,
-        rate_of_descent: parts[8].split(':').last().unwrap_or(parts[8]).parse()?,
+        rate_of_climb: parts[8].parse()?,
+        rate_of_descent: parts[9].parse()?,
     })
 }
 
+/// Find the performance file in a data directory under any of its known names
+pub fn find_performance_file<P: AsRef<Path>>(data_dir: P) -> Result<PathBuf> {
+    PERFORMANCE_FILE_NAMES
+        .iter()
+        .map(|name| data_dir.as_ref().join(name))
+        .find(|path| path.exists())
+        .ok_or_else(|| anyhow::anyhow!(
+            "No performance file in {:?} (tried {})",
+            data_dir.as_ref(),
+            PERFORMANCE_FILE_NAMES.join(", ")
+        ))
+}
+
+/// Load aircraft performance data from whichever known performance file exists in a data directory
+pub fn load_performance_from_dir<P: AsRef<Path>>(data_dir: P) -> Result<PerformanceDatabase> {
+    let path = find_performance_file(data_dir)?;
+    tracing::info!("Using performance file {:?}", path);
+    load_performance_data(path)
+}
+
 /// Load aircraft performance data from file
 pub fn load_performance_data<P: AsRef<Path>>(path: P) -> Result<PerformanceDatabase> {
     let content = fs::read_to_string(path.as_ref())
@@ -119,23 +271,23 @@ pub fn load_performance_data<P: AsRef<Path>>(path: P) -> Result<PerformanceDatab
             continue;
         }
 
-        if line.starts_with("PERFAC:") {
+        if let Some(aircraft_type) = line.strip_prefix("PERFAC:") {
             // Save previous aircraft if exists
             if let Some(aircraft_type) = current_aircraft.take() {
                 if !current_lines.is_empty() {
                     database.insert(
                         aircraft_type.clone(),
-                        AircraftPerformance {
+                        Arc::new(AircraftPerformance {
                             aircraft_type,
                             performance_lines: current_lines.clone(),
-                        },
+                        }),
                     );
                     current_lines.clear();
                 }
             }
 
             // Start new aircraft
-            current_aircraft = Some(line[7..].to_string());
+            current_aircraft = Some(aircraft_type.to_string());
         } else if line.starts_with("PERFLINE:") {
             if let Ok(perf_line) = parse_perf_line(line) {
                 current_lines.push(perf_line);
@@ -148,10 +300,10 @@ pub fn load_performance_data<P: AsRef<Path>>(path: P) -> Result<PerformanceDatab
         if !current_lines.is_empty() {
             database.insert(
                 aircraft_type.clone(),
-                AircraftPerformance {
+                Arc::new(AircraftPerformance {
                     aircraft_type,
                     performance_lines: current_lines,
-                },
+                }),
             );
         }
     }
@@ -163,6 +315,14 @@ pub fn load_performance_data<P: AsRef<Path>>(path: P) -> Result<PerformanceDatab
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_climb_profile_factor() {
+        let profile = ClimbProfile { initial_factor: 0.6, recovery_minutes: 20.0 };
+        assert_eq!(profile.factor(0.0), 0.6);
+        assert!((profile.factor(10.0) - 0.8).abs() < 1e-9);
+        assert_eq!(profile.factor(45.0), 1.0);
+    }
+
     #[test]
     fn test_parse_perf_line() {
         let line = "PERFLINE:030:190:230:210:0:0:0:2800:900";
@@ -175,6 +335,59 @@ mod tests {
         assert_eq!(perf.rate_of_descent, 900);
     }
 
+    #[test]
+    fn test_find_performance_file_alternate_name() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("sweatbox-perf-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        assert!(find_performance_file(&dir).is_err());
+
+        fs::write(
+            dir.join("AircraftPerformance.txt"),
+            "PERFAC:A320\nPERFLINE:030:190:230:210:0:0:0:2800:900\n",
+        )?;
+        assert_eq!(find_performance_file(&dir)?, dir.join("AircraftPerformance.txt"));
+
+        let database = load_performance_from_dir(&dir)?;
+        assert_eq!(database["A320"].get_rate_of_climb(3000.0), 2800);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_speed_target_crossover() {
+        let line = |flight_level, speed, mach| PerformanceLine {
+            flight_level,
+            climb_speed: speed,
+            cruise_speed: speed,
+            descent_speed: speed,
+            climb_mach: mach,
+            cruise_mach: mach,
+            descent_mach: mach,
+            rate_of_climb: 2000,
+            rate_of_descent: 2000,
+        };
+        let perf = AircraftPerformance {
+            aircraft_type: "TEST".to_string(),
+            performance_lines: vec![line(100, 250, 0.0), line(240, 290, 0.78)],
+        };
+
+        // Knots only below FL240; 290kt/M0.78 cross over at about FL310
+        assert_eq!(perf.target_speed_at_altitude(12000.0, SpeedPhase::Climb), SpeedTarget::Ias(250));
+        assert_eq!(perf.target_speed_at_altitude(26000.0, SpeedPhase::Climb), SpeedTarget::Ias(290));
+        assert_eq!(perf.target_speed_at_altitude(35000.0, SpeedPhase::Cruise), SpeedTarget::Mach(0.78));
+        assert_eq!(perf.target_speed_at_altitude(33000.0, SpeedPhase::Descent), SpeedTarget::Mach(0.78));
+
+        // Either side of the crossover both give about the same true airspeed
+        let crossover = crossover_altitude(290.0, 0.78);
+        let below = SpeedTarget::Ias(290).tas(crossover);
+        let above = SpeedTarget::Mach(0.78).tas(crossover);
+        assert!((below - above).abs() < 1.0, "{:.0}kt vs {:.0}kt", below, above);
+        assert!((SpeedTarget::Mach(0.80).tas(36000.0) - 459.0).abs() < 1.0);
+        assert!((SpeedTarget::Ias(250).tas(0.0) - 250.0).abs() < 0.5);
+    }
+
     #[test]
     fn test_get_performance_at_altitude() {
         let perf = AircraftPerformance {
@@ -213,4 +426,40 @@ mod tests {
         let p = perf.get_performance_at_altitude(12000.0).unwrap();
         assert_eq!(p.rate_of_climb, 2600);
     }
+
+    #[test]
+    fn test_interpolated_performance() {
+        let line = |flight_level, speed, rate_of_climb, rate_of_descent| PerformanceLine {
+            flight_level,
+            climb_speed: speed,
+            cruise_speed: speed,
+            descent_speed: 0,
+            climb_mach: 0.0,
+            cruise_mach: 0.0,
+            descent_mach: 0.0,
+            rate_of_climb,
+            rate_of_descent,
+        };
+        // Lines out of order to check they're sorted
+        let perf = AircraftPerformance {
+            aircraft_type: "TEST".to_string(),
+            performance_lines: vec![line(100, 250, 2600, 1500), line(30, 190, 2800, 900)],
+        };
+
+        // FL065 is halfway between the lines
+        let climb = perf.interpolated_rate_of_climb(6500.0);
+        assert!(climb > 2600.0 && climb < 2800.0);
+        assert_eq!(climb, 2700.0);
+        assert_eq!(perf.interpolated_rate_of_descent(6500.0), -1200.0);
+        assert_eq!(perf.interpolated_climb_speed(6500.0), 220.0);
+        assert_eq!(perf.interpolated_descent_speed(6500.0), 220.0);
+
+        // Clamped outside the lines
+        assert_eq!(perf.interpolated_rate_of_climb(1000.0), 2800.0);
+        assert_eq!(perf.interpolated_rate_of_climb(35000.0), 2600.0);
+        assert_eq!(perf.interpolated_rate_of_climb(10000.0), 2600.0);
+
+        let empty = AircraftPerformance { aircraft_type: "NONE".to_string(), performance_lines: Vec::new() };
+        assert_eq!(empty.interpolated_rate_of_climb(6500.0), 2000.0);
+    }
 }
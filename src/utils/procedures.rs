@@ -2,9 +2,22 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use anyhow::{Result, Context};
+use crate::utils::navigation::{sf_coords_to_decimal, bearing_from_to};
 
 pub type ProcedureDatabase = HashMap<String, HashMap<String, String>>;
 
+/// One end of a runway
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunwayEnd {
+    pub designator: String,
+    /// Threshold position (lat, lon)
+    pub threshold: (f64, f64),
+    /// True course from this threshold towards the opposite one
+    pub course: f64,
+}
+
+pub type RunwayDatabase = HashMap<String, RunwayEnd>;
+
 /// Parse SIDs from airport file
 /// Format: SID:ICAO:RUNWAY:SIDNAME:FIXES...
 pub fn load_sids<P: AsRef<Path>>(airport_dir: P) -> Result<ProcedureDatabase> {
@@ -33,7 +46,7 @@ pub fn load_sids<P: AsRef<Path>>(airport_dir: P) -> Result<ProcedureDatabase> {
             let fixes = parts[4].to_string();
 
             sids.entry(sid_name)
-                .or_insert_with(HashMap::new)
+                .or_default()
                 .insert(runway, fixes);
         }
     }
@@ -69,7 +82,7 @@ pub fn load_stars<P: AsRef<Path>>(airport_dir: P) -> Result<ProcedureDatabase> {
             let fixes = parts[4].to_string();
 
             stars.entry(star_name)
-                .or_insert_with(HashMap::new)
+                .or_default()
                 .insert(runway, fixes);
         }
     }
@@ -77,6 +90,49 @@ pub fn load_stars<P: AsRef<Path>>(airport_dir: P) -> Result<ProcedureDatabase> {
     Ok(stars)
 }
 
+/// Parse runway thresholds from airport file
+/// Format: RWY1 RWY2 HDG1 HDG2 LAT1 LON1 LAT2 LON2
+pub fn load_runways<P: AsRef<Path>>(airport_dir: P) -> Result<RunwayDatabase> {
+    let runway_file = airport_dir.as_ref().join("Runway.txt");
+
+    if !runway_file.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let content = fs::read_to_string(&runway_file)
+        .with_context(|| format!("Failed to read runway file: {:?}", runway_file))?;
+
+    let mut runways: RunwayDatabase = HashMap::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 8 {
+            continue;
+        }
+
+        // One bad runway shouldn't lose the airport's others
+        let (Ok(first), Ok(second)) = (sf_coords_to_decimal(parts[4], parts[5]), sf_coords_to_decimal(parts[6], parts[7])) else {
+            tracing::warn!("[RUNWAYS] Skipping runway {}/{} with bad threshold coordinates in {:?}", parts[0], parts[1], runway_file);
+            continue;
+        };
+
+        for (designator, threshold, opposite) in [(parts[0], first, second), (parts[1], second, first)] {
+            runways.insert(designator.to_string(), RunwayEnd {
+                designator: designator.to_string(),
+                threshold,
+                course: bearing_from_to(threshold.0, threshold.1, opposite.0, opposite.1),
+            });
+        }
+    }
+
+    Ok(runways)
+}
+
 /// Load both SIDs and STARs for an airport
 pub fn load_procedures<P: AsRef<Path>>(
     data_dir: P,
@@ -129,4 +185,35 @@ mod tests {
         println!("Loaded {} STARs", stars.len());
         Ok(())
     }
+
+    #[test]
+    fn test_load_egll_runways() -> Result<()> {
+        let runways = load_runways("data/Airports/EGLL")?;
+
+        let rwy27r = runways.get("27R").expect("27R missing");
+        assert!((rwy27r.threshold.0 - 51.4774).abs() < 0.001);
+        assert!((rwy27r.threshold.1 + 0.4333).abs() < 0.001);
+        assert!((rwy27r.course - 269.7).abs() < 1.0, "{}", rwy27r.course);
+        assert!((runways["09L"].course - 89.7).abs() < 1.0, "{}", runways["09L"].course);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bad_runway_skipped() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("sweatbox-runways-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("Runway.txt"),
+            "09L 27R 089 269 N051.28.39.000 W000.29.05.970 N051.28.39.630 W000.25.59.820\n\
+             09R 27L 089 269 N051.27.53.250 W000.28.56.330 BADLAT W000.26.02.760\n",
+        )?;
+
+        let runways = load_runways(&dir)?;
+        fs::remove_dir_all(&dir)?;
+
+        let mut designators: Vec<&str> = runways.keys().map(String::as_str).collect();
+        designators.sort();
+        assert_eq!(designators, ["09L", "27R"]);
+        Ok(())
+    }
 }
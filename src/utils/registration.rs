@@ -0,0 +1,111 @@
+//! Plausible aircraft registrations and mode-S addresses by airline
+
+use rand::Rng;
+
+/// Registration scheme for an aircraft's country of registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Registry {
+    /// Nationality prefix, e.g. "G" or "EI"
+    pub prefix: &'static str,
+    /// Number of letters after the dash
+    pub suffix_len: usize,
+    /// First letter of the suffix, if the country's airliners share one (e.g. D-A...)
+    pub suffix_start: Option<char>,
+    /// Block of 24-bit ICAO addresses allocated to the country
+    pub address_block: (u32, u32),
+}
+
+const UK: Registry = Registry { prefix: "G", suffix_len: 4, suffix_start: None, address_block: (0x400000, 0x43FFFF) };
+const IRELAND: Registry = Registry { prefix: "EI", suffix_len: 3, suffix_start: None, address_block: (0x4CA000, 0x4CAFFF) };
+const HUNGARY: Registry = Registry { prefix: "HA", suffix_len: 3, suffix_start: None, address_block: (0x470000, 0x477FFF) };
+const GERMANY: Registry = Registry { prefix: "D", suffix_len: 4, suffix_start: Some('A'), address_block: (0x3C0000, 0x3FFFFF) };
+const FRANCE: Registry = Registry { prefix: "F", suffix_len: 4, suffix_start: Some('G'), address_block: (0x380000, 0x3BFFFF) };
+const NETHERLANDS: Registry = Registry { prefix: "PH", suffix_len: 3, suffix_start: None, address_block: (0x480000, 0x487FFF) };
+const UAE: Registry = Registry { prefix: "A6", suffix_len: 3, suffix_start: Some('E'), address_block: (0x896000, 0x896FFF) };
+
+impl Registry {
+    /// Country of registry for an airline's ICAO designator
+    pub fn for_airline(airline: &str) -> Option<Self> {
+        match airline.to_uppercase().as_str() {
+            "BAW" | "EZY" | "VIR" | "TOM" | "EXS" => Some(UK),
+            "RYR" | "EIN" => Some(IRELAND),
+            "WZZ" => Some(HUNGARY),
+            "DLH" | "EWG" => Some(GERMANY),
+            "AFR" => Some(FRANCE),
+            "KLM" | "TRA" => Some(NETHERLANDS),
+            "UAE" => Some(UAE),
+            _ => None,
+        }
+    }
+
+    /// Check whether a registration follows this country's pattern
+    pub fn matches(&self, registration: &str) -> bool {
+        let Some(suffix) = registration
+            .strip_prefix(self.prefix)
+            .and_then(|r| r.strip_prefix('-'))
+        else {
+            return false;
+        };
+
+        suffix.len() == self.suffix_len
+            && suffix.chars().all(|c| c.is_ascii_uppercase())
+            && self.suffix_start.is_none_or(|start| suffix.starts_with(start))
+    }
+}
+
+/// Mode-S transponder details
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModeS {
+    pub registration: String,
+    /// 24-bit ICAO aircraft address
+    pub address: u32,
+}
+
+impl ModeS {
+    /// Generate a registration and address for a callsign's airline (e.g. "EZY1234"),
+    /// or `None` if the airline's country is unknown
+    pub fn generate(callsign: &str, rng: &mut impl Rng) -> Option<Self> {
+        let registry = Registry::for_airline(callsign.get(..3)?)?;
+
+        let mut suffix: String = (0..registry.suffix_len)
+            .map(|_| rng.gen_range(b'A'..=b'Z') as char)
+            .collect();
+        if let Some(start) = registry.suffix_start {
+            suffix.replace_range(..1, &start.to_string());
+        }
+
+        Some(Self {
+            registration: format!("{}-{}", registry.prefix, suffix),
+            address: rng.gen_range(registry.address_block.0..=registry.address_block.1),
+        })
+    }
+
+    /// Address as six hex digits, as filed in a flight plan (CODE/)
+    pub fn address_hex(&self) -> String {
+        format!("{:06X}", self.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_registrations() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..50 {
+            let mode_s = ModeS::generate("DLH4AB", &mut rng).unwrap();
+            assert!(GERMANY.matches(&mode_s.registration), "{}", mode_s.registration);
+            assert!((0x3C0000..=0x3FFFFF).contains(&mode_s.address));
+            assert_eq!(mode_s.address_hex().len(), 6);
+
+            let mode_s = ModeS::generate("RYR1234", &mut rng).unwrap();
+            assert!(IRELAND.matches(&mode_s.registration), "{}", mode_s.registration);
+        }
+
+        assert!(ModeS::generate("ZZZ123", &mut rng).is_none());
+        assert!(!UK.matches("EI-DCL"));
+        assert!(!UK.matches("G-ABC"));
+    }
+}
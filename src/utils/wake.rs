@@ -0,0 +1,122 @@
+//! ICAO wake turbulence categories and approach spacing
+
+/// Wake turbulence category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WakeCategory {
+    /// A380 and similar (J)
+    Super,
+    Heavy,
+    Medium,
+    Light,
+}
+
+/// Types heavier than 136t MTOW (H)
+const HEAVY_TYPES: &[&str] = &[
+    "A124", "A306", "A30B", "A310", "A332", "A333", "A338", "A339", "A342", "A343",
+    "A345", "A346", "A359", "A35K", "B744", "B748", "B762", "B763", "B764", "B772",
+    "B773", "B77L", "B77W", "B778", "B779", "B788", "B789", "B78X", "C17", "C5M",
+    "IL76", "IL96", "K35R", "MD11",
+];
+
+/// Super types (J)
+const SUPER_TYPES: &[&str] = &["A388", "A225"];
+
+/// Types at or below 7t MTOW (L)
+const LIGHT_TYPES: &[&str] = &[
+    "BE20", "BE58", "C150", "C152", "C172", "C182", "C208", "C25A", "C510", "DA40",
+    "DA42", "P28A", "PA28", "PA34", "SR20", "SR22", "TBM9",
+];
+
+impl WakeCategory {
+    /// Look up the wake category for an ICAO aircraft type, defaulting to medium
+    pub fn for_type(aircraft_type: &str) -> Self {
+        let aircraft_type = aircraft_type.to_uppercase();
+        let aircraft_type = aircraft_type.as_str();
+
+        if SUPER_TYPES.contains(&aircraft_type) {
+            WakeCategory::Super
+        } else if HEAVY_TYPES.contains(&aircraft_type) {
+            WakeCategory::Heavy
+        } else if LIGHT_TYPES.contains(&aircraft_type) {
+            WakeCategory::Light
+        } else {
+            WakeCategory::Medium
+        }
+    }
+
+    /// Single-letter code as used in flight plans (J/H/M/L)
+    pub fn code(&self) -> char {
+        match self {
+            WakeCategory::Super => 'J',
+            WakeCategory::Heavy => 'H',
+            WakeCategory::Medium => 'M',
+            WakeCategory::Light => 'L',
+        }
+    }
+}
+
+/// ICAO approach wake separation (NM) for a follower behind a leader,
+/// or `None` when only the radar minimum applies
+pub fn wake_separation_nm(leader: WakeCategory, follower: WakeCategory) -> Option<f64> {
+    use WakeCategory::*;
+
+    match (leader, follower) {
+        (Super, Heavy) => Some(6.0),
+        (Super, Medium) => Some(7.0),
+        (Super, Light) => Some(8.0),
+        (Heavy, Heavy) => Some(4.0),
+        (Heavy, Medium) => Some(5.0),
+        (Heavy, Light) => Some(6.0),
+        (Medium, Light) => Some(5.0),
+        _ => None,
+    }
+}
+
+/// In-trail spacing target between two aircraft types on final (NM)
+pub fn in_trail_spacing_nm(
+    leader_type: &str,
+    follower_type: &str,
+    min_spacing_nm: f64,
+    use_wake_spacing: bool,
+) -> f64 {
+    if !use_wake_spacing {
+        return min_spacing_nm;
+    }
+
+    let leader = WakeCategory::for_type(leader_type);
+    let follower = WakeCategory::for_type(follower_type);
+
+    wake_separation_nm(leader, follower)
+        .map(|wake| wake.max(min_spacing_nm))
+        .unwrap_or(min_spacing_nm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wake_categories() {
+        assert_eq!(WakeCategory::for_type("A388"), WakeCategory::Super);
+        assert_eq!(WakeCategory::for_type("b77w"), WakeCategory::Heavy);
+        assert_eq!(WakeCategory::for_type("A320"), WakeCategory::Medium);
+        assert_eq!(WakeCategory::for_type("B752"), WakeCategory::Medium);
+        assert_eq!(WakeCategory::for_type("C172"), WakeCategory::Light);
+        assert_eq!(WakeCategory::for_type("ZZZZ"), WakeCategory::Medium);
+    }
+
+    #[test]
+    fn test_in_trail_spacing() {
+        // Light behind a heavy opens to the wake minimum
+        assert_eq!(in_trail_spacing_nm("B744", "C172", 3.0, true), 6.0);
+
+        // Medium behind medium only needs the radar minimum
+        assert_eq!(in_trail_spacing_nm("A320", "B738", 3.0, true), 3.0);
+
+        // A larger configured minimum wins over a smaller wake minimum
+        assert_eq!(in_trail_spacing_nm("B772", "B772", 5.0, true), 5.0);
+
+        // Wake spacing can be disabled
+        assert_eq!(in_trail_spacing_nm("B744", "C172", 3.0, false), 3.0);
+    }
+}
@@ -0,0 +1,128 @@
+//! Winds that vary by position and altitude
+
+/// A wind, as reported: the direction it blows from (degrees true) and its speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wind {
+    pub direction: f64,
+    pub speed: f64,
+}
+
+impl Wind {
+    pub fn new(direction: f64, speed: f64) -> Self {
+        Self { direction, speed }
+    }
+
+    pub fn calm() -> Self {
+        Self::new(0.0, 0.0)
+    }
+
+    /// Ground speed (kt) and track (degrees true) for an aircraft flying
+    /// `heading` at `airspeed` through this wind
+    pub fn ground_vector(&self, heading: f64, airspeed: f64) -> (f64, f64) {
+        let heading = heading.to_radians();
+        let from = self.direction.to_radians();
+
+        let north = airspeed * heading.cos() - self.speed * from.cos();
+        let east = airspeed * heading.sin() - self.speed * from.sin();
+
+        let ground_speed = north.hypot(east);
+        let track = (east.atan2(north).to_degrees() + 360.0) % 360.0;
+        (ground_speed, track)
+    }
+}
+
+/// A box of airspace with its own wind
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindZone {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+    /// Altitude band (feet), inclusive
+    pub min_altitude: i32,
+    pub max_altitude: i32,
+    pub wind: Wind,
+}
+
+impl WindZone {
+    pub fn contains(&self, lat: f64, lon: f64, altitude: i32) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat)
+            && (self.min_lon..=self.max_lon).contains(&lon)
+            && (self.min_altitude..=self.max_altitude).contains(&altitude)
+    }
+}
+
+/// Wind lookup by position: the first zone containing the position wins,
+/// falling back to a default wind everywhere else
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindField {
+    pub default: Wind,
+    pub zones: Vec<WindZone>,
+}
+
+impl Default for WindField {
+    fn default() -> Self {
+        Self::uniform(Wind::calm())
+    }
+}
+
+impl WindField {
+    /// The same wind everywhere
+    pub fn uniform(wind: Wind) -> Self {
+        Self {
+            default: wind,
+            zones: Vec::new(),
+        }
+    }
+
+    pub fn add_zone(&mut self, zone: WindZone) {
+        self.zones.push(zone);
+    }
+
+    /// Wind at a position and altitude (feet)
+    pub fn wind_at(&self, lat: f64, lon: f64, altitude: i32) -> Wind {
+        self.zones
+            .iter()
+            .find(|zone| zone.contains(lat, lon, altitude))
+            .map(|zone| zone.wind)
+            .unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ground_vector() {
+        // Straight headwind and tailwind
+        let (gs, track) = Wind::new(270.0, 50.0).ground_vector(270.0, 250.0);
+        assert!((gs - 200.0).abs() < 0.01);
+        assert!((track - 270.0).abs() < 0.01);
+
+        let (gs, _) = Wind::new(90.0, 50.0).ground_vector(270.0, 250.0);
+        assert!((gs - 300.0).abs() < 0.01);
+
+        // Wind from the left drifts the track right
+        let (_, track) = Wind::new(270.0, 30.0).ground_vector(0.0, 200.0);
+        assert!(track > 0.0 && track < 20.0);
+    }
+
+    #[test]
+    fn test_zone_lookup() {
+        let mut field = WindField::uniform(Wind::new(240.0, 20.0));
+        field.add_zone(WindZone {
+            min_lat: 51.0,
+            max_lat: 52.0,
+            min_lon: -1.0,
+            max_lon: 0.0,
+            min_altitude: 10000,
+            max_altitude: 45000,
+            wind: Wind::new(270.0, 100.0),
+        });
+
+        assert_eq!(field.wind_at(51.5, -0.5, 30000), Wind::new(270.0, 100.0));
+        assert_eq!(field.wind_at(51.5, -0.5, 5000), Wind::new(240.0, 20.0));
+        assert_eq!(field.wind_at(51.5, 0.5, 30000), Wind::new(240.0, 20.0));
+    }
+}
@@ -24,51 +24,66 @@ fn test_load_fixes() -> Result<()> {
 }
 
 #[test]
-fn test_plane_with_fixes() -> Result<()> {
-    use custom_sweatbox_rust::simulator::{Plane, FlightPlan, Route};
-    
+fn test_aircraft_from_fix() -> Result<()> {
+    use custom_sweatbox_rust::aircraft::aircraft::PLACEHOLDER_POSITION;
+    use custom_sweatbox_rust::aircraft::Aircraft;
+
     // Load fix database
     let fix_db = Arc::new(navigation::load_navigation_data("data")?);
-    
-    // Create a simple route
-    let route = Route::new(
-        "TIMBA LAM BIG".to_string(),
+
+    // Create aircraft at TIMBA
+    let aircraft = Aircraft::from_fix(
+        "TEST123".to_string(),
+        "B738".to_string(),
+        "1234".to_string(),
         "EGLL".to_string(),
-        Some("EGPF".to_string())
-    );
-    
-    let flight_plan = FlightPlan::new(
-        "I".to_string(),
+        "EGPF".to_string(),
+        "TIMBA LAM BIG".to_string(),
+        "TIMBA",
+        270.0,
+        15000,
+        280,
+        Some(&fix_db),
+    )?;
+
+    // Verify aircraft was created with real coordinates
+    println!("Aircraft at {}, {}", aircraft.latitude, aircraft.longitude);
+    assert_eq!(Some(&(aircraft.latitude, aircraft.longitude)), fix_db.get("TIMBA"));
+
+    // Should not be at the default position
+    assert_ne!((aircraft.latitude, aircraft.longitude), PLACEHOLDER_POSITION);
+
+    // Without a database it stays at the placeholder
+    let placeholder = Aircraft::from_fix(
+        "TEST456".to_string(),
         "B738".to_string(),
-        250,
+        "1235".to_string(),
         "EGLL".to_string(),
-        1200,
-        130,
-        36000,
         "EGPF".to_string(),
-        route
-    );
-    
-    // Create aircraft at TIMBA
-    let plane = Plane::from_fix(
-        "TEST123".to_string(),
-        "TIMBA".to_string(),
-        1234,
-        15000.0,
+        "TIMBA LAM BIG".to_string(),
+        "TIMBA",
         270.0,
-        280.0,
-        0.0,
-        flight_plan,
+        15000,
+        280,
         None,
-        None,
-        Some(fix_db)
+    )?;
+    assert_eq!((placeholder.latitude, placeholder.longitude), PLACEHOLDER_POSITION);
+
+    // With a database, a fix that isn't in it is an error rather than the placeholder
+    let missing = Aircraft::from_fix(
+        "TEST789".to_string(),
+        "B738".to_string(),
+        "1236".to_string(),
+        "EGLL".to_string(),
+        "EGPF".to_string(),
+        "NOWHERE LAM BIG".to_string(),
+        "NOWHERE",
+        270.0,
+        15000,
+        280,
+        Some(&fix_db),
     );
-    
-    // Verify aircraft was created with real coordinates
-    println!("Aircraft at {}, {}", plane.lat, plane.lon);
-    
-    // Should not be at the default position
-    assert!(plane.lat != 51.15487 || plane.lon != -0.16454);
-    
+    assert!(missing.is_err());
+
     Ok(())
 }
@@ -1,4 +1,4 @@
-use custom_sweatbox_rust::simulator::Route;
+use custom_sweatbox_rust::aircraft::Route;
 
 #[test]
 fn test_route_with_sid() {
@@ -14,7 +14,7 @@ fn test_route_with_sid() {
     
     // BPK5K/09L should expand to: RW27R D110B D070J D196J D196F BAPAG BPK
     // Then followed by: DVR UL9 KONAN
-    assert!(route.fixes.len() > 0);
+    assert!(!route.fixes.is_empty());
     assert!(route.fixes.contains(&"BPK".to_string()));
     assert!(route.fixes.contains(&"BAPAG".to_string()));
 }
@@ -32,7 +32,7 @@ fn test_route_with_star() {
     println!("Fixes: {:?}", route.fixes);
     
     // ALESO1H/27R should expand to: ALESO ROTNO ETVAX TIGER LLE01 BIG CF27R FI27R RW27R
-    assert!(route.fixes.len() > 0);
+    assert!(!route.fixes.is_empty());
     assert!(route.fixes.contains(&"ALESO".to_string()));
     assert!(route.fixes.contains(&"TIGER".to_string()));
     assert!(route.fixes.contains(&"RW27R".to_string()));
@@ -51,7 +51,7 @@ fn test_route_with_sid_and_star() {
     println!("Fixes: {:?}", route.fixes);
     
     // Should have both SID and STAR expanded
-    assert!(route.fixes.len() > 0);
+    assert!(!route.fixes.is_empty());
     
     // Check SID fixes
     assert!(route.fixes.contains(&"BPK".to_string()));